@@ -0,0 +1,29 @@
+-- Create discovered_tokens table for per-account token monitoring settings
+CREATE TABLE discovered_tokens (
+    account_id TEXT NOT NULL REFERENCES monitored_accounts(account_id) ON DELETE CASCADE,
+    token_id VARCHAR(128) NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    discovered_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (account_id, token_id)
+);
+
+-- Index for looking up disabled tokens during monitoring
+CREATE INDEX idx_discovered_tokens_disabled ON discovered_tokens(account_id) WHERE enabled = false;
+
+-- Trigger to automatically update updated_at timestamp
+CREATE OR REPLACE FUNCTION update_discovered_tokens_updated_at()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.updated_at = NOW();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER discovered_tokens_updated_at
+    BEFORE UPDATE ON discovered_tokens
+    FOR EACH ROW
+    EXECUTE FUNCTION update_discovered_tokens_updated_at();
+
+COMMENT ON TABLE discovered_tokens IS 'Tokens discovered for monitored accounts, with a flag to exclude them from monitoring';
+COMMENT ON COLUMN discovered_tokens.enabled IS 'When false, the monitoring cycle skips this token for the account (e.g. spam airdrops)';
@@ -0,0 +1,5 @@
+-- Store the spam classification assigned to a token at discovery time
+ALTER TABLE discovered_tokens
+    ADD COLUMN classification VARCHAR(32) NOT NULL DEFAULT 'legit';
+
+COMMENT ON COLUMN discovered_tokens.classification IS 'Discovery heuristic result: legit or suspected_spam (suspected spam starts disabled)';
@@ -0,0 +1,16 @@
+-- Full receipt JSON kept for forensic audits (only written when AUDIT_MODE is enabled)
+CREATE TABLE balance_change_receipts (
+    id BIGSERIAL PRIMARY KEY,
+    account_id VARCHAR(64) NOT NULL,
+    block_height BIGINT NOT NULL,
+    receipt_id VARCHAR(64) NOT NULL,
+    receipt JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT unique_balance_change_receipt UNIQUE (account_id, block_height, receipt_id)
+);
+
+CREATE INDEX idx_balance_change_receipts_account_block
+    ON balance_change_receipts(account_id, block_height);
+
+COMMENT ON TABLE balance_change_receipts IS 'Serialized ReceiptView of each receipt involving the account at a balance change block';
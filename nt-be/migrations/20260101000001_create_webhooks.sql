@@ -0,0 +1,34 @@
+-- Webhooks notified when a monitored account's balance changes
+CREATE TABLE webhooks (
+    id BIGSERIAL PRIMARY KEY,
+    account_id VARCHAR(64) NOT NULL,
+    url TEXT NOT NULL,
+    -- Key for the HMAC-SHA256 signature sent with each delivery
+    secret TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    CONSTRAINT unique_webhook_account_url UNIQUE (account_id, url)
+);
+
+CREATE INDEX idx_webhooks_account_enabled ON webhooks(account_id) WHERE enabled = true;
+
+-- One row per balance change notification and webhook, with its retry state
+CREATE TABLE webhook_deliveries (
+    id BIGSERIAL PRIMARY KEY,
+    webhook_id BIGINT NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+    payload JSONB NOT NULL,
+    -- pending, delivered or failed (retries exhausted)
+    status VARCHAR(16) NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    next_attempt_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    delivered_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_webhook_deliveries_due
+    ON webhook_deliveries(next_attempt_at) WHERE status = 'pending';
+
+COMMENT ON TABLE webhook_deliveries IS 'Delivery log of balance change webhooks, retried with exponential backoff';
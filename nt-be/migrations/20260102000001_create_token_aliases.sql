@@ -0,0 +1,11 @@
+-- FT contracts that migrated to a new address, so their balances can be charted as one series
+CREATE TABLE token_aliases (
+    old_contract VARCHAR(128) NOT NULL,
+    new_contract VARCHAR(128) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (old_contract, new_contract),
+    CONSTRAINT distinct_alias_contracts CHECK (old_contract <> new_contract)
+);
+
+CREATE INDEX idx_token_aliases_new_contract ON token_aliases(new_contract);
@@ -0,0 +1,9 @@
+-- Decimals of every token id balances are recorded for, seeded from the intents tokens map
+-- and filled from ft_metadata as FT tokens are resolved
+CREATE TABLE token_decimals (
+    token_id VARCHAR(256) PRIMARY KEY,
+    decimals SMALLINT NOT NULL,
+    -- 'intents' (tokens map) or 'ft_metadata'
+    source VARCHAR(32) NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,6 @@
+-- Remember which balance changes FT discovery has already examined, so each cycle
+-- only checks counterparties of changes recorded since the previous run
+ALTER TABLE monitored_accounts
+    ADD COLUMN last_discovery_change_id BIGINT NOT NULL DEFAULT 0;
+
+COMMENT ON COLUMN monitored_accounts.last_discovery_change_id IS 'Highest balance_changes.id whose counterparty FT discovery has checked';
@@ -0,0 +1,15 @@
+-- Gaps whose fill keeps failing, retried with exponential backoff instead of every cycle
+CREATE TABLE fill_failures (
+    account_id VARCHAR(64) NOT NULL,
+    token_id VARCHAR(256) NOT NULL,
+    start_block BIGINT NOT NULL,
+    end_block BIGINT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT NOT NULL,
+    next_retry_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (account_id, token_id, start_block, end_block)
+);
+
+CREATE INDEX idx_fill_failures_next_retry_at ON fill_failures(next_retry_at);
@@ -0,0 +1,6 @@
+-- Let accounts record NEAR balance changes caused only by gas rewards as one record
+-- per run instead of one record per change
+ALTER TABLE monitored_accounts
+    ADD COLUMN coalesce_gas_rewards BOOLEAN NOT NULL DEFAULT false;
+
+COMMENT ON COLUMN monitored_accounts.coalesce_gas_rewards IS 'Coalesce consecutive gas-reward-only NEAR changes into a single GAS_REWARDS record';
@@ -0,0 +1,9 @@
+-- Deliveries are claimed ('in_flight') before they are sent, so the first attempt and
+-- the retry loop can't send the same row twice. A claim whose holder died is retried
+-- once its next_attempt_at (the claim's lease) has passed.
+DROP INDEX idx_webhook_deliveries_due;
+
+CREATE INDEX idx_webhook_deliveries_due
+    ON webhook_deliveries(next_attempt_at) WHERE status IN ('pending', 'in_flight');
+
+COMMENT ON COLUMN webhook_deliveries.status IS 'pending, in_flight (claimed by a sender), delivered or failed (retries exhausted)';
@@ -0,0 +1,26 @@
+-- A balance change is delivered at most once per webhook, even when its record is
+-- written again (chain rebuilds, reprocessing, enrichment of a placeholder row).
+ALTER TABLE webhook_deliveries
+    ADD COLUMN account_id VARCHAR(64),
+    ADD COLUMN token_id VARCHAR(128),
+    ADD COLUMN block_height BIGINT;
+
+UPDATE webhook_deliveries
+SET account_id = payload->>'account_id',
+    token_id = payload->>'token_id',
+    block_height = (payload->>'block_height')::BIGINT;
+
+-- Keep the earliest delivery of changes that were already notified more than once
+DELETE FROM webhook_deliveries d
+USING webhook_deliveries earlier
+WHERE earlier.webhook_id = d.webhook_id
+  AND earlier.account_id = d.account_id
+  AND earlier.token_id = d.token_id
+  AND earlier.block_height = d.block_height
+  AND earlier.id < d.id;
+
+ALTER TABLE webhook_deliveries
+    ALTER COLUMN account_id SET NOT NULL,
+    ALTER COLUMN token_id SET NOT NULL,
+    ALTER COLUMN block_height SET NOT NULL,
+    ADD CONSTRAINT unique_webhook_delivery_change UNIQUE (webhook_id, account_id, token_id, block_height);
@@ -0,0 +1,7 @@
+-- FT decimals resolved before token_decimals became the single registry were only
+-- stored in counterparties; carry them over so lookups don't go back to RPC
+INSERT INTO token_decimals (token_id, decimals, source)
+SELECT account_id, token_decimals, 'ft_metadata'
+FROM counterparties
+WHERE account_type = 'ft_token' AND token_decimals IS NOT NULL
+ON CONFLICT (token_id) DO NOTHING;
@@ -1,10 +1,11 @@
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use utoipa::ToSchema;
 
 const ICON_PREFIX: &str = "https://near-intents.org/static/icons/network/";
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ChainIcons {
     pub dark: String,
     pub light: String,
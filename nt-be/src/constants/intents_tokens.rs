@@ -69,6 +69,16 @@ pub enum TokenDeployment {
     },
 }
 
+impl TokenDeployment {
+    /// The chain the token is deployed on (e.g. `near`, `eth`, `solana`)
+    pub fn chain_name(&self) -> &str {
+        match self {
+            TokenDeployment::Native { chain_name, .. } => chain_name,
+            TokenDeployment::Fungible { chain_name, .. } => chain_name,
+        }
+    }
+}
+
 /// Static map of unified tokens loaded from data/tokens.json for fast lookup
 static TOKENS_MAP_CELL: OnceLock<HashMap<String, UnifiedTokenInfo>> = OnceLock::new();
 
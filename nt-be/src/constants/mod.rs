@@ -10,7 +10,9 @@ pub const INTENTS_CONTRACT_ID: &AccountIdRef = AccountIdRef::new_or_panic("inten
 
 pub const NEAR_ICON: &str = "https://s2.coinmarketcap.com/static/img/coins/128x128/6535.png";
 pub const WRAP_NEAR_ICON: &str = "https://s2.coinmarketcap.com/static/img/coins/128x128/6535.png";
-pub const BLOCKS_PER_HOUR: u64 = 300; // Approximate blocks per hour on NEAR
+/// Approximate blocks per hour on NEAR (~1 block per second), used until the rate is measured
+/// (see `handlers::block::rate`)
+pub const BLOCKS_PER_HOUR: u64 = 3_600;
 
 pub const BATCH_PAYMENT_ACCOUNT_ID: &AccountIdRef = AccountIdRef::new_or_panic("bulkpayment.near");
 pub const TREASURY_FACTORY_CONTRACT_ID: &AccountIdRef =
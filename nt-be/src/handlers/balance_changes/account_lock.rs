@@ -0,0 +1,352 @@
+//! Account Locks
+//!
+//! Session-level Postgres advisory locks keyed by account. The monitoring cycle and
+//! admin rebuilds take this lock so they never work on the same account at once.
+//!
+//! The lock belongs to the connection that took it, so the connection is handed back
+//! to the caller wrapped in an `AccountLock` and must be passed to `unlock_account`
+//! when done. If the guard is dropped without unlocking (a panic, or the holding
+//! future being cancelled), its connection is closed instead of returned to the pool,
+//! which ends the session and releases the lock.
+//!
+//! Work on a single account/token chain is additionally serialized with a chain
+//! lock, so a manual fill and the monitor don't binary-search and insert the same
+//! gaps concurrently. Gap fills and block reprocessing spend most of their time
+//! waiting on RPC, so they hold it at session level on a pooled connection
+//! (`lock_chain_session`, guarded like `AccountLock`) rather than keeping a
+//! transaction open; short writes take the same lock inside their transaction
+//! (`lock_chain`).
+
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::Postgres;
+use sqlx::{PgConnection, PgPool};
+use std::ops::{Deref, DerefMut};
+use std::time::Duration;
+
+/// Wait between attempts to take a chain lock held by another session
+const CHAIN_LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+fn account_lock_key(account_id: &str) -> String {
+    format!("account:{}", account_id)
+}
+
+fn chain_lock_key(account_id: &str, token_id: &str) -> String {
+    format!("{}:{}", account_id, token_id)
+}
+
+/// An account lock and the pooled connection holding it
+///
+/// Derefs to the connection so work can run on the locking session.
+pub struct AccountLock {
+    conn: Option<PoolConnection<Postgres>>,
+    account_id: String,
+}
+
+impl Deref for AccountLock {
+    type Target = PgConnection;
+
+    fn deref(&self) -> &PgConnection {
+        self.conn
+            .as_ref()
+            .expect("connection is only taken on unlock")
+    }
+}
+
+impl DerefMut for AccountLock {
+    fn deref_mut(&mut self) -> &mut PgConnection {
+        self.conn
+            .as_mut()
+            .expect("connection is only taken on unlock")
+    }
+}
+
+impl Drop for AccountLock {
+    fn drop(&mut self) {
+        // Not unlocked cleanly: don't hand a locked session back to the pool
+        if let Some(mut conn) = self.conn.take() {
+            log::warn!(
+                "Account lock for {} dropped without unlocking, closing its connection",
+                self.account_id
+            );
+            conn.close_on_drop();
+        }
+    }
+}
+
+/// Try to take the advisory lock for an account without waiting
+///
+/// # Returns
+/// * `Some(lock)` - The lock was taken and is held by the returned guard
+/// * `None` - Another session holds the lock
+pub async fn try_lock_account(
+    pool: &PgPool,
+    account_id: &str,
+) -> Result<Option<AccountLock>, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+
+    let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock(hashtext($1))")
+        .bind(account_lock_key(account_id))
+        .fetch_one(&mut *conn)
+        .await?;
+
+    Ok(locked.then(|| AccountLock {
+        conn: Some(conn),
+        account_id: account_id.to_string(),
+    }))
+}
+
+/// Release an account lock taken with `try_lock_account`
+///
+/// If unlocking fails the connection is closed instead of being returned to the
+/// pool, which also releases the lock.
+pub async fn unlock_account(mut lock: AccountLock) {
+    let result = sqlx::query("SELECT pg_advisory_unlock(hashtext($1))")
+        .bind(account_lock_key(&lock.account_id))
+        .execute(&mut *lock)
+        .await;
+
+    match result {
+        // Return the connection to the pool
+        Ok(_) => drop(lock.conn.take()),
+        Err(e) => log::warn!("Failed to unlock account {}: {}", lock.account_id, e),
+    }
+}
+
+/// Wait for the transaction-level advisory lock on an account/token chain
+///
+/// `conn` must be inside a transaction; the lock is released when that
+/// transaction commits or rolls back.
+pub async fn lock_chain(
+    conn: &mut PgConnection,
+    account_id: &str,
+    token_id: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext($1))")
+        .bind(chain_lock_key(account_id, token_id))
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+/// A chain lock and the pooled connection holding it
+///
+/// Like `AccountLock`, a guard dropped without `unlock_chain` closes its connection
+/// instead of returning the locked session to the pool.
+pub struct ChainLock {
+    conn: Option<PoolConnection<Postgres>>,
+    key: String,
+}
+
+impl Drop for ChainLock {
+    fn drop(&mut self) {
+        if let Some(mut conn) = self.conn.take() {
+            log::warn!(
+                "Chain lock for {} dropped without unlocking, closing its connection",
+                self.key
+            );
+            conn.close_on_drop();
+        }
+    }
+}
+
+/// Wait for the session-level advisory lock on an account/token chain
+///
+/// The lock conflicts with `lock_chain` on the same chain, so a fill holding it
+/// also keeps rebuilds and reprocessing of that chain waiting. While the chain is
+/// locked elsewhere the connection goes back to the pool between attempts, so
+/// waiting fills don't starve the holder's inserts of connections.
+pub async fn lock_chain_session(
+    pool: &PgPool,
+    account_id: &str,
+    token_id: &str,
+) -> Result<ChainLock, sqlx::Error> {
+    let key = chain_lock_key(account_id, token_id);
+
+    loop {
+        let mut conn = pool.acquire().await?;
+        let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock(hashtext($1))")
+            .bind(&key)
+            .fetch_one(&mut *conn)
+            .await?;
+
+        if locked {
+            return Ok(ChainLock {
+                conn: Some(conn),
+                key,
+            });
+        }
+        drop(conn);
+        tokio::time::sleep(CHAIN_LOCK_RETRY_INTERVAL).await;
+    }
+}
+
+/// Release a chain lock taken with `lock_chain_session`
+///
+/// If unlocking fails the connection is closed instead of being returned to the
+/// pool, which also releases the lock.
+pub async fn unlock_chain(mut lock: ChainLock) {
+    let Some(conn) = lock.conn.as_mut() else {
+        return;
+    };
+    let result = sqlx::query("SELECT pg_advisory_unlock(hashtext($1))")
+        .bind(&lock.key)
+        .execute(&mut **conn)
+        .await;
+
+    match result {
+        // Return the connection to the pool
+        Ok(_) => drop(lock.conn.take()),
+        Err(e) => log::warn!("Failed to unlock chain {}: {}", lock.key, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test]
+    async fn test_account_lock_is_exclusive(pool: PgPool) -> sqlx::Result<()> {
+        let lock = try_lock_account(&pool, "test.near")
+            .await?
+            .expect("First lock should succeed");
+
+        assert!(try_lock_account(&pool, "test.near").await?.is_none());
+        assert!(try_lock_account(&pool, "other.near").await?.is_some());
+
+        unlock_account(lock).await;
+        assert!(try_lock_account(&pool, "test.near").await?.is_some());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_dropped_lock_is_released(pool: PgPool) -> sqlx::Result<()> {
+        let holder_pool = pool.clone();
+        let holder = tokio::spawn(async move {
+            let _lock = try_lock_account(&holder_pool, "test.near")
+                .await
+                .unwrap()
+                .expect("First lock should succeed");
+            panic!("holder fails while locked");
+        });
+        assert!(holder.await.is_err());
+
+        // The connection is closed in the background; its session ends shortly after
+        let mut relocked = None;
+        for _ in 0..50 {
+            relocked = try_lock_account(&pool, "test.near").await?;
+            if relocked.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert!(relocked.is_some(), "Dropped lock should be released");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_chain_lock_waits_for_holder(pool: PgPool) -> sqlx::Result<()> {
+        let mut holder = pool.begin().await?;
+        lock_chain(&mut holder, "test.near", "near").await?;
+
+        let waiter_pool = pool.clone();
+        let waiter = tokio::spawn(async move {
+            let mut tx = waiter_pool.begin().await?;
+            lock_chain(&mut tx, "test.near", "near").await?;
+            tx.commit().await
+        });
+
+        // A different token of the same account is not blocked
+        let mut other = pool.begin().await?;
+        lock_chain(&mut other, "test.near", "usdc.near").await?;
+        other.commit().await?;
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(
+            !waiter.is_finished(),
+            "Second lock should wait for the first"
+        );
+
+        holder.commit().await?;
+        tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .expect("Second lock should be granted after commit")
+            .unwrap()?;
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_session_chain_lock_blocks_until_released(pool: PgPool) -> sqlx::Result<()> {
+        let holder = lock_chain_session(&pool, "test.near", "near").await?;
+
+        // Transaction-level chain locks wait for the session lock
+        let waiter_pool = pool.clone();
+        let waiter = tokio::spawn(async move {
+            let mut tx = waiter_pool.begin().await?;
+            lock_chain(&mut tx, "test.near", "near").await?;
+            tx.commit().await
+        });
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!waiter.is_finished(), "Chain lock should wait for the fill");
+
+        unlock_chain(holder).await;
+        tokio::time::timeout(Duration::from_secs(5), waiter)
+            .await
+            .expect("Chain lock should be granted after unlock")
+            .unwrap()?;
+
+        // A dropped guard releases the lock as well
+        let dropped = lock_chain_session(&pool, "test.near", "near").await?;
+        drop(dropped);
+        let relocked = tokio::time::timeout(
+            Duration::from_secs(5),
+            lock_chain_session(&pool, "test.near", "near"),
+        )
+        .await
+        .expect("Dropped chain lock should be released")?;
+        unlock_chain(relocked).await;
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_waiting_chain_locks_leave_connections_to_the_holder(
+        pool: PgPool,
+    ) -> sqlx::Result<()> {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(2)
+            .acquire_timeout(Duration::from_secs(5))
+            .connect_with(pool.connect_options().as_ref().clone())
+            .await?;
+        let holder = lock_chain_session(&pool, "test.near", "near").await?;
+
+        let waiters: Vec<_> = (0..3)
+            .map(|_| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    let lock = lock_chain_session(&pool, "test.near", "near").await?;
+                    unlock_chain(lock).await;
+                    Ok::<_, sqlx::Error>(())
+                })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        // The holder still gets a connection for its writes
+        sqlx::query("SELECT 1").execute(&pool).await?;
+
+        unlock_chain(holder).await;
+        for waiter in waiters {
+            tokio::time::timeout(Duration::from_secs(5), waiter)
+                .await
+                .expect("Waiting fills should take the lock in turn")
+                .unwrap()?;
+        }
+
+        Ok(())
+    }
+}
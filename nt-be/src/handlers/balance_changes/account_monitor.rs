@@ -1,24 +1,52 @@
 use near_api::NetworkConfig;
 use sqlx::PgPool;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 
+use super::account_lock::{try_lock_account, unlock_account};
 use super::balance::ft::get_balance_at_block as get_ft_balance;
-use super::gap_filler::{fill_gaps, insert_snapshot_record};
-use super::token_discovery::snapshot_intents_tokens;
+use super::balance::near::is_lockup_account;
+use super::circuit_breaker::is_unanswered;
+use super::fill_cancellation;
+use super::gap_detector::find_gaps;
+use super::gap_filler::{
+    FilledGap, GapFillerError, fill_gaps_with_indexer, insert_snapshot_record,
+};
+use super::indexer_source::IndexerSource;
+use super::lockup_vesting::{LOCKUP_LOCKED_TOKEN_ID, snapshot_vesting};
+use super::monitor_progress::{MonitorProgress, ProgressSender, publish};
+use super::rpc_budget;
+use super::token_discovery::{
+    TokenClassification, classify_token, ft_tokens_from_transaction_logs, gather_token_signals,
+    snapshot_intents_tokens,
+};
+use crate::utils::fill_config::FillConfig;
 
 /// Run one cycle of monitoring for all enabled accounts
 ///
 /// This function:
 /// 1. Queries all enabled accounts from monitored_accounts table
 /// 2. For each account:
-///    - Gets all known tokens for that account from balance_changes
-///    - Runs gap filling for each token up to the specified block
+///    - Runs gap filling for each known token up to the specified block (see `fill_all_tokens`)
 ///    - Updates last_synced_at timestamp after processing
+///    - Skips the account if it is locked (e.g. by an admin rebuild)
 /// 3. Handles errors gracefully, continuing with next account if one fails
+///
+/// The gap to present is searched at most up to `up_to_block - head_lag_blocks`
+/// (see `gap_filler::fill_gaps_with_head_lag`). Gap searches are narrowed with
+/// `indexer` when given (see `gap_filler::fill_gaps_with_indexer`). A `MonitorProgress` event is
+/// published to `progress` after each token is filled.
+///
+/// Inside `rpc_budget::with_budget`, the cycle stops after the account that exhausted
+/// the budget; the remaining accounts were synced least recently, so the next cycle
+/// starts with them.
 pub async fn run_monitor_cycle(
     pool: &PgPool,
     network: &NetworkConfig,
+    config: &FillConfig,
     up_to_block: i64,
+    head_lag_blocks: u64,
+    indexer: Option<&dyn IndexerSource>,
+    progress: Option<&ProgressSender>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Get all enabled monitored accounts
     let accounts = sqlx::query!(
@@ -44,142 +72,476 @@ pub async fn run_monitor_cycle(
     for account in accounts {
         let account_id = &account.account_id;
 
-        // Get all unique tokens for this account (excluding nulls which shouldn't happen but be safe)
-        let mut tokens: Vec<String> = sqlx::query_scalar(
+        let Some(lock) = try_lock_account(pool, account_id).await? else {
+            println!(
+                "  {}: Skipping, account is locked by another process",
+                account_id
+            );
+            continue;
+        };
+
+        // Stringify the error so nothing non-Send is held across the unlock
+        let result = monitor_account(
+            pool,
+            network,
+            config,
+            account_id,
+            up_to_block,
+            head_lag_blocks,
+            indexer,
+            progress,
+        )
+        .await
+        .map_err(|e| e.to_string());
+        unlock_account(lock).await;
+        result?;
+
+        if rpc_budget::is_exhausted() {
+            println!(
+                "RPC budget exhausted at {}, deferring the remaining accounts to the next cycle",
+                account_id
+            );
+            break;
+        }
+    }
+
+    println!("Monitor cycle complete");
+    Ok(())
+}
+
+/// Fill gaps and discover new tokens for a single account
+///
+/// Expects the caller to hold the account lock.
+#[allow(clippy::too_many_arguments)]
+async fn monitor_account(
+    pool: &PgPool,
+    network: &NetworkConfig,
+    config: &FillConfig,
+    account_id: &str,
+    up_to_block: i64,
+    head_lag_blocks: u64,
+    indexer: Option<&dyn IndexerSource>,
+    progress: Option<&ProgressSender>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let _fill = fill_cancellation::start_fill(account_id);
+
+    let results = fill_all_tokens(
+        pool,
+        network,
+        config,
+        account_id,
+        up_to_block,
+        head_lag_blocks,
+        indexer,
+        progress,
+    )
+    .await?;
+
+    let mut processed_tokens = 0;
+    let mut errors = Vec::new();
+
+    for (token_id, result) in &results {
+        match result {
+            Ok(filled) => {
+                if !filled.is_empty() {
+                    println!("    {}: Filled {} gaps", token_id, filled.len());
+                }
+                processed_tokens += 1;
+            }
+            Err(e) => {
+                eprintln!("    {}: Error filling gaps: {}", token_id, e);
+                errors.push(format!("{}: {}", token_id, e));
+            }
+        }
+    }
+
+    // Update last_synced_at even if some tokens had errors. An account that ran out of
+    // RPC budget had its turn too, so it doesn't hold back the others next cycle.
+    if processed_tokens > 0 || rpc_budget::is_exhausted() {
+        sqlx::query!(
             r#"
-            SELECT DISTINCT token_id
-            FROM balance_changes
-            WHERE account_id = $1 AND token_id IS NOT NULL
-            ORDER BY token_id
+            UPDATE monitored_accounts
+            SET last_synced_at = NOW()
+            WHERE account_id = $1
             "#,
+            account_id
         )
-        .bind(account_id)
-        .fetch_all(pool)
+        .execute(pool)
         .await?;
 
-        // If no tokens are tracked yet, ensure we at least check NEAR balance
-        if tokens.is_empty() {
-            println!("  {}: No known tokens, will seed NEAR balance", account_id);
-            tokens.push("near".to_string());
-        }
+        println!(
+            "  {}: Updated sync timestamp ({}/{} tokens processed)",
+            account_id,
+            processed_tokens,
+            results.len()
+        );
+    }
 
-        println!("  {}: Checking {} tokens", account_id, tokens.len());
+    if !errors.is_empty() {
+        eprintln!(
+            "  {}: {} errors occurred: {:?}",
+            account_id,
+            errors.len(),
+            errors
+        );
+    }
 
-        let mut processed_tokens = 0;
-        let mut errors = Vec::new();
+    if let Err(e) = fill_cancellation::check(account_id) {
+        println!("  {}: {}, skipping token discovery", account_id, e);
+        return Ok(());
+    }
+    if rpc_budget::is_exhausted() {
+        println!(
+            "  {}: RPC budget exhausted, skipping token discovery",
+            account_id
+        );
+        return Ok(());
+    }
 
-        for token_id in &tokens {
-            match fill_gaps(pool, network, account_id, token_id, up_to_block).await {
-                Ok(filled) => {
-                    if !filled.is_empty() {
-                        println!("    {}: Filled {} gaps", token_id, filled.len());
-                    }
-                    processed_tokens += 1;
-                }
-                Err(e) => {
-                    eprintln!("    {}: Error filling gaps: {}", token_id, e);
-                    errors.push(format!("{}: {}", token_id, e));
-                }
+    // Snapshot the vesting progress of lockup accounts. The error is stringified so
+    // nothing non-Send is held across later awaits.
+    if is_lockup_account(account_id) {
+        let snapshot_block = (up_to_block as u64).saturating_sub(head_lag_blocks);
+        let snapshot = snapshot_vesting(pool, network, account_id, snapshot_block)
+            .await
+            .map_err(|e| e.to_string());
+        match snapshot {
+            Ok(Some(split)) => println!(
+                "  {}: Recorded vesting snapshot at block {} (locked {}, unlocked {})",
+                account_id, snapshot_block, split.locked, split.unlocked
+            ),
+            Ok(None) => {}
+            Err(e) => eprintln!("  {}: Error snapshotting lockup vesting: {}", account_id, e),
+        }
+    }
+
+    // Discover new FT tokens from collected receipts
+    match discover_ft_tokens_from_receipts(pool, network, account_id, up_to_block).await {
+        Ok(discovered_count) => {
+            if discovered_count > 0 {
+                println!(
+                    "  {}: Discovered {} new FT tokens",
+                    account_id, discovered_count
+                );
             }
         }
+        Err(e) => {
+            eprintln!("  {}: Error discovering FT tokens: {}", account_id, e);
+        }
+    }
 
-        // Update last_synced_at even if some tokens had errors
-        if processed_tokens > 0 {
-            sqlx::query!(
-                r#"
-                UPDATE monitored_accounts
-                SET last_synced_at = NOW()
-                WHERE account_id = $1
-                "#,
-                account_id
+    // Discover intents tokens via mt_tokens_for_owner snapshot, then fill their
+    // history right away instead of waiting for the next cycle. The error is
+    // stringified so nothing non-Send is held across the fill.
+    let discovered = discover_intents_tokens(pool, network, account_id, up_to_block)
+        .await
+        .map_err(|e| e.to_string());
+    match discovered {
+        Ok(discovered) if !discovered.is_empty() => {
+            println!(
+                "  {}: Discovered {} new intents tokens",
+                account_id,
+                discovered.len()
+            );
+
+            let results = fill_tokens(
+                pool,
+                network,
+                config,
+                account_id,
+                discovered,
+                up_to_block,
+                head_lag_blocks,
+                indexer,
+                progress,
             )
-            .execute(pool)
             .await?;
+            for (token_id, result) in &results {
+                match result {
+                    Ok(filled) => println!("    {}: Filled {} gaps", token_id, filled.len()),
+                    Err(e) => eprintln!("    {}: Error filling gaps: {}", token_id, e),
+                }
+            }
+        }
+        Ok(_) => {}
+        Err(e) => {
+            eprintln!("  {}: Error discovering intents tokens: {}", account_id, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Fill gaps for every token of an account in one call
+///
+/// Processes the tokens returned by `get_monitored_tokens` plus enabled entries in
+/// `discovered_tokens` that have no balance changes yet. A failure for one token
+/// doesn't stop the others.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `network` - NEAR network configuration (archival RPC)
+/// * `config` - Settings passed into each token's fill
+/// * `account_id` - Account to process
+/// * `up_to_block` - Only process gaps up to this block height
+/// * `head_lag_blocks` - How far below `up_to_block` the gap to present is searched
+/// * `indexer` - Optional source of candidate blocks to narrow gap searches
+/// * `progress` - Where to publish a `MonitorProgress` event after each token
+///
+/// # Returns
+/// The filled records (or the error) for each processed token, keyed by token_id
+#[allow(clippy::too_many_arguments)]
+pub async fn fill_all_tokens(
+    pool: &PgPool,
+    network: &NetworkConfig,
+    config: &FillConfig,
+    account_id: &str,
+    up_to_block: i64,
+    head_lag_blocks: u64,
+    indexer: Option<&dyn IndexerSource>,
+    progress: Option<&ProgressSender>,
+) -> Result<BTreeMap<String, Result<Vec<FilledGap>, GapFillerError>>, sqlx::Error> {
+    let mut tokens: BTreeSet<String> = get_monitored_tokens(pool, account_id)
+        .await?
+        .into_iter()
+        .collect();
+
+    let discovered: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT token_id
+        FROM discovered_tokens
+        WHERE account_id = $1 AND enabled = true
+        "#,
+    )
+    .bind(account_id)
+    .fetch_all(pool)
+    .await?;
+    tokens.extend(discovered);
 
+    println!("  {}: Checking {} tokens", account_id, tokens.len());
+
+    fill_tokens(
+        pool,
+        network,
+        config,
+        account_id,
+        tokens,
+        up_to_block,
+        head_lag_blocks,
+        indexer,
+        progress,
+    )
+    .await
+}
+
+/// Fill gaps for the given tokens of an account, one after another
+///
+/// Stops early if the account's fills are cancelled. See `fill_all_tokens` for the
+/// arguments and result.
+#[allow(clippy::too_many_arguments)]
+async fn fill_tokens(
+    pool: &PgPool,
+    network: &NetworkConfig,
+    config: &FillConfig,
+    account_id: &str,
+    tokens: impl IntoIterator<Item = String>,
+    up_to_block: i64,
+    head_lag_blocks: u64,
+    indexer: Option<&dyn IndexerSource>,
+    progress: Option<&ProgressSender>,
+) -> Result<BTreeMap<String, Result<Vec<FilledGap>, GapFillerError>>, sqlx::Error> {
+    let mut results = BTreeMap::new();
+    for token_id in tokens {
+        if fill_cancellation::check(account_id).is_err() {
             println!(
-                "  {}: Updated sync timestamp ({}/{} tokens processed)",
-                account_id,
-                processed_tokens,
-                tokens.len()
+                "  {}: Fill cancelled, skipping remaining tokens",
+                account_id
             );
+            break;
         }
 
-        if !errors.is_empty() {
-            eprintln!(
-                "  {}: {} errors occurred: {:?}",
-                account_id,
-                errors.len(),
-                errors
+        let result = fill_gaps_with_indexer(
+            pool,
+            network,
+            config,
+            account_id,
+            &token_id,
+            up_to_block,
+            head_lag_blocks,
+            indexer,
+        )
+        .await;
+
+        if progress.is_some() {
+            let gaps_remaining = find_gaps(pool, account_id, &token_id, up_to_block)
+                .await?
+                .len();
+            publish(
+                progress,
+                MonitorProgress {
+                    account_id: account_id.to_string(),
+                    token_id: token_id.clone(),
+                    gaps_remaining,
+                    filled_this_cycle: result.as_ref().map_or(0, |filled| filled.len()),
+                },
             );
         }
 
-        // Discover new FT tokens from collected receipts
-        match discover_ft_tokens_from_receipts(pool, network, account_id, up_to_block).await {
-            Ok(discovered_count) => {
-                if discovered_count > 0 {
-                    println!(
-                        "  {}: Discovered {} new FT tokens",
-                        account_id, discovered_count
-                    );
-                }
-            }
-            Err(e) => {
-                eprintln!("  {}: Error discovering FT tokens: {}", account_id, e);
-            }
-        }
+        results.insert(token_id, result);
+    }
 
-        // Discover intents tokens via mt_tokens_for_owner snapshot
-        match discover_intents_tokens(pool, network, account_id, up_to_block).await {
-            Ok(discovered_count) => {
-                if discovered_count > 0 {
-                    println!(
-                        "  {}: Discovered {} new intents tokens",
-                        account_id, discovered_count
-                    );
-                }
-            }
-            Err(e) => {
-                eprintln!("  {}: Error discovering intents tokens: {}", account_id, e);
-            }
-        }
+    Ok(results)
+}
+
+/// Get the tokens to process for an account in a monitoring cycle
+///
+/// Returns all tokens with recorded balance changes, minus tokens disabled in
+/// `discovered_tokens` and the lockup vesting pseudo-token (see `lockup_vesting`).
+/// If no tokens are tracked yet, returns just NEAR so the account gets seeded.
+pub async fn get_monitored_tokens(
+    pool: &PgPool,
+    account_id: &str,
+) -> Result<Vec<String>, sqlx::Error> {
+    // Get all unique tokens for this account (excluding nulls which shouldn't happen but be safe)
+    let tokens: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT DISTINCT token_id
+        FROM balance_changes
+        WHERE account_id = $1 AND token_id IS NOT NULL AND token_id <> $2
+        ORDER BY token_id
+        "#,
+    )
+    .bind(account_id)
+    .bind(LOCKUP_LOCKED_TOKEN_ID)
+    .fetch_all(pool)
+    .await?;
+
+    // If no tokens are tracked yet, ensure we at least check NEAR balance
+    if tokens.is_empty() {
+        println!("  {}: No known tokens, will seed NEAR balance", account_id);
+        return Ok(vec!["near".to_string()]);
     }
 
-    println!("Monitor cycle complete");
+    let disabled_tokens: HashSet<String> = sqlx::query_scalar(
+        r#"
+        SELECT token_id
+        FROM discovered_tokens
+        WHERE account_id = $1 AND enabled = false
+        "#,
+    )
+    .bind(account_id)
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    if !disabled_tokens.is_empty() {
+        println!(
+            "  {}: Skipping {} disabled tokens",
+            account_id,
+            disabled_tokens.len()
+        );
+    }
+
+    Ok(tokens
+        .into_iter()
+        .filter(|t| !disabled_tokens.contains(t))
+        .collect())
+}
+
+/// Record a newly discovered token for an account
+///
+/// Suspected spam tokens are recorded as disabled so monitoring skips them by default.
+/// Existing entries (including manually toggled ones) are left untouched.
+async fn record_discovered_token(
+    pool: &PgPool,
+    account_id: &str,
+    token_id: &str,
+    classification: TokenClassification,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO discovered_tokens (account_id, token_id, enabled, classification)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (account_id, token_id) DO NOTHING
+        "#,
+    )
+    .bind(account_id)
+    .bind(token_id)
+    .bind(classification != TokenClassification::SuspectedSpam)
+    .bind(classification.as_str())
+    .execute(pool)
+    .await?;
+
     Ok(())
 }
 
+/// Max counterparties checked by one FT discovery run
+const DISCOVERY_BATCH_SIZE: i64 = 100;
+
 /// Discover FT tokens from counterparties in collected balance changes
 ///
 /// This function:
-/// 1. Gets distinct counterparties from recent NEAR balance changes
+/// 1. Gets distinct counterparties of NEAR balance changes recorded since the last run
 /// 2. Checks if each counterparty is an FT contract (by calling ft_balance_of)
-/// 3. For newly discovered FT tokens, seeds an initial balance change record
+/// 3. If it isn't, scans the event logs of its newest transaction for NEP-141 events
+///    involving the account (e.g. tokens swapped through a DEX), and checks those
+///    contracts the same way
+/// 4. For newly discovered FT tokens, seeds an initial balance change record
+///
+/// Progress is checkpointed in `monitored_accounts.last_discovery_change_id`. It tracks
+/// record ids rather than block heights because gap filling also inserts older blocks.
+/// It only advances past counterparties with a definitive answer: if the FT check goes
+/// unanswered (endpoint failure, open breaker, spent budget), its transaction can't be
+/// fetched for the same reasons, or classifying or seeding a discovered token fails,
+/// that counterparty and the ones after it are checked again next cycle.
 async fn discover_ft_tokens_from_receipts(
     pool: &PgPool,
     network: &NetworkConfig,
     account_id: &str,
     up_to_block: i64,
 ) -> Result<usize, Box<dyn std::error::Error>> {
-    // Get distinct counterparties from recent NEAR balance changes
-    // Exclude metadata values that are not actual account IDs
-    let counterparties: Vec<String> = sqlx::query_scalar(
+    let checkpoint: i64 = sqlx::query_scalar(
+        "SELECT last_discovery_change_id FROM monitored_accounts WHERE account_id = $1",
+    )
+    .bind(account_id)
+    .fetch_optional(pool)
+    .await?
+    .unwrap_or(0);
+
+    // Distinct counterparties of new NEAR balance changes, oldest first, with the
+    // newest record id and transaction of each. Metadata values that are not account
+    // ids are excluded.
+    let new_counterparties: Vec<(String, i64, Option<String>)> = sqlx::query_as(
         r#"
-        SELECT DISTINCT counterparty
+        SELECT
+            counterparty,
+            MAX(id) AS last_id,
+            (ARRAY_AGG(transaction_hashes[1] ORDER BY id DESC)
+                FILTER (WHERE cardinality(transaction_hashes) > 0))[1] AS tx_hash
         FROM balance_changes
-        WHERE account_id = $1 
+        WHERE account_id = $1
           AND token_id = 'near'
           AND counterparty != 'SNAPSHOT'
-        ORDER BY counterparty
-        LIMIT 100
+          AND id > $2
+        GROUP BY counterparty
+        ORDER BY last_id
+        LIMIT $3
         "#,
     )
     .bind(account_id)
+    .bind(checkpoint)
+    .bind(DISCOVERY_BATCH_SIZE)
     .fetch_all(pool)
     .await?;
 
-    if counterparties.is_empty() {
+    // Every counterparty whose newest record is at or below the last one returned is
+    // covered by this batch
+    let Some(&(_, batch_end, _)) = new_counterparties.last() else {
         return Ok(0);
-    }
+    };
+    // Newest record id of the first counterparty without a definitive answer
+    let mut first_failed: Option<i64> = None;
 
     // Get tokens we already know about
     let known_tokens: HashSet<String> = sqlx::query_scalar(
@@ -196,9 +558,9 @@ async fn discover_ft_tokens_from_receipts(
     .collect();
 
     // Check each counterparty to see if it's an FT contract
-    let mut discovered_tokens = HashSet::new();
+    let mut discovered_tokens: Vec<(String, i64)> = Vec::new();
 
-    for counterparty in counterparties {
+    'counterparties: for (counterparty, last_id, tx_hash) in new_counterparties {
         // Skip if we already track this token
         if known_tokens.contains(&counterparty) {
             continue;
@@ -208,23 +570,102 @@ async fn discover_ft_tokens_from_receipts(
         match get_ft_balance(pool, network, account_id, &counterparty, up_to_block as u64).await {
             Ok(_balance) => {
                 log::debug!("Counterparty {} is an FT contract", counterparty);
-                discovered_tokens.insert(counterparty);
+                discovered_tokens.push((counterparty, last_id));
+                continue;
+            }
+            Err(e) if e.is::<sqlx::Error>() => return Err(e),
+            Err(e) if is_unanswered(e.as_ref()) => {
+                // No answer yet; the rest of the batch waits for the next cycle too
+                log::warn!(
+                    "FT check of {} for {} went unanswered: {} - retrying next cycle",
+                    counterparty,
+                    account_id,
+                    e
+                );
+                first_failed = Some(last_id);
+                break;
             }
-            Err(_) => {
-                // Not an FT contract, or error querying - skip it
-                log::debug!("Counterparty {} is not an FT contract", counterparty);
+            Err(e) => {
+                log::debug!("Counterparty {} is not an FT contract: {}", counterparty, e);
             }
         }
-    }
 
-    if discovered_tokens.is_empty() {
-        return Ok(0);
+        // Not a token itself; the transaction may still have moved tokens of the account
+        let Some(tx_hash) = tx_hash else {
+            continue;
+        };
+        let logged_tokens =
+            match ft_tokens_from_transaction_logs(network, &tx_hash, account_id).await {
+                Ok(tokens) => tokens,
+                Err(e) if is_unanswered(e.as_ref()) => {
+                    log::warn!(
+                        "Fetching transaction {} for {} went unanswered: {} - retrying next cycle",
+                        tx_hash,
+                        account_id,
+                        e
+                    );
+                    first_failed = Some(last_id);
+                    break;
+                }
+                Err(e) => {
+                    log::debug!("Failed to scan logs of transaction {}: {}", tx_hash, e);
+                    continue;
+                }
+            };
+
+        for token_contract in logged_tokens {
+            if known_tokens.contains(&token_contract)
+                || discovered_tokens
+                    .iter()
+                    .any(|(token, _)| *token == token_contract)
+            {
+                continue;
+            }
+
+            // Events can be forged by any contract, so require a working ft_balance_of too
+            match get_ft_balance(
+                pool,
+                network,
+                account_id,
+                &token_contract,
+                up_to_block as u64,
+            )
+            .await
+            {
+                Ok(_balance) => {
+                    log::debug!(
+                        "FT contract {} logged events in transaction {}",
+                        token_contract,
+                        tx_hash
+                    );
+                    discovered_tokens.push((token_contract, last_id));
+                }
+                Err(e) if e.is::<sqlx::Error>() => return Err(e),
+                Err(e) if is_unanswered(e.as_ref()) => {
+                    log::warn!(
+                        "FT check of {} for {} went unanswered: {} - retrying next cycle",
+                        token_contract,
+                        account_id,
+                        e
+                    );
+                    first_failed = Some(last_id);
+                    break 'counterparties;
+                }
+                Err(e) => {
+                    log::debug!(
+                        "Logged contract {} is not an FT contract: {}",
+                        token_contract,
+                        e
+                    );
+                }
+            }
+        }
     }
 
     // For each discovered FT token, insert it into monitored tokens list
     // The next monitoring cycle will automatically fill gaps for these tokens
-    let seeded_count = discovered_tokens.len();
-    for token_contract in discovered_tokens {
+    let mut seeded_count = 0;
+    for (token_contract, last_id) in discovered_tokens {
         // Insert a marker record so the token appears in the distinct token_id query
         // Use the earliest block where we have data to start gap filling from there
         let earliest_block: Option<i64> = sqlx::query_scalar(
@@ -239,6 +680,22 @@ async fn discover_ft_tokens_from_receipts(
         .await?;
 
         if let Some(_start_block) = earliest_block {
+            // Classify before seeding; if the signals can't be gathered, the token is
+            // left unclassified and checked again next cycle
+            let classification =
+                match gather_token_signals(pool, network, account_id, &token_contract).await {
+                    Ok(signals) => classify_token(&signals),
+                    Err(e) => {
+                        log::warn!(
+                            "Failed to gather spam signals for {}: {} - retrying next cycle",
+                            token_contract,
+                            e
+                        );
+                        first_failed = Some(first_failed.map_or(last_id, |id| id.min(last_id)));
+                        continue;
+                    }
+                };
+
             // Insert a snapshot record using the shared helper
             match insert_snapshot_record(
                 pool,
@@ -255,6 +712,16 @@ async fn discover_ft_tokens_from_receipts(
                         token_contract,
                         account_id
                     );
+                    if classification == TokenClassification::SuspectedSpam {
+                        log::warn!(
+                            "FT token {} for account {} looks like spam - disabling monitoring",
+                            token_contract,
+                            account_id
+                        );
+                    }
+                    record_discovered_token(pool, account_id, &token_contract, classification)
+                        .await?;
+                    seeded_count += 1;
                 }
                 Err(e) => {
                     log::warn!(
@@ -263,39 +730,67 @@ async fn discover_ft_tokens_from_receipts(
                         up_to_block,
                         e
                     );
+                    first_failed = Some(first_failed.map_or(last_id, |id| id.min(last_id)));
                     continue;
                 }
             }
         }
     }
 
+    // Stop just short of the first failed counterparty's newest record, so it and
+    // everything after it are checked again; the answered ones before it stay covered
+    let checkpoint = first_failed.map_or(batch_end, |last_id| last_id - 1);
+    advance_discovery_checkpoint(pool, account_id, checkpoint).await?;
+
     Ok(seeded_count)
 }
 
+/// Record that FT discovery has checked balance changes up to `change_id`
+async fn advance_discovery_checkpoint(
+    pool: &PgPool,
+    account_id: &str,
+    change_id: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        UPDATE monitored_accounts
+        SET last_discovery_change_id = GREATEST(last_discovery_change_id, $2)
+        WHERE account_id = $1
+        "#,
+    )
+    .bind(account_id)
+    .bind(change_id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
 /// Discover intents tokens via mt_tokens_for_owner snapshot
 ///
 /// This function:
 /// 1. Calls mt_tokens_for_owner on intents.near to get all tokens held by the account
 /// 2. For newly discovered intents tokens, seeds an initial balance change record
-/// 3. The next monitoring cycle will automatically fill gaps for these tokens
+///
+/// # Returns
+/// The newly seeded intents tokens
 async fn discover_intents_tokens(
     pool: &PgPool,
     network: &NetworkConfig,
     account_id: &str,
     up_to_block: i64,
-) -> Result<usize, Box<dyn std::error::Error>> {
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
     // Get current intents tokens for this account
     let intents_tokens = match snapshot_intents_tokens(network, account_id).await {
         Ok(tokens) => tokens,
         Err(e) => {
             // Not all accounts have intents tokens - this is expected
             log::debug!("No intents tokens for {}: {}", account_id, e);
-            return Ok(0);
+            return Ok(Vec::new());
         }
     };
 
     if intents_tokens.is_empty() {
-        return Ok(0);
+        return Ok(Vec::new());
     }
 
     // Get tokens we already know about
@@ -319,13 +814,13 @@ async fn discover_intents_tokens(
         .collect();
 
     if new_tokens.is_empty() {
-        return Ok(0);
+        return Ok(Vec::new());
     }
 
     println!("    Discovered {} new intents tokens", new_tokens.len());
 
     // For each new intents token, insert a snapshot record
-    let mut seeded_count = 0;
+    let mut seeded = Vec::new();
     for token_id in new_tokens {
         match insert_snapshot_record(pool, network, account_id, &token_id, up_to_block as u64).await
         {
@@ -335,7 +830,9 @@ async fn discover_intents_tokens(
                     token_id,
                     account_id
                 );
-                seeded_count += 1;
+                record_discovered_token(pool, account_id, &token_id, TokenClassification::Legit)
+                    .await?;
+                seeded.push(token_id);
             }
             Err(e) => {
                 log::warn!(
@@ -348,12 +845,14 @@ async fn discover_intents_tokens(
         }
     }
 
-    Ok(seeded_count)
+    Ok(seeded)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::test_utils::spawn_mock_rpc;
+    use sqlx::types::chrono::{DateTime, Utc};
 
     #[tokio::test]
     async fn test_monitor_cycle_with_no_accounts() {
@@ -361,7 +860,616 @@ mod tests {
         let network = NetworkConfig::mainnet();
 
         // Should not error with no accounts
-        let result = run_monitor_cycle(&state.db_pool, &network, 177_000_000).await;
+        let result = run_monitor_cycle(
+            &state.db_pool,
+            &network,
+            &FillConfig::default(),
+            177_000_000,
+            0,
+            None,
+            None,
+        )
+        .await;
         assert!(result.is_ok());
     }
+
+    async fn insert_balance_change(
+        pool: &PgPool,
+        account_id: &str,
+        token_id: &str,
+        block_height: i64,
+        before: &str,
+        after: &str,
+    ) -> sqlx::Result<()> {
+        use super::super::gap_filler::block_timestamp_to_datetime;
+        use sqlx::types::BigDecimal;
+        use std::str::FromStr;
+
+        let before = BigDecimal::from_str(before).unwrap();
+        let after = BigDecimal::from_str(after).unwrap();
+        let block_timestamp = block_height * 1_000_000_000;
+
+        sqlx::query(
+            r#"
+            INSERT INTO balance_changes
+            (account_id, token_id, block_height, block_timestamp, block_time, amount, balance_before, balance_after, counterparty, actions, raw_data)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, 'sender.near', '{}', '{}')
+            "#,
+        )
+        .bind(account_id)
+        .bind(token_id)
+        .bind(block_height)
+        .bind(block_timestamp)
+        .bind(block_timestamp_to_datetime(block_timestamp))
+        .bind(&after - &before)
+        .bind(&before)
+        .bind(&after)
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_get_monitored_tokens_skips_disabled(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO monitored_accounts (account_id) VALUES ('test.near')")
+            .execute(&pool)
+            .await?;
+        insert_balance_change(&pool, "test.near", "near", 100, "0", "5").await?;
+        insert_balance_change(&pool, "test.near", "spam.near", 100, "0", "1000").await?;
+
+        sqlx::query(
+            "INSERT INTO discovered_tokens (account_id, token_id, enabled) VALUES ('test.near', 'spam.near', false)",
+        )
+        .execute(&pool)
+        .await?;
+
+        let tokens = get_monitored_tokens(&pool, "test.near").await?;
+        assert_eq!(tokens, vec!["near".to_string()]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_disabled_token_not_filled_in_cycle(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO monitored_accounts (account_id) VALUES ('test.near')")
+            .execute(&pool)
+            .await?;
+
+        // Two records with a gap between them (1000 -> 400)
+        insert_balance_change(&pool, "test.near", "spam.near", 100, "0", "1000").await?;
+        insert_balance_change(&pool, "test.near", "spam.near", 200, "400", "500").await?;
+
+        sqlx::query(
+            "INSERT INTO discovered_tokens (account_id, token_id, enabled) VALUES ('test.near', 'spam.near', false)",
+        )
+        .execute(&pool)
+        .await?;
+
+        let network = NetworkConfig::mainnet();
+        run_monitor_cycle(&pool, &network, &FillConfig::default(), 300, 0, None, None)
+            .await
+            .unwrap();
+
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM balance_changes WHERE account_id = 'test.near' AND token_id = 'spam.near'",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(count, 2, "Disabled token should not be gap-filled");
+
+        // No token was processed, so the account should not be marked as synced
+        let last_synced_at: Option<DateTime<Utc>> = sqlx::query_scalar(
+            "SELECT last_synced_at FROM monitored_accounts WHERE account_id = 'test.near'",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert!(last_synced_at.is_none());
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_fill_all_tokens_processes_near_and_ft(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO monitored_accounts (account_id) VALUES ('test.near')")
+            .execute(&pool)
+            .await?;
+        insert_balance_change(&pool, "test.near", "near", 100, "0", "5").await?;
+        insert_balance_change(&pool, "test.near", "usdc.near", 100, "0", "10").await?;
+        insert_balance_change(&pool, "test.near", "spam.near", 100, "0", "1000").await?;
+        sqlx::query(
+            "INSERT INTO discovered_tokens (account_id, token_id, enabled) VALUES ('test.near', 'spam.near', false)",
+        )
+        .execute(&pool)
+        .await?;
+
+        let network = NetworkConfig::mainnet();
+        let results = fill_all_tokens(
+            &pool,
+            &network,
+            &FillConfig::default(),
+            "test.near",
+            100,
+            0,
+            None,
+            None,
+        )
+        .await?;
+
+        let processed: Vec<&String> = results.keys().collect();
+        assert_eq!(processed, vec!["near", "usdc.near"]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_monitor_cycle_publishes_progress(pool: PgPool) -> sqlx::Result<()> {
+        use super::super::monitor_progress::progress_channel;
+        use near_api::RPCEndpoint;
+
+        sqlx::query("INSERT INTO monitored_accounts (account_id) VALUES ('test.near')")
+            .execute(&pool)
+            .await?;
+        insert_balance_change(&pool, "test.near", "near", 100, "0", "5").await?;
+
+        // Unreachable RPC: the fill fails, but progress is still reported
+        let network = NetworkConfig {
+            rpc_endpoints: vec![RPCEndpoint::new("http://127.0.0.1:1/".parse().unwrap())],
+            ..NetworkConfig::mainnet()
+        };
+        let progress = progress_channel();
+        let mut subscriber = progress.subscribe();
+
+        run_monitor_cycle(
+            &pool,
+            &network,
+            &FillConfig::default(),
+            100,
+            0,
+            None,
+            Some(&progress),
+        )
+        .await
+        .unwrap();
+
+        let event = subscriber
+            .try_recv()
+            .expect("Should publish a progress event");
+        assert_eq!(event.account_id, "test.near");
+        assert_eq!(event.token_id, "near");
+        assert_eq!(event.gaps_remaining, 0);
+        assert_eq!(event.filled_this_cycle, 0);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_discovery_skips_already_checked_changes(pool: PgPool) -> sqlx::Result<()> {
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        sqlx::query("INSERT INTO monitored_accounts (account_id) VALUES ('discovery.near')")
+            .execute(&pool)
+            .await?;
+        insert_balance_change(&pool, "discovery.near", "near", 100, "0", "5").await?;
+
+        // Node that counts requests; the counterparty has no FT methods
+        let requests = Arc::new(AtomicUsize::new(0));
+        let rpc = {
+            let requests = requests.clone();
+            move |axum::Json(request): axum::Json<serde_json::Value>| {
+                requests.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    axum::Json(serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "result": {
+                            "error": "wasm execution failed with error: MethodResolveError(MethodNotFound)",
+                            "logs": [],
+                            "block_height": 200,
+                            "block_hash": "11111111111111111111111111111111"
+                        }
+                    }))
+                }
+            }
+        };
+        let network = spawn_mock_rpc(rpc).await;
+
+        let discover = || async {
+            discover_ft_tokens_from_receipts(&pool, &network, "discovery.near", 200)
+                .await
+                .unwrap()
+        };
+
+        assert_eq!(discover().await, 0);
+        let first_run = requests.load(Ordering::SeqCst);
+        assert!(first_run > 0, "The new counterparty should be checked");
+
+        assert_eq!(discover().await, 0);
+        assert_eq!(
+            requests.load(Ordering::SeqCst),
+            first_run,
+            "Nothing new was recorded, so nothing should be checked"
+        );
+
+        // A change filled at an older block is still new to discovery
+        insert_balance_change(&pool, "discovery.near", "near", 50, "0", "1").await?;
+        discover().await;
+        assert!(requests.load(Ordering::SeqCst) > first_run);
+
+        Ok(())
+    }
+
+    /// JSON-RPC node for a swap through `dex.near` that paid out `swapped-token.near`
+    async fn swap_rpc(
+        axum::Json(request): axum::Json<serde_json::Value>,
+    ) -> axum::Json<serde_json::Value> {
+        use near_primitives::views::{BlockHeaderView, BlockView};
+        use serde_json::json;
+
+        let params = &request["params"];
+        let outcome = |executor_id: &str, logs: &[&str]| {
+            json!({
+                "proof": [],
+                "block_hash": "11111111111111111111111111111111",
+                "id": "11111111111111111111111111111111",
+                "outcome": {
+                    "logs": logs,
+                    "receipt_ids": [],
+                    "gas_burnt": 0,
+                    "tokens_burnt": "0",
+                    "executor_id": executor_id,
+                    "status": {"SuccessValue": ""},
+                    "metadata": {"version": 1, "gas_profile": null}
+                }
+            })
+        };
+
+        let result = match request["method"].as_str() {
+            Some("tx") => json!({
+                "final_execution_status": "FINAL",
+                "status": {"SuccessValue": ""},
+                "transaction": {
+                    "signer_id": "discovery.near",
+                    "public_key": "ed25519:11111111111111111111111111111111",
+                    "nonce": 1,
+                    "receiver_id": "dex.near",
+                    "actions": [],
+                    "priority_fee": 0,
+                    "signature": "ed25519:1111111111111111111111111111111111111111111111111111111111111111",
+                    "hash": "11111111111111111111111111111111"
+                },
+                "transaction_outcome": outcome("discovery.near", &[]),
+                "receipts_outcome": [outcome(
+                    "swapped-token.near",
+                    &[r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_transfer","data":[{"old_owner_id":"dex.near","new_owner_id":"discovery.near","amount":"5"}]}"#],
+                )]
+            }),
+            Some("block") => serde_json::to_value(BlockView {
+                author: "test.near".parse().unwrap(),
+                header: BlockHeaderView {
+                    height: 200,
+                    timestamp: 200_000_000_000,
+                    timestamp_nanosec: 200_000_000_000,
+                    ..Default::default()
+                },
+                chunks: vec![],
+            })
+            .unwrap(),
+            _ => {
+                let value = match (
+                    params["account_id"].as_str(),
+                    params["method_name"].as_str(),
+                ) {
+                    (Some("swapped-token.near"), Some("ft_balance_of")) => json!("5"),
+                    (Some("swapped-token.near"), Some("ft_metadata")) => json!({
+                        "spec": "ft-1.0.0",
+                        "name": "Swapped",
+                        "symbol": "SWP",
+                        "icon": null,
+                        "reference": null,
+                        "reference_hash": null,
+                        "decimals": 6
+                    }),
+                    _ => {
+                        return axum::Json(json!({
+                            "jsonrpc": "2.0",
+                            "id": request["id"],
+                            "result": {
+                                "error": "wasm execution failed with error: MethodResolveError(MethodNotFound)",
+                                "logs": [],
+                                "block_height": 200,
+                                "block_hash": "11111111111111111111111111111111"
+                            }
+                        }));
+                    }
+                };
+                json!({
+                    "result": serde_json::to_vec(&value).unwrap(),
+                    "logs": [],
+                    "block_height": 200,
+                    "block_hash": "11111111111111111111111111111111"
+                })
+            }
+        };
+
+        axum::Json(json!({"jsonrpc": "2.0", "id": request["id"], "result": result}))
+    }
+
+    #[sqlx::test]
+    async fn test_discovery_finds_tokens_in_transaction_logs(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO monitored_accounts (account_id) VALUES ('discovery.near')")
+            .execute(&pool)
+            .await?;
+        insert_balance_change(&pool, "discovery.near", "near", 100, "5", "4").await?;
+        sqlx::query(
+            "UPDATE balance_changes SET counterparty = 'dex.near', transaction_hashes = ARRAY['11111111111111111111111111111111']",
+        )
+        .execute(&pool)
+        .await?;
+
+        let network = spawn_mock_rpc(swap_rpc).await;
+
+        assert_eq!(
+            discover_ft_tokens_from_receipts(&pool, &network, "discovery.near", 200)
+                .await
+                .unwrap(),
+            1
+        );
+
+        let discovered: Vec<String> = sqlx::query_scalar(
+            "SELECT token_id FROM discovered_tokens WHERE account_id = 'discovery.near'",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(discovered, vec!["swapped-token.near".to_string()]);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_discovery_rechecks_unanswered_counterparties(pool: PgPool) -> sqlx::Result<()> {
+        use axum::http::StatusCode;
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        sqlx::query("INSERT INTO monitored_accounts (account_id) VALUES ('discovery.near')")
+            .execute(&pool)
+            .await?;
+        insert_balance_change(&pool, "discovery.near", "near", 100, "0", "5").await?;
+
+        // Node that is down
+        let requests = Arc::new(AtomicUsize::new(0));
+        let rpc = {
+            let requests = requests.clone();
+            move || {
+                requests.fetch_add(1, Ordering::SeqCst);
+                async { StatusCode::SERVICE_UNAVAILABLE }
+            }
+        };
+        let network = spawn_mock_rpc(rpc).await;
+
+        for _ in 0..2 {
+            let before = requests.load(Ordering::SeqCst);
+            assert_eq!(
+                discover_ft_tokens_from_receipts(&pool, &network, "discovery.near", 200)
+                    .await
+                    .unwrap(),
+                0
+            );
+            assert!(
+                requests.load(Ordering::SeqCst) > before,
+                "The unanswered counterparty should be checked again"
+            );
+        }
+
+        let checkpoint: i64 = sqlx::query_scalar(
+            "SELECT last_discovery_change_id FROM monitored_accounts WHERE account_id = 'discovery.near'",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(checkpoint, 0);
+
+        Ok(())
+    }
+
+    const INTENTS_BTC: &str = "intents.near:nep141:btc.omft.near";
+
+    /// JSON-RPC node for an account holding 5 NEAR and 32868 intents BTC at every block
+    async fn intents_holder_rpc(
+        axum::Json(request): axum::Json<serde_json::Value>,
+    ) -> axum::Json<serde_json::Value> {
+        use near_primitives::views::{BlockHeaderView, BlockView};
+        use serde_json::json;
+
+        let params = &request["params"];
+        let block_height = params["block_id"].as_u64().unwrap_or(1_000_000);
+        let call_result = |value: serde_json::Value| {
+            json!({
+                "result": serde_json::to_vec(&value).unwrap(),
+                "logs": [],
+                "block_height": block_height,
+                "block_hash": "11111111111111111111111111111111"
+            })
+        };
+
+        let result = if request["method"] == "EXPERIMENTAL_changes" {
+            json!({"block_hash": "11111111111111111111111111111111", "changes": []})
+        } else if request["method"] == "block" {
+            serde_json::to_value(BlockView {
+                author: "validator.near".parse().unwrap(),
+                header: BlockHeaderView {
+                    height: block_height,
+                    timestamp: block_height * 1_000_000_000,
+                    timestamp_nanosec: block_height * 1_000_000_000,
+                    ..Default::default()
+                },
+                chunks: vec![],
+            })
+            .unwrap()
+        } else {
+            match (
+                params["request_type"].as_str(),
+                params["method_name"].as_str(),
+            ) {
+                (Some("view_account"), _) => json!({
+                    "amount": "5000000000000000000000000",
+                    "locked": "0",
+                    "code_hash": "11111111111111111111111111111111",
+                    "storage_usage": 100,
+                    "storage_paid_at": 0,
+                    "block_height": block_height,
+                    "block_hash": "11111111111111111111111111111111"
+                }),
+                (_, Some("mt_tokens_for_owner")) => {
+                    call_result(json!([{"token_id": "nep141:btc.omft.near"}]))
+                }
+                (_, Some("mt_balance_of")) => call_result(json!("32868")),
+                _ => {
+                    return axum::Json(json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": {"code": -32000, "message": "Server error", "data": "unsupported"}
+                    }));
+                }
+            }
+        };
+
+        axum::Json(json!({"jsonrpc": "2.0", "id": request["id"], "result": result}))
+    }
+
+    #[sqlx::test]
+    async fn test_monitor_cycle_fills_intents_tokens(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO monitored_accounts (account_id) VALUES ('test.near')")
+            .execute(&pool)
+            .await?;
+        insert_balance_change(&pool, "test.near", "near", 900_000, "0", "5").await?;
+
+        let network = spawn_mock_rpc(intents_holder_rpc).await;
+
+        run_monitor_cycle(
+            &pool,
+            &network,
+            &FillConfig::default(),
+            1_000_000,
+            0,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let blocks: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT block_height
+            FROM balance_changes
+            WHERE account_id = 'test.near' AND token_id = $1 AND balance_after = 32868
+            ORDER BY block_height
+            "#,
+        )
+        .bind(INTENTS_BTC)
+        .fetch_all(&pool)
+        .await?;
+
+        assert_eq!(
+            blocks.last(),
+            Some(&1_000_000),
+            "Discovered intents token should be seeded at the monitored block"
+        );
+        assert!(
+            blocks.len() > 1,
+            "Intents history should be filled in the same cycle, got records at {:?}",
+            blocks
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_rpc_budget_stops_cycle_and_resumes_next(pool: PgPool) -> sqlx::Result<()> {
+        use super::super::rpc_budget::{RpcBudget, with_budget};
+        use std::sync::Arc;
+
+        // first.near was synced least recently, so it goes first
+        sqlx::query(
+            r#"
+            INSERT INTO monitored_accounts (account_id, last_synced_at) VALUES
+            ('first.near', '2025-01-01T00:00:00Z'),
+            ('second.near', '2025-01-02T00:00:00Z')
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+        insert_balance_change(&pool, "first.near", "near", 900_000, "0", "5").await?;
+        insert_balance_change(&pool, "second.near", "near", 900_000, "0", "5").await?;
+
+        let network = spawn_mock_rpc(intents_holder_rpc).await;
+
+        let last_synced_at = |account_id: &'static str| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query_scalar::<_, DateTime<Utc>>(
+                    "SELECT last_synced_at FROM monitored_accounts WHERE account_id = $1",
+                )
+                .bind(account_id)
+                .fetch_one(&pool)
+                .await
+            }
+        };
+        let initial_second = last_synced_at("second.near").await?;
+
+        let budget = Arc::new(RpcBudget::new(Some(2)));
+        with_budget(
+            budget.clone(),
+            run_monitor_cycle(
+                &pool,
+                &network,
+                &FillConfig::default(),
+                1_000_000,
+                0,
+                None,
+                None,
+            ),
+        )
+        .await
+        .unwrap();
+
+        assert!(budget.is_exhausted());
+        assert_eq!(budget.spent(), 2);
+        let first_synced = last_synced_at("first.near").await?;
+        assert!(first_synced > initial_second, "first.near had its turn");
+        assert_eq!(
+            last_synced_at("second.near").await?,
+            initial_second,
+            "Cycle should stop before second.near"
+        );
+
+        let budget = Arc::new(RpcBudget::new(Some(2)));
+        with_budget(
+            budget.clone(),
+            run_monitor_cycle(
+                &pool,
+                &network,
+                &FillConfig::default(),
+                1_000_000,
+                0,
+                None,
+                None,
+            ),
+        )
+        .await
+        .unwrap();
+
+        assert!(
+            last_synced_at("second.near").await? > initial_second,
+            "Next cycle should resume with second.near"
+        );
+        assert_eq!(
+            last_synced_at("first.near").await?,
+            first_synced,
+            "Budget ran out before first.near's turn came again"
+        );
+
+        Ok(())
+    }
 }
@@ -3,12 +3,59 @@
 //! Functions to query FT token balances at specific block heights via RPC.
 //! Returns decimal-adjusted balance values for storage and display.
 
-use near_api::types::json::U128;
 use near_api::{AccountId, Contract, NetworkConfig, Reference};
+use serde_json::Value;
+use serde_json::value::RawValue;
 use sqlx::PgPool;
 use std::str::FromStr;
 
-use crate::handlers::balance_changes::counterparty::{convert_raw_to_decimal, ensure_ft_metadata};
+use crate::handlers::balance_changes::circuit_breaker::box_rpc_error;
+use crate::handlers::balance_changes::counterparty::convert_raw_to_decimal;
+use crate::utils::decimals::decimals;
+
+/// Error returned when `ft_balance_of` returns something other than an integer balance
+#[derive(Debug)]
+pub struct UnsupportedFtReturn {
+    pub token_contract: String,
+    /// The returned JSON, as sent by the contract
+    pub value: String,
+}
+
+impl std::fmt::Display for UnsupportedFtReturn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unsupported ft_balance_of return value from {}: {}",
+            self.token_contract, self.value
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedFtReturn {}
+
+/// Parse an `ft_balance_of` result into the raw balance
+///
+/// NEP-141 specifies a U128 JSON string, but some contracts return a plain JSON
+/// number instead. Both are accepted; anything else (fractions, negative numbers,
+/// objects, ...) is an `UnsupportedFtReturn`.
+///
+/// Takes the raw JSON text: `serde_json::Value` would round numbers above `u64::MAX`
+/// through `f64`, so numbers are parsed from their digits instead.
+pub fn parse_ft_balance(token_contract: &str, raw: &str) -> Result<u128, UnsupportedFtReturn> {
+    let raw = raw.trim();
+    let digits = match serde_json::from_str::<Value>(raw) {
+        Ok(Value::String(s)) => Some(s),
+        Ok(Value::Number(_)) => Some(raw.to_string()),
+        _ => None,
+    };
+
+    digits
+        .and_then(|digits| digits.trim().parse::<u128>().ok())
+        .ok_or_else(|| UnsupportedFtReturn {
+            token_contract: token_contract.to_string(),
+            value: raw.to_string(),
+        })
+}
 
 /// Query fungible token balance at a specific block height
 ///
@@ -34,8 +81,8 @@ pub async fn get_balance_at_block(
     token_contract: &str,
     block_height: u64,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    // Ensure metadata is cached and get decimals for conversion
-    let decimals = ensure_ft_metadata(pool, network, token_contract).await?;
+    // Decimals for conversion, from the registry (queried once per token)
+    let decimals = decimals(pool, network, token_contract).await?;
 
     let token_contract_obj = AccountId::from_str(token_contract)?;
     let max_retries = 10;
@@ -45,7 +92,7 @@ pub async fn get_balance_at_block(
 
         // Call ft_balance_of directly to get raw U128 value without conversion
         let contract = Contract(token_contract_obj.clone());
-        let result: Result<near_api::Data<serde_json::Value>, _> = contract
+        let result: Result<near_api::Data<Box<RawValue>>, _> = contract
             .call_function(
                 "ft_balance_of",
                 serde_json::json!({
@@ -69,14 +116,11 @@ pub async fn get_balance_at_block(
                     );
                 }
 
-                // Parse the raw U128 value from the contract response
-                // NEP-141 ft_balance_of returns a U128 which can be either a string or number in JSON
-                // Using near_api::types::json::U128 handles both formats automatically
-                let raw_balance: U128 = serde_json::from_value(data.data.clone())
-                    .map_err(|e| format!("Failed to deserialize U128 balance: {}", e))?;
+                // Parse the raw balance, accepting both string and number forms
+                let raw_balance = parse_ft_balance(token_contract, data.data.get())?;
 
-                // Convert raw U128 to decimal-adjusted value for storage
-                let decimal_balance = convert_raw_to_decimal(&raw_balance.0.to_string(), decimals)?;
+                // Convert raw balance to decimal-adjusted value for storage
+                let decimal_balance = convert_raw_to_decimal(&raw_balance.to_string(), decimals)?;
 
                 return Ok(decimal_balance);
             }
@@ -101,7 +145,8 @@ pub async fn get_balance_at_block(
                     }
                 } else {
                     // For other errors, fail immediately
-                    return Err(e.into());
+                    let e: Box<dyn std::error::Error> = box_rpc_error(e);
+                    return Err(e);
                 }
             }
         }
@@ -114,3 +159,77 @@ pub async fn get_balance_at_block(
     )
     .into())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::spawn_mock_rpc;
+    use axum::Json;
+    use serde_json::json;
+
+    const BLOCK: u64 = 42_000_000;
+
+    /// JSON-RPC node for a contract whose ft_balance_of returns a JSON number
+    /// above `u64::MAX`
+    async fn mock_rpc(Json(request): Json<Value>) -> Json<Value> {
+        let params = &request["params"];
+        let result = match params["method_name"].as_str() {
+            Some("ft_metadata") => serde_json::to_vec(&json!({
+                "spec": "ft-1.0.0",
+                "name": "Numeric Token",
+                "symbol": "NUM",
+                "decimals": 6
+            }))
+            .unwrap(),
+            Some("ft_balance_of") => b"25000000000000000001".to_vec(),
+            other => panic!("Unexpected method {:?}", other),
+        };
+
+        Json(json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": {
+                "result": result,
+                "logs": [],
+                "block_height": BLOCK,
+                "block_hash": "11111111111111111111111111111111"
+            }
+        }))
+    }
+
+    #[sqlx::test]
+    async fn test_numeric_ft_balance(pool: PgPool) -> sqlx::Result<()> {
+        let network = spawn_mock_rpc(mock_rpc).await;
+
+        let balance = get_balance_at_block(&pool, &network, "test.near", "numeric.near", BLOCK)
+            .await
+            .unwrap();
+
+        assert_eq!(balance, "25000000000000.000001");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_ft_balance_forms() {
+        assert_eq!(parse_ft_balance("t.near", r#""1000""#).unwrap(), 1000);
+        assert_eq!(parse_ft_balance("t.near", "1000").unwrap(), 1000);
+        assert_eq!(
+            parse_ft_balance("t.near", r#""340282366920938463463374607431768211455""#).unwrap(),
+            u128::MAX
+        );
+        // Numbers above u64::MAX keep every digit
+        assert_eq!(
+            parse_ft_balance("t.near", "18446744073709551617").unwrap(),
+            18_446_744_073_709_551_617
+        );
+        assert_eq!(
+            parse_ft_balance("t.near", "340282366920938463463374607431768211455").unwrap(),
+            u128::MAX
+        );
+
+        for unsupported in ["-1", "1.5", "1e21", "null", r#"{"balance": "1"}"#] {
+            let err = parse_ft_balance("t.near", unsupported).unwrap_err();
+            assert_eq!(err.value, unsupported);
+        }
+    }
+}
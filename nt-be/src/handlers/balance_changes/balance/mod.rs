@@ -7,13 +7,37 @@
 //! - NEAR Intents multi-tokens (via `intents` submodule)
 //!
 //! Uses the near-api crate with FastNEAR archival RPC for historical queries.
+//! Balances at a point in time are resolved to the last block at or before that
+//! time first (see `get_balance_at_time`). `get_all_balances_at_block` queries every
+//! token an account holds at a block at once, for portfolio views.
 
 pub mod ft;
 pub mod intents;
 pub mod near;
 
-use near_api::NetworkConfig;
+use bigdecimal::{BigDecimal, Zero};
+use futures::stream::{self, StreamExt};
+use moka::future::Cache;
+use near_api::{Chain, NetworkConfig};
+use once_cell::sync::Lazy;
 use sqlx::PgPool;
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+use super::account_monitor::get_monitored_tokens;
+use super::token_discovery::intents_tokens_at_block;
+use crate::handlers::block::timestamp::find_block_at_time;
+use crate::utils::network::primary_rpc_url;
+
+/// Max balance queries `get_all_balances_at_block` runs at once
+const ALL_BALANCES_CONCURRENCY: usize = 8;
+
+/// Resolved (RPC url, timestamp (ns)) -> block height lookups
+///
+/// A timestamp in the past always resolves to the same block, so entries never
+/// need to be invalidated. Keyed by RPC url so an operator-specified endpoint
+/// (see `custom_rpc_network`) can't serve or poison the archival network's entries.
+static BLOCK_AT_TIME_CACHE: Lazy<Cache<(String, i64), u64>> = Lazy::new(|| Cache::new(10_000));
 
 /// Query balance at a specific block height for any token type
 ///
@@ -45,6 +69,7 @@ pub async fn get_balance_at_block(
         token_id,
         block_height
     );
+    super::rpc_budget::spend()?;
     if token_id == "NEAR" || token_id == "near" {
         near::get_balance_at_block(network, account_id, block_height).await
     } else if token_id.contains(':') {
@@ -56,6 +81,141 @@ pub async fn get_balance_at_block(
     }
 }
 
+/// Query the balances of all token types of an account at a block
+///
+/// Gathers NEAR, the FT and intents tokens known for the account (recorded balance
+/// changes minus disabled tokens, see `get_monitored_tokens`) and the intents tokens
+/// it owned at the block (`mt_tokens_for_owner`), then queries their balances
+/// concurrently. Tokens whose balance query fails are logged and left out, as are
+/// zero balances other than NEAR.
+///
+/// # Arguments
+/// * `pool` - Database connection pool for the known tokens and FT metadata
+/// * `network` - The NEAR network configuration (use archival network for historical queries)
+/// * `account_id` - The NEAR account to query
+/// * `block_height` - The block height to query at
+///
+/// # Returns
+/// `(token_id, balance)` pairs, NEAR first, then ordered by token id
+pub async fn get_all_balances_at_block(
+    pool: &PgPool,
+    network: &NetworkConfig,
+    account_id: &str,
+    block_height: u64,
+) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let mut token_ids: BTreeSet<String> = get_monitored_tokens(pool, account_id)
+        .await?
+        .into_iter()
+        .filter(|token_id| !token_id.eq_ignore_ascii_case("near"))
+        .collect();
+    match intents_tokens_at_block(network, account_id, block_height).await {
+        Ok(intents_tokens) => token_ids.extend(intents_tokens),
+        Err(e) => log::warn!(
+            "Failed to list intents tokens of {} at block {}: {}",
+            account_id,
+            block_height,
+            e
+        ),
+    }
+
+    let near_balance =
+        get_balance_at_block(pool, network, account_id, "near", block_height).await?;
+
+    let balances: Vec<_> = stream::iter(token_ids)
+        .map(|token_id| async move {
+            let balance =
+                get_balance_at_block(pool, network, account_id, &token_id, block_height).await;
+            (token_id, balance.map_err(|e| e.to_string()))
+        })
+        .buffered(ALL_BALANCES_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut all = vec![("near".to_string(), near_balance)];
+    for (token_id, balance) in balances {
+        match balance {
+            Ok(balance) if is_zero(&balance) => {}
+            Ok(balance) => all.push((token_id, balance)),
+            Err(e) => log::warn!(
+                "Skipping {} of {} at block {}: {}",
+                token_id,
+                account_id,
+                block_height,
+                e
+            ),
+        }
+    }
+    Ok(all)
+}
+
+fn is_zero(balance: &str) -> bool {
+    BigDecimal::from_str(balance).is_ok_and(|balance| balance.is_zero())
+}
+
+/// `BLOCK_AT_TIME_CACHE` key of a timestamp resolved on `network`
+fn block_at_time_key(network: &NetworkConfig, timestamp: i64) -> (String, i64) {
+    (primary_rpc_url(network), timestamp)
+}
+
+/// Resolve the last block produced at or before a timestamp
+///
+/// Resolutions are cached per RPC url, except for times at or past the chain head
+/// (the head keeps moving).
+///
+/// # Returns
+/// The block height, or `None` if the time is before the first block
+pub async fn resolve_block_at_time(
+    network: &NetworkConfig,
+    timestamp: i64,
+) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+    let cache_key = block_at_time_key(network, timestamp);
+    if let Some(block_height) = BLOCK_AT_TIME_CACHE.get(&cache_key).await {
+        return Ok(Some(block_height));
+    }
+
+    let head = Chain::block().fetch_from(network).await?;
+    let head = (head.header.height, head.header.timestamp as i64);
+
+    let Some((block_height, _)) = find_block_at_time(network, timestamp, head).await? else {
+        return Ok(None);
+    };
+
+    if block_height != head.0 {
+        BLOCK_AT_TIME_CACHE.insert(cache_key, block_height).await;
+    }
+
+    Ok(Some(block_height))
+}
+
+/// Query balance at a point in time for any token type
+///
+/// Resolves the last block at or before `timestamp`, then queries the balance
+/// there with `get_balance_at_block`.
+///
+/// # Arguments
+/// * `pool` - Database connection pool for querying token metadata (needed for FT tokens)
+/// * `network` - The NEAR network configuration (use archival network for historical queries)
+/// * `account_id` - The NEAR account to query
+/// * `token_id` - Token identifier (see `get_balance_at_block` for format)
+/// * `timestamp` - Nanoseconds since the Unix epoch
+///
+/// # Returns
+/// Tuple of (block_height, balance), or `None` if the time is before the first block
+pub async fn get_balance_at_time(
+    pool: &PgPool,
+    network: &NetworkConfig,
+    account_id: &str,
+    token_id: &str,
+    timestamp: i64,
+) -> Result<Option<(u64, String)>, Box<dyn std::error::Error>> {
+    let Some(block_height) = resolve_block_at_time(network, timestamp).await? else {
+        return Ok(None);
+    };
+
+    let balance = get_balance_at_block(pool, network, account_id, token_id, block_height).await?;
+    Ok(Some((block_height, balance)))
+}
+
 /// Query balance change at a specific block (both before and after)
 ///
 /// # Arguments
@@ -90,6 +250,7 @@ pub async fn get_balance_change_at_block(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::network::{NetworkKind, build_network};
     use crate::utils::test_utils::init_test_state;
 
     #[tokio::test]
@@ -133,4 +294,84 @@ mod tests {
         assert_eq!(before, "6.1002111266305371");
         assert_eq!(after, "11.1002111266305371");
     }
+
+    #[sqlx::test]
+    async fn test_all_balances_at_block_include_intents(pool: sqlx::PgPool) -> sqlx::Result<()> {
+        let state = init_test_state().await;
+
+        // Block 165324279 has a 0.0002 BTC intents deposit to the treasury
+        let balances = get_all_balances_at_block(
+            &pool,
+            &state.archival_network,
+            "webassemblymusic-treasury.sputnik-dao.near",
+            165_324_280,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(balances[0].0, "near");
+        assert!(
+            balances
+                .iter()
+                .any(|(token_id, _)| token_id == "intents.near:nep141:btc.omft.near"),
+            "Expected the BTC intents token, got {:?}",
+            balances
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_balance_at_time() {
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+
+        let state = init_test_state().await;
+
+        // Timestamp of block 151386339 from test data
+        let timestamp = 1750097144159145697;
+        let result = get_balance_at_time(
+            &state.db_pool,
+            &state.archival_network,
+            "webassemblymusic-treasury.sputnik-dao.near",
+            "NEAR",
+            timestamp,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result, Some((151386339, "11.1002111266305371".to_string())));
+        assert_eq!(
+            BLOCK_AT_TIME_CACHE
+                .get(&block_at_time_key(&state.archival_network, timestamp))
+                .await,
+            Some(151386339),
+            "Resolution should be cached"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_block_at_time_cache_is_per_network() {
+        let archival = build_network(NetworkKind::Archival, &[]);
+        let unreachable = NetworkConfig {
+            rpc_endpoints: vec![near_api::RPCEndpoint::new(
+                "http://127.0.0.1:1/".parse().unwrap(),
+            )],
+            ..NetworkConfig::mainnet()
+        };
+        let timestamp = 1_600_000_000_000_000_000;
+        BLOCK_AT_TIME_CACHE
+            .insert(block_at_time_key(&archival, timestamp), 42)
+            .await;
+
+        assert_eq!(
+            resolve_block_at_time(&archival, timestamp).await.unwrap(),
+            Some(42)
+        );
+        assert!(
+            resolve_block_at_time(&unreachable, timestamp)
+                .await
+                .is_err(),
+            "Another network must not be served the archival network's resolution"
+        );
+    }
 }
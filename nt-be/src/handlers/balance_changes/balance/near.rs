@@ -4,10 +4,12 @@
 //! Balances are returned as human-readable NEAR strings (e.g., "11.1002" not "11100211126630537100000000")
 //! using 24 decimals, consistent with FT token decimal conversion.
 
-use near_api::{AccountId, NetworkConfig, Reference, Tokens};
+use near_api::types::json::U128;
+use near_api::{AccountId, Contract, NetworkConfig, Reference, Tokens};
 use std::str::FromStr;
 
 use crate::handlers::balance_changes::counterparty::convert_raw_to_decimal;
+use crate::utils::decimals::NEAR_DECIMALS;
 
 /// Query NEAR native token balance at a specific block height, converted to human-readable format
 ///
@@ -52,7 +54,7 @@ pub async fn get_balance_at_block(
 
                 // Convert yoctoNEAR to human-readable NEAR (24 decimals)
                 let yocto_near = balance.total.as_yoctonear().to_string();
-                let decimal_near = convert_raw_to_decimal(&yocto_near, 24)?;
+                let decimal_near = convert_raw_to_decimal(&yocto_near, NEAR_DECIMALS)?;
 
                 return Ok(decimal_near);
             }
@@ -89,3 +91,136 @@ pub async fn get_balance_at_block(
     )
     .into())
 }
+
+/// NEAR balance of an account split into its components
+///
+/// Amounts are human-readable NEAR strings, like `get_balance_at_block`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FullNearBalance {
+    /// Amount held by the account itself (`account.amount`, includes storage)
+    pub liquid: String,
+    /// Amount still locked by the lockup contract; "0" for non-lockup accounts
+    pub locked: String,
+    /// Amount staked at the protocol level (`account.locked`)
+    pub staked: String,
+    /// Storage used by the account in bytes
+    pub storage_usage: u64,
+}
+
+/// Whether an account is a lockup contract created by the lockup factory
+pub fn is_lockup_account(account_id: &str) -> bool {
+    account_id.ends_with(".lockup.near")
+}
+
+/// Query the full NEAR balance breakdown at a specific block height
+///
+/// Unlike `get_balance_at_block`, the block must exist: there is no fallback to
+/// earlier blocks, so all components come from the same block.
+///
+/// # Arguments
+/// * `network` - The NEAR network configuration (use archival network for historical queries)
+/// * `account_id` - The NEAR account to query
+/// * `block_height` - The block height to query at
+///
+/// # Returns
+/// The liquid, lockup-locked and staked amounts plus storage usage. For lockup
+/// accounts the locked amount comes from the contract's `get_locked_amount`.
+pub async fn get_full_balance_at_block(
+    network: &NetworkConfig,
+    account_id: &str,
+    block_height: u64,
+) -> Result<FullNearBalance, Box<dyn std::error::Error>> {
+    let account = AccountId::from_str(account_id)?;
+
+    let balance = Tokens::account(account.clone())
+        .near_balance()
+        .at(Reference::AtBlock(block_height))
+        .fetch_from(network)
+        .await?;
+
+    let locked_yocto = if is_lockup_account(account_id) {
+        let locked: U128 = Contract(account)
+            .call_function("get_locked_amount", ())
+            .read_only()
+            .at(Reference::AtBlock(block_height))
+            .fetch_from(network)
+            .await?
+            .data;
+        locked.0
+    } else {
+        0
+    };
+
+    Ok(FullNearBalance {
+        liquid: convert_raw_to_decimal(&balance.total.as_yoctonear().to_string(), NEAR_DECIMALS)?,
+        locked: convert_raw_to_decimal(&locked_yocto.to_string(), NEAR_DECIMALS)?,
+        staked: convert_raw_to_decimal(&balance.locked.as_yoctonear().to_string(), NEAR_DECIMALS)?,
+        storage_usage: balance.storage_usage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::spawn_mock_rpc;
+    use axum::Json;
+    use serde_json::{Value, json};
+
+    const LOCKUP_ACCOUNT: &str = "3d3c9a2d6f6d7b3f1e8c4a5b6c7d8e9f0a1b2c3d.lockup.near";
+    const BLOCK: u64 = 42_000_000;
+
+    /// Minimal JSON-RPC node answering view_account and get_locked_amount for a lockup
+    async fn mock_rpc(Json(request): Json<Value>) -> Json<Value> {
+        let params = &request["params"];
+        assert_eq!(params["block_id"], json!(BLOCK));
+
+        let result = match params["request_type"].as_str() {
+            Some("view_account") => json!({
+                "amount": "50000000000000000000000000",
+                "locked": "20000000000000000000000000",
+                "code_hash": "11111111111111111111111111111111",
+                "storage_usage": 1234,
+                "storage_paid_at": 0,
+                "block_height": BLOCK,
+                "block_hash": "11111111111111111111111111111111"
+            }),
+            Some("call_function") => {
+                assert_eq!(params["method_name"], "get_locked_amount");
+                json!({
+                    "result": "\"30000000000000000000000000\"".as_bytes(),
+                    "logs": [],
+                    "block_height": BLOCK,
+                    "block_hash": "11111111111111111111111111111111"
+                })
+            }
+            other => panic!("Unexpected request type {:?}", other),
+        };
+
+        Json(json!({"jsonrpc": "2.0", "id": request["id"], "result": result}))
+    }
+
+    #[tokio::test]
+    async fn test_full_balance_of_lockup_account() {
+        let network = spawn_mock_rpc(mock_rpc).await;
+
+        let balance = get_full_balance_at_block(&network, LOCKUP_ACCOUNT, BLOCK)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            balance,
+            FullNearBalance {
+                liquid: "50".to_string(),
+                locked: "30".to_string(),
+                staked: "20".to_string(),
+                storage_usage: 1234,
+            }
+        );
+    }
+
+    #[test]
+    fn test_is_lockup_account() {
+        assert!(is_lockup_account(LOCKUP_ACCOUNT));
+        assert!(!is_lockup_account("treasury.sputnik-dao.near"));
+    }
+}
@@ -2,8 +2,10 @@
 //!
 //! This module implements RPC-based binary search to find the exact block where a balance change occurred.
 //! Uses the balance query service to efficiently locate transaction blocks.
+//! Searches stop between queries when the account's fill is cancelled (see
+//! `fill_cancellation`).
 
-use crate::handlers::balance_changes::balance;
+use crate::handlers::balance_changes::{balance, fill_cancellation};
 use near_api::NetworkConfig;
 use sqlx::PgPool;
 
@@ -38,6 +40,8 @@ pub async fn find_balance_change_block(
         return Ok(None);
     }
 
+    fill_cancellation::check(account_id)?;
+
     // Check balance at end_block first
     let end_balance =
         balance::get_balance_at_block(pool, network, account_id, token_id, end_block).await?;
@@ -47,6 +51,8 @@ pub async fn find_balance_change_block(
         return Ok(None);
     }
 
+    fill_cancellation::check(account_id)?;
+
     // Check balance at start_block
     let start_balance =
         balance::get_balance_at_block(pool, network, account_id, token_id, start_block).await?;
@@ -64,6 +70,7 @@ pub async fn find_balance_change_block(
     while left <= right {
         let mid = left + (right - left) / 2;
 
+        fill_cancellation::check(account_id)?;
         let mid_balance =
             balance::get_balance_at_block(pool, network, account_id, token_id, mid).await?;
 
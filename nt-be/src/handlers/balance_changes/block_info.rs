@@ -1,15 +1,19 @@
 //! Block Information Service
 //!
 //! Functions to query block metadata including timestamps and receipt data via RPC.
+//! All RPC calls go through the per-endpoint circuit breaker, so a failing archival
+//! node is short-circuited with a `CircuitOpen` error instead of being retried.
 
+use crate::handlers::balance_changes::circuit_breaker::call_with_breaker;
 use near_api::{Chain, NetworkConfig, Reference};
 use near_jsonrpc_client::{JsonRpcClient, auth, methods};
 use near_primitives::types::{BlockId, BlockReference};
-use near_primitives::views::StateChangesRequestView;
+use near_primitives::views::{StateChangeKindView, StateChangesRequestView};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use utoipa::ToSchema;
 
 // Re-export types from near-primitives for convenience
 pub use near_primitives::views::{
@@ -52,10 +56,14 @@ pub async fn get_block_timestamp(
     }
 
     // Query from RPC
-    let block = Chain::block()
-        .at(Reference::AtBlock(block_height))
-        .fetch_from(network)
-        .await?;
+    let block = call_with_breaker(
+        network,
+        Chain::block()
+            .at(Reference::AtBlock(block_height))
+            .fetch_from(network),
+    )
+    .await
+    .map_err(|e| -> Box<dyn std::error::Error> { e })?;
 
     let timestamp = block.header.timestamp as i64;
 
@@ -86,10 +94,13 @@ pub async fn get_block_data(
     block_height: u64,
 ) -> Result<BlockReceiptData, Box<dyn std::error::Error + Send + Sync>> {
     // Query the block first
-    let block = Chain::block()
-        .at(Reference::AtBlock(block_height))
-        .fetch_from(network)
-        .await?;
+    let block = call_with_breaker(
+        network,
+        Chain::block()
+            .at(Reference::AtBlock(block_height))
+            .fetch_from(network),
+    )
+    .await?;
 
     let block_hash = block.header.hash.to_string();
     let mut all_receipts = Vec::new();
@@ -119,7 +130,7 @@ pub async fn get_block_data(
             },
         };
 
-        let chunk_response = match client.call(chunk_request).await {
+        let chunk_response = match call_with_breaker(network, client.call(chunk_request)).await {
             Ok(chunk) => chunk,
             Err(e) => {
                 eprintln!("Warning: Failed to fetch chunk {}: {}", chunk_hash_str, e);
@@ -173,10 +184,13 @@ pub async fn get_all_account_receipts(
     block_height: u64,
 ) -> Result<Vec<ReceiptView>, Box<dyn std::error::Error + Send + Sync>> {
     // Query the block first
-    let block = Chain::block()
-        .at(Reference::AtBlock(block_height))
-        .fetch_from(network)
-        .await?;
+    let block = call_with_breaker(
+        network,
+        Chain::block()
+            .at(Reference::AtBlock(block_height))
+            .fetch_from(network),
+    )
+    .await?;
 
     let mut all_receipts = Vec::new();
 
@@ -202,7 +216,7 @@ pub async fn get_all_account_receipts(
             },
         };
 
-        let chunk_response = match client.call(chunk_request).await {
+        let chunk_response = match call_with_breaker(network, client.call(chunk_request)).await {
             Ok(chunk) => chunk,
             Err(e) => {
                 eprintln!("Warning: Failed to fetch chunk {}: {}", chunk_hash_str, e);
@@ -260,11 +274,59 @@ pub async fn get_account_changes(
         },
     };
 
-    let response = client.call(request).await?;
+    let response = call_with_breaker(network, client.call(request)).await?;
 
     Ok(response.changes)
 }
 
+/// Get all accounts whose state changed in a block
+///
+/// Queries the EXPERIMENTAL_changes_in_block RPC endpoint, which only lists the
+/// touched accounts (account, access key, data or contract code changes). This is
+/// much cheaper than fetching every chunk, so callers can check whether an account
+/// appears in a block before scanning its receipts.
+///
+/// # Arguments
+/// * `network` - NEAR network configuration (archival RPC)
+/// * `block_height` - The block height to query
+///
+/// # Returns
+/// Set of account IDs touched in the block, or an error
+pub async fn get_changed_accounts_in_block(
+    network: &NetworkConfig,
+    block_height: u64,
+) -> Result<HashSet<String>, Box<dyn std::error::Error + Send + Sync>> {
+    // Set up JSON-RPC client
+    let rpc_endpoint = network
+        .rpc_endpoints
+        .first()
+        .ok_or("No RPC endpoint configured")?;
+
+    let mut client = JsonRpcClient::connect(rpc_endpoint.url.as_str());
+
+    if let Some(bearer) = &rpc_endpoint.bearer_header {
+        let token = bearer.strip_prefix("Bearer ").unwrap_or(bearer);
+        client = client.header(auth::Authorization::bearer(token)?);
+    }
+
+    let request = methods::EXPERIMENTAL_changes_in_block::RpcStateChangesInBlockRequest {
+        block_reference: BlockReference::BlockId(BlockId::Height(block_height)),
+    };
+
+    let response = call_with_breaker(network, client.call(request)).await?;
+
+    Ok(response
+        .changes
+        .into_iter()
+        .map(|change| match change {
+            StateChangeKindView::AccountTouched { account_id }
+            | StateChangeKindView::AccessKeyTouched { account_id }
+            | StateChangeKindView::DataTouched { account_id }
+            | StateChangeKindView::ContractCodeTouched { account_id } => account_id.to_string(),
+        })
+        .collect())
+}
+
 /// Get transaction details by transaction hash
 ///
 /// Queries the EXPERIMENTAL_tx_status RPC endpoint to get full transaction details
@@ -312,11 +374,103 @@ pub async fn get_transaction(
         wait_until: near_primitives::views::TxExecutionStatus::Final,
     };
 
-    let response = client.call(request).await?;
+    let response = call_with_breaker(network, client.call(request)).await?;
 
     Ok(response)
 }
 
+/// Execution outcome of a single receipt
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReceiptOutcome {
+    pub receipt_id: String,
+    /// Block the receipt was executed in
+    pub block_hash: String,
+    pub predecessor_id: String,
+    pub executor_id: String,
+    /// Execution status as returned by RPC (`SuccessValue`, `SuccessReceiptId`, `Failure`, ...)
+    #[schema(value_type = Object)]
+    pub status: serde_json::Value,
+    pub logs: Vec<String>,
+    pub gas_burnt: u64,
+    /// NEAR burnt for gas, in yoctoNEAR
+    pub tokens_burnt: String,
+    /// Receipts created while executing this receipt
+    pub receipt_ids: Vec<String>,
+}
+
+/// Get the execution outcome of a receipt
+///
+/// RPC has no lookup of an outcome by receipt id alone, so the receipt is fetched
+/// first (EXPERIMENTAL_receipt) for its receiver, then its outcome is read from a
+/// light client proof against the latest final block.
+///
+/// # Arguments
+/// * `network` - NEAR network configuration (archival RPC for old receipts)
+/// * `receipt_id` - The receipt id to query
+///
+/// # Returns
+/// The receipt's status, logs and gas, or an error
+pub async fn get_receipt_outcome(
+    network: &NetworkConfig,
+    receipt_id: &str,
+) -> Result<ReceiptOutcome, Box<dyn std::error::Error + Send + Sync>> {
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::types::TransactionOrReceiptId;
+
+    let rpc_endpoint = network
+        .rpc_endpoints
+        .first()
+        .ok_or("No RPC endpoint configured")?;
+
+    let mut client = JsonRpcClient::connect(rpc_endpoint.url.as_str());
+
+    if let Some(bearer) = &rpc_endpoint.bearer_header {
+        let token = bearer.strip_prefix("Bearer ").unwrap_or(bearer);
+        client = client.header(auth::Authorization::bearer(token)?);
+    }
+
+    let receipt_id: CryptoHash = receipt_id.parse()?;
+
+    let receipt_request = methods::EXPERIMENTAL_receipt::RpcReceiptRequest {
+        receipt_reference: near_jsonrpc_primitives::types::receipts::ReceiptReference {
+            receipt_id,
+        },
+    };
+    let receipt = call_with_breaker(network, client.call(receipt_request)).await?;
+
+    let head_request = methods::block::RpcBlockRequest {
+        block_reference: BlockReference::Finality(near_primitives::types::Finality::Final),
+    };
+    let head = call_with_breaker(network, client.call(head_request)).await?;
+
+    let proof_request = methods::light_client_proof::RpcLightClientExecutionProofRequest {
+        id: TransactionOrReceiptId::Receipt {
+            receipt_id,
+            receiver_id: receipt.receiver_id.clone(),
+        },
+        light_client_head: head.header.hash,
+    };
+    let proof = call_with_breaker(network, client.call(proof_request)).await?;
+    let outcome = proof.outcome_proof;
+
+    Ok(ReceiptOutcome {
+        receipt_id: outcome.id.to_string(),
+        block_hash: outcome.block_hash.to_string(),
+        predecessor_id: receipt.predecessor_id.to_string(),
+        executor_id: outcome.outcome.executor_id.to_string(),
+        status: serde_json::to_value(&outcome.outcome.status)?,
+        logs: outcome.outcome.logs,
+        gas_burnt: outcome.outcome.gas_burnt.as_gas(),
+        tokens_burnt: outcome.outcome.tokens_burnt.to_string(),
+        receipt_ids: outcome
+            .outcome
+            .receipt_ids
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+    })
+}
+
 /// Create a new block timestamp cache
 pub fn new_cache() -> BlockTimestampCache {
     Arc::new(RwLock::new(HashMap::new()))
@@ -372,6 +526,53 @@ mod tests {
         assert!(read_cache.contains_key(&151386339));
     }
 
+    #[tokio::test]
+    async fn test_get_receipt_outcome_of_ft_transfer() {
+        let state = init_test_state().await;
+
+        // ft_transfer of NPRO from distribution.nearmobile.near to petersalomonsen.near
+        let outcome = get_receipt_outcome(
+            &state.archival_network,
+            "CX6MePrrcvuQA6Pgv4BueCkSVpbPbq1voDC5KuNRMg1t",
+        )
+        .await
+        .expect("Should fetch the receipt outcome");
+
+        assert_eq!(
+            outcome.receipt_id,
+            "CX6MePrrcvuQA6Pgv4BueCkSVpbPbq1voDC5KuNRMg1t"
+        );
+        assert_eq!(outcome.executor_id, "npro.nearmobile.near");
+        assert!(
+            outcome.status.get("SuccessValue").is_some(),
+            "Transfer should have succeeded: {}",
+            outcome.status
+        );
+        assert!(outcome.gas_burnt > 0);
+        assert!(
+            outcome.logs.iter().any(|log| log.starts_with("EVENT_JSON:")
+                && log.contains("ft_transfer")
+                && log.contains("petersalomonsen.near")),
+            "Should log the ft_transfer event: {:?}",
+            outcome.logs
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_changed_accounts_in_block_178148634() {
+        let state = init_test_state().await;
+
+        // petersalomonsen.near's NEAR balance changes at this block
+        let changed = get_changed_accounts_in_block(&state.archival_network, 178148634)
+            .await
+            .expect("Should successfully query changes in block");
+
+        assert!(
+            changed.contains("petersalomonsen.near"),
+            "petersalomonsen.near should be reported as changed"
+        );
+    }
+
     #[tokio::test]
     async fn test_get_account_changes_block_178148634() {
         use near_primitives::views::{StateChangeCauseView, StateChangeValueView};
@@ -0,0 +1,496 @@
+//! Circuit Breaker for RPC Endpoints
+//!
+//! When an RPC endpoint (typically archival) starts failing broadly, the monitoring
+//! loop would otherwise keep hammering it. After `failure_threshold` consecutive
+//! failures within `failure_window` the breaker opens and calls are rejected with
+//! `CircuitOpen` for `cooldown`. After the cooldown a single half-open probe is let
+//! through: success closes the breaker, failure opens it again. A probe that never
+//! reports back (its call timed out or was cancelled) frees the probe slot when its
+//! `BreakerPermit` is dropped.
+//!
+//! Only failures of the endpoint itself count: transport errors, timeouts, 5xx and
+//! 429 responses. Errors the RPC handler returns for the request (an unknown block,
+//! a missing chunk, ...) show the endpoint is up and are passed through untouched.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use near_api::NetworkConfig;
+use near_api::errors::{QueryError, RetryError, SendRequestError};
+use near_jsonrpc_client::errors::{
+    JsonRpcError, JsonRpcServerError, JsonRpcServerResponseStatusError,
+};
+use once_cell::sync::Lazy;
+
+use crate::utils::network::primary_rpc_url;
+
+/// Consecutive failures before the breaker opens
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+
+/// Failures further apart than this don't count as consecutive
+pub const DEFAULT_FAILURE_WINDOW: Duration = Duration::from_secs(60);
+
+/// How long the breaker stays open before allowing a probe
+pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Error returned when a call is short-circuited by an open breaker
+#[derive(Debug)]
+pub struct CircuitOpen {
+    pub endpoint: String,
+    pub retry_after: Duration,
+}
+
+impl std::fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Circuit open for {}: retry after {}s",
+            self.endpoint,
+            self.retry_after.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    last_failure_at: Option<Instant>,
+    opened_at: Option<Instant>,
+    probe_in_flight: bool,
+}
+
+/// Permission to make one call, returned by `CircuitBreaker::try_acquire`
+///
+/// Report the outcome with `record_success` or `record_failure`. If the half-open
+/// probe is dropped without reporting, the probe slot is released so the next caller
+/// can probe instead.
+#[must_use]
+#[derive(Debug)]
+pub struct BreakerPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+}
+
+impl BreakerPermit<'_> {
+    pub fn record_success(mut self) {
+        self.probe = false;
+        self.breaker.record_success();
+    }
+
+    pub fn record_failure(mut self) {
+        self.probe = false;
+        self.breaker.record_failure();
+    }
+}
+
+impl Drop for BreakerPermit<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breaker.state.lock().unwrap().probe_in_flight = false;
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    endpoint: String,
+    failure_threshold: u32,
+    failure_window: Duration,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(
+        endpoint: impl Into<String>,
+        failure_threshold: u32,
+        failure_window: Duration,
+        cooldown: Duration,
+    ) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            failure_threshold,
+            failure_window,
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// Check whether a call may proceed
+    ///
+    /// Once the cooldown has passed, exactly one caller is let through as the
+    /// half-open probe; everyone else keeps getting `CircuitOpen` until it reports back
+    /// or its permit is dropped.
+    pub fn try_acquire(&self) -> Result<BreakerPermit<'_>, CircuitOpen> {
+        self.try_acquire_at(Instant::now())
+    }
+
+    /// Record a successful call, closing the breaker
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.opened_at.is_some() {
+            log::info!("Circuit closed for {}", self.endpoint);
+        }
+        *state = BreakerState::default();
+    }
+
+    /// Record a failed call, opening the breaker if the threshold is reached
+    pub fn record_failure(&self) {
+        self.record_failure_at(Instant::now())
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.state.lock().unwrap().opened_at.is_some()
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> Result<BreakerPermit<'_>, CircuitOpen> {
+        let mut state = self.state.lock().unwrap();
+
+        let Some(opened_at) = state.opened_at else {
+            return Ok(BreakerPermit {
+                breaker: self,
+                probe: false,
+            });
+        };
+
+        let elapsed = now.saturating_duration_since(opened_at);
+        if elapsed < self.cooldown {
+            return Err(CircuitOpen {
+                endpoint: self.endpoint.clone(),
+                retry_after: self.cooldown - elapsed,
+            });
+        }
+
+        if state.probe_in_flight {
+            return Err(CircuitOpen {
+                endpoint: self.endpoint.clone(),
+                retry_after: Duration::ZERO,
+            });
+        }
+
+        state.probe_in_flight = true;
+        Ok(BreakerPermit {
+            breaker: self,
+            probe: true,
+        })
+    }
+
+    fn record_failure_at(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+
+        if state.opened_at.is_some() {
+            // Half-open probe failed: start a new cooldown
+            state.opened_at = Some(now);
+            state.probe_in_flight = false;
+            log::warn!("Circuit re-opened for {} after failed probe", self.endpoint);
+            return;
+        }
+
+        let within_window = state
+            .last_failure_at
+            .is_some_and(|last| now.saturating_duration_since(last) <= self.failure_window);
+        state.consecutive_failures = if within_window {
+            state.consecutive_failures + 1
+        } else {
+            1
+        };
+        state.last_failure_at = Some(now);
+
+        if state.consecutive_failures >= self.failure_threshold {
+            state.opened_at = Some(now);
+            log::warn!(
+                "Circuit opened for {} after {} consecutive failures",
+                self.endpoint,
+                state.consecutive_failures
+            );
+        }
+    }
+}
+
+/// Errors that can tell whether the RPC endpoint itself failed
+pub trait EndpointFailure {
+    /// Whether the error is a transport error, timeout, 5xx or 429 rather than an
+    /// error answer to the request
+    fn is_endpoint_failure(&self) -> bool;
+}
+
+impl<E> EndpointFailure for JsonRpcError<E> {
+    fn is_endpoint_failure(&self) -> bool {
+        match self {
+            JsonRpcError::TransportError(_) => true,
+            JsonRpcError::ServerError(JsonRpcServerError::InternalError { .. }) => true,
+            JsonRpcError::ServerError(JsonRpcServerError::ResponseStatusError(status)) => {
+                match status {
+                    JsonRpcServerResponseStatusError::TooManyRequests
+                    | JsonRpcServerResponseStatusError::TimeoutError
+                    | JsonRpcServerResponseStatusError::ServiceUnavailable => true,
+                    JsonRpcServerResponseStatusError::Unexpected { status } => {
+                        status.is_server_error()
+                    }
+                    _ => false,
+                }
+            }
+            JsonRpcError::ServerError(_) => false,
+        }
+    }
+}
+
+impl<E: std::fmt::Debug + Send + Sync> EndpointFailure for QueryError<E> {
+    fn is_endpoint_failure(&self) -> bool {
+        let QueryError::QueryError(retry) = self else {
+            return false;
+        };
+        let (RetryError::RetriesExhausted(err) | RetryError::Critical(err)) = retry.as_ref() else {
+            return false;
+        };
+        match err {
+            SendRequestError::TransportError(transport) => {
+                transport.status().is_none_or(|status| {
+                    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                })
+            }
+            SendRequestError::InternalError(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// An RPC call that failed at the endpoint instead of being answered
+///
+/// Callers that read some error answers as results (e.g. "not an FT contract") wrap
+/// endpoint failures in this, so they can still be told apart once boxed.
+#[derive(Debug)]
+pub struct EndpointFailed(pub Box<dyn std::error::Error + Send + Sync>);
+
+impl std::fmt::Display for EndpointFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RPC endpoint failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for EndpointFailed {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.0.as_ref())
+    }
+}
+
+/// Box an RPC error, wrapping endpoint failures in `EndpointFailed`
+pub fn box_rpc_error<E>(err: E) -> Box<dyn std::error::Error + Send + Sync>
+where
+    E: EndpointFailure + Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    if err.is_endpoint_failure() {
+        Box::new(EndpointFailed(err.into()))
+    } else {
+        err.into()
+    }
+}
+
+/// Whether an error means an RPC call went unanswered: the endpoint failed, the
+/// breaker is open or the RPC budget is spent
+pub fn is_unanswered(err: &(dyn std::error::Error + 'static)) -> bool {
+    err.is::<EndpointFailed>()
+        || err.is::<CircuitOpen>()
+        || err.is::<super::rpc_budget::BudgetExhausted>()
+}
+
+/// One breaker per RPC endpoint, shared across the process
+static BREAKERS: Lazy<Mutex<HashMap<String, Arc<CircuitBreaker>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Get the shared breaker for the first RPC endpoint of a network
+pub fn breaker_for(network: &NetworkConfig) -> Arc<CircuitBreaker> {
+    let endpoint = primary_rpc_url(network);
+
+    BREAKERS
+        .lock()
+        .unwrap()
+        .entry(endpoint.clone())
+        .or_insert_with(|| {
+            Arc::new(CircuitBreaker::new(
+                endpoint,
+                DEFAULT_FAILURE_THRESHOLD,
+                DEFAULT_FAILURE_WINDOW,
+                DEFAULT_COOLDOWN,
+            ))
+        })
+        .clone()
+}
+
+/// Run an RPC call through the network's circuit breaker
+///
+/// The call spends from the current RPC budget (see `rpc_budget`) and waits for the
+/// outbound rate limit (see `rpc_rate_limit`). Only endpoint failures (see
+/// `EndpointFailure`) count towards opening the breaker.
+///
+/// # Returns
+/// The call's result, or a `CircuitOpen` / `BudgetExhausted` error without making
+/// the call if the breaker is open or the budget is spent. Endpoint failures are
+/// wrapped in `EndpointFailed` (see `is_unanswered`).
+pub async fn call_with_breaker<T, E, Fut>(
+    network: &NetworkConfig,
+    call: Fut,
+) -> Result<T, Box<dyn std::error::Error + Send + Sync>>
+where
+    Fut: std::future::Future<Output = Result<T, E>>,
+    E: EndpointFailure + Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    super::rpc_budget::spend()?;
+    let breaker = breaker_for(network);
+    let permit = breaker.try_acquire()?;
+    super::rpc_rate_limit::acquire().await;
+
+    match call.await {
+        Ok(value) => {
+            permit.record_success();
+            Ok(value)
+        }
+        Err(e) if e.is_endpoint_failure() => {
+            permit.record_failure();
+            Err(Box::new(EndpointFailed(e.into())))
+        }
+        Err(e) => {
+            // The endpoint answered, the request itself was bad
+            permit.record_success();
+            Err(e.into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_breaker() -> CircuitBreaker {
+        CircuitBreaker::new(
+            "https://archival.example",
+            3,
+            Duration::from_secs(60),
+            Duration::from_secs(30),
+        )
+    }
+
+    #[test]
+    fn test_repeated_failures_open_and_success_closes() {
+        let breaker = test_breaker();
+        let start = Instant::now();
+
+        for i in 0..3 {
+            let permit = breaker.try_acquire_at(start).unwrap();
+            breaker.record_failure_at(start + Duration::from_secs(i));
+            drop(permit);
+        }
+
+        assert!(breaker.is_open());
+        let err = breaker
+            .try_acquire_at(start + Duration::from_secs(10))
+            .unwrap_err();
+        assert_eq!(err.retry_after, Duration::from_secs(22));
+
+        // After the cooldown only a single probe is let through
+        let after_cooldown = start + Duration::from_secs(40);
+        let probe = breaker.try_acquire_at(after_cooldown).unwrap();
+        assert!(breaker.try_acquire_at(after_cooldown).is_err());
+
+        probe.record_success();
+        assert!(!breaker.is_open());
+        assert!(breaker.try_acquire_at(after_cooldown).is_ok());
+    }
+
+    #[test]
+    fn test_failed_probe_reopens_breaker() {
+        let breaker = test_breaker();
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            breaker.record_failure_at(start);
+        }
+
+        let probe_time = start + Duration::from_secs(31);
+        let probe = breaker.try_acquire_at(probe_time).unwrap();
+        breaker.record_failure_at(probe_time);
+        drop(probe);
+
+        assert!(breaker.is_open());
+        assert!(
+            breaker
+                .try_acquire_at(probe_time + Duration::from_secs(1))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_dropped_probe_frees_probe_slot() {
+        let breaker = test_breaker();
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            breaker.record_failure_at(start);
+        }
+
+        // The probe's call is cancelled before it reports back
+        let probe_time = start + Duration::from_secs(31);
+        let probe = breaker.try_acquire_at(probe_time).unwrap();
+        assert!(breaker.try_acquire_at(probe_time).is_err());
+        drop(probe);
+
+        assert!(breaker.is_open());
+        assert!(breaker.try_acquire_at(probe_time).is_ok());
+    }
+
+    #[test]
+    fn test_only_endpoint_failures_count() {
+        type Error = JsonRpcError<&'static str>;
+
+        let handler_error: Error =
+            JsonRpcError::ServerError(JsonRpcServerError::HandlerError("UNKNOWN_BLOCK"));
+        assert!(!handler_error.is_endpoint_failure());
+
+        let rate_limited: Error =
+            JsonRpcError::ServerError(JsonRpcServerError::ResponseStatusError(
+                JsonRpcServerResponseStatusError::TooManyRequests,
+            ));
+        assert!(rate_limited.is_endpoint_failure());
+
+        let internal: Error =
+            JsonRpcError::ServerError(JsonRpcServerError::InternalError { info: None });
+        assert!(internal.is_endpoint_failure());
+    }
+
+    #[tokio::test]
+    async fn test_handler_errors_do_not_open_breaker() {
+        let network = NetworkConfig {
+            rpc_endpoints: vec![near_api::RPCEndpoint::new(
+                "https://handler-errors.example".parse().unwrap(),
+            )],
+            ..NetworkConfig::mainnet()
+        };
+
+        for _ in 0..DEFAULT_FAILURE_THRESHOLD + 1 {
+            let result = call_with_breaker(&network, async {
+                Err::<(), JsonRpcError<&'static str>>(JsonRpcError::ServerError(
+                    JsonRpcServerError::HandlerError("UNKNOWN_BLOCK"),
+                ))
+            })
+            .await;
+            assert!(result.is_err());
+        }
+
+        assert!(!breaker_for(&network).is_open());
+    }
+
+    #[test]
+    fn test_failures_outside_window_do_not_open() {
+        let breaker = test_breaker();
+        let start = Instant::now();
+
+        for i in 0..5 {
+            breaker.record_failure_at(start + Duration::from_secs(i * 120));
+        }
+
+        assert!(!breaker.is_open());
+    }
+}
@@ -1,12 +1,65 @@
 //! Counterparty Management
 //!
 //! Handles storage and retrieval of counterparty metadata, including FT token information
-//! for decimal conversion.
+//! for decimal conversion, and classifies counterparties as contracts, users or system
+//! events.
 
-use near_api::{AccountId, Contract, NetworkConfig};
+use futures::StreamExt;
+use near_api::types::account::ContractState;
+use near_api::{Account, AccountId, Contract, NetworkConfig};
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use std::collections::HashMap;
 use std::str::FromStr;
+use utoipa::ToSchema;
+
+use crate::handlers::balance_changes::circuit_breaker::box_rpc_error;
+
+/// Counterparty values that mark system events rather than accounts
+pub const SYSTEM_COUNTERPARTIES: &[&str] = &[
+    "SNAPSHOT",
+    "UNKNOWN",
+    "NOT_REGISTERED",
+    "STAKING_REWARD",
+    "LOCKUP_VESTING",
+    // Protocol account issuing gas refunds
+    "system",
+];
+
+/// Maximum number of `view_account` lookups run concurrently by `classify_counterparties`
+const CLASSIFY_CONCURRENCY: usize = 4;
+
+/// What kind of party is on the other side of a balance change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CounterpartyType {
+    /// An account with contract code deployed
+    Contract,
+    /// An account without contract code
+    User,
+    /// A system event such as a SNAPSHOT
+    System,
+}
+
+impl CounterpartyType {
+    /// Classification of a `counterparties.account_type` value
+    fn from_account_type(account_type: &str) -> Self {
+        match account_type {
+            "personal" => Self::User,
+            "system" => Self::System,
+            // ft_token, staking_pool, dao, other
+            _ => Self::Contract,
+        }
+    }
+
+    fn account_type(self) -> &'static str {
+        match self {
+            Self::Contract => "other",
+            Self::User => "personal",
+            Self::System => "system",
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FtMetadata {
@@ -32,12 +85,16 @@ pub async fn query_ft_metadata(
         .call_function("ft_metadata", serde_json::json!({}))
         .read_only()
         .fetch_from(network)
-        .await?;
+        .await
+        .map_err(|e| -> Box<dyn std::error::Error> { box_rpc_error(e) })?;
 
     Ok(response.data)
 }
 
 /// Store or update FT token metadata in counterparties table
+///
+/// The account is (re)marked as an FT token, since it may already have been
+/// classified as a generic contract by `classify_counterparty`.
 pub async fn upsert_ft_counterparty(
     pool: &PgPool,
     account_id: &str,
@@ -56,6 +113,7 @@ pub async fn upsert_ft_counterparty(
         ) VALUES ($1, 'ft_token', $2, $3, $4, $5, NOW())
         ON CONFLICT (account_id) 
         DO UPDATE SET
+            account_type = 'ft_token',
             token_symbol = EXCLUDED.token_symbol,
             token_name = EXCLUDED.token_name,
             token_decimals = EXCLUDED.token_decimals,
@@ -121,6 +179,103 @@ pub async fn ensure_ft_metadata(
     Ok(decimals)
 }
 
+/// Classify a counterparty as a contract, user or system event
+///
+/// System markers are recognized by name. Accounts are classified by whether they
+/// have contract code deployed (via `view_account`); the result is cached in the
+/// counterparties table, so each account is only queried once. Accounts already
+/// known there (e.g. FT tokens) are classified without RPC calls.
+pub async fn classify_counterparty(
+    pool: &PgPool,
+    network: &NetworkConfig,
+    counterparty: &str,
+) -> Result<CounterpartyType, Box<dyn std::error::Error + Send + Sync>> {
+    if SYSTEM_COUNTERPARTIES.contains(&counterparty) {
+        return Ok(CounterpartyType::System);
+    }
+
+    let known: Option<String> =
+        sqlx::query_scalar("SELECT account_type::TEXT FROM counterparties WHERE account_id = $1")
+            .bind(counterparty)
+            .fetch_optional(pool)
+            .await?;
+    if let Some(account_type) = known {
+        return Ok(CounterpartyType::from_account_type(&account_type));
+    }
+
+    let account = Account(AccountId::from_str(counterparty)?)
+        .view()
+        .fetch_from(network)
+        .await?;
+    let counterparty_type = if account.data.contract_state == ContractState::None {
+        CounterpartyType::User
+    } else {
+        CounterpartyType::Contract
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO counterparties (account_id, account_type, last_verified_at)
+        VALUES ($1, $2::account_type_enum, NOW())
+        ON CONFLICT (account_id) DO NOTHING
+        "#,
+    )
+    .bind(counterparty)
+    .bind(counterparty_type.account_type())
+    .execute(pool)
+    .await?;
+
+    Ok(counterparty_type)
+}
+
+/// Classifications already known for a set of counterparties, without RPC calls
+///
+/// System markers are recognized by name and accounts are looked up in the
+/// counterparties table. Accounts that haven't been classified yet are left out.
+pub async fn known_counterparty_types(
+    pool: &PgPool,
+    counterparties: &[&str],
+) -> Result<HashMap<String, CounterpartyType>, sqlx::Error> {
+    let mut types: HashMap<String, CounterpartyType> = counterparties
+        .iter()
+        .filter(|counterparty| SYSTEM_COUNTERPARTIES.contains(counterparty))
+        .map(|counterparty| (counterparty.to_string(), CounterpartyType::System))
+        .collect();
+
+    let rows: Vec<(String, String)> = sqlx::query_as(
+        "SELECT account_id, account_type::TEXT FROM counterparties WHERE account_id = ANY($1)",
+    )
+    .bind(counterparties)
+    .fetch_all(pool)
+    .await?;
+    for (account_id, account_type) in rows {
+        types
+            .entry(account_id)
+            .or_insert_with(|| CounterpartyType::from_account_type(&account_type));
+    }
+
+    Ok(types)
+}
+
+/// Classify a set of counterparties, a few `view_account` lookups at a time
+///
+/// Failures are logged and skipped; the account is retried the next time it's classified.
+pub async fn classify_counterparties(
+    pool: &PgPool,
+    network: &NetworkConfig,
+    counterparties: Vec<String>,
+) {
+    futures::stream::iter(counterparties)
+        .map(|counterparty| async move {
+            if let Err(e) = classify_counterparty(pool, network, &counterparty).await {
+                log::warn!("Failed to classify counterparty {}: {}", counterparty, e);
+            }
+        })
+        .buffer_unordered(CLASSIFY_CONCURRENCY)
+        .collect::<Vec<()>>()
+        .await;
+}
+
 /// Convert raw FT amount to human-readable decimal string
 ///
 /// # Arguments
@@ -149,9 +304,109 @@ pub fn convert_raw_to_decimal(
     Ok(decimal.normalized().to_string())
 }
 
+/// Convert a human-readable decimal amount back to raw FT units
+///
+/// Inverse of `convert_raw_to_decimal`; digits beyond `decimals` are truncated.
+///
+/// # Returns
+/// An integer string like "2500000" for "2.5" with 6 decimals
+pub fn convert_decimal_to_raw(
+    decimal_amount: &str,
+    decimals: u8,
+) -> Result<String, Box<dyn std::error::Error>> {
+    use bigdecimal::BigDecimal;
+    use std::str::FromStr;
+
+    let decimal = BigDecimal::from_str(decimal_amount)?;
+    let multiplier = BigDecimal::from_str(&format!("1{}", "0".repeat(decimals as usize)))?;
+
+    Ok((decimal * multiplier).with_scale(0).to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::test_utils::spawn_mock_rpc_with_state;
+    use axum::Json;
+    use serde_json::{Value, json};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// JSON-RPC node where only `usdc-ft.near` has contract code
+    async fn view_account_rpc(
+        axum::extract::State(queries): axum::extract::State<Arc<AtomicUsize>>,
+        Json(request): Json<Value>,
+    ) -> Json<Value> {
+        queries.fetch_add(1, Ordering::SeqCst);
+        let code_hash = match request["params"]["account_id"].as_str() {
+            Some("usdc-ft.near") => "3ZJJqCjAPwCKfcXpDu1HmwuFmiD56UqXaPF8Aw9EZJPd",
+            _ => "11111111111111111111111111111111",
+        };
+
+        Json(json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": {
+                "amount": "1000000000000000000000000",
+                "locked": "0",
+                "code_hash": code_hash,
+                "storage_usage": 100,
+                "storage_paid_at": 0,
+                "block_height": 1,
+                "block_hash": "11111111111111111111111111111111"
+            }
+        }))
+    }
+
+    #[sqlx::test]
+    async fn test_classify_counterparty(pool: PgPool) -> sqlx::Result<()> {
+        let queries = Arc::new(AtomicUsize::new(0));
+        let network = spawn_mock_rpc_with_state(view_account_rpc, queries.clone()).await;
+
+        let classify = |counterparty: &'static str| {
+            let (pool, network) = (pool.clone(), network.clone());
+            async move {
+                classify_counterparty(&pool, &network, counterparty)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        assert_eq!(classify("usdc-ft.near").await, CounterpartyType::Contract);
+        assert_eq!(classify("alice.near").await, CounterpartyType::User);
+        assert_eq!(classify("SNAPSHOT").await, CounterpartyType::System);
+        assert_eq!(queries.load(Ordering::SeqCst), 2);
+
+        // Cached in the counterparties table
+        assert_eq!(classify("usdc-ft.near").await, CounterpartyType::Contract);
+        assert_eq!(queries.load(Ordering::SeqCst), 2);
+
+        let known =
+            known_counterparty_types(&pool, &["usdc-ft.near", "SNAPSHOT", "bob.near"]).await?;
+        assert_eq!(known.get("usdc-ft.near"), Some(&CounterpartyType::Contract));
+        assert_eq!(known.get("SNAPSHOT"), Some(&CounterpartyType::System));
+        assert!(!known.contains_key("bob.near"));
+
+        // Storing FT metadata turns the generic contract row into an FT token
+        let metadata = FtMetadata {
+            spec: "ft-1.0.0".to_string(),
+            name: "USD Coin".to_string(),
+            symbol: "USDC".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 6,
+        };
+        upsert_ft_counterparty(&pool, "usdc-ft.near", &metadata)
+            .await
+            .unwrap();
+        assert_eq!(
+            get_ft_decimals(&pool, "usdc-ft.near").await.unwrap(),
+            Some(6)
+        );
+
+        Ok(())
+    }
 
     #[test]
     fn test_convert_raw_to_decimal() {
@@ -172,4 +427,15 @@ mod tests {
         // Zero decimals
         assert_eq!(convert_raw_to_decimal("100", 0).unwrap(), "100");
     }
+
+    #[test]
+    fn test_convert_decimal_to_raw() {
+        assert_eq!(convert_decimal_to_raw("2.5", 6).unwrap(), "2500000");
+        assert_eq!(
+            convert_decimal_to_raw("11.1002111266305371", 24).unwrap(),
+            "11100211126630537100000000"
+        );
+        assert_eq!(convert_decimal_to_raw("0", 24).unwrap(), "0");
+        assert_eq!(convert_decimal_to_raw("100", 0).unwrap(), "100");
+    }
 }
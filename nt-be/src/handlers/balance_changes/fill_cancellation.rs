@@ -0,0 +1,172 @@
+//! Fill Cancellation
+//!
+//! A runaway fill of a large account can saturate RPC. Operators cancel the
+//! in-flight fills of an account with `POST /api/admin/monitor/cancel/{account_id}`.
+//!
+//! The registry lives in `AppState`. Fills run inside `with_cancellations`, which
+//! makes the registry available to the gap filler and binary search without
+//! threading it through every call. They `check` between iterations and abort with
+//! `FillCancelled`, releasing their locks as on any other error.
+//!
+//! A cancellation only applies to fills in flight: once the last fill of the
+//! account finishes, the next cycle fills it normally again.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+tokio::task_local! {
+    static CANCELLATIONS: Arc<FillCancellations>;
+}
+
+#[derive(Debug, Default)]
+struct AccountFills {
+    in_flight: usize,
+    cancelled: bool,
+}
+
+/// Per-account cancellation flags of in-flight fills
+#[derive(Debug, Default)]
+pub struct FillCancellations {
+    accounts: Mutex<HashMap<String, AccountFills>>,
+}
+
+impl FillCancellations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel the in-flight fills of an account
+    ///
+    /// # Returns
+    /// Whether any fill of the account was in flight
+    pub fn cancel(&self, account_id: &str) -> bool {
+        let mut accounts = self.accounts.lock().unwrap();
+        match accounts.get_mut(account_id) {
+            Some(fills) => {
+                fills.cancelled = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_cancelled(&self, account_id: &str) -> bool {
+        let accounts = self.accounts.lock().unwrap();
+        accounts
+            .get(account_id)
+            .is_some_and(|fills| fills.cancelled)
+    }
+
+    /// Register a fill of `account_id` until the returned guard is dropped
+    pub fn start(self: &Arc<Self>, account_id: &str) -> FillGuard {
+        let mut accounts = self.accounts.lock().unwrap();
+        accounts
+            .entry(account_id.to_string())
+            .or_default()
+            .in_flight += 1;
+
+        FillGuard {
+            registry: self.clone(),
+            account_id: account_id.to_string(),
+        }
+    }
+
+    fn finish(&self, account_id: &str) {
+        let mut accounts = self.accounts.lock().unwrap();
+        if let Some(fills) = accounts.get_mut(account_id) {
+            fills.in_flight = fills.in_flight.saturating_sub(1);
+            if fills.in_flight == 0 {
+                accounts.remove(account_id);
+            }
+        }
+    }
+}
+
+/// Keeps a fill registered as in flight
+pub struct FillGuard {
+    registry: Arc<FillCancellations>,
+    account_id: String,
+}
+
+impl Drop for FillGuard {
+    fn drop(&mut self) {
+        self.registry.finish(&self.account_id);
+    }
+}
+
+/// Error returned by fills that were cancelled
+#[derive(Debug)]
+pub struct FillCancelled {
+    pub account_id: String,
+}
+
+impl std::fmt::Display for FillCancelled {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Fill cancelled for {}", self.account_id)
+    }
+}
+
+impl std::error::Error for FillCancelled {}
+
+/// Run fills that can be cancelled through `registry`
+pub async fn with_cancellations<F: Future>(
+    registry: Arc<FillCancellations>,
+    future: F,
+) -> F::Output {
+    CANCELLATIONS.scope(registry, future).await
+}
+
+/// Register an in-flight fill of `account_id`
+///
+/// Returns `None` (the fill can't be cancelled) outside `with_cancellations`.
+pub fn start_fill(account_id: &str) -> Option<FillGuard> {
+    CANCELLATIONS
+        .try_with(|registry| registry.start(account_id))
+        .ok()
+}
+
+/// Fail if the in-flight fills of `account_id` were cancelled
+pub fn check(account_id: &str) -> Result<(), FillCancelled> {
+    let cancelled = CANCELLATIONS
+        .try_with(|registry| registry.is_cancelled(account_id))
+        .unwrap_or(false);
+
+    if cancelled {
+        return Err(FillCancelled {
+            account_id: account_id.to_string(),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cancellation_only_applies_to_fills_in_flight() {
+        let registry = Arc::new(FillCancellations::new());
+        assert!(!registry.cancel("idle.near"), "Nothing in flight to cancel");
+
+        with_cancellations(registry.clone(), async {
+            let outer = start_fill("busy.near");
+            let inner = start_fill("busy.near");
+            assert!(check("busy.near").is_ok());
+
+            assert!(registry.cancel("busy.near"));
+            assert!(check("busy.near").is_err());
+            assert!(check("other.near").is_ok());
+
+            drop(inner);
+            assert!(check("busy.near").is_err(), "Outer fill is still in flight");
+            drop(outer);
+            assert!(check("busy.near").is_ok(), "Later fills run normally");
+        })
+        .await;
+
+        // Outside a scope there is nothing to check against
+        assert!(start_fill("busy.near").is_none());
+        assert!(check("busy.near").is_ok());
+    }
+}
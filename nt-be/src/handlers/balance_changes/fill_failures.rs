@@ -0,0 +1,203 @@
+//! Fill Failure Dead-Lettering
+//!
+//! A gap whose fill fails (e.g. an archival node answering 422 for its blocks) would
+//! otherwise be retried every monitoring cycle forever. Each failure is recorded in
+//! `fill_failures` with an exponentially growing `next_retry_at`; until then the gap
+//! is skipped, and operators can list the persistently failing gaps. A successful
+//! fill clears the record.
+
+use serde::Serialize;
+use sqlx::PgPool;
+use sqlx::types::chrono::{DateTime, Utc};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+use crate::handlers::balance_changes::gap_detector::BalanceGap;
+
+/// Delay before retrying a gap that failed once
+pub const BASE_RETRY_DELAY: Duration = Duration::from_secs(5 * 60);
+
+/// Longest delay between retries, however often a gap failed
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, ToSchema)]
+pub struct FillFailure {
+    pub account_id: String,
+    pub token_id: String,
+    pub start_block: i64,
+    pub end_block: i64,
+    pub attempts: i32,
+    pub last_error: String,
+    pub next_retry_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Delay before the next retry after `attempts` consecutive failures
+///
+/// Doubles with every failure, starting at `BASE_RETRY_DELAY` and capped at
+/// `MAX_RETRY_DELAY`.
+pub fn retry_delay(attempts: u32) -> Duration {
+    let doublings = attempts.saturating_sub(1).min(31);
+    BASE_RETRY_DELAY
+        .saturating_mul(1 << doublings)
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Record a failed fill of `gap`, scheduling its next retry
+///
+/// # Returns
+/// The updated failure record
+pub async fn record_failure(
+    pool: &PgPool,
+    gap: &BalanceGap,
+    error: &str,
+) -> Result<FillFailure, sqlx::Error> {
+    let existing: Option<(i32,)> = sqlx::query_as(
+        r#"
+        SELECT attempts FROM fill_failures
+        WHERE account_id = $1 AND token_id = $2 AND start_block = $3 AND end_block = $4
+        "#,
+    )
+    .bind(&gap.account_id)
+    .bind(&gap.token_id)
+    .bind(gap.start_block)
+    .bind(gap.end_block)
+    .fetch_optional(pool)
+    .await?;
+
+    let attempts = existing.map_or(0, |(a,)| a.max(0) as u32) + 1;
+    let delay =
+        chrono::Duration::from_std(retry_delay(attempts)).expect("retry delay is capped at a day");
+
+    sqlx::query_as::<_, FillFailure>(
+        r#"
+        INSERT INTO fill_failures
+            (account_id, token_id, start_block, end_block, attempts, last_error, next_retry_at)
+        VALUES ($1, $2, $3, $4, $5, $6, NOW() + $7)
+        ON CONFLICT (account_id, token_id, start_block, end_block) DO UPDATE
+        SET attempts = EXCLUDED.attempts,
+            last_error = EXCLUDED.last_error,
+            next_retry_at = EXCLUDED.next_retry_at,
+            updated_at = NOW()
+        RETURNING account_id, token_id, start_block, end_block, attempts, last_error,
+                  next_retry_at, updated_at
+        "#,
+    )
+    .bind(&gap.account_id)
+    .bind(&gap.token_id)
+    .bind(gap.start_block)
+    .bind(gap.end_block)
+    .bind(attempts as i32)
+    .bind(error)
+    .bind(delay)
+    .fetch_one(pool)
+    .await
+}
+
+/// When `gap` may be retried, if an earlier failure deferred it past now
+pub async fn deferred_until(
+    pool: &PgPool,
+    gap: &BalanceGap,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let row: Option<(DateTime<Utc>,)> = sqlx::query_as(
+        r#"
+        SELECT next_retry_at FROM fill_failures
+        WHERE account_id = $1 AND token_id = $2 AND start_block = $3 AND end_block = $4
+          AND next_retry_at > NOW()
+        "#,
+    )
+    .bind(&gap.account_id)
+    .bind(&gap.token_id)
+    .bind(gap.start_block)
+    .bind(gap.end_block)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(row.map(|(at,)| at))
+}
+
+/// Forget the failures of `gap` once it has been filled
+pub async fn clear_failure(pool: &PgPool, gap: &BalanceGap) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        r#"
+        DELETE FROM fill_failures
+        WHERE account_id = $1 AND token_id = $2 AND start_block = $3 AND end_block = $4
+        "#,
+    )
+    .bind(&gap.account_id)
+    .bind(&gap.token_id)
+    .bind(gap.start_block)
+    .bind(gap.end_block)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// List recorded fill failures, most attempted first
+pub async fn list_failures(pool: &PgPool) -> Result<Vec<FillFailure>, sqlx::Error> {
+    sqlx::query_as::<_, FillFailure>(
+        r#"
+        SELECT account_id, token_id, start_block, end_block, attempts, last_error,
+               next_retry_at, updated_at
+        FROM fill_failures
+        ORDER BY attempts DESC, account_id, token_id, start_block
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gap() -> BalanceGap {
+        BalanceGap {
+            account_id: "treasury.sputnik-dao.near".to_string(),
+            token_id: "usdc.near".to_string(),
+            start_block: 100,
+            end_block: 200,
+            actual_balance_after: "10".to_string(),
+            expected_balance_before: "5".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_cap() {
+        assert_eq!(retry_delay(1), BASE_RETRY_DELAY);
+        assert_eq!(retry_delay(2), BASE_RETRY_DELAY * 2);
+        assert_eq!(retry_delay(4), BASE_RETRY_DELAY * 8);
+        assert_eq!(retry_delay(20), MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(u32::MAX), MAX_RETRY_DELAY);
+    }
+
+    #[sqlx::test]
+    async fn test_failing_gap_retry_backs_off(pool: PgPool) -> sqlx::Result<()> {
+        let gap = gap();
+        assert_eq!(deferred_until(&pool, &gap).await?, None);
+
+        let first = record_failure(&pool, &gap, "422 Unprocessable Entity").await?;
+        assert_eq!(first.attempts, 1);
+        assert!(deferred_until(&pool, &gap).await?.is_some());
+
+        let second = record_failure(&pool, &gap, "422 Unprocessable Entity").await?;
+        let third = record_failure(&pool, &gap, "timeout").await?;
+        assert_eq!(third.attempts, 3);
+        assert_eq!(third.last_error, "timeout");
+
+        // Each failure pushes the next retry further out than the previous delay
+        let first_delay = first.next_retry_at - first.updated_at;
+        let third_delay = third.next_retry_at - third.updated_at;
+        assert!(second.next_retry_at > first.next_retry_at);
+        assert!(third.next_retry_at > second.next_retry_at);
+        assert!(third_delay > first_delay * 3);
+
+        assert_eq!(list_failures(&pool).await?.len(), 1);
+        clear_failure(&pool, &gap).await?;
+        assert_eq!(deferred_until(&pool, &gap).await?, None);
+        assert!(list_failures(&pool).await?.is_empty());
+
+        Ok(())
+    }
+}
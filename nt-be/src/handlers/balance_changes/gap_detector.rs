@@ -4,7 +4,11 @@
 //! A "gap" occurs when the balance_after of one record doesn't match the balance_before
 //! of the next record for the same account and token.
 
+use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
+use utoipa::ToSchema;
+
+use super::account_monitor::get_monitored_tokens;
 
 #[cfg(test)]
 use super::gap_filler::block_timestamp_to_datetime;
@@ -78,6 +82,125 @@ pub async fn find_gaps(
     Ok(gaps)
 }
 
+/// Gaps of one token of a monitored account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct TokenGapsSummary {
+    pub token_id: String,
+    pub gaps: usize,
+    /// Blocks between the records around each gap, summed over all gaps
+    pub missing_blocks: i64,
+}
+
+/// Gaps of a monitored account's tracked tokens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct AccountGapsSummary {
+    pub account_id: String,
+    pub gaps: usize,
+    pub missing_blocks: i64,
+    pub tokens: Vec<TokenGapsSummary>,
+}
+
+/// Gaps across all enabled monitored accounts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct GapsSummary {
+    pub accounts: Vec<AccountGapsSummary>,
+    /// Whether accounts beyond `max_accounts` were left out
+    pub truncated: bool,
+}
+
+/// Summarize the gaps of every enabled monitored account and its tracked tokens
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `max_accounts` - Most accounts to check, in account id order
+///
+/// # Returns
+/// One entry per checked account, including accounts without gaps
+pub async fn summarize_gaps(
+    pool: &PgPool,
+    max_accounts: usize,
+) -> Result<GapsSummary, sqlx::Error> {
+    let mut account_ids: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT account_id
+        FROM monitored_accounts
+        WHERE enabled = true
+        ORDER BY account_id
+        LIMIT $1
+        "#,
+    )
+    .bind(max_accounts as i64 + 1)
+    .fetch_all(pool)
+    .await?;
+    let truncated = account_ids.len() > max_accounts;
+    account_ids.truncate(max_accounts);
+
+    let mut accounts = Vec::with_capacity(account_ids.len());
+    for account_id in account_ids {
+        let mut tokens = Vec::new();
+        for token_id in get_monitored_tokens(pool, &account_id).await? {
+            let gaps = find_gaps(pool, &account_id, &token_id, i64::MAX).await?;
+            tokens.push(TokenGapsSummary {
+                token_id,
+                gaps: gaps.len(),
+                missing_blocks: gaps.iter().map(|gap| gap.end_block - gap.start_block).sum(),
+            });
+        }
+        accounts.push(AccountGapsSummary {
+            account_id,
+            gaps: tokens.iter().map(|token| token.gaps).sum(),
+            missing_blocks: tokens.iter().map(|token| token.missing_blocks).sum(),
+            tokens,
+        });
+    }
+
+    Ok(GapsSummary {
+        accounts,
+        truncated,
+    })
+}
+
+/// A (account, block, token) key stored in more than one balance_changes row
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct DuplicateKey {
+    pub account_id: String,
+    pub token_id: Option<String>,
+    pub block_height: i64,
+    /// Ids of all rows sharing the key, ascending
+    pub ids: Vec<i64>,
+}
+
+/// Find balance change rows that share the same account, block and token.
+///
+/// The unique constraint doesn't cover rows with a NULL `token_id` (NULLs never
+/// compare equal), so duplicates can exist for legacy data. Grouping treats NULL
+/// tokens as equal.
+///
+/// # Arguments
+/// * `pool` - Database connection pool
+/// * `account_id` - Account to check
+///
+/// # Returns
+/// Vector of duplicated keys, ordered by token and block height. Empty if there are none.
+pub async fn find_duplicates(
+    pool: &PgPool,
+    account_id: &str,
+) -> Result<Vec<DuplicateKey>, sqlx::Error> {
+    sqlx::query_as::<_, DuplicateKey>(
+        r#"
+        SELECT account_id, token_id, block_height, ARRAY_AGG(id ORDER BY id) as ids
+        FROM balance_changes
+        WHERE account_id = $1
+        GROUP BY account_id, token_id, block_height
+        HAVING COUNT(*) > 1
+        ORDER BY token_id, block_height
+        "#,
+    )
+    .bind(account_id)
+    .fetch_all(pool)
+    .await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -246,4 +369,73 @@ mod tests {
 
         Ok(())
     }
+
+    #[sqlx::test]
+    async fn test_summarize_gaps_across_accounts(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query(
+            "INSERT INTO monitored_accounts (account_id) VALUES ('complete.near'), ('gappy.near')",
+        )
+        .execute(&pool)
+        .await?;
+        // gappy.near is missing the change from 5 to 6 somewhere between blocks 100 and 300
+        let records = [
+            ("complete.near", 100i64, "0", "5"),
+            ("complete.near", 200, "5", "7"),
+            ("gappy.near", 100, "0", "5"),
+            ("gappy.near", 300, "6", "8"),
+        ];
+        for (account_id, block, before, after) in records {
+            sqlx::query(
+                r#"
+                INSERT INTO balance_changes
+                (account_id, token_id, block_height, block_timestamp, block_time, amount, balance_before, balance_after, counterparty, actions, raw_data)
+                VALUES ($1, 'near', $2, $3, $4, $6::NUMERIC - $5::NUMERIC, $5::NUMERIC, $6::NUMERIC, 'sender.near', '{}', '{}')
+                "#,
+            )
+            .bind(account_id)
+            .bind(block)
+            .bind(block * 10000000)
+            .bind(block_timestamp_to_datetime(block * 10000000))
+            .bind(before)
+            .bind(after)
+            .execute(&pool)
+            .await?;
+        }
+
+        let summary = summarize_gaps(&pool, 10).await?;
+        assert_eq!(
+            summary,
+            GapsSummary {
+                accounts: vec![
+                    AccountGapsSummary {
+                        account_id: "complete.near".to_string(),
+                        gaps: 0,
+                        missing_blocks: 0,
+                        tokens: vec![TokenGapsSummary {
+                            token_id: "near".to_string(),
+                            gaps: 0,
+                            missing_blocks: 0,
+                        }],
+                    },
+                    AccountGapsSummary {
+                        account_id: "gappy.near".to_string(),
+                        gaps: 1,
+                        missing_blocks: 200,
+                        tokens: vec![TokenGapsSummary {
+                            token_id: "near".to_string(),
+                            gaps: 1,
+                            missing_blocks: 200,
+                        }],
+                    },
+                ],
+                truncated: false,
+            }
+        );
+
+        let bounded = summarize_gaps(&pool, 1).await?;
+        assert_eq!(bounded.accounts.len(), 1);
+        assert!(bounded.truncated);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,78 @@
+//! Gas Reward Coalescing
+//!
+//! Contract accounts receive a share of the gas burnt by calls to them
+//! (`action_receipt_gas_reward`), so their NEAR balance creeps up by tiny amounts
+//! without any transfer. For accounts with `monitored_accounts.coalesce_gas_rewards`
+//! set, the gap filler records a run of such changes as one `GAS_REWARDS` record
+//! spanning at most `GAS_REWARD_COALESCE_BLOCKS` instead of one record per change.
+
+use near_primitives::views::{StateChangeCauseView, StateChangeWithCauseView};
+use sqlx::PgPool;
+
+/// Counterparty of a record coalescing gas-reward-only changes
+pub const GAS_REWARDS_COUNTERPARTY: &str = "GAS_REWARDS";
+
+/// Longest span of blocks (~1 day) one coalesced record covers
+pub const GAS_REWARD_COALESCE_BLOCKS: u64 = 86_400;
+
+/// Whether the account changes of a block come from gas rewards only
+pub fn is_gas_reward_only(changes: &[StateChangeWithCauseView]) -> bool {
+    !changes.is_empty()
+        && changes
+            .iter()
+            .all(|c| matches!(c.cause, StateChangeCauseView::ActionReceiptGasReward { .. }))
+}
+
+/// Whether gas-reward-only changes of an account are coalesced
+///
+/// Accounts that aren't monitored keep one record per change.
+pub async fn coalesces_gas_rewards(pool: &PgPool, account_id: &str) -> Result<bool, sqlx::Error> {
+    let row: Option<(bool,)> =
+        sqlx::query_as("SELECT coalesce_gas_rewards FROM monitored_accounts WHERE account_id = $1")
+            .bind(account_id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(row.is_some_and(|(coalesce,)| coalesce))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn change(cause: serde_json::Value) -> StateChangeWithCauseView {
+        serde_json::from_value(json!({
+            "cause": cause,
+            "type": "account_update",
+            "change": {
+                "account_id": "contract.near",
+                "amount": "5000000000000000000000000",
+                "locked": "0",
+                "code_hash": "11111111111111111111111111111111",
+                "storage_usage": 100,
+                "storage_paid_at": 0
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_is_gas_reward_only() {
+        let gas_reward = change(json!({
+            "type": "action_receipt_gas_reward",
+            "receipt_hash": "11111111111111111111111111111111"
+        }));
+        let transfer = change(json!({
+            "type": "receipt_processing",
+            "receipt_hash": "11111111111111111111111111111111"
+        }));
+
+        assert!(is_gas_reward_only(&[
+            gas_reward.clone(),
+            gas_reward.clone()
+        ]));
+        assert!(!is_gas_reward_only(&[gas_reward, transfer]));
+        assert!(!is_gas_reward_only(&[]));
+    }
+}
@@ -0,0 +1,124 @@
+//! Indexer Sources for Gap Filling
+//!
+//! Binary search over RPC finds any balance change, but needs ~log2(range) balance
+//! queries per gap. An indexer that knows an account's transaction history can
+//! point at the few blocks where a change may have happened, so the gap filler
+//! only has to binary search a small window after a candidate block (see
+//! `gap_filler::fill_gaps_with_indexer`).
+//!
+//! Indexer results are only hints: every candidate is verified over RPC, and the
+//! gap filler falls back to searching the whole gap when the indexer fails or
+//! none of its candidates explains the change.
+
+use futures::future::BoxFuture;
+use serde::Deserialize;
+
+/// Error type for indexer queries
+pub type IndexerError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Pages of account history fetched per query before giving up
+const MAX_PAGES: usize = 10;
+
+/// A source of candidate blocks where an account's balance may have changed
+pub trait IndexerSource: Send + Sync {
+    /// Candidate blocks in `[from_block, to_block]` for an account/token, ascending
+    ///
+    /// A candidate is the block a transaction involving the account was included
+    /// in; its receipts (and so the balance change) may execute a few blocks later.
+    fn candidate_blocks<'a>(
+        &'a self,
+        account_id: &'a str,
+        token_id: &'a str,
+        from_block: u64,
+        to_block: u64,
+    ) -> BoxFuture<'a, Result<Vec<u64>, IndexerError>>;
+}
+
+/// Candidate blocks from the FastNear account transaction history
+///
+/// FastNear lists the transactions touching an account regardless of token, so
+/// the same candidates are returned for every token of the account.
+pub struct FastNearIndexer {
+    http_client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AccountTxsResponse {
+    #[serde(default)]
+    account_txs: Vec<AccountTx>,
+}
+
+#[derive(Deserialize)]
+struct AccountTx {
+    tx_block_height: u64,
+}
+
+impl FastNearIndexer {
+    pub fn new(http_client: reqwest::Client, base_url: &str, api_key: Option<String>) -> Self {
+        Self {
+            http_client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+
+    /// One page of the account's transactions, newest first
+    async fn fetch_page(
+        &self,
+        account_id: &str,
+        max_block_height: u64,
+    ) -> Result<Vec<AccountTx>, IndexerError> {
+        let mut request = self
+            .http_client
+            .post(format!("{}/v0/account", self.base_url))
+            .json(&serde_json::json!({
+                "account_id": account_id,
+                "max_block_height": max_block_height,
+            }));
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response: AccountTxsResponse = request.send().await?.error_for_status()?.json().await?;
+        Ok(response.account_txs)
+    }
+}
+
+impl IndexerSource for FastNearIndexer {
+    fn candidate_blocks<'a>(
+        &'a self,
+        account_id: &'a str,
+        _token_id: &'a str,
+        from_block: u64,
+        to_block: u64,
+    ) -> BoxFuture<'a, Result<Vec<u64>, IndexerError>> {
+        Box::pin(async move {
+            let mut blocks = Vec::new();
+            let mut max_block_height = to_block;
+
+            for _ in 0..MAX_PAGES {
+                let txs = self.fetch_page(account_id, max_block_height).await?;
+                let Some(oldest) = txs.iter().map(|tx| tx.tx_block_height).min() else {
+                    break;
+                };
+
+                blocks.extend(
+                    txs.iter()
+                        .map(|tx| tx.tx_block_height)
+                        .filter(|height| (from_block..=to_block).contains(height)),
+                );
+
+                if oldest <= from_block || oldest == 0 {
+                    break;
+                }
+                max_block_height = oldest - 1;
+            }
+
+            blocks.sort_unstable();
+            blocks.dedup();
+            Ok(blocks)
+        })
+    }
+}
@@ -0,0 +1,260 @@
+//! Lockup Vesting Snapshots
+//!
+//! The locked amount of a lockup contract shrinks as its vesting / release schedule
+//! progresses, without any transaction. The gap filler only sees balance changes,
+//! so each monitoring cycle snapshots the locked/unlocked split of monitored lockup
+//! accounts (see `near::is_lockup_account`) from the contract's views.
+//!
+//! Snapshots are stored in `balance_changes` under the `LOCKUP_LOCKED_TOKEN_ID`
+//! pseudo-token with the `LOCKUP_VESTING` counterparty: `balance_after` is the
+//! locked amount, `amount` the change since the previous snapshot, and `raw_data`
+//! holds the full split. A snapshot is only recorded when the locked amount changed.
+
+use near_api::types::json::U128;
+use near_api::{AccountId, Contract, NetworkConfig, Reference};
+use serde::Serialize;
+use sqlx::PgPool;
+use sqlx::types::BigDecimal;
+use std::str::FromStr;
+
+use super::block_info;
+use super::counterparty::convert_raw_to_decimal;
+use super::gap_filler::block_timestamp_to_datetime;
+use crate::utils::decimals::NEAR_DECIMALS;
+
+/// Counterparty marking vesting snapshots
+pub const LOCKUP_VESTING_COUNTERPARTY: &str = "LOCKUP_VESTING";
+
+/// Pseudo-token holding the locked amount of a lockup account
+pub const LOCKUP_LOCKED_TOKEN_ID: &str = "near-lockup-locked";
+
+/// Locked/unlocked split of a lockup account, in human-readable NEAR
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VestingSplit {
+    /// Total balance owned by the lockup, including staked amounts (`get_balance`)
+    pub total: String,
+    /// Amount still locked (`get_locked_amount`)
+    pub locked: String,
+    /// `total - locked`
+    pub unlocked: String,
+}
+
+/// Query the locked/unlocked split of a lockup account at a block
+pub async fn get_vesting_split_at_block(
+    network: &NetworkConfig,
+    account_id: &str,
+    block_height: u64,
+) -> Result<VestingSplit, Box<dyn std::error::Error>> {
+    let contract = Contract(AccountId::from_str(account_id)?);
+
+    let total: U128 = contract
+        .call_function("get_balance", ())
+        .read_only()
+        .at(Reference::AtBlock(block_height))
+        .fetch_from(network)
+        .await?
+        .data;
+    let locked: U128 = contract
+        .call_function("get_locked_amount", ())
+        .read_only()
+        .at(Reference::AtBlock(block_height))
+        .fetch_from(network)
+        .await?
+        .data;
+    let unlocked = total.0.saturating_sub(locked.0);
+
+    Ok(VestingSplit {
+        total: convert_raw_to_decimal(&total.0.to_string(), NEAR_DECIMALS)?,
+        locked: convert_raw_to_decimal(&locked.0.to_string(), NEAR_DECIMALS)?,
+        unlocked: convert_raw_to_decimal(&unlocked.to_string(), NEAR_DECIMALS)?,
+    })
+}
+
+/// Record the vesting split of a lockup account at a block
+///
+/// # Returns
+/// The split if a snapshot was recorded, or `None` if the locked amount is unchanged
+/// since the previous snapshot
+pub async fn snapshot_vesting(
+    pool: &PgPool,
+    network: &NetworkConfig,
+    account_id: &str,
+    block_height: u64,
+) -> Result<Option<VestingSplit>, Box<dyn std::error::Error>> {
+    let split = get_vesting_split_at_block(network, account_id, block_height).await?;
+    let locked = BigDecimal::from_str(&split.locked)?;
+
+    let previous: Option<BigDecimal> = sqlx::query_scalar(
+        r#"
+        SELECT balance_after
+        FROM balance_changes
+        WHERE account_id = $1 AND token_id = $2 AND block_height < $3
+        ORDER BY block_height DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(account_id)
+    .bind(LOCKUP_LOCKED_TOKEN_ID)
+    .bind(block_height as i64)
+    .fetch_optional(pool)
+    .await?;
+
+    if previous.as_ref() == Some(&locked) {
+        return Ok(None);
+    }
+    let previous = previous.unwrap_or_else(|| BigDecimal::from(0));
+
+    let block_timestamp = block_info::get_block_timestamp(network, block_height, None).await?;
+    let inserted = sqlx::query(
+        r#"
+        INSERT INTO balance_changes
+        (account_id, token_id, block_height, block_timestamp, block_time, amount, balance_before, balance_after, counterparty, actions, raw_data)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, '{}', $10)
+        ON CONFLICT (account_id, block_height, token_id) DO NOTHING
+        "#,
+    )
+    .bind(account_id)
+    .bind(LOCKUP_LOCKED_TOKEN_ID)
+    .bind(block_height as i64)
+    .bind(block_timestamp)
+    .bind(block_timestamp_to_datetime(block_timestamp))
+    .bind(&locked - &previous)
+    .bind(&previous)
+    .bind(&locked)
+    .bind(LOCKUP_VESTING_COUNTERPARTY)
+    .bind(serde_json::to_value(&split)?)
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok((inserted > 0).then_some(split))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::spawn_mock_rpc;
+    use axum::Json;
+    use serde_json::{Value, json};
+
+    const LOCKUP_ACCOUNT: &str = "5a3c9a2d6f6d7b3f1e8c4a5b6c7d8e9f0a1b2c3d.lockup.near";
+    /// Block from which another 10 NEAR are released
+    const RELEASE_BLOCK: u64 = 150_000_000;
+
+    /// JSON-RPC node for a lockup owning 100 NEAR, 40 locked (30 from `RELEASE_BLOCK`)
+    async fn vesting_rpc(Json(request): Json<Value>) -> Json<Value> {
+        use near_primitives::views::{BlockHeaderView, BlockView};
+
+        let params = &request["params"];
+        let block_height = params["block_id"].as_u64().unwrap();
+        let call_result = |value: &str| {
+            json!({
+                "result": serde_json::to_vec(value).unwrap(),
+                "logs": [],
+                "block_height": block_height,
+                "block_hash": "11111111111111111111111111111111"
+            })
+        };
+
+        let result = if request["method"] == "block" {
+            serde_json::to_value(BlockView {
+                author: "validator.near".parse().unwrap(),
+                header: BlockHeaderView {
+                    height: block_height,
+                    timestamp: block_height * 1_000_000_000,
+                    timestamp_nanosec: block_height * 1_000_000_000,
+                    ..Default::default()
+                },
+                chunks: vec![],
+            })
+            .unwrap()
+        } else {
+            match params["method_name"].as_str() {
+                Some("get_balance") => call_result("100000000000000000000000000"),
+                Some("get_locked_amount") if block_height >= RELEASE_BLOCK => {
+                    call_result("30000000000000000000000000")
+                }
+                Some("get_locked_amount") => call_result("40000000000000000000000000"),
+                other => panic!("Unexpected method {:?}", other),
+            }
+        };
+
+        Json(json!({"jsonrpc": "2.0", "id": request["id"], "result": result}))
+    }
+
+    #[sqlx::test]
+    async fn test_vesting_snapshots_track_locked_amount(pool: PgPool) -> sqlx::Result<()> {
+        let network = spawn_mock_rpc(vesting_rpc).await;
+
+        let snapshot =
+            |block_height| snapshot_vesting(&pool, &network, LOCKUP_ACCOUNT, block_height);
+
+        let first = snapshot(RELEASE_BLOCK - 100).await.unwrap();
+        assert_eq!(
+            first,
+            Some(VestingSplit {
+                total: "100".to_string(),
+                locked: "40".to_string(),
+                unlocked: "60".to_string(),
+            })
+        );
+        assert_eq!(
+            snapshot(RELEASE_BLOCK - 50).await.unwrap(),
+            None,
+            "Unchanged locked amount isn't recorded again"
+        );
+        let released = snapshot(RELEASE_BLOCK).await.unwrap().unwrap();
+        assert_eq!(released.unlocked, "70");
+
+        let rows: Vec<(i64, String, String, String, String, Value)> = sqlx::query_as(
+            r#"
+            SELECT block_height, amount::TEXT, balance_before::TEXT, balance_after::TEXT, counterparty, raw_data
+            FROM balance_changes
+            WHERE account_id = $1 AND token_id = $2
+            ORDER BY block_height
+            "#,
+        )
+        .bind(LOCKUP_ACCOUNT)
+        .bind(LOCKUP_LOCKED_TOKEN_ID)
+        .fetch_all(&pool)
+        .await?;
+
+        let summary: Vec<(i64, &str, &str, &str, &str)> = rows
+            .iter()
+            .map(|(block, amount, before, after, counterparty, _)| {
+                (
+                    *block,
+                    amount.as_str(),
+                    before.as_str(),
+                    after.as_str(),
+                    counterparty.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    (RELEASE_BLOCK - 100) as i64,
+                    "40",
+                    "0",
+                    "40",
+                    LOCKUP_VESTING_COUNTERPARTY
+                ),
+                (
+                    RELEASE_BLOCK as i64,
+                    "-10",
+                    "40",
+                    "30",
+                    LOCKUP_VESTING_COUNTERPARTY
+                ),
+            ]
+        );
+        assert_eq!(
+            rows[1].5,
+            json!({"total": "100", "locked": "30", "unlocked": "70"})
+        );
+
+        Ok(())
+    }
+}
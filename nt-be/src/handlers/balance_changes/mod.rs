@@ -1,8 +1,27 @@
+pub mod account_lock;
 pub mod account_monitor;
 pub mod balance;
 pub mod binary_search;
 pub mod block_info;
+pub mod circuit_breaker;
 pub mod counterparty;
+pub mod fill_cancellation;
+pub mod fill_failures;
 pub mod gap_detector;
 pub mod gap_filler;
+pub mod gas_rewards;
+pub mod indexer_source;
+pub mod lockup_vesting;
+pub mod monitor_ceiling;
+pub mod monitor_liveness;
+pub mod monitor_progress;
+pub mod monitor_supervisor;
+pub mod nep141_event;
+pub mod receipt_audit;
+pub mod reconciliation;
+pub mod rpc_budget;
+pub mod rpc_rate_limit;
+pub mod rpc_tape;
 pub mod token_discovery;
+pub mod transaction_detail;
+pub mod webhooks;
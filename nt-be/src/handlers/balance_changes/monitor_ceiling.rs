@@ -0,0 +1,65 @@
+//! Monitor Ceiling
+//!
+//! Each monitoring cycle normally processes up to the live chain head. For
+//! deterministic replays and testing, operators can pin the block the monitor
+//! processes up to with `MONITOR_UP_TO_BLOCK` or
+//! `POST /api/admin/monitor/ceiling`. A pinned ceiling above the head is capped at
+//! the head, so the monitor never queries blocks that don't exist yet.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// No ceiling is pinned
+const UNPINNED: u64 = 0;
+
+/// Pinned `up_to_block` of the monitor, shared through `AppState`
+#[derive(Debug)]
+pub struct MonitorCeiling(AtomicU64);
+
+impl MonitorCeiling {
+    pub fn new(pinned: Option<u64>) -> Self {
+        Self(AtomicU64::new(pinned.unwrap_or(UNPINNED)))
+    }
+
+    pub fn get(&self) -> Option<u64> {
+        match self.0.load(Ordering::Relaxed) {
+            UNPINNED => None,
+            block => Some(block),
+        }
+    }
+
+    /// Pin the ceiling, or follow the head again with `None`
+    pub fn set(&self, pinned: Option<u64>) {
+        self.0.store(pinned.unwrap_or(UNPINNED), Ordering::Relaxed);
+    }
+
+    /// Block a monitoring cycle processes up to, given the current head
+    pub fn up_to_block(&self, head: u64) -> u64 {
+        self.get().map_or(head, |pinned| pinned.min(head))
+    }
+}
+
+impl Default for MonitorCeiling {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pinned_ceiling_is_respected() {
+        let ceiling = MonitorCeiling::new(Some(150_000_000));
+        assert_eq!(ceiling.up_to_block(180_000_000), 150_000_000);
+        assert_eq!(
+            ceiling.up_to_block(140_000_000),
+            140_000_000,
+            "A ceiling above the head is capped at the head"
+        );
+
+        ceiling.set(None);
+        assert_eq!(ceiling.get(), None);
+        assert_eq!(ceiling.up_to_block(180_000_000), 180_000_000);
+    }
+}
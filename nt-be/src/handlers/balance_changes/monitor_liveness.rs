@@ -0,0 +1,91 @@
+//! Monitor Liveness
+//!
+//! The background monitor records when each cycle completes, so the health check
+//! can report stale data if the monitor task died while the HTTP server kept running.
+//! It also counts how often the supervisor restarted the task after a panic.
+
+use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+
+/// No cycle has completed yet
+const NEVER: i64 = i64::MIN;
+
+#[derive(Debug)]
+pub struct MonitorLiveness {
+    started_at: DateTime<Utc>,
+    /// Unix timestamp in milliseconds of the last completed cycle, or `NEVER`
+    last_cycle_completed_at: AtomicI64,
+    /// Restarts of the monitor task after a panic (see `monitor_supervisor`)
+    restarts: AtomicU64,
+}
+
+impl MonitorLiveness {
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            last_cycle_completed_at: AtomicI64::new(NEVER),
+            restarts: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record_cycle_completed(&self) {
+        self.record_cycle_completed_at(Utc::now());
+    }
+
+    pub fn record_cycle_completed_at(&self, at: DateTime<Utc>) {
+        self.last_cycle_completed_at
+            .store(at.timestamp_millis(), Ordering::Relaxed);
+    }
+
+    pub fn last_cycle_completed_at(&self) -> Option<DateTime<Utc>> {
+        match self.last_cycle_completed_at.load(Ordering::Relaxed) {
+            NEVER => None,
+            millis => DateTime::from_timestamp_millis(millis),
+        }
+    }
+
+    pub fn record_restart(&self) {
+        self.restarts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn restarts(&self) -> u64 {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
+    /// Whether no cycle completed within twice the monitoring interval
+    ///
+    /// Before the first cycle completes, the time is counted from startup.
+    pub fn is_stale(&self, interval: Duration, now: DateTime<Utc>) -> bool {
+        let since = self.last_cycle_completed_at().unwrap_or(self.started_at);
+        let max_age = chrono::Duration::from_std(interval * 2).unwrap_or(chrono::Duration::MAX);
+        now - since > max_age
+    }
+}
+
+impl Default for MonitorLiveness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_staleness_after_two_intervals() {
+        let interval = Duration::from_secs(300);
+        let liveness = MonitorLiveness::new();
+        let now = Utc::now();
+
+        assert!(liveness.last_cycle_completed_at().is_none());
+        assert!(!liveness.is_stale(interval, now));
+        assert!(liveness.is_stale(interval, now + chrono::Duration::minutes(11)));
+
+        liveness.record_cycle_completed_at(now - chrono::Duration::minutes(9));
+        assert!(!liveness.is_stale(interval, now));
+        liveness.record_cycle_completed_at(now - chrono::Duration::minutes(11));
+        assert!(liveness.is_stale(interval, now));
+    }
+}
@@ -0,0 +1,36 @@
+//! Monitor Progress Events
+//!
+//! The monitoring cycle publishes a `MonitorProgress` event after processing each
+//! account/token, so operators can follow a large backfill live (see the
+//! `/api/admin/monitor/progress` SSE endpoint) instead of tailing logs.
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+/// How many events a slow subscriber may fall behind before it starts missing them
+pub const PROGRESS_CHANNEL_CAPACITY: usize = 256;
+
+/// Progress for one account/token after a fill in the monitoring cycle
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MonitorProgress {
+    pub account_id: String,
+    pub token_id: String,
+    /// Gaps still present in the chain after this fill
+    pub gaps_remaining: usize,
+    /// Records inserted for this token during the current cycle
+    pub filled_this_cycle: usize,
+}
+
+pub type ProgressSender = broadcast::Sender<MonitorProgress>;
+
+pub fn progress_channel() -> ProgressSender {
+    broadcast::channel(PROGRESS_CHANNEL_CAPACITY).0
+}
+
+/// Publish an event; having no subscribers is not an error
+pub fn publish(progress: Option<&ProgressSender>, event: MonitorProgress) {
+    if let Some(sender) = progress {
+        let _ = sender.send(event);
+    }
+}
@@ -0,0 +1,134 @@
+//! Monitor Supervisor
+//!
+//! A panic inside a monitoring cycle would otherwise kill the background task for
+//! good while the HTTP server keeps running. `supervise` runs the monitor in its own
+//! task and, when that task panics, logs the panic and starts a fresh one after a
+//! backoff that doubles on every restart (up to `MAX_RESTART_BACKOFF`) and starts over
+//! once a task ran healthily for longer than that. Restarts are counted in
+//! `MonitorLiveness` and reported by the health check.
+
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use super::monitor_liveness::MonitorLiveness;
+
+/// Wait before the first restart
+pub const INITIAL_RESTART_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Upper bound of the wait between restarts
+pub const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// Message of a panic payload (`panic!` with a literal or a formatted string)
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Wait before the next restart of a task that panicked after running for `ran_for`
+///
+/// A task that ran longer than `MAX_RESTART_BACKOFF` was healthy before it panicked,
+/// so the backoff starts over from `initial_backoff` instead of staying at the cap.
+fn restart_backoff(backoff: Duration, initial_backoff: Duration, ran_for: Duration) -> Duration {
+    if ran_for > MAX_RESTART_BACKOFF {
+        initial_backoff
+    } else {
+        backoff
+    }
+}
+
+/// Run the task built by `start` until it returns, restarting it whenever it panics
+///
+/// Each restart waits `initial_backoff`, doubled per restart up to
+/// `MAX_RESTART_BACKOFF` (see `restart_backoff` for when it starts over), and is
+/// recorded in `liveness`.
+pub async fn supervise<F, Fut>(liveness: &MonitorLiveness, initial_backoff: Duration, mut start: F)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let mut backoff = initial_backoff;
+    loop {
+        let started = Instant::now();
+        match tokio::spawn(start()).await {
+            Ok(()) => return,
+            Err(e) if e.is_panic() => {
+                let payload = e.into_panic();
+                backoff = restart_backoff(backoff, initial_backoff, started.elapsed());
+                liveness.record_restart();
+                log::error!(
+                    "Monitor task panicked: {}; restarting in {:?} (restart #{})",
+                    panic_message(payload.as_ref()),
+                    backoff,
+                    liveness.restarts()
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_RESTART_BACKOFF);
+            }
+            Err(e) => {
+                log::warn!("Monitor task was cancelled: {}", e);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_supervisor_restarts_panicking_task() {
+        let liveness = MonitorLiveness::new();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let cycle = {
+            let runs = runs.clone();
+            move || {
+                let runs = runs.clone();
+                async move {
+                    if runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                        panic!("cycle failed");
+                    }
+                }
+            }
+        };
+        supervise(&liveness, Duration::from_millis(10), cycle).await;
+
+        assert_eq!(
+            runs.load(Ordering::SeqCst),
+            2,
+            "Should run again after the panic"
+        );
+        assert_eq!(liveness.restarts(), 1);
+    }
+
+    #[test]
+    fn test_backoff_starts_over_after_healthy_run() {
+        let initial = Duration::from_secs(5);
+
+        // Crash loop: the doubled backoff is kept
+        assert_eq!(
+            restart_backoff(MAX_RESTART_BACKOFF, initial, Duration::from_secs(1)),
+            MAX_RESTART_BACKOFF
+        );
+        assert_eq!(
+            restart_backoff(Duration::from_secs(40), initial, MAX_RESTART_BACKOFF),
+            Duration::from_secs(40)
+        );
+
+        // Ran for hours before panicking: restart quickly again
+        assert_eq!(
+            restart_backoff(
+                MAX_RESTART_BACKOFF,
+                initial,
+                Duration::from_secs(3 * 60 * 60)
+            ),
+            initial
+        );
+    }
+}
@@ -0,0 +1,305 @@
+//! NEP-141 Event Logs
+//!
+//! Strict parsing of `EVENT_JSON:` logs emitted by fungible token contracts
+//! (NEP-297 envelope with the NEP-141 `ft_transfer`/`ft_mint`/`ft_burn` events).
+//! Logs with a missing or malformed envelope are reported as errors rather than
+//! guessed at, so callers can skip them.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// Prefix of NEP-297 event logs
+pub const EVENT_LOG_PREFIX: &str = "EVENT_JSON:";
+
+const NEP141_STANDARD: &str = "nep141";
+
+/// Supported major version of the NEP-141 event standard
+const NEP141_MAJOR_VERSION: &str = "1";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FtTransfer {
+    pub old_owner_id: String,
+    pub new_owner_id: String,
+    pub amount: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FtMint {
+    pub owner_id: String,
+    pub amount: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FtBurn {
+    pub owner_id: String,
+    pub amount: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// A validated NEP-141 event with its typed data
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum Nep141Event {
+    FtTransfer(Vec<FtTransfer>),
+    FtMint(Vec<FtMint>),
+    FtBurn(Vec<FtBurn>),
+}
+
+impl Nep141Event {
+    /// Whether any entry of the event moves tokens of `account_id`
+    pub fn involves(&self, account_id: &str) -> bool {
+        match self {
+            Nep141Event::FtTransfer(transfers) => transfers
+                .iter()
+                .any(|t| t.old_owner_id == account_id || t.new_owner_id == account_id),
+            Nep141Event::FtMint(mints) => mints.iter().any(|m| m.owner_id == account_id),
+            Nep141Event::FtBurn(burns) => burns.iter().any(|b| b.owner_id == account_id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Nep141EventError {
+    /// The log doesn't start with `EVENT_JSON:`
+    NotAnEvent,
+    InvalidJson(String),
+    MissingField(&'static str),
+    /// An event of another standard (e.g. `nep171` NFT events)
+    UnsupportedStandard(String),
+    UnsupportedVersion(String),
+    UnknownEvent(String),
+    InvalidData {
+        event: String,
+        reason: String,
+    },
+}
+
+impl std::fmt::Display for Nep141EventError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Nep141EventError::NotAnEvent => write!(f, "Log is not an {} event", EVENT_LOG_PREFIX),
+            Nep141EventError::InvalidJson(e) => write!(f, "Invalid event JSON: {}", e),
+            Nep141EventError::MissingField(field) => {
+                write!(f, "Event is missing the '{}' field", field)
+            }
+            Nep141EventError::UnsupportedStandard(standard) => {
+                write!(f, "Not a NEP-141 event (standard '{}')", standard)
+            }
+            Nep141EventError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported NEP-141 event version '{}'", version)
+            }
+            Nep141EventError::UnknownEvent(event) => {
+                write!(f, "Unknown NEP-141 event '{}'", event)
+            }
+            Nep141EventError::InvalidData { event, reason } => {
+                write!(f, "Invalid data for {} event: {}", event, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Nep141EventError {}
+
+/// Read a required string field of the event envelope
+fn envelope_field<'a>(
+    envelope: &'a Value,
+    field: &'static str,
+) -> Result<&'a str, Nep141EventError> {
+    envelope
+        .get(field)
+        .and_then(Value::as_str)
+        .ok_or(Nep141EventError::MissingField(field))
+}
+
+fn event_data<T: for<'de> Deserialize<'de>>(
+    event: &str,
+    data: &Value,
+) -> Result<Vec<T>, Nep141EventError> {
+    let entries: Vec<T> =
+        serde_json::from_value(data.clone()).map_err(|e| Nep141EventError::InvalidData {
+            event: event.to_string(),
+            reason: e.to_string(),
+        })?;
+
+    if entries.is_empty() {
+        return Err(Nep141EventError::InvalidData {
+            event: event.to_string(),
+            reason: "data is empty".to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+/// Parse an `EVENT_JSON:` log line into a NEP-141 event
+///
+/// The envelope must have `standard` = `nep141`, a `1.x.x` `version`, a known
+/// `event` and a non-empty `data` array whose entries have the event's fields.
+/// Amounts must be U128 strings.
+pub fn parse(log: &str) -> Result<Nep141Event, Nep141EventError> {
+    let json = log
+        .trim()
+        .strip_prefix(EVENT_LOG_PREFIX)
+        .ok_or(Nep141EventError::NotAnEvent)?;
+    let envelope: Value =
+        serde_json::from_str(json).map_err(|e| Nep141EventError::InvalidJson(e.to_string()))?;
+
+    let standard = envelope_field(&envelope, "standard")?;
+    let version = envelope_field(&envelope, "version")?;
+    let event = envelope_field(&envelope, "event")?;
+    let data = envelope
+        .get("data")
+        .ok_or(Nep141EventError::MissingField("data"))?;
+
+    if standard != NEP141_STANDARD {
+        return Err(Nep141EventError::UnsupportedStandard(standard.to_string()));
+    }
+    if version.split('.').next() != Some(NEP141_MAJOR_VERSION) {
+        return Err(Nep141EventError::UnsupportedVersion(version.to_string()));
+    }
+
+    let parsed = match event {
+        "ft_transfer" => Nep141Event::FtTransfer(event_data(event, data)?),
+        "ft_mint" => Nep141Event::FtMint(event_data(event, data)?),
+        "ft_burn" => Nep141Event::FtBurn(event_data(event, data)?),
+        other => return Err(Nep141EventError::UnknownEvent(other.to_string())),
+    };
+
+    let amounts: Vec<&str> = match &parsed {
+        Nep141Event::FtTransfer(t) => t.iter().map(|t| t.amount.as_str()).collect(),
+        Nep141Event::FtMint(m) => m.iter().map(|m| m.amount.as_str()).collect(),
+        Nep141Event::FtBurn(b) => b.iter().map(|b| b.amount.as_str()).collect(),
+    };
+    if let Some(amount) = amounts.iter().find(|a| a.parse::<u128>().is_err()) {
+        return Err(Nep141EventError::InvalidData {
+            event: event.to_string(),
+            reason: format!("amount '{}' is not a U128", amount),
+        });
+    }
+
+    Ok(parsed)
+}
+
+/// Parse all valid NEP-141 events out of a list of logs
+///
+/// Plain logs are ignored; malformed events are logged and skipped.
+pub fn parse_logs(logs: &[String]) -> Vec<Nep141Event> {
+    logs.iter()
+        .filter(|log| log.trim().starts_with(EVENT_LOG_PREFIX))
+        .filter_map(|log| match parse(log) {
+            Ok(event) => Some(event),
+            // Other standards (NFT, DAO, ...) are expected, not worth a warning
+            Err(Nep141EventError::UnsupportedStandard(_)) => None,
+            Err(e) => {
+                log::warn!("Skipping malformed event log {:?}: {}", log, e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_valid_events() {
+        let transfer = parse(
+            r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_transfer","data":[{"old_owner_id":"alice.near","new_owner_id":"bob.near","amount":"1000","memo":"rent"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            transfer,
+            Nep141Event::FtTransfer(vec![FtTransfer {
+                old_owner_id: "alice.near".to_string(),
+                new_owner_id: "bob.near".to_string(),
+                amount: "1000".to_string(),
+                memo: Some("rent".to_string()),
+            }])
+        );
+        assert!(transfer.involves("bob.near"));
+        assert!(!transfer.involves("carol.near"));
+
+        let mint = parse(
+            r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_mint","data":[{"owner_id":"alice.near","amount":"5"}]}"#,
+        )
+        .unwrap();
+        assert!(matches!(mint, Nep141Event::FtMint(ref m) if m[0].memo.is_none()));
+
+        let burn = parse(
+            r#"EVENT_JSON:{"standard":"nep141","version":"1.1.0","event":"ft_burn","data":[{"owner_id":"alice.near","amount":"5"}]}"#,
+        )
+        .unwrap();
+        assert!(burn.involves("alice.near"));
+    }
+
+    #[test]
+    fn test_parse_malformed_events() {
+        let cases = [
+            (
+                "Transfer 1000 from alice.near",
+                Nep141EventError::NotAnEvent,
+            ),
+            (
+                r#"EVENT_JSON:{"version":"1.0.0","event":"ft_transfer","data":[]}"#,
+                Nep141EventError::MissingField("standard"),
+            ),
+            (
+                r#"EVENT_JSON:{"standard":"nep141","event":"ft_transfer","data":[]}"#,
+                Nep141EventError::MissingField("version"),
+            ),
+            (
+                r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","data":[]}"#,
+                Nep141EventError::MissingField("event"),
+            ),
+            (
+                r#"EVENT_JSON:{"standard":"nep171","version":"1.0.0","event":"nft_mint","data":[]}"#,
+                Nep141EventError::UnsupportedStandard("nep171".to_string()),
+            ),
+            (
+                r#"EVENT_JSON:{"standard":"nep141","version":"2.0.0","event":"ft_mint","data":[]}"#,
+                Nep141EventError::UnsupportedVersion("2.0.0".to_string()),
+            ),
+            (
+                r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_swap","data":[]}"#,
+                Nep141EventError::UnknownEvent("ft_swap".to_string()),
+            ),
+        ];
+        for (log, expected) in cases {
+            assert_eq!(parse(log).unwrap_err(), expected, "{}", log);
+        }
+
+        assert!(matches!(
+            parse("EVENT_JSON:{not json"),
+            Err(Nep141EventError::InvalidJson(_))
+        ));
+        // Wrong data shape, empty data and non-numeric amounts
+        for log in [
+            r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_transfer","data":[{"owner_id":"alice.near","amount":"1"}]}"#,
+            r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_mint","data":[]}"#,
+            r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_burn","data":[{"owner_id":"alice.near","amount":"-1"}]}"#,
+        ] {
+            assert!(
+                matches!(parse(log), Err(Nep141EventError::InvalidData { .. })),
+                "{}",
+                log
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_logs_skips_invalid() {
+        let logs = vec![
+            "plain log".to_string(),
+            r#"EVENT_JSON:{"standard":"nep141","event":"ft_mint","data":[]}"#.to_string(),
+            r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_mint","data":[{"owner_id":"a.near","amount":"1"}]}"#.to_string(),
+        ];
+
+        assert_eq!(parse_logs(&logs).len(), 1);
+    }
+}
@@ -0,0 +1,138 @@
+//! Receipt Audit Storage
+//!
+//! Balance change records only keep receipt ids. When audit mode is enabled
+//! (`AUDIT_MODE=true`), the full `ReceiptView` of every receipt involving the
+//! account at a balance change block is also stored in `balance_change_receipts`,
+//! so forensic audits don't depend on archival RPC staying available.
+//!
+//! Audit mode is part of the fill settings (`FillConfig::audit_mode`); it is off by
+//! default to avoid bloating the database in normal operation.
+
+use near_primitives::views::ReceiptView;
+use serde_json::Value;
+use sqlx::PgPool;
+
+use crate::utils::fill_config::FillConfig;
+
+/// Store the receipts of a balance change block if audit mode is enabled
+///
+/// Receipts already stored for the block are left untouched.
+///
+/// # Returns
+/// Number of receipts inserted (always 0 outside audit mode)
+pub async fn store_receipts(
+    pool: &PgPool,
+    config: &FillConfig,
+    account_id: &str,
+    block_height: i64,
+    receipts: &[ReceiptView],
+) -> Result<u64, sqlx::Error> {
+    if !config.audit_mode {
+        return Ok(0);
+    }
+
+    let mut inserted = 0;
+    for receipt in receipts {
+        let receipt_json = serde_json::to_value(receipt).unwrap_or(Value::Null);
+        inserted += sqlx::query(
+            r#"
+            INSERT INTO balance_change_receipts (account_id, block_height, receipt_id, receipt)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (account_id, block_height, receipt_id) DO NOTHING
+            "#,
+        )
+        .bind(account_id)
+        .bind(block_height)
+        .bind(receipt.receipt_id.to_string())
+        .bind(receipt_json)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    }
+
+    Ok(inserted)
+}
+
+/// Get the stored receipts of an account at a block, in insertion order
+pub async fn get_receipts(
+    pool: &PgPool,
+    account_id: &str,
+    block_height: i64,
+) -> Result<Vec<ReceiptView>, sqlx::Error> {
+    let rows: Vec<Value> = sqlx::query_scalar(
+        r#"
+        SELECT receipt
+        FROM balance_change_receipts
+        WHERE account_id = $1 AND block_height = $2
+        ORDER BY id
+        "#,
+    )
+    .bind(account_id)
+    .bind(block_height)
+    .fetch_all(pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| serde_json::from_value(row).map_err(|e| sqlx::Error::Decode(Box::new(e))))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn receipt(receipt_id: &str) -> ReceiptView {
+        serde_json::from_value(json!({
+            "predecessor_id": "sender.near",
+            "receiver_id": "audit.near",
+            "receipt_id": receipt_id,
+            "receipt": {
+                "Action": {
+                    "signer_id": "sender.near",
+                    "signer_public_key": "ed25519:6E8sCci9badyRkXb3JoRpBj5p8C6Tw41ELDZoiihKEtp",
+                    "gas_price": "100000000",
+                    "output_data_receivers": [],
+                    "input_data_ids": [],
+                    "actions": [{ "Transfer": { "deposit": "1000" } }],
+                    "is_promise_yield": false
+                }
+            },
+            "priority": 0
+        }))
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn test_receipts_persisted_in_audit_mode(pool: PgPool) -> sqlx::Result<()> {
+        let receipts = vec![
+            receipt("Dx5hVmZZtYcCzsEdvYTeDuAygStbVwqUfXYhGPyuHKMd"),
+            receipt("8Lv6N4B7kWeqnmfVLFeo2e4nkvGtd7BV2EDvRbT8gzNa"),
+        ];
+
+        let audit = FillConfig {
+            audit_mode: true,
+            ..FillConfig::default()
+        };
+        assert_eq!(
+            store_receipts(&pool, &audit, "audit.near", 100, &receipts).await?,
+            2
+        );
+        // Storing the same block again doesn't duplicate
+        assert_eq!(
+            store_receipts(&pool, &audit, "audit.near", 100, &receipts).await?,
+            0
+        );
+
+        let stored = get_receipts(&pool, "audit.near", 100).await?;
+        assert_eq!(stored, receipts);
+        assert!(get_receipts(&pool, "audit.near", 101).await?.is_empty());
+
+        assert_eq!(
+            store_receipts(&pool, &FillConfig::default(), "audit.near", 102, &receipts).await?,
+            0
+        );
+
+        Ok(())
+    }
+}
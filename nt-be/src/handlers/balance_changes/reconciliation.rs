@@ -0,0 +1,198 @@
+//! FT Amount Reconciliation
+//!
+//! The `amount` of an FT balance change is derived from the balances before and after
+//! its block (a state diff), independently of the NEP-141 events the token contract
+//! logged. When the record's transactions logged events for its token, the net amount
+//! those events move for the account should equal the recorded amount; a mismatch
+//! points at a bug in change detection or in the contract's event logging.
+
+use bigdecimal::BigDecimal;
+use serde::Serialize;
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+use crate::handlers::balance_changes::nep141_event::Nep141Event;
+use crate::handlers::balance_changes::transaction_detail::FtEventDetail;
+
+/// A recorded FT amount that disagrees with the logged events
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ReconciliationWarning {
+    pub token_contract: String,
+    /// Decimal-adjusted `amount` of the record
+    pub recorded_amount: String,
+    /// Decimal-adjusted net amount the logged events move for the account
+    pub event_amount: String,
+    pub message: String,
+}
+
+/// Whether a token id is a plain NEP-141 contract (not NEAR, intents or lockup)
+pub fn is_ft_token(token_id: &str) -> bool {
+    !token_id.eq_ignore_ascii_case("near") && !token_id.contains(':')
+}
+
+/// Net raw amount the events of `token_contract` move for `account_id`
+///
+/// Incoming transfers and mints count positive, outgoing transfers and burns negative.
+/// Returns `None` when no event of the contract involves the account.
+pub fn net_event_amount(
+    account_id: &str,
+    token_contract: &str,
+    events: &[FtEventDetail],
+) -> Option<BigDecimal> {
+    let mut involved = false;
+    let mut net = BigDecimal::from(0);
+    let mut add = |amount: &str, sign: i32| {
+        // Amounts were validated as U128 when the event was parsed
+        if let Ok(amount) = BigDecimal::from_str(amount) {
+            net += amount * BigDecimal::from(sign);
+            involved = true;
+        }
+    };
+
+    for detail in events.iter().filter(|e| e.token_contract == token_contract) {
+        match &detail.event {
+            Nep141Event::FtTransfer(transfers) => {
+                for t in transfers {
+                    if t.new_owner_id == account_id {
+                        add(&t.amount, 1);
+                    }
+                    if t.old_owner_id == account_id {
+                        add(&t.amount, -1);
+                    }
+                }
+            }
+            Nep141Event::FtMint(mints) => {
+                for m in mints.iter().filter(|m| m.owner_id == account_id) {
+                    add(&m.amount, 1);
+                }
+            }
+            Nep141Event::FtBurn(burns) => {
+                for b in burns.iter().filter(|b| b.owner_id == account_id) {
+                    add(&b.amount, -1);
+                }
+            }
+        }
+    }
+
+    involved.then_some(net)
+}
+
+/// Compare a recorded FT amount with the events logged for it
+///
+/// # Arguments
+/// * `account_id` - Account the balance change belongs to
+/// * `token_contract` - The FT contract (the record's `token_id`)
+/// * `recorded_amount` - Decimal-adjusted `amount` of the record
+/// * `decimals` - Decimals of the token, to scale the raw event amounts
+/// * `events` - NEP-141 events logged by the record's transactions
+///
+/// # Returns
+/// A warning when the events involve the account and their net amount differs from
+/// the recorded one; `None` when they agree or no event involves the account.
+pub fn reconcile_ft_amount(
+    account_id: &str,
+    token_contract: &str,
+    recorded_amount: &BigDecimal,
+    decimals: u8,
+    events: &[FtEventDetail],
+) -> Option<ReconciliationWarning> {
+    let net_raw = net_event_amount(account_id, token_contract, events)?;
+    let event_amount = net_raw * BigDecimal::new(1.into(), decimals as i64);
+
+    if event_amount == *recorded_amount {
+        return None;
+    }
+
+    let warning = ReconciliationWarning {
+        token_contract: token_contract.to_string(),
+        recorded_amount: recorded_amount.normalized().to_string(),
+        event_amount: event_amount.normalized().to_string(),
+        message: format!(
+            "Recorded amount {} of {} for {} differs from the {} moved by ft events",
+            recorded_amount.normalized(),
+            token_contract,
+            account_id,
+            event_amount.normalized()
+        ),
+    };
+    log::warn!("{}", warning.message);
+    Some(warning)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::balance_changes::nep141_event::{FtBurn, FtTransfer};
+
+    const TREASURY: &str = "treasury.sputnik-dao.near";
+
+    fn transfer(from: &str, to: &str, amount: &str) -> FtEventDetail {
+        FtEventDetail {
+            token_contract: "usdc.near".to_string(),
+            event: Nep141Event::FtTransfer(vec![FtTransfer {
+                old_owner_id: from.to_string(),
+                new_owner_id: to.to_string(),
+                amount: amount.to_string(),
+                memo: None,
+            }]),
+        }
+    }
+
+    fn amount(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn test_matching_amounts_are_not_flagged() {
+        let events = vec![
+            transfer("alice.near", TREASURY, "2500000"),
+            transfer(TREASURY, "bob.near", "500000"),
+            // Other tokens and accounts are ignored
+            FtEventDetail {
+                token_contract: "wrap.near".to_string(),
+                ..transfer("alice.near", TREASURY, "1")
+            },
+            transfer("alice.near", "bob.near", "7"),
+        ];
+
+        assert_eq!(
+            reconcile_ft_amount(TREASURY, "usdc.near", &amount("2"), 6, &events),
+            None
+        );
+    }
+
+    #[test]
+    fn test_mismatch_is_flagged() {
+        let events = vec![
+            transfer("alice.near", TREASURY, "2500000"),
+            FtEventDetail {
+                token_contract: "usdc.near".to_string(),
+                event: Nep141Event::FtBurn(vec![FtBurn {
+                    owner_id: TREASURY.to_string(),
+                    amount: "100000".to_string(),
+                    memo: None,
+                }]),
+            },
+        ];
+
+        let warning = reconcile_ft_amount(TREASURY, "usdc.near", &amount("2.5"), 6, &events)
+            .expect("2.5 recorded vs 2.4 logged should be flagged");
+        assert_eq!(warning.token_contract, "usdc.near");
+        assert_eq!(warning.recorded_amount, "2.5");
+        assert_eq!(warning.event_amount, "2.4");
+    }
+
+    #[test]
+    fn test_records_without_events_are_not_checked() {
+        let events = vec![transfer("alice.near", "bob.near", "1")];
+
+        assert_eq!(net_event_amount(TREASURY, "usdc.near", &events), None);
+        assert_eq!(
+            reconcile_ft_amount(TREASURY, "usdc.near", &amount("5"), 6, &events),
+            None
+        );
+        assert!(is_ft_token("usdc.near"));
+        assert!(!is_ft_token("near"));
+        assert!(!is_ft_token("intents.near:nep141:usdc.near"));
+    }
+}
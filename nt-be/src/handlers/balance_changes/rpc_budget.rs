@@ -0,0 +1,136 @@
+//! RPC Call Budget
+//!
+//! A monitoring cycle over many accounts can burn through the FastNEAR quota.
+//! `MONITOR_RPC_BUDGET` caps the RPC calls of one cycle: cycles run inside
+//! `with_budget`, and every call through `circuit_breaker::call_with_breaker` or
+//! `balance::get_balance_at_block` spends from it. Once the budget is spent, calls
+//! fail with `BudgetExhausted` and the cycle stops after the current account.
+//!
+//! Progress is persisted as usual (records already inserted stay, and the account
+//! that ran out is marked as synced), so the next cycle resumes with the accounts
+//! that didn't get a turn.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+tokio::task_local! {
+    static BUDGET: Arc<RpcBudget>;
+}
+
+/// RPC calls allowed in one monitoring cycle
+#[derive(Debug)]
+pub struct RpcBudget {
+    /// `None` for an unlimited budget that only counts calls
+    limit: Option<u64>,
+    spent: AtomicU64,
+    exhausted: AtomicBool,
+}
+
+impl RpcBudget {
+    pub fn new(limit: Option<u64>) -> Self {
+        Self {
+            limit,
+            spent: AtomicU64::new(0),
+            exhausted: AtomicBool::new(false),
+        }
+    }
+
+    /// Spend one call from the budget
+    pub fn try_spend(&self) -> Result<(), BudgetExhausted> {
+        let Some(limit) = self.limit else {
+            self.spent.fetch_add(1, Ordering::Relaxed);
+            return Ok(());
+        };
+
+        let spent = self
+            .spent
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |spent| {
+                (spent < limit).then_some(spent + 1)
+            });
+        if spent.is_err() {
+            self.exhausted.store(true, Ordering::Relaxed);
+            return Err(BudgetExhausted { limit });
+        }
+        Ok(())
+    }
+
+    /// Calls spent so far
+    pub fn spent(&self) -> u64 {
+        self.spent.load(Ordering::Relaxed)
+    }
+
+    /// Whether a call was refused because the budget was spent
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::Relaxed)
+    }
+}
+
+/// Error returned by calls made after the budget was spent
+#[derive(Debug)]
+pub struct BudgetExhausted {
+    pub limit: u64,
+}
+
+impl std::fmt::Display for BudgetExhausted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RPC budget of {} calls exhausted", self.limit)
+    }
+}
+
+impl std::error::Error for BudgetExhausted {}
+
+/// Run a monitoring cycle whose RPC calls spend from `budget`
+pub async fn with_budget<F: Future>(budget: Arc<RpcBudget>, future: F) -> F::Output {
+    BUDGET.scope(budget, future).await
+}
+
+/// Spend one call from the current budget
+///
+/// Always succeeds outside `with_budget`.
+pub fn spend() -> Result<(), BudgetExhausted> {
+    BUDGET
+        .try_with(|budget| budget.try_spend())
+        .unwrap_or(Ok(()))
+}
+
+/// Whether the current budget is exhausted (never outside `with_budget`)
+pub fn is_exhausted() -> bool {
+    BUDGET
+        .try_with(|budget| budget.is_exhausted())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_budget_refuses_calls_once_spent() {
+        let budget = Arc::new(RpcBudget::new(Some(2)));
+
+        with_budget(budget.clone(), async {
+            assert!(spend().is_ok());
+            assert!(spend().is_ok());
+            assert!(!is_exhausted(), "Spending the last call doesn't exhaust it");
+            assert!(spend().is_err());
+            assert!(is_exhausted());
+        })
+        .await;
+        assert_eq!(budget.spent(), 2);
+
+        let unlimited = Arc::new(RpcBudget::new(None));
+        with_budget(unlimited.clone(), async {
+            for _ in 0..10 {
+                assert!(spend().is_ok());
+            }
+        })
+        .await;
+        assert_eq!(unlimited.spent(), 10);
+        assert!(!unlimited.is_exhausted());
+
+        // Outside a scope nothing is counted
+        assert!(spend().is_ok());
+        assert!(!is_exhausted());
+    }
+}
@@ -0,0 +1,168 @@
+//! Outbound RPC Rate Limit
+//!
+//! Concurrent fills can burst far above what FastNEAR allows and get answered with
+//! 429s. With `RPC_RATE_LIMIT_RPS` set, `AppState::rpc_rate_limit` holds a bucket
+//! refilled at that rate, holding at most one second's worth of tokens. Inside
+//! `with_rate_limit` (the monitoring cycle and every API request), each call through
+//! `circuit_breaker::call_with_breaker` (all `block_info` queries) first takes a token
+//! from it. Callers that find the bucket empty wait their turn in order.
+
+use axum::extract::{Request, State};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::AppState;
+
+#[derive(Debug)]
+struct BucketState {
+    /// Available tokens; negative when callers are queued for future tokens
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token bucket pacing calls to `rate_per_second`
+#[derive(Debug)]
+pub struct TokenBucket {
+    rate_per_second: f64,
+    capacity: f64,
+    state: Mutex<BucketState>,
+}
+
+impl TokenBucket {
+    /// A full bucket allowing bursts of `burst` calls
+    pub fn new(rate_per_second: u32, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+        Self {
+            rate_per_second: f64::from(rate_per_second.max(1)),
+            capacity,
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// How long the caller must wait for the token it takes now
+    fn reserve(&self) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let refill = now.duration_since(state.last_refill).as_secs_f64() * self.rate_per_second;
+        state.tokens = (state.tokens + refill).min(self.capacity);
+        state.last_refill = now;
+
+        state.tokens -= 1.0;
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / self.rate_per_second)
+        }
+    }
+
+    /// Wait for a token
+    pub async fn acquire(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+tokio::task_local! {
+    static RATE_LIMIT: Arc<TokenBucket>;
+}
+
+/// Bucket allowing `rate_per_second` outbound RPC calls (`None` disables the limit)
+pub fn rate_limit(rate_per_second: Option<u32>) -> Option<Arc<TokenBucket>> {
+    rate_per_second.map(|rps| Arc::new(TokenBucket::new(rps, rps)))
+}
+
+/// Run `future` with its RPC calls paced by `bucket` (unpaced when `None`)
+pub async fn with_rate_limit<F: Future>(bucket: Option<Arc<TokenBucket>>, future: F) -> F::Output {
+    match bucket {
+        Some(bucket) => RATE_LIMIT.scope(bucket, future).await,
+        None => future.await,
+    }
+}
+
+/// Middleware pacing the RPC calls a request makes with `AppState::rpc_rate_limit`
+pub async fn rate_limit_layer(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    with_rate_limit(state.rpc_rate_limit.clone(), next.run(request)).await
+}
+
+/// Wait until the current rate limit allows another RPC call
+///
+/// Returns right away outside `with_rate_limit`.
+pub async fn acquire() {
+    let bucket = RATE_LIMIT.try_with(Arc::clone).ok();
+    if let Some(bucket) = bucket {
+        bucket.acquire().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrent_calls_are_paced() {
+        let bucket = Arc::new(TokenBucket::new(50, 1));
+        let start = std::time::Instant::now();
+
+        // The first call takes the stored token, the other 10 wait 20ms each in turn
+        let calls: Vec<_> = (0..11)
+            .map(|_| {
+                let bucket = bucket.clone();
+                tokio::spawn(async move { bucket.acquire().await })
+            })
+            .collect();
+        for call in calls {
+            call.await.unwrap();
+        }
+
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(190),
+            "11 calls at 50 rps finished too fast: {:?}",
+            elapsed
+        );
+        assert!(
+            elapsed < Duration::from_millis(600),
+            "Calls were paced too slowly: {:?}",
+            elapsed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_calls_are_paced_only_within_scope() {
+        let three_calls = async || {
+            let start = std::time::Instant::now();
+            for _ in 0..3 {
+                acquire().await;
+            }
+            start.elapsed()
+        };
+
+        assert!(three_calls().await < Duration::from_millis(50));
+        let paced = with_rate_limit(rate_limit(Some(1)), three_calls()).await;
+        assert!(paced >= Duration::from_millis(1900), "{:?}", paced);
+        assert!(with_rate_limit(None, three_calls()).await < Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_burst_within_capacity_does_not_wait() {
+        let bucket = TokenBucket::new(10, 5);
+        let start = std::time::Instant::now();
+        for _ in 0..5 {
+            bucket.acquire().await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,393 @@
+//! RPC Recording and Replay
+//!
+//! To reproduce a data bug offline, a monitoring run (typically pinned with
+//! `MONITOR_UP_TO_BLOCK`) can be recorded with `RPC_RECORD_FILE` and replayed later
+//! with `RPC_REPLAY_FILE`.
+//!
+//! `start_rpc_tape` serves a JSON-RPC endpoint on localhost and returns a network
+//! pointing at it, so every client (near-api and near-jsonrpc-client) goes through it:
+//! - `Record` forwards each request to the upstream network and appends the request
+//!   and response as one JSON line to the file
+//! - `Replay` answers from the file without touching the network
+//!
+//! Requests are matched without their `id`. A request recorded several times is
+//! answered with its responses in order, repeating the last one once they run out.
+
+use axum::{Json, Router, extract::State, routing::post};
+use near_api::{NetworkConfig, RPCEndpoint};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+/// Whether the tape records from the network or replays from the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RpcTapeMode {
+    Record,
+    Replay,
+}
+
+/// One line of a tape file
+#[derive(Debug, Serialize, Deserialize)]
+struct TapeEntry {
+    request: Value,
+    response: Value,
+}
+
+// A single tape lives behind an `Arc` for the whole process
+#[allow(clippy::large_enum_variant)]
+enum Tape {
+    Record {
+        upstream: RPCEndpoint,
+        http_client: reqwest::Client,
+        file: Mutex<tokio::fs::File>,
+    },
+    Replay {
+        responses: Mutex<HashMap<String, VecDeque<Value>>>,
+    },
+}
+
+/// Request (or response) without its JSON-RPC `id`, as a tape lookup key
+fn without_id(value: &Value) -> Value {
+    let mut value = value.clone();
+    if let Some(map) = value.as_object_mut() {
+        map.remove("id");
+    }
+    value
+}
+
+fn with_id(mut response: Value, id: &Value) -> Value {
+    if let Some(map) = response.as_object_mut() {
+        map.insert("id".to_string(), id.clone());
+    }
+    response
+}
+
+async fn record(tape: &Tape, request: &Value) -> Result<Value, String> {
+    let Tape::Record {
+        upstream,
+        http_client,
+        file,
+    } = tape
+    else {
+        unreachable!("Only called on recording tapes");
+    };
+
+    let mut upstream_request = http_client.post(upstream.url.clone()).json(request);
+    if let Some(bearer) = &upstream.bearer_header {
+        upstream_request = upstream_request.header("Authorization", bearer);
+    }
+    let response: Value = upstream_request
+        .send()
+        .await
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let entry = TapeEntry {
+        request: without_id(request),
+        response: without_id(&response),
+    };
+    let mut line = serde_json::to_vec(&entry).map_err(|e| e.to_string())?;
+    line.push(b'\n');
+    file.lock()
+        .await
+        .write_all(&line)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(response)
+}
+
+async fn replay(tape: &Tape, request: &Value) -> Result<Value, String> {
+    let Tape::Replay { responses } = tape else {
+        unreachable!("Only called on replaying tapes");
+    };
+
+    let key = without_id(request).to_string();
+    let mut responses = responses.lock().await;
+    let queue = responses
+        .get_mut(&key)
+        .ok_or_else(|| format!("Request not on tape: {}", key))?;
+    let response = if queue.len() > 1 {
+        queue.pop_front()
+    } else {
+        queue.front().cloned()
+    };
+    response.ok_or_else(|| format!("Request not on tape: {}", key))
+}
+
+async fn handle(State(tape): State<Arc<Tape>>, Json(request): Json<Value>) -> Json<Value> {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let response = match tape.as_ref() {
+        Tape::Record { .. } => record(&tape, &request).await,
+        Tape::Replay { .. } => replay(&tape, &request).await,
+    };
+
+    Json(match response {
+        Ok(response) => with_id(response, &id),
+        Err(e) => {
+            log::error!("RPC tape: {}", e);
+            json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {"code": -32000, "message": "Server error", "data": e}
+            })
+        }
+    })
+}
+
+async fn load_tape(path: &Path) -> Result<HashMap<String, VecDeque<Value>>, std::io::Error> {
+    let contents = tokio::fs::read_to_string(path).await?;
+    let mut responses: HashMap<String, VecDeque<Value>> = HashMap::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let entry: TapeEntry = serde_json::from_str(line)?;
+        responses
+            .entry(entry.request.to_string())
+            .or_default()
+            .push_back(entry.response);
+    }
+    Ok(responses)
+}
+
+/// Serve `upstream` through a recording or replaying tape at `path`
+///
+/// Recording truncates the file. The tape is served until the process exits.
+///
+/// # Returns
+/// A copy of `upstream` whose only RPC endpoint is the tape
+pub async fn start_rpc_tape(
+    mode: RpcTapeMode,
+    path: impl Into<PathBuf>,
+    upstream: &NetworkConfig,
+) -> Result<NetworkConfig, Box<dyn std::error::Error>> {
+    let path = path.into();
+    let tape = match mode {
+        RpcTapeMode::Record => Tape::Record {
+            upstream: upstream
+                .rpc_endpoints
+                .first()
+                .cloned()
+                .ok_or("No RPC endpoint configured")?,
+            http_client: reqwest::Client::new(),
+            file: Mutex::new(tokio::fs::File::create(&path).await?),
+        },
+        RpcTapeMode::Replay => Tape::Replay {
+            responses: Mutex::new(load_tape(&path).await?),
+        },
+    };
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/", listener.local_addr()?);
+    let app = Router::new()
+        .route("/", post(handle))
+        .with_state(Arc::new(tape));
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(listener, app).await {
+            log::error!("RPC tape server stopped: {}", e);
+        }
+    });
+
+    log::info!("RPC tape ({:?}) at {} using {}", mode, url, path.display());
+    Ok(NetworkConfig {
+        rpc_endpoints: vec![RPCEndpoint::new(url.parse()?)],
+        ..upstream.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handlers::balance_changes::account_monitor::run_monitor_cycle;
+    use crate::utils::fill_config::FillConfig;
+    use crate::utils::test_utils::spawn_mock_rpc;
+    use sqlx::PgPool;
+
+    const ACCOUNT: &str = "replayed-account.near";
+    /// Block at which the account's balance goes from 5 to 7 NEAR
+    const CHANGE_BLOCK: u64 = 950_000;
+
+    const HASH: &str = "11111111111111111111111111111111";
+
+    /// The only chunk of `CHANGE_BLOCK`, carrying the transfer to `ACCOUNT`
+    fn chunk_header() -> Value {
+        json!({
+            "chunk_hash": HASH,
+            "prev_block_hash": HASH,
+            "outcome_root": HASH,
+            "prev_state_root": HASH,
+            "encoded_merkle_root": HASH,
+            "encoded_length": 0,
+            "height_created": CHANGE_BLOCK,
+            "height_included": CHANGE_BLOCK,
+            "shard_id": 0,
+            "gas_used": 0,
+            "gas_limit": 0,
+            "balance_burnt": "0",
+            "outgoing_receipts_root": HASH,
+            "tx_root": HASH,
+            "validator_proposals": [],
+            "congestion_info": null,
+            "bandwidth_requests": null,
+            "signature": format!("ed25519:{}", "1".repeat(64))
+        })
+    }
+
+    /// JSON-RPC node where `ACCOUNT` receives 2 NEAR from `sender.near` at `CHANGE_BLOCK`
+    async fn upstream_rpc(Json(request): Json<Value>) -> Json<Value> {
+        use near_primitives::views::{BlockHeaderView, BlockView};
+
+        let params = &request["params"];
+        let block_height = params["block_id"].as_u64().unwrap_or(1_000_000);
+        let result = match request["method"].as_str() {
+            Some("EXPERIMENTAL_changes") => json!({"block_hash": HASH, "changes": []}),
+            Some("block") => serde_json::to_value(BlockView {
+                author: "validator.near".parse().unwrap(),
+                header: BlockHeaderView {
+                    height: block_height,
+                    timestamp: block_height * 1_000_000_000,
+                    timestamp_nanosec: block_height * 1_000_000_000,
+                    ..Default::default()
+                },
+                chunks: if block_height == CHANGE_BLOCK {
+                    vec![serde_json::from_value(chunk_header()).unwrap()]
+                } else {
+                    vec![]
+                },
+            })
+            .unwrap(),
+            Some("chunk") => json!({
+                "author": "validator.near",
+                "header": chunk_header(),
+                "transactions": [],
+                "receipts": [{
+                    "predecessor_id": "sender.near",
+                    "receiver_id": ACCOUNT,
+                    "receipt_id": HASH,
+                    "receipt": {"Action": {
+                        "signer_id": "sender.near",
+                        "signer_public_key": format!("ed25519:{}", HASH),
+                        "gas_price": "0",
+                        "output_data_receivers": [],
+                        "input_data_ids": [],
+                        "actions": [{"Transfer": {"deposit": "2000000000000000000000000"}}]
+                    }}
+                }]
+            }),
+            _ if params["request_type"] == "view_account" => json!({
+                "amount": if block_height >= CHANGE_BLOCK {
+                    "7000000000000000000000000"
+                } else {
+                    "5000000000000000000000000"
+                },
+                "locked": "0",
+                "code_hash": HASH,
+                "storage_usage": 100,
+                "storage_paid_at": 0,
+                "block_height": block_height,
+                "block_hash": HASH
+            }),
+            _ => {
+                return Json(json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": {"code": -32000, "message": "Server error", "data": "unsupported"}
+                }));
+            }
+        };
+
+        Json(json!({"jsonrpc": "2.0", "id": request["id"], "result": result}))
+    }
+
+    type Row = (i64, String, String, String, Option<String>);
+
+    async fn monitor_and_collect(pool: &PgPool, network: &NetworkConfig) -> sqlx::Result<Vec<Row>> {
+        run_monitor_cycle(
+            pool,
+            network,
+            &FillConfig::default(),
+            1_000_000,
+            0,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+        sqlx::query_as(
+            r#"
+            SELECT block_height, token_id, balance_before::TEXT, balance_after::TEXT, counterparty
+            FROM balance_changes
+            WHERE account_id = $1
+            ORDER BY block_height, token_id
+            "#,
+        )
+        .bind(ACCOUNT)
+        .fetch_all(pool)
+        .await
+    }
+
+    async fn reset(pool: &PgPool) -> sqlx::Result<()> {
+        sqlx::query("DELETE FROM balance_changes WHERE account_id = $1")
+            .bind(ACCOUNT)
+            .execute(pool)
+            .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO balance_changes
+            (account_id, token_id, block_height, block_timestamp, block_time, amount, balance_before, balance_after, counterparty, actions, raw_data)
+            VALUES ($1, 'near', 900000, 900000000000000, to_timestamp(900000), 5, 0, 5, 'sender.near', '{}', '{}')
+            "#,
+        )
+        .bind(ACCOUNT)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_recorded_fill_replays_offline_to_identical_results(
+        pool: PgPool,
+    ) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO monitored_accounts (account_id) VALUES ($1)")
+            .bind(ACCOUNT)
+            .execute(&pool)
+            .await?;
+        reset(&pool).await?;
+
+        let upstream = spawn_mock_rpc(upstream_rpc).await;
+
+        let path = std::env::temp_dir().join(format!("rpc-tape-{}.jsonl", uuid::Uuid::new_v4()));
+        let recording = start_rpc_tape(RpcTapeMode::Record, &path, &upstream)
+            .await
+            .unwrap();
+        let recorded = monitor_and_collect(&pool, &recording).await?;
+        assert!(
+            recorded
+                .iter()
+                .any(|(block, _, _, after, _)| *block == CHANGE_BLOCK as i64 && after == "7"),
+            "The fill should find the change, got {:?}",
+            recorded
+        );
+
+        // Replay against an unreachable upstream: everything must come from the file
+        reset(&pool).await?;
+        let offline = NetworkConfig {
+            rpc_endpoints: vec![RPCEndpoint::new("http://127.0.0.1:1/".parse().unwrap())],
+            ..NetworkConfig::mainnet()
+        };
+        let replaying = start_rpc_tape(RpcTapeMode::Replay, &path, &offline)
+            .await
+            .unwrap();
+        let replayed = monitor_and_collect(&pool, &replaying).await?;
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(replayed, recorded);
+
+        Ok(())
+    }
+}
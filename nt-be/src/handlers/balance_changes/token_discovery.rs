@@ -3,9 +3,170 @@
 //! Functions to discover new tokens for monitored accounts by analyzing
 //! transaction receipts and querying contract states.
 
-use near_api::NetworkConfig;
-use near_primitives::views::ReceiptView;
+use moka::future::Cache;
+use near_account_id::AccountIdRef;
+use near_api::{AccountId, Contract, NetworkConfig};
+use near_primitives::views::{ExecutionOutcomeView, FinalExecutionOutcomeViewEnum, ReceiptView};
+use once_cell::sync::Lazy;
+use sqlx::PgPool;
 use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::constants::intents_tokens::get_tokens_map;
+use crate::constants::{INTENTS_CONTRACT_ID, TREASURY_FACTORY_CONTRACT_ID};
+use crate::handlers::balance_changes::block_info;
+use crate::handlers::balance_changes::circuit_breaker::EndpointFailure;
+use crate::handlers::balance_changes::counterparty::FtMetadata;
+use crate::handlers::balance_changes::nep141_event;
+use crate::utils::decimals::known_decimals;
+use crate::utils::network::primary_rpc_url;
+
+/// How long an account's `mt_tokens_for_owner` result is reused
+pub const OWNED_INTENTS_TOKENS_TTL: Duration = Duration::from_secs(60);
+
+/// Intents token ids owned per (RPC url, contract, account), shared by the assets
+/// endpoint and monitoring
+///
+/// Keyed by RPC url so networks (e.g. mock nodes in tests) never see each other's results.
+static OWNED_INTENTS_TOKENS: Lazy<Cache<(String, AccountId, String), Vec<String>>> =
+    Lazy::new(|| {
+        Cache::builder()
+            .max_capacity(10_000)
+            .time_to_live(OWNED_INTENTS_TOKENS_TTL)
+            .build()
+    });
+
+/// Result of the spam heuristic applied to a newly discovered token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenClassification {
+    Legit,
+    SuspectedSpam,
+}
+
+impl TokenClassification {
+    /// Value stored in the `discovered_tokens.classification` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenClassification::Legit => "legit",
+            TokenClassification::SuspectedSpam => "suspected_spam",
+        }
+    }
+}
+
+/// Signals used to classify a discovered FT token
+#[derive(Debug, Clone, Default)]
+pub struct TokenSignals {
+    /// The contract returned valid `ft_metadata`
+    pub has_metadata: bool,
+    /// The token is listed in the NEAR Intents token list
+    pub is_known_token: bool,
+    /// The monitored account signed at least one transaction to the token contract
+    /// (e.g. storage_deposit or ft_transfer), i.e. it acknowledged the token.
+    /// `None` for Sputnik DAOs, which never sign transactions themselves: their
+    /// calls are executed proposals signed by a member.
+    pub account_initiated_interaction: Option<bool>,
+}
+
+/// Classify a discovered token as legit or suspected spam
+///
+/// Tokens without metadata are always suspected spam. Known tokens are always legit.
+/// Otherwise, a token the account never interacted with itself (only received
+/// an unsolicited transfer) is treated as a likely airdrop. Where the interaction
+/// signal doesn't apply (DAOs), a token with metadata is legit.
+pub fn classify_token(signals: &TokenSignals) -> TokenClassification {
+    if !signals.has_metadata {
+        return TokenClassification::SuspectedSpam;
+    }
+
+    if signals.is_known_token || signals.account_initiated_interaction.unwrap_or(true) {
+        TokenClassification::Legit
+    } else {
+        TokenClassification::SuspectedSpam
+    }
+}
+
+/// Whether a contract answers `ft_metadata` with valid metadata
+///
+/// # Returns
+/// * `Ok(false)` - The contract has no valid `ft_metadata`
+/// * `Err` - The RPC endpoint failed, so the answer is unknown
+async fn has_ft_metadata(
+    network: &NetworkConfig,
+    token_contract: &str,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let Ok(contract_id) = token_contract.parse::<AccountId>() else {
+        return Ok(false);
+    };
+
+    let result: Result<near_api::Data<FtMetadata>, _> = Contract(contract_id)
+        .call_function("ft_metadata", serde_json::json!({}))
+        .read_only()
+        .fetch_from(network)
+        .await;
+
+    match result {
+        Ok(_) => Ok(true),
+        Err(e) if e.is_endpoint_failure() => Err(e.into()),
+        Err(_) => Ok(false),
+    }
+}
+
+/// Gather classification signals for an FT token discovered for an account
+///
+/// Uses decimals already in the token decimals registry when available, falling back
+/// to an `ft_metadata` RPC call; if that call fails at the RPC level the error is
+/// returned rather than the token being judged metadata-less. Interaction history comes from recorded NEAR
+/// balance changes where the account signed a transaction to the token contract;
+/// it isn't gathered for Sputnik DAOs, which never sign.
+pub async fn gather_token_signals(
+    pool: &PgPool,
+    network: &NetworkConfig,
+    account_id: &str,
+    token_contract: &str,
+) -> Result<TokenSignals, Box<dyn std::error::Error>> {
+    let cached_decimals = known_decimals(pool, token_contract).await?;
+    let has_metadata = match cached_decimals {
+        Some(_) => true,
+        None => has_ft_metadata(network, token_contract).await?,
+    };
+
+    let defuse_asset_id = format!("nep141:{}", token_contract);
+    let is_known_token = get_tokens_map().values().any(|unified| {
+        unified
+            .grouped_tokens
+            .iter()
+            .any(|t| t.defuse_asset_id == defuse_asset_id)
+    });
+
+    let is_dao = AccountIdRef::new(account_id)
+        .is_ok_and(|account| account.is_sub_account_of(TREASURY_FACTORY_CONTRACT_ID));
+    let account_initiated_interaction = if is_dao {
+        None
+    } else {
+        let interacted: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1
+                FROM balance_changes
+                WHERE account_id = $1
+                  AND counterparty = $2
+                  AND signer_id = $1
+            )
+            "#,
+        )
+        .bind(account_id)
+        .bind(token_contract)
+        .fetch_one(pool)
+        .await?;
+        Some(interacted)
+    };
+
+    Ok(TokenSignals {
+        has_metadata,
+        is_known_token,
+        account_initiated_interaction,
+    })
+}
 
 /// Extract FT token contract addresses from a receipt
 ///
@@ -49,6 +210,65 @@ pub fn extract_ft_tokens_from_receipt(receipt: &ReceiptView, account_id: &str) -
     tokens
 }
 
+/// Extract FT token contract addresses from the event logs of an execution outcome
+///
+/// A contract is only returned if it emitted a valid NEP-141 event moving tokens
+/// of the monitored account; malformed `EVENT_JSON` logs are skipped.
+///
+/// # Arguments
+/// * `outcome` - The execution outcome to analyze (its executor is the token contract)
+/// * `account_id` - The account we're monitoring
+pub fn extract_ft_tokens_from_logs(
+    outcome: &ExecutionOutcomeView,
+    account_id: &str,
+) -> HashSet<String> {
+    let mut tokens = HashSet::new();
+
+    if nep141_event::parse_logs(&outcome.logs)
+        .iter()
+        .any(|event| event.involves(account_id))
+    {
+        tokens.insert(outcome.executor_id.to_string());
+    }
+
+    tokens
+}
+
+/// Extract FT token contract addresses from the event logs of a whole transaction
+///
+/// Catches tokens moved by contracts other than the transaction's counterparty, e.g.
+/// the tokens swapped through a DEX on the account's behalf.
+///
+/// # Arguments
+/// * `network` - NEAR network configuration (archival RPC for old transactions)
+/// * `tx_hash` - The transaction to scan
+/// * `account_id` - The account we're monitoring
+///
+/// # Returns
+/// Set of token contract addresses that logged NEP-141 events for the account
+pub async fn ft_tokens_from_transaction_logs(
+    network: &NetworkConfig,
+    tx_hash: &str,
+    account_id: &str,
+) -> Result<HashSet<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let response = block_info::get_transaction(network, tx_hash, account_id).await?;
+
+    let outcome = match response
+        .final_execution_outcome
+        .ok_or("Transaction response has no final_execution_outcome")?
+    {
+        FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(outcome) => outcome,
+        FinalExecutionOutcomeViewEnum::FinalExecutionOutcomeWithReceipt(outcome) => {
+            outcome.final_outcome
+        }
+    };
+
+    Ok(std::iter::once(&outcome.transaction_outcome)
+        .chain(&outcome.receipts_outcome)
+        .flat_map(|outcome| extract_ft_tokens_from_logs(&outcome.outcome, account_id))
+        .collect())
+}
+
 /// Snapshot current NEAR Intents token holdings for an account
 ///
 /// Queries the intents.near multi-token contract via mt_tokens_for_owner
@@ -68,25 +288,78 @@ pub async fn snapshot_intents_tokens(
     network: &NetworkConfig,
     account_id: &str,
 ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    call_mt_tokens_for_owner(network, account_id).await
+    // Prepend "intents.near:" to match our format
+    Ok(
+        owned_intents_tokens(network, INTENTS_CONTRACT_ID, account_id)
+            .await?
+            .into_iter()
+            .map(|token_id| format!("{}:{}", INTENTS_CONTRACT_ID, token_id))
+            .collect(),
+    )
 }
 
-/// Internal helper to call mt_tokens_for_owner on intents.near contract
+/// Intents token ids owned by an account on `contract_id` (e.g. "nep141:btc.omft.near")
+///
+/// Results of `mt_tokens_for_owner` are cached per network, contract and account for
+/// `OWNED_INTENTS_TOKENS_TTL`, so the assets endpoint and a monitoring cycle
+/// don't query the contract for the same account again. Errors are not cached.
+pub async fn owned_intents_tokens(
+    network: &NetworkConfig,
+    contract_id: &AccountIdRef,
+    account_id: &str,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let key = (
+        primary_rpc_url(network),
+        contract_id.to_owned(),
+        account_id.to_string(),
+    );
+    if let Some(tokens) = OWNED_INTENTS_TOKENS.get(&key).await {
+        return Ok(tokens);
+    }
+
+    let tokens =
+        call_mt_tokens_for_owner(network, contract_id, account_id, near_api::Reference::Final)
+            .await?;
+    OWNED_INTENTS_TOKENS.insert(key, tokens.clone()).await;
+    Ok(tokens)
+}
+
+/// Intents tokens an account owned at a past block, in "intents.near:nep141:..." format
+///
+/// Not cached; use an archival network for old blocks.
+pub async fn intents_tokens_at_block(
+    network: &NetworkConfig,
+    account_id: &str,
+    block_height: u64,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    Ok(call_mt_tokens_for_owner(
+        network,
+        INTENTS_CONTRACT_ID,
+        account_id,
+        near_api::Reference::AtBlock(block_height),
+    )
+    .await?
+    .into_iter()
+    .map(|token_id| format!("{}:{}", INTENTS_CONTRACT_ID, token_id))
+    .collect())
+}
+
+/// Internal helper to call mt_tokens_for_owner on the intents contract
 async fn call_mt_tokens_for_owner(
     network: &NetworkConfig,
+    contract_id: &AccountIdRef,
     account_id: &str,
+    reference: near_api::Reference,
 ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    use near_api::{Contract, Reference};
+    use near_api::Contract;
     use serde::Deserialize;
-    use std::str::FromStr;
 
     #[derive(Deserialize)]
     struct TokenEntry {
         token_id: String,
     }
 
-    let contract_id = near_api::types::AccountId::from_str("intents.near")?;
-    let contract = Contract(contract_id);
+    let contract = Contract(contract_id.into());
 
     let args = serde_json::json!({
         "account_id": account_id
@@ -96,16 +369,194 @@ async fn call_mt_tokens_for_owner(
     let response: near_api::Data<Vec<TokenEntry>> = contract
         .call_function("mt_tokens_for_owner", args)
         .read_only()
-        .at(Reference::Final)
+        .at(reference)
         .fetch_from(network)
         .await?;
 
-    // Extract token_ids and prepend "intents.near:" to match our format
-    let tokens: Vec<String> = response
+    Ok(response
         .data
         .into_iter()
-        .map(|entry| format!("intents.near:{}", entry.token_id))
-        .collect();
+        .map(|entry| entry.token_id)
+        .collect())
+}
 
-    Ok(tokens)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{spawn_mock_rpc_with_state, spawn_mock_server};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn outcome_with_logs(executor_id: &str, logs: &[&str]) -> ExecutionOutcomeView {
+        serde_json::from_value(serde_json::json!({
+            "logs": logs,
+            "receipt_ids": [],
+            "gas_burnt": 0,
+            "tokens_burnt": "0",
+            "executor_id": executor_id,
+            "status": { "SuccessValue": "" }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_extract_ft_tokens_from_event_logs() {
+        let transfer = r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_transfer","data":[{"old_owner_id":"alice.near","new_owner_id":"treasury.sputnik-dao.near","amount":"100"}]}"#;
+        let outcome = outcome_with_logs("usdt.tether-token.near", &[transfer]);
+
+        assert_eq!(
+            extract_ft_tokens_from_logs(&outcome, "treasury.sputnik-dao.near"),
+            HashSet::from(["usdt.tether-token.near".to_string()])
+        );
+        assert!(extract_ft_tokens_from_logs(&outcome, "bob.near").is_empty());
+
+        // An event without its standard envelope isn't trusted
+        let malformed = outcome_with_logs(
+            "spam.near",
+            &[
+                r#"EVENT_JSON:{"event":"ft_transfer","data":[{"old_owner_id":"alice.near","new_owner_id":"treasury.sputnik-dao.near","amount":"100"}]}"#,
+            ],
+        );
+        assert!(extract_ft_tokens_from_logs(&malformed, "treasury.sputnik-dao.near").is_empty());
+    }
+
+    #[test]
+    fn test_metadata_less_airdrop_is_suspected_spam() {
+        let signals = TokenSignals {
+            has_metadata: false,
+            is_known_token: false,
+            account_initiated_interaction: Some(false),
+        };
+
+        assert_eq!(classify_token(&signals), TokenClassification::SuspectedSpam);
+    }
+
+    #[test]
+    fn test_unacknowledged_incoming_transfer_is_suspected_spam() {
+        let signals = TokenSignals {
+            has_metadata: true,
+            is_known_token: false,
+            account_initiated_interaction: Some(false),
+        };
+
+        assert_eq!(classify_token(&signals), TokenClassification::SuspectedSpam);
+    }
+
+    #[test]
+    fn test_known_or_used_token_is_legit() {
+        let known = TokenSignals {
+            has_metadata: true,
+            is_known_token: true,
+            account_initiated_interaction: Some(false),
+        };
+        let used = TokenSignals {
+            has_metadata: true,
+            is_known_token: false,
+            account_initiated_interaction: Some(true),
+        };
+
+        assert_eq!(classify_token(&known), TokenClassification::Legit);
+        assert_eq!(classify_token(&used), TokenClassification::Legit);
+    }
+
+    #[test]
+    fn test_dao_token_with_metadata_is_legit() {
+        // DAOs never sign, so the interaction signal doesn't apply
+        let dao = TokenSignals {
+            has_metadata: true,
+            is_known_token: false,
+            account_initiated_interaction: None,
+        };
+        let metadata_less = TokenSignals {
+            has_metadata: false,
+            ..dao.clone()
+        };
+
+        assert_eq!(classify_token(&dao), TokenClassification::Legit);
+        assert_eq!(
+            classify_token(&metadata_less),
+            TokenClassification::SuspectedSpam
+        );
+    }
+
+    #[tokio::test]
+    async fn test_metadata_lookup_separates_rpc_failures() {
+        use axum::{Router, http::StatusCode, routing::post};
+
+        // A contract answering something other than metadata
+        async fn not_metadata(
+            axum::Json(request): axum::Json<serde_json::Value>,
+        ) -> axum::Json<serde_json::Value> {
+            axum::Json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": {
+                    "result": b"42".to_vec(),
+                    "logs": [],
+                    "block_height": 1,
+                    "block_hash": "11111111111111111111111111111111"
+                }
+            }))
+        }
+
+        async fn unavailable() -> StatusCode {
+            StatusCode::SERVICE_UNAVAILABLE
+        }
+
+        for (app, expected) in [
+            (Router::new().route("/", post(not_metadata)), Some(false)),
+            (Router::new().route("/", post(unavailable)), None),
+        ] {
+            let url = spawn_mock_server(app).await;
+            let network = NetworkConfig {
+                rpc_endpoints: vec![near_api::RPCEndpoint::new(url.parse().unwrap())],
+                ..NetworkConfig::mainnet()
+            };
+
+            let has_metadata = has_ft_metadata(&network, "token.near").await.ok();
+            assert_eq!(has_metadata, expected);
+        }
+    }
+
+    /// JSON-RPC node answering `mt_tokens_for_owner`, counting the calls
+    async fn mt_tokens_rpc(
+        axum::extract::State(calls): axum::extract::State<std::sync::Arc<AtomicUsize>>,
+        axum::Json(request): axum::Json<serde_json::Value>,
+    ) -> axum::Json<serde_json::Value> {
+        assert_eq!(request["params"]["method_name"], "mt_tokens_for_owner");
+        calls.fetch_add(1, Ordering::SeqCst);
+
+        let tokens = serde_json::json!([{"token_id": "nep141:btc.omft.near"}]);
+        axum::Json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": {
+                "result": serde_json::to_vec(&tokens).unwrap(),
+                "logs": [],
+                "block_height": 1,
+                "block_hash": "11111111111111111111111111111111"
+            }
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_owned_intents_tokens_are_cached() {
+        let calls = std::sync::Arc::new(AtomicUsize::new(0));
+        let network = spawn_mock_rpc_with_state(mt_tokens_rpc, calls.clone()).await;
+
+        // The assets endpoint and monitoring both go through the cache
+        let owned = owned_intents_tokens(&network, INTENTS_CONTRACT_ID, "cached-owner.near")
+            .await
+            .unwrap();
+        assert_eq!(owned, vec!["nep141:btc.omft.near"]);
+        let snapshot = snapshot_intents_tokens(&network, "cached-owner.near")
+            .await
+            .unwrap();
+        assert_eq!(snapshot, vec!["intents.near:nep141:btc.omft.near"]);
+
+        assert_eq!(
+            calls.load(Ordering::SeqCst),
+            1,
+            "Second lookup within the TTL should not call the contract"
+        );
+    }
 }
@@ -0,0 +1,192 @@
+//! Transaction Detail
+//!
+//! Decodes the transaction behind a balance change into a readable summary of its
+//! actions (function calls with their arguments, transfers, ...), so clients don't
+//! need to query RPC themselves to see what a transaction did. NEP-141 events
+//! logged during execution are included as typed transfers, mints and burns.
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use near_api::NetworkConfig;
+use near_primitives::views::{
+    ActionView, ExecutionOutcomeWithIdView, FinalExecutionOutcomeViewEnum,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+use crate::handlers::balance_changes::block_info;
+use crate::handlers::balance_changes::nep141_event::{self, Nep141Event};
+
+/// A single transaction action, decoded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActionSummary {
+    FunctionCall {
+        method_name: String,
+        /// JSON arguments, or a base64 string if the arguments are not JSON
+        args: Value,
+        gas: u64,
+        /// Attached deposit in yoctoNEAR
+        deposit: String,
+    },
+    Transfer {
+        /// Amount in yoctoNEAR
+        deposit: String,
+    },
+    /// Any other action (key management, staking, deployments, ...)
+    Other { kind: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransactionDetail {
+    pub transaction_hash: String,
+    pub signer_id: String,
+    pub receiver_id: String,
+    pub actions: Vec<ActionSummary>,
+    /// Valid NEP-141 events logged while executing the transaction
+    #[serde(default)]
+    pub ft_events: Vec<FtEventDetail>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FtEventDetail {
+    /// The contract that emitted the event
+    pub token_contract: String,
+    pub event: Nep141Event,
+}
+
+/// Collect the NEP-141 events of the given execution outcomes, in execution order
+pub fn ft_events_from_outcomes<'a>(
+    outcomes: impl IntoIterator<Item = &'a ExecutionOutcomeWithIdView>,
+) -> Vec<FtEventDetail> {
+    outcomes
+        .into_iter()
+        .flat_map(|outcome| {
+            let token_contract = outcome.outcome.executor_id.to_string();
+            nep141_event::parse_logs(&outcome.outcome.logs)
+                .into_iter()
+                .map(move |event| FtEventDetail {
+                    token_contract: token_contract.clone(),
+                    event,
+                })
+        })
+        .collect()
+}
+
+/// Decode function call arguments as JSON, falling back to base64
+fn decode_args(args: &[u8]) -> Value {
+    serde_json::from_slice(args).unwrap_or_else(|_| Value::String(BASE64_STANDARD.encode(args)))
+}
+
+pub fn summarize_action(action: &ActionView) -> ActionSummary {
+    match action {
+        ActionView::FunctionCall {
+            method_name,
+            args,
+            gas,
+            deposit,
+        } => ActionSummary::FunctionCall {
+            method_name: method_name.clone(),
+            args: decode_args(args),
+            gas: gas.as_gas(),
+            deposit: deposit.as_yoctonear().to_string(),
+        },
+        ActionView::Transfer { deposit } => ActionSummary::Transfer {
+            deposit: deposit.as_yoctonear().to_string(),
+        },
+        other => {
+            // Use the variant name, e.g. "AddKey" or "Stake"
+            let debug = format!("{:?}", other);
+            let kind = debug
+                .split(|c: char| !c.is_alphanumeric())
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            ActionSummary::Other { kind }
+        }
+    }
+}
+
+/// Fetch a transaction and summarize its actions
+///
+/// # Arguments
+/// * `network` - NEAR network configuration (archival RPC)
+/// * `tx_hash` - The transaction hash
+/// * `sender_id` - The transaction signer, or the account the balance change belongs to
+pub async fn fetch_transaction_detail(
+    network: &NetworkConfig,
+    tx_hash: &str,
+    sender_id: &str,
+) -> Result<TransactionDetail, Box<dyn std::error::Error + Send + Sync>> {
+    let response = block_info::get_transaction(network, tx_hash, sender_id).await?;
+
+    let outcome = match response
+        .final_execution_outcome
+        .ok_or("Transaction response has no final_execution_outcome")?
+    {
+        FinalExecutionOutcomeViewEnum::FinalExecutionOutcome(outcome) => outcome,
+        FinalExecutionOutcomeViewEnum::FinalExecutionOutcomeWithReceipt(outcome) => {
+            outcome.final_outcome
+        }
+    };
+    let transaction = outcome.transaction;
+    let ft_events = ft_events_from_outcomes(
+        std::iter::once(&outcome.transaction_outcome).chain(&outcome.receipts_outcome),
+    );
+
+    Ok(TransactionDetail {
+        transaction_hash: tx_hash.to_string(),
+        signer_id: transaction.signer_id.to_string(),
+        receiver_id: transaction.receiver_id.to_string(),
+        actions: transaction.actions.iter().map(summarize_action).collect(),
+        ft_events,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_api::NearToken;
+    use near_primitives::types::{FunctionArgs, Gas};
+
+    #[test]
+    fn test_summarize_function_call_decodes_json_args() {
+        let action = ActionView::FunctionCall {
+            method_name: "ft_transfer".to_string(),
+            args: FunctionArgs::from(br#"{"receiver_id":"bob.near","amount":"1000"}"#.to_vec()),
+            gas: Gas::from_teragas(30),
+            deposit: NearToken::from_yoctonear(1),
+        };
+
+        assert_eq!(
+            summarize_action(&action),
+            ActionSummary::FunctionCall {
+                method_name: "ft_transfer".to_string(),
+                args: serde_json::json!({"receiver_id": "bob.near", "amount": "1000"}),
+                gas: 30_000_000_000_000,
+                deposit: "1".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_summarize_non_json_args_and_other_actions() {
+        let action = ActionView::FunctionCall {
+            method_name: "raw".to_string(),
+            args: FunctionArgs::from(vec![0xff, 0x00]),
+            gas: Gas::from_gas(1),
+            deposit: NearToken::from_yoctonear(0),
+        };
+        let ActionSummary::FunctionCall { args, .. } = summarize_action(&action) else {
+            panic!("Expected a function call");
+        };
+        assert_eq!(args, Value::String("/wA=".to_string()));
+
+        assert_eq!(
+            summarize_action(&ActionView::CreateAccount),
+            ActionSummary::Other {
+                kind: "CreateAccount".to_string()
+            }
+        );
+    }
+}
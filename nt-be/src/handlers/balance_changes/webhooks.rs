@@ -0,0 +1,488 @@
+//! Balance Change Webhooks
+//!
+//! Integrators can register webhook URLs for a monitored account. Whenever the gap
+//! filler records a new balance change (not a SNAPSHOT) for the account, a JSON payload
+//! is POSTed to each enabled webhook. Each change is delivered at most once per
+//! webhook, even if its record is later rewritten by a rebuild or an enrichment.
+//!
+//! Every notification is first written to `webhook_deliveries`, then attempted right
+//! away in the background. Failed attempts are retried with exponential backoff by
+//! `spawn_delivery_retries` until `MAX_ATTEMPTS` is reached.
+//!
+//! A sender claims a delivery by switching it to `in_flight` before sending, so the
+//! first attempt and the retry loop never send the same row twice. A claim left
+//! behind by a crashed sender expires after `CLAIM_LEASE` and is retried.
+//!
+//! Each request carries an `X-Signature: t=<unix seconds>,v1=<hex>` header signing
+//! the timestamp and body with the webhook's secret (see `utils::webhook`).
+//!
+//! The URL is resolved again before every attempt and refused if it now points at a
+//! non-public address; the request is pinned to the checked addresses and redirects
+//! are not followed, so DNS changes can't redirect a delivery into the network.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::time::Duration;
+
+use super::gap_filler::{FilledGap, block_timestamp_to_datetime};
+use crate::utils::webhook::{SIGNATURE_HEADER, resolve_public_url, sign};
+
+/// Attempts after which a delivery is marked failed
+pub const MAX_ATTEMPTS: i32 = 6;
+
+/// Delay before the first retry; doubled for every further attempt
+const BASE_RETRY_DELAY_SECS: i64 = 30;
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a claimed delivery is left to its sender before others may retry it
+const CLAIM_LEASE_SECS: i64 = 300;
+
+/// POST a signed payload to a webhook URL that resolves to public addresses only
+async fn send_payload(
+    url: &str,
+    secret: &str,
+    body: String,
+    allow_private_urls: bool,
+) -> Result<(), String> {
+    let (host, addrs) = resolve_public_url(url, allow_private_urls).await?;
+
+    let mut builder = reqwest::Client::builder()
+        .timeout(DELIVERY_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none());
+    if !addrs.is_empty() {
+        builder = builder.resolve_to_addrs(&host, &addrs);
+    }
+    let client = builder.build().map_err(|e| e.to_string())?;
+
+    client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(
+            SIGNATURE_HEADER,
+            sign(secret, Utc::now().timestamp(), body.as_bytes()),
+        )
+        .body(body)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Body POSTed to webhooks for a balance change
+#[derive(Debug, Clone, Serialize)]
+pub struct BalanceChangePayload {
+    pub event: &'static str,
+    pub account_id: String,
+    pub token_id: String,
+    pub block_height: i64,
+    pub block_time: DateTime<Utc>,
+    pub balance_before: String,
+    pub balance_after: String,
+}
+
+impl From<&FilledGap> for BalanceChangePayload {
+    fn from(change: &FilledGap) -> Self {
+        Self {
+            event: "balance_change",
+            account_id: change.account_id.clone(),
+            token_id: change.token_id.clone(),
+            block_height: change.block_height,
+            block_time: block_timestamp_to_datetime(change.block_timestamp),
+            balance_before: change.balance_before.clone(),
+            balance_after: change.balance_after.clone(),
+        }
+    }
+}
+
+/// Delay before retrying a delivery that has failed `attempts` times
+fn retry_delay(attempts: i32) -> chrono::Duration {
+    let exponent = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    chrono::Duration::seconds(BASE_RETRY_DELAY_SECS * 2_i64.pow(exponent))
+}
+
+/// Log a delivery for each enabled webhook of the account
+///
+/// Webhooks that already have a delivery for the same account, token and block are
+/// skipped, so a change whose record is written again isn't notified twice.
+///
+/// # Returns
+/// Ids of the new deliveries
+pub async fn enqueue_deliveries(
+    pool: &PgPool,
+    change: &FilledGap,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let payload = serde_json::to_value(BalanceChangePayload::from(change)).unwrap_or(Value::Null);
+
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO webhook_deliveries (webhook_id, account_id, token_id, block_height, payload)
+        SELECT id, $1, $2, $3, $4
+        FROM webhooks
+        WHERE account_id = $1 AND enabled = true
+        ON CONFLICT (webhook_id, account_id, token_id, block_height) DO NOTHING
+        RETURNING id
+        "#,
+    )
+    .bind(&change.account_id)
+    .bind(&change.token_id)
+    .bind(change.block_height)
+    .bind(payload)
+    .fetch_all(pool)
+    .await
+}
+
+/// Attempt a single delivery and record the outcome
+///
+/// The delivery is claimed first; if it is already delivered, failed or claimed by
+/// another sender, nothing is sent.
+///
+/// # Returns
+/// Whether the webhook accepted the payload (2xx response)
+pub async fn attempt_delivery(
+    pool: &PgPool,
+    delivery_id: i64,
+    allow_private_urls: bool,
+) -> Result<bool, sqlx::Error> {
+    let Some((url, secret, payload, attempts)) = sqlx::query_as::<_, (String, String, Value, i32)>(
+        r#"
+        UPDATE webhook_deliveries d
+        SET status = 'in_flight', next_attempt_at = NOW() + make_interval(secs => $2)
+        FROM webhooks w
+        WHERE d.id = $1
+          AND w.id = d.webhook_id
+          AND (d.status = 'pending' OR (d.status = 'in_flight' AND d.next_attempt_at <= NOW()))
+        RETURNING w.url, w.secret, d.payload, d.attempts
+        "#,
+    )
+    .bind(delivery_id)
+    .bind(CLAIM_LEASE_SECS as f64)
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(false);
+    };
+
+    let result = send_payload(&url, &secret, payload.to_string(), allow_private_urls).await;
+
+    let attempts = attempts + 1;
+    match result {
+        Ok(_) => {
+            sqlx::query(
+                r#"
+                UPDATE webhook_deliveries
+                SET status = 'delivered', attempts = $2, last_error = NULL, delivered_at = NOW()
+                WHERE id = $1
+                "#,
+            )
+            .bind(delivery_id)
+            .bind(attempts)
+            .execute(pool)
+            .await?;
+            Ok(true)
+        }
+        Err(e) => {
+            let status = if attempts >= MAX_ATTEMPTS {
+                "failed"
+            } else {
+                "pending"
+            };
+            log::warn!(
+                "Webhook delivery {} to {} failed (attempt {}/{}): {}",
+                delivery_id,
+                url,
+                attempts,
+                MAX_ATTEMPTS,
+                e
+            );
+            sqlx::query(
+                r#"
+                UPDATE webhook_deliveries
+                SET status = $2, attempts = $3, last_error = $4, next_attempt_at = $5
+                WHERE id = $1
+                "#,
+            )
+            .bind(delivery_id)
+            .bind(status)
+            .bind(attempts)
+            .bind(e)
+            .bind(Utc::now() + retry_delay(attempts))
+            .execute(pool)
+            .await?;
+            Ok(false)
+        }
+    }
+}
+
+/// Notify the account's webhooks of a recorded balance change
+///
+/// Deliveries are logged before this returns; the first attempts run in the
+/// background so a slow webhook doesn't hold up gap filling.
+pub async fn notify_balance_change(
+    pool: &PgPool,
+    change: &FilledGap,
+    allow_private_urls: bool,
+) -> Result<Vec<i64>, sqlx::Error> {
+    let delivery_ids = enqueue_deliveries(pool, change).await?;
+
+    for &delivery_id in &delivery_ids {
+        let pool = pool.clone();
+        tokio::spawn(async move {
+            if let Err(e) = attempt_delivery(&pool, delivery_id, allow_private_urls).await {
+                log::error!("Failed to record webhook delivery {}: {}", delivery_id, e);
+            }
+        });
+    }
+
+    Ok(delivery_ids)
+}
+
+/// Retry all pending deliveries whose backoff has elapsed, and claims that expired
+///
+/// # Returns
+/// Number of deliveries that succeeded
+pub async fn deliver_due(pool: &PgPool, allow_private_urls: bool) -> Result<usize, sqlx::Error> {
+    let due: Vec<i64> = sqlx::query_scalar(
+        r#"
+        SELECT id
+        FROM webhook_deliveries
+        WHERE status IN ('pending', 'in_flight') AND next_attempt_at <= NOW()
+        ORDER BY next_attempt_at
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut delivered = 0;
+    for delivery_id in due {
+        if attempt_delivery(pool, delivery_id, allow_private_urls).await? {
+            delivered += 1;
+        }
+    }
+    Ok(delivered)
+}
+
+/// Periodically retry failed webhook deliveries
+pub fn spawn_delivery_retries(
+    pool: PgPool,
+    interval: Duration,
+    allow_private_urls: bool,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+
+            match deliver_due(&pool, allow_private_urls).await {
+                Ok(0) => {}
+                Ok(delivered) => log::info!("Delivered {} retried webhook(s)", delivered),
+                Err(e) => log::error!("Failed to retry webhook deliveries: {}", e),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::webhook::{DEFAULT_TOLERANCE_SECS, verify};
+    use axum::{Router, body::Bytes, extract::State, http::HeaderMap, routing::post};
+    use std::sync::{Arc, Mutex};
+
+    type Received = Arc<Mutex<Vec<(String, Value)>>>;
+
+    async fn receive(
+        State(received): State<Received>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> &'static str {
+        let signature = headers[SIGNATURE_HEADER].to_str().unwrap().to_string();
+        assert_eq!(
+            verify(
+                "s3cret",
+                &signature,
+                &body,
+                Utc::now().timestamp(),
+                DEFAULT_TOLERANCE_SECS
+            ),
+            Ok(()),
+            "Payload should be signed with the secret"
+        );
+
+        received
+            .lock()
+            .unwrap()
+            .push((signature, serde_json::from_slice(&body).unwrap()));
+        "ok"
+    }
+
+    /// The mock receivers listen on loopback
+    const ALLOW_PRIVATE: bool = true;
+
+    async fn register(pool: &PgPool, url: &str) -> sqlx::Result<()> {
+        sqlx::query("INSERT INTO webhooks (account_id, url, secret) VALUES ($1, $2, 's3cret')")
+            .bind("webhook-test.near")
+            .bind(url)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    fn change() -> FilledGap {
+        FilledGap {
+            account_id: "webhook-test.near".to_string(),
+            token_id: "near".to_string(),
+            block_height: 150_000_000,
+            block_timestamp: 1_700_000_000_000_000_000,
+            balance_before: "1".to_string(),
+            balance_after: "2.5".to_string(),
+        }
+    }
+
+    async fn delivery_status(pool: &PgPool, id: i64) -> sqlx::Result<(String, i32)> {
+        sqlx::query_as("SELECT status, attempts FROM webhook_deliveries WHERE id = $1")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+    }
+
+    #[sqlx::test]
+    async fn test_balance_change_is_delivered_to_webhook(pool: PgPool) -> sqlx::Result<()> {
+        let received: Received = Arc::default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(received.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        register(&pool, &url).await?;
+
+        let ids = notify_balance_change(&pool, &change(), ALLOW_PRIVATE).await?;
+        assert_eq!(ids.len(), 1);
+
+        // The first attempt runs in the background
+        for _ in 0..50 {
+            let (status, _) = delivery_status(&pool, ids[0]).await?;
+            if status != "pending" && status != "in_flight" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        assert_eq!(
+            delivery_status(&pool, ids[0]).await?,
+            ("delivered".to_string(), 1)
+        );
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].1["account_id"], "webhook-test.near");
+        assert_eq!(received[0].1["balance_after"], "2.5");
+        assert_eq!(received[0].1["block_height"], 150_000_000);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_concurrent_attempts_send_once(pool: PgPool) -> sqlx::Result<()> {
+        let received: Received = Arc::default();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let app = Router::new()
+            .route("/hook", post(receive))
+            .with_state(received.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        register(&pool, &url).await?;
+        let ids = enqueue_deliveries(&pool, &change()).await?;
+
+        // The first attempt racing the retry loop
+        let (first, retried) = tokio::join!(
+            attempt_delivery(&pool, ids[0], ALLOW_PRIVATE),
+            deliver_due(&pool, ALLOW_PRIVATE)
+        );
+        assert_eq!(u32::from(first?) + retried? as u32, 1);
+
+        assert_eq!(received.lock().unwrap().len(), 1);
+        assert_eq!(
+            delivery_status(&pool, ids[0]).await?,
+            ("delivered".to_string(), 1)
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_same_change_is_enqueued_once(pool: PgPool) -> sqlx::Result<()> {
+        register(&pool, "http://127.0.0.1:1/hook").await?;
+
+        assert_eq!(enqueue_deliveries(&pool, &change()).await?.len(), 1);
+        // Written again, e.g. by a chain rebuild
+        assert!(enqueue_deliveries(&pool, &change()).await?.is_empty());
+
+        let next_block = FilledGap {
+            block_height: 150_000_001,
+            ..change()
+        };
+        assert_eq!(enqueue_deliveries(&pool, &next_block).await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_expired_claim_is_retried(pool: PgPool) -> sqlx::Result<()> {
+        register(&pool, "http://127.0.0.1:1/hook").await?;
+        let ids = enqueue_deliveries(&pool, &change()).await?;
+
+        // Claimed by a sender that never finished
+        sqlx::query(
+            "UPDATE webhook_deliveries SET status = 'in_flight', next_attempt_at = NOW() + INTERVAL '5 minutes' WHERE id = $1",
+        )
+        .bind(ids[0])
+        .execute(&pool)
+        .await?;
+        assert!(!attempt_delivery(&pool, ids[0], ALLOW_PRIVATE).await?);
+        assert_eq!(
+            delivery_status(&pool, ids[0]).await?,
+            ("in_flight".to_string(), 0)
+        );
+
+        sqlx::query(
+            "UPDATE webhook_deliveries SET next_attempt_at = NOW() - INTERVAL '1 second' WHERE id = $1",
+        )
+        .bind(ids[0])
+        .execute(&pool)
+        .await?;
+        assert_eq!(deliver_due(&pool, ALLOW_PRIVATE).await?, 0);
+        assert_eq!(
+            delivery_status(&pool, ids[0]).await?,
+            ("pending".to_string(), 1)
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_failed_delivery_is_retried_later(pool: PgPool) -> sqlx::Result<()> {
+        // Nothing listens on port 1
+        register(&pool, "http://127.0.0.1:1/hook").await?;
+
+        let ids = enqueue_deliveries(&pool, &change()).await?;
+        assert!(!attempt_delivery(&pool, ids[0], ALLOW_PRIVATE).await?);
+        assert_eq!(
+            delivery_status(&pool, ids[0]).await?,
+            ("pending".to_string(), 1)
+        );
+
+        // Backing off: not due yet
+        assert_eq!(deliver_due(&pool, ALLOW_PRIVATE).await?, 0);
+        assert_eq!(delivery_status(&pool, ids[0]).await?.1, 1);
+
+        assert_eq!(retry_delay(1), chrono::Duration::seconds(30));
+        assert_eq!(retry_delay(3), chrono::Duration::seconds(120));
+
+        Ok(())
+    }
+}
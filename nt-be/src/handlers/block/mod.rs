@@ -0,0 +1,3 @@
+pub mod rate;
+pub mod receipt;
+pub mod timestamp;
@@ -0,0 +1,162 @@
+//! Measured Block Rate
+//!
+//! NEAR block time fluctuates, so a fixed blocks-per-hour estimate drifts from the
+//! chain. The rate is measured from the timestamps of the head block and a block
+//! `SAMPLE_BLOCKS` below it, at startup and then periodically (see
+//! `spawn_blocks_per_hour_refresh`). Until the first sample succeeds,
+//! `constants::BLOCKS_PER_HOUR` is used.
+
+use near_api::{Chain, NetworkConfig};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::AppState;
+use crate::constants::BLOCKS_PER_HOUR;
+use crate::handlers::balance_changes::block_info;
+
+/// Blocks between the two sampled blocks (~3 hours)
+pub const SAMPLE_BLOCKS: u64 = 10_000;
+
+/// Rates outside a 0.1s - 6s block time are treated as bad samples
+const PLAUSIBLE_BLOCKS_PER_HOUR: RangeInclusive<u64> = 600..=36_000;
+
+/// Consecutive missing heights tolerated when looking up the older block
+const MAX_SKIPPED_BLOCKS: u64 = 10;
+
+const NANOS_PER_HOUR: u128 = 3_600_000_000_000;
+
+/// Effective blocks per hour, shared through `AppState`
+#[derive(Debug)]
+pub struct BlocksPerHour(AtomicU64);
+
+impl BlocksPerHour {
+    pub fn new() -> Self {
+        Self(AtomicU64::new(BLOCKS_PER_HOUR))
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, blocks_per_hour: u64) {
+        self.0.store(blocks_per_hour, Ordering::Relaxed);
+    }
+
+    /// Number of blocks produced in `duration` at the current rate
+    pub fn blocks_in(&self, duration: Duration) -> u64 {
+        (self.get() as u128 * duration.as_nanos() / NANOS_PER_HOUR) as u64
+    }
+}
+
+impl Default for BlocksPerHour {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Blocks per hour between two `(height, timestamp_nanos)` samples
+///
+/// # Returns
+/// The rate, or `None` if the samples are out of order or give an implausible rate
+pub fn blocks_per_hour_between(older: (u64, i64), newer: (u64, i64)) -> Option<u64> {
+    let blocks = newer.0.checked_sub(older.0).filter(|&b| b > 0)?;
+    let nanos = newer.1.checked_sub(older.1).filter(|&n| n > 0)? as u128;
+
+    let rate = (blocks as u128 * NANOS_PER_HOUR / nanos) as u64;
+    PLAUSIBLE_BLOCKS_PER_HOUR.contains(&rate).then_some(rate)
+}
+
+/// Measure blocks per hour from the head and a block `SAMPLE_BLOCKS` below it
+pub async fn sample_blocks_per_hour(network: &NetworkConfig) -> Result<u64, String> {
+    let head = Chain::block()
+        .fetch_from(network)
+        .await
+        .map_err(|e| format!("Failed to fetch head block: {}", e))?;
+    let newer = (head.header.height, head.header.timestamp as i64);
+
+    let start = newer.0.saturating_sub(SAMPLE_BLOCKS);
+    let mut last_error = String::new();
+    for height in start..start + MAX_SKIPPED_BLOCKS {
+        match block_info::get_block_timestamp(network, height, None).await {
+            Ok(timestamp) => {
+                return blocks_per_hour_between((height, timestamp), newer).ok_or(format!(
+                    "Implausible block rate between blocks {} and {}",
+                    height, newer.0
+                ));
+            }
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+
+    Err(format!(
+        "Failed to fetch a block near {}: {}",
+        start, last_error
+    ))
+}
+
+/// Spawns a background task that re-measures the block rate
+///
+/// The first sample is taken right away; failed samples keep the previous rate.
+pub fn spawn_blocks_per_hour_refresh(
+    state: Arc<AppState>,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match sample_blocks_per_hour(&state.network).await {
+                Ok(rate) => {
+                    log::info!("Measured {} blocks per hour", rate);
+                    state.blocks_per_hour.set(rate);
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Failed to measure block rate, keeping {} blocks per hour: {}",
+                        state.blocks_per_hour.get(),
+                        e
+                    );
+                }
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::init_test_state;
+
+    #[test]
+    fn test_blocks_per_hour_between() {
+        // One block per second
+        let hour = NANOS_PER_HOUR as i64;
+        assert_eq!(blocks_per_hour_between((0, 0), (3_600, hour)), Some(3_600));
+        assert_eq!(
+            blocks_per_hour_between((1_000, 0), (8_200, 2 * hour)),
+            Some(3_600)
+        );
+
+        // Out of order or implausible samples are rejected
+        assert_eq!(blocks_per_hour_between((3_600, hour), (0, 0)), None);
+        assert_eq!(blocks_per_hour_between((0, 0), (10, hour)), None);
+
+        let rate = BlocksPerHour::new();
+        rate.set(3_600);
+        assert_eq!(rate.blocks_in(Duration::from_secs(30 * 60)), 1_800);
+    }
+
+    #[tokio::test]
+    async fn test_sampled_rate_is_plausible() {
+        let state = init_test_state().await;
+
+        let rate = sample_blocks_per_hour(&state.network).await.unwrap();
+        assert!(
+            (2_000..=6_000).contains(&rate),
+            "NEAR produces roughly one block per second, got {} per hour",
+            rate
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use near_primitives::hash::CryptoHash;
+use std::sync::Arc;
+
+use crate::AppState;
+use crate::handlers::balance_changes::block_info::{self, ReceiptOutcome};
+use crate::utils::api_error::ApiError;
+use crate::utils::timeout::with_timeout;
+
+#[utoipa::path(
+    get,
+    path = "/api/receipt/{receipt_id}/outcome",
+    tag = "block",
+    params(("receipt_id" = String, Path, description = "Receipt id (base58)")),
+    responses(
+        (status = 200, description = "Execution outcome of the receipt", body = ReceiptOutcome),
+        (status = 400, description = "Invalid receipt id"),
+        (status = 404, description = "Receipt not found"),
+        (status = 504, description = "RPC did not respond in time"),
+    )
+)]
+pub async fn get_receipt_outcome(
+    State(state): State<Arc<AppState>>,
+    Path(receipt_id): Path<String>,
+) -> Result<Json<ReceiptOutcome>, ApiError> {
+    receipt_id
+        .parse::<CryptoHash>()
+        .map_err(|_| ApiError::bad_request(format!("Invalid receipt id: {}", receipt_id)))?;
+
+    // Outcomes of executed receipts never change
+    let cache_key = format!("receipt-outcome:{}", receipt_id);
+    if let Some(cached) = state.cache.get(&cache_key).await
+        && let Ok(outcome) = serde_json::from_value(cached)
+    {
+        return Ok(Json(outcome));
+    }
+
+    let request = block_info::get_receipt_outcome(&state.archival_network, &receipt_id);
+    let outcome = with_timeout(state.external_timeout(), "RPC", request)
+        .await?
+        .map_err(|e| {
+            eprintln!("Error fetching outcome of receipt {}: {}", receipt_id, e);
+            ApiError::not_found(format!("Failed to fetch receipt {}: {}", receipt_id, e))
+        })?;
+
+    if let Ok(value) = serde_json::to_value(&outcome) {
+        state.cache.insert(cache_key, value).await;
+    }
+
+    Ok(Json(outcome))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::init_test_state;
+    use axum::http::StatusCode;
+
+    #[tokio::test]
+    async fn test_invalid_receipt_id_is_rejected() {
+        let state = Arc::new(init_test_state().await);
+
+        let error = get_receipt_outcome(State(state), Path("not-a-receipt".to_string()))
+            .await
+            .expect_err("Should reject an invalid receipt id");
+
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+    }
+}
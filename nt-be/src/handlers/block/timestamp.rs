@@ -0,0 +1,345 @@
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+use near_api::{Chain, NetworkConfig};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::AppState;
+use crate::handlers::balance_changes::block_info;
+use crate::utils::api_error::ApiError;
+use crate::utils::timeout::with_timeout;
+
+/// Height of the first block on mainnet
+pub const FIRST_MAINNET_BLOCK: u64 = 9_820_210;
+
+/// Consecutive missing heights tolerated while probing (NEAR skips some heights)
+const MAX_SKIPPED_BLOCKS: u64 = 10;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BlockAtTimeQuery {
+    /// RFC 3339 date-time (e.g. `2025-06-16T18:05:44Z`) or nanoseconds since the Unix epoch
+    pub timestamp: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct BlockTimestampResponse {
+    #[serde(rename = "blockHeight")]
+    pub block_height: u64,
+    /// Block timestamp in nanoseconds since the Unix epoch
+    #[serde(rename = "timestampNanos")]
+    pub timestamp_nanos: i64,
+    pub time: DateTime<Utc>,
+}
+
+impl BlockTimestampResponse {
+    fn new(block_height: u64, timestamp_nanos: i64) -> Self {
+        Self {
+            block_height,
+            timestamp_nanos,
+            time: DateTime::from_timestamp_nanos(timestamp_nanos),
+        }
+    }
+}
+
+/// Parse an RFC 3339 date-time or a nanosecond timestamp
+pub(crate) fn parse_timestamp(value: &str) -> Option<i64> {
+    let value = value.trim();
+    if let Ok(nanos) = value.parse::<i64>() {
+        return Some(nanos);
+    }
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .and_then(|time| time.timestamp_nanos_opt())
+}
+
+/// Timestamp of the first existing block at or after `height`, up to `max_height`
+///
+/// Skipped heights (and transient lookup failures) are stepped over, up to
+/// `MAX_SKIPPED_BLOCKS` in a row.
+async fn timestamp_at_or_after<F, Fut>(
+    lookup: &F,
+    height: u64,
+    max_height: u64,
+) -> Result<Option<(u64, i64)>, String>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<i64, String>>,
+{
+    let mut last_error = None;
+    for candidate in height..=max_height.min(height + MAX_SKIPPED_BLOCKS) {
+        match lookup(candidate).await {
+            Ok(timestamp) => return Ok(Some((candidate, timestamp))),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    match last_error {
+        Some(e) if height + MAX_SKIPPED_BLOCKS <= max_height => Err(e),
+        // Ran into the end of the range: nothing exists from `height` on
+        _ => Ok(None),
+    }
+}
+
+/// Binary search the last block with a timestamp at or before `target`
+///
+/// # Arguments
+/// * `lookup` - Returns the timestamp of a block height
+/// * `low` - A block at or before the target time
+/// * `high` - A block at or after the target time
+///
+/// # Returns
+/// The height and timestamp of the found block
+async fn search_block_at_time<F, Fut>(
+    lookup: F,
+    target: i64,
+    low: (u64, i64),
+    high: u64,
+) -> Result<(u64, i64), String>
+where
+    F: Fn(u64) -> Fut,
+    Fut: Future<Output = Result<i64, String>>,
+{
+    // Invariant: `best` exists and is at or before the target
+    let mut best = low;
+    let mut left = low.0 + 1;
+    let mut right = high;
+
+    while left <= right {
+        let mid = left + (right - left) / 2;
+
+        match timestamp_at_or_after(&lookup, mid, right).await? {
+            Some((height, timestamp)) if timestamp <= target => {
+                best = (height, timestamp);
+                left = height + 1;
+            }
+            // The block at `mid` (or the next existing one) is already too late
+            _ => {
+                if mid == 0 {
+                    break;
+                }
+                right = mid - 1;
+            }
+        }
+    }
+
+    Ok(best)
+}
+
+async fn fetch_block_timestamp(state: &Arc<AppState>, block_height: u64) -> Result<i64, ApiError> {
+    let cache_key = format!("block-timestamp:{}", block_height);
+    if let Some(cached) = state.cache.get(&cache_key).await
+        && let Some(timestamp) = cached.as_i64()
+    {
+        return Ok(timestamp);
+    }
+
+    let timestamp = block_info::get_block_timestamp(&state.archival_network, block_height, None)
+        .await
+        .map_err(|e| {
+            eprintln!("Error fetching timestamp for block {}: {}", block_height, e);
+            ApiError::not_found(format!("Failed to fetch block {}: {}", block_height, e))
+        })?;
+
+    state
+        .cache
+        .insert(cache_key, serde_json::json!(timestamp))
+        .await;
+
+    Ok(timestamp)
+}
+
+/// Find the block at a point in time
+///
+/// Binary-searches the archival network for the last block produced at or
+/// before the given time.
+///
+/// # Returns
+/// The block height and timestamp, or `None` if the time is before the first block
+pub async fn find_block_at_time(
+    network: &NetworkConfig,
+    target: i64,
+    head: (u64, i64),
+) -> Result<Option<(u64, i64)>, String> {
+    if target >= head.1 {
+        return Ok(Some(head));
+    }
+
+    let cache = block_info::new_cache();
+    let lookup = |height: u64| {
+        let cache = cache.clone();
+        async move {
+            block_info::get_block_timestamp(network, height, Some(&cache))
+                .await
+                .map_err(|e| e.to_string())
+        }
+    };
+
+    let first = timestamp_at_or_after(&lookup, FIRST_MAINNET_BLOCK, head.0)
+        .await?
+        .ok_or("No blocks found")?;
+    if target < first.1 {
+        return Ok(None);
+    }
+
+    search_block_at_time(lookup, target, first, head.0)
+        .await
+        .map(Some)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/block/at-time",
+    tag = "block",
+    params(BlockAtTimeQuery),
+    responses(
+        (status = 200, description = "Last block at or before the given time", body = BlockTimestampResponse),
+        (status = 400, description = "Invalid timestamp or before the first block"),
+        (status = 502, description = "Block lookups failed"),
+    )
+)]
+pub async fn get_block_at_time(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<BlockAtTimeQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let target = parse_timestamp(&params.timestamp).ok_or(ApiError::bad_request(
+        "timestamp must be RFC 3339 or nanoseconds since the Unix epoch",
+    ))?;
+
+    let cache_key = format!("block-at-time:{}", target);
+    if let Some(cached) = state.cache.get(&cache_key).await {
+        return Ok((StatusCode::OK, Json(cached)));
+    }
+
+    let head = with_timeout(
+        state.external_timeout(),
+        "RPC",
+        Chain::block().fetch_from(&state.network),
+    )
+    .await?
+    .map_err(|e| {
+        eprintln!("Error fetching head block: {}", e);
+        ApiError::internal(format!("Failed to fetch head block: {}", e))
+    })?;
+    let head = (head.header.height, head.header.timestamp as i64);
+
+    let search = find_block_at_time(&state.archival_network, target, head);
+    let (block_height, timestamp) = with_timeout(state.external_timeout(), "RPC", search)
+        .await?
+        .map_err(|e| {
+            eprintln!("Error searching block at {}: {}", target, e);
+            ApiError::new(
+                StatusCode::BAD_GATEWAY,
+                format!("Failed to search block: {}", e),
+            )
+        })?
+        .ok_or(ApiError::bad_request("timestamp is before the first block"))?;
+
+    let result_value = serde_json::to_value(BlockTimestampResponse::new(block_height, timestamp))
+        .map_err(|e| {
+        eprintln!("Error serializing block: {}", e);
+        ApiError::internal("Failed to serialize block")
+    })?;
+
+    // A time past the head resolves to the head, which moves; don't cache it
+    if block_height != head.0 {
+        state.cache.insert(cache_key, result_value.clone()).await;
+    }
+
+    Ok((StatusCode::OK, Json(result_value)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/block/{height}/timestamp",
+    tag = "block",
+    params(("height" = u64, Path, description = "Block height")),
+    responses(
+        (status = 200, description = "Timestamp of the block", body = BlockTimestampResponse),
+        (status = 404, description = "Block not found"),
+    )
+)]
+pub async fn get_block_timestamp(
+    State(state): State<Arc<AppState>>,
+    Path(height): Path<u64>,
+) -> Result<impl IntoResponse, ApiError> {
+    let timestamp = fetch_block_timestamp(&state, height).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(BlockTimestampResponse::new(height, timestamp)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::init_test_state;
+
+    /// Fake chain: block `h` at `h * 1000`ns, with every 7th height skipped
+    async fn fake_lookup(height: u64) -> Result<i64, String> {
+        if height.is_multiple_of(7) {
+            Err(format!("Block {} not found", height))
+        } else {
+            Ok(height as i64 * 1000)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_finds_last_block_before_time() {
+        let low = (1, 1000);
+
+        for height in [2u64, 50, 99, 100] {
+            let found = search_block_at_time(fake_lookup, height as i64 * 1000, low, 100)
+                .await
+                .unwrap();
+            assert_eq!(found, (height, height as i64 * 1000));
+        }
+
+        // Between two blocks resolves to the earlier one
+        let found = search_block_at_time(fake_lookup, 50_500, low, 100)
+            .await
+            .unwrap();
+        assert_eq!(found.0, 50);
+
+        // A skipped height resolves to the block before it
+        let found = search_block_at_time(fake_lookup, 49_000, low, 100)
+            .await
+            .unwrap();
+        assert_eq!(found.0, 48);
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(
+            parse_timestamp("1750097144159145697"),
+            Some(1750097144159145697)
+        );
+        assert_eq!(parse_timestamp("1970-01-01T00:00:01Z"), Some(1_000_000_000));
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[tokio::test]
+    async fn test_block_timestamp_round_trip() {
+        let state = init_test_state().await;
+
+        // Block 151386339 has a fixed timestamp that won't change
+        let head = Chain::block().fetch_from(&state.network).await.unwrap();
+        let found = find_block_at_time(
+            &state.archival_network,
+            1750097144159145697,
+            (head.header.height, head.header.timestamp as i64),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(found, Some((151386339, 1750097144159145697)));
+    }
+}
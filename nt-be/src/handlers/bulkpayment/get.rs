@@ -7,64 +7,78 @@ use axum::{
 use near_api::AccountId;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
+use crate::utils::api_error::ApiError;
+use crate::utils::timeout::with_timeout;
 use crate::{AppState, constants::BATCH_PAYMENT_ACCOUNT_ID};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct BatchPaymentQuery {
     #[serde(rename = "batchId")]
     pub batch_id: String,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, ToSchema)]
 pub struct BatchPayment {
+    #[schema(value_type = String)]
     pub recipient: AccountId,
     pub amount: String,
     pub status: serde_json::Value,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, ToSchema)]
 pub struct BatchPaymentResponse {
+    #[schema(value_type = String)]
     pub token_id: AccountId,
+    #[schema(value_type = String)]
     pub submitter: AccountId,
     pub status: String,
     pub payments: Vec<BatchPayment>,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/bulkpayment/get",
+    tag = "bulkpayment",
+    params(BatchPaymentQuery),
+    responses(
+        (status = 200, description = "Batch payment", body = BatchPaymentResponse),
+    )
+)]
 pub async fn get_batch_payment(
     State(state): State<Arc<AppState>>,
     Query(params): Query<BatchPaymentQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let cache_key = format!("batch-payment:{}", params.batch_id);
     if let Some(cached_data) = state.cache.get(&cache_key).await {
         return Ok((StatusCode::OK, Json(cached_data)));
     }
 
-    let list: BatchPaymentResponse = near_api::Contract(BATCH_PAYMENT_ACCOUNT_ID.into())
-        .call_function(
-            "view_list",
-            serde_json::json!({
-                "list_id": params.batch_id,
-            }),
-        )
-        .read_only()
-        .fetch_from(&state.network)
-        .await
-        .map_err(|e| {
-            eprintln!("Error fetching batch payment: {}: {}", params.batch_id, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch batch payment: {}", e),
+    let list: BatchPaymentResponse = with_timeout(
+        state.external_timeout(),
+        "RPC",
+        near_api::Contract(BATCH_PAYMENT_ACCOUNT_ID.into())
+            .call_function(
+                "view_list",
+                serde_json::json!({
+                    "list_id": params.batch_id,
+                }),
             )
-        })?
-        .data;
+            .read_only()
+            .fetch_from(&state.network),
+    )
+    .await?
+    .map_err(|e| {
+        eprintln!("Error fetching batch payment: {}: {}", params.batch_id, e);
+        ApiError::internal(format!("Failed to fetch batch payment: {}", e))
+    })?
+    .data;
 
     let result_value = serde_json::to_value(&list).map_err(|e| {
         eprintln!("Error serializing batch payment: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to serialize batch payment: {}", e),
-        )
+        ApiError::internal(format!("Failed to serialize batch payment: {}", e))
     })?;
     state.cache.insert(cache_key, result_value.clone()).await;
 
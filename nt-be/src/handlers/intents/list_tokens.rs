@@ -0,0 +1,136 @@
+use axum::{Json, extract::Query, http::StatusCode, response::IntoResponse};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::utils::api_error::ApiError;
+use crate::{
+    constants::intents_tokens::get_tokens_map,
+    handlers::intents::search_tokens::{NetworkInfo, TokenSearchResult},
+    utils::pagination::{Pagination, PaginationQuery, paginate},
+};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListTokensQuery {
+    /// Only list deployments on this chain (e.g. `near`, `eth`, `solana`)
+    pub chain: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ListTokensResponse {
+    pub tokens: Vec<TokenSearchResult>,
+    /// Number of matching deployments across all pages
+    pub total: usize,
+    /// The page size actually used, after clamping the requested `limit`
+    #[serde(rename = "limitApplied")]
+    pub limit_applied: i64,
+    pub offset: i64,
+}
+
+/// List every token deployment, optionally restricted to one chain
+///
+/// Each deployment of a token is its own entry (with `networkInfo` set), sorted by
+/// symbol, asset id and chain so pages are stable.
+fn list_token_deployments(chain: Option<&str>) -> Vec<TokenSearchResult> {
+    let chain = chain.map(|c| c.trim().to_lowercase());
+    let mut tokens = Vec::new();
+
+    for unified_token in get_tokens_map().values() {
+        for base_token in &unified_token.grouped_tokens {
+            for deployment in &base_token.deployments {
+                if let Some(chain) = &chain
+                    && deployment.chain_name().to_lowercase() != *chain
+                {
+                    continue;
+                }
+
+                tokens.push(TokenSearchResult {
+                    defuse_asset_id: base_token.defuse_asset_id.clone(),
+                    symbol: base_token.symbol.clone(),
+                    name: base_token.name.clone(),
+                    decimals: base_token.decimals,
+                    icon: base_token.icon.clone(),
+                    origin_chain_name: base_token.origin_chain_name.clone(),
+                    unified_asset_id: unified_token.unified_asset_id.clone(),
+                    network_info: Some(NetworkInfo::from_deployment(deployment)),
+                });
+            }
+        }
+    }
+
+    tokens.sort_by(|a, b| {
+        let chain_name = |t: &TokenSearchResult| {
+            t.network_info
+                .as_ref()
+                .map(|n| n.chain_name.clone())
+                .unwrap_or_default()
+        };
+        a.symbol
+            .cmp(&b.symbol)
+            .then_with(|| a.defuse_asset_id.cmp(&b.defuse_asset_id))
+            .then_with(|| chain_name(a).cmp(&chain_name(b)))
+    });
+    tokens
+}
+
+/// Handler for browsing intents tokens page by page
+///
+/// Query parameters:
+/// - chain: Optional chain name to filter deployments by
+/// - limit / offset: Page size and start
+#[utoipa::path(
+    get,
+    path = "/api/intents/tokens",
+    tag = "intents",
+    params(ListTokensQuery, PaginationQuery),
+    responses(
+        (status = 200, description = "A page of intents token deployments", body = ListTokensResponse),
+    )
+)]
+pub async fn list_tokens(
+    Pagination { limit, offset, .. }: Pagination,
+    Query(params): Query<ListTokensQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let tokens = list_token_deployments(params.chain.as_deref());
+    let total = tokens.len();
+
+    Ok((
+        StatusCode::OK,
+        Json(ListTokensResponse {
+            tokens: paginate(tokens, offset, limit),
+            total,
+            limit_applied: limit,
+            offset,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_filters_by_chain() {
+        let solana = list_token_deployments(Some("solana"));
+
+        assert!(!solana.is_empty(), "Should list Solana deployments");
+        assert!(
+            solana
+                .iter()
+                .all(|t| t.network_info.as_ref().unwrap().chain_name == "solana")
+        );
+        assert!(solana.len() < list_token_deployments(None).len());
+        assert!(list_token_deployments(Some("not-a-chain")).is_empty());
+    }
+
+    #[test]
+    fn test_list_order_is_stable_for_paging() {
+        let all = list_token_deployments(None);
+        let ids = |tokens: &[TokenSearchResult]| -> Vec<String> {
+            tokens.iter().map(|t| t.defuse_asset_id.clone()).collect()
+        };
+
+        assert_eq!(ids(&all), ids(&list_token_deployments(None)));
+        assert_eq!(ids(&paginate(all.clone(), 2, 3)), ids(&all[2..5]));
+    }
+}
@@ -1 +1,3 @@
+pub mod list_tokens;
+pub mod resolve;
 pub mod search_tokens;
@@ -0,0 +1,166 @@
+use axum::{Json, extract::Query};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::search_tokens::NetworkInfo;
+use crate::constants::{INTENTS_CONTRACT_ID, intents_tokens::get_tokens_map};
+use crate::utils::api_error::ApiError;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ResolveTokenQuery {
+    /// Defuse asset id, e.g. `nep141:wrap.near`
+    pub defuse_asset_id: Option<String>,
+    /// Intents token id as stored in balance changes, e.g. `intents.near:nep141:wrap.near`
+    pub token_id: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, ToSchema)]
+pub struct ResolvedToken {
+    #[serde(rename = "defuseAssetId")]
+    pub defuse_asset_id: String,
+    #[serde(rename = "tokenId")]
+    pub token_id: String,
+    /// Token standard on intents.near (`nep141` or `nep245`)
+    pub standard: String,
+    /// The token contract on NEAR, without the standard prefix
+    #[serde(rename = "contractId")]
+    pub contract_id: String,
+    #[serde(rename = "unifiedAssetId")]
+    pub unified_asset_id: String,
+    pub symbol: String,
+    pub name: String,
+    pub decimals: u8,
+    #[serde(rename = "originChainName")]
+    pub origin_chain_name: String,
+    pub deployments: Vec<NetworkInfo>,
+}
+
+/// Convert an intents token id (`intents.near:nep141:x`) to its defuse asset id (`nep141:x`)
+fn defuse_asset_id_from_token_id(token_id: &str) -> Option<&str> {
+    token_id
+        .strip_prefix(INTENTS_CONTRACT_ID.as_str())
+        .and_then(|rest| rest.strip_prefix(':'))
+}
+
+/// Look up a defuse asset id in the intents tokens map
+///
+/// # Returns
+/// The canonical forms of the token and all of its chain deployments, or None if
+/// the asset is unknown
+fn resolve_defuse_asset_id(defuse_asset_id: &str) -> Option<ResolvedToken> {
+    get_tokens_map().values().find_map(|unified_token| {
+        let base_token = unified_token
+            .grouped_tokens
+            .iter()
+            .find(|t| t.defuse_asset_id.eq_ignore_ascii_case(defuse_asset_id))?;
+
+        let (standard, contract_id) = base_token.defuse_asset_id.split_once(':')?;
+
+        let deployments = base_token
+            .deployments
+            .iter()
+            .map(NetworkInfo::from_deployment)
+            .collect();
+
+        Some(ResolvedToken {
+            defuse_asset_id: base_token.defuse_asset_id.clone(),
+            token_id: format!("{}:{}", INTENTS_CONTRACT_ID, base_token.defuse_asset_id),
+            standard: standard.to_string(),
+            contract_id: contract_id.to_string(),
+            unified_asset_id: unified_token.unified_asset_id.clone(),
+            symbol: base_token.symbol.clone(),
+            name: base_token.name.clone(),
+            decimals: base_token.decimals,
+            origin_chain_name: base_token.origin_chain_name.clone(),
+            deployments,
+        })
+    })
+}
+
+/// Resolve an intents token between its defuse asset id and intents token id
+///
+/// Exactly one of `defuse_asset_id` or `token_id` must be given.
+#[utoipa::path(
+    get,
+    path = "/api/intents/resolve",
+    tag = "intents",
+    params(ResolveTokenQuery),
+    responses(
+        (status = 200, description = "Canonical forms and deployments of the token", body = ResolvedToken),
+        (status = 400, description = "Neither or both query parameters were given"),
+        (status = 404, description = "Unknown token"),
+    )
+)]
+pub async fn resolve_token(
+    Query(params): Query<ResolveTokenQuery>,
+) -> Result<Json<ResolvedToken>, ApiError> {
+    let defuse_asset_id = match (&params.defuse_asset_id, &params.token_id) {
+        (Some(defuse_asset_id), None) => defuse_asset_id.as_str(),
+        (None, Some(token_id)) => {
+            defuse_asset_id_from_token_id(token_id).ok_or(ApiError::bad_request(format!(
+                "token_id must start with {}: (e.g. {}:nep141:wrap.near)",
+                INTENTS_CONTRACT_ID, INTENTS_CONTRACT_ID
+            )))?
+        }
+        _ => {
+            return Err(ApiError::bad_request(
+                "Exactly one of defuse_asset_id or token_id is required",
+            ));
+        }
+    };
+
+    resolve_defuse_asset_id(defuse_asset_id)
+        .map(Json)
+        .ok_or(ApiError::not_found("Token not found"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    const USDC_DEFUSE_ASSET_ID: &str =
+        "nep141:17208628f84f5d6ad33f0da3bbbeb27ffcb398eac501a31bd6ad2011e36133a1";
+
+    #[test]
+    fn test_resolve_usdc_defuse_asset_id() {
+        let resolved = resolve_defuse_asset_id(USDC_DEFUSE_ASSET_ID).expect("USDC should resolve");
+
+        assert_eq!(resolved.standard, "nep141");
+        assert_eq!(
+            resolved.contract_id,
+            "17208628f84f5d6ad33f0da3bbbeb27ffcb398eac501a31bd6ad2011e36133a1"
+        );
+        assert_eq!(
+            resolved.token_id,
+            format!("intents.near:{}", USDC_DEFUSE_ASSET_ID)
+        );
+        assert_eq!(resolved.unified_asset_id, "usdc");
+        assert!(
+            resolved
+                .deployments
+                .iter()
+                .any(|d| d.chain_name == "near" && d.contract_address.is_some())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_by_token_id() {
+        let Json(resolved) = resolve_token(Query(ResolveTokenQuery {
+            defuse_asset_id: None,
+            token_id: Some(format!("intents.near:{}", USDC_DEFUSE_ASSET_ID)),
+        }))
+        .await
+        .unwrap();
+        assert_eq!(resolved.defuse_asset_id, USDC_DEFUSE_ASSET_ID);
+
+        let ApiError { status, .. } = resolve_token(Query(ResolveTokenQuery {
+            defuse_asset_id: Some("nep141:unknown.near".to_string()),
+            token_id: None,
+        }))
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
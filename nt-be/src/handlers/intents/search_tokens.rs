@@ -1,18 +1,19 @@
 use axum::{
     Json,
     extract::{Query, State},
-    http::StatusCode,
-    response::IntoResponse,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
+use crate::utils::api_error::ApiError;
 use crate::{
     AppState,
     constants::intents_tokens::{TokenDeployment, get_tokens_map},
 };
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SearchTokensQuery {
     #[serde(rename = "tokenIn")]
     pub token_in: Option<String>,
@@ -22,9 +23,20 @@ pub struct SearchTokensQuery {
     pub intents_token_contract_id: Option<String>,
     #[serde(rename = "destinationNetwork")]
     pub destination_network: Option<String>,
+    /// Return ranked partial matches (`tokenInMatches`/`tokenOutMatches`) instead of exact matches
+    #[serde(default)]
+    pub fuzzy: bool,
+    /// Maximum number of fuzzy matches per query (default 10)
+    pub limit: Option<usize>,
 }
 
-#[derive(Serialize, Clone, Debug)]
+/// Default number of results returned per fuzzy query
+const DEFAULT_FUZZY_LIMIT: usize = 10;
+
+/// Fuzzy matches scoring below this are dropped
+const MIN_FUZZY_SCORE: f64 = 0.3;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
 pub struct NetworkInfo {
     #[serde(rename = "chainId")]
     pub chain_id: String,
@@ -36,7 +48,35 @@ pub struct NetworkInfo {
     pub bridge: String,
 }
 
-#[derive(Serialize, Clone, Debug)]
+impl NetworkInfo {
+    /// Network info of one token deployment
+    ///
+    /// `chainId` is always the chain name, for native and fungible deployments
+    /// alike, so it matches the `destinationNetwork` accepted by search.
+    pub fn from_deployment(deployment: &TokenDeployment) -> Self {
+        let (contract_address, decimals, bridge) = match deployment {
+            TokenDeployment::Native {
+                decimals, bridge, ..
+            } => (None, *decimals, bridge),
+            TokenDeployment::Fungible {
+                address,
+                decimals,
+                bridge,
+                ..
+            } => (Some(address.clone()), *decimals, bridge),
+        };
+
+        NetworkInfo {
+            chain_id: deployment.chain_name().to_string(),
+            chain_name: deployment.chain_name().to_string(),
+            contract_address,
+            decimals,
+            bridge: bridge.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
 pub struct TokenSearchResult {
     #[serde(rename = "defuseAssetId")]
     pub defuse_asset_id: String,
@@ -52,12 +92,130 @@ pub struct TokenSearchResult {
     pub network_info: Option<NetworkInfo>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, ToSchema)]
+pub struct ScoredTokenSearchResult {
+    #[serde(flatten)]
+    pub token: TokenSearchResult,
+    /// Relevance between 0 and 1, where 1 is an exact symbol match
+    pub score: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, ToSchema)]
 pub struct SearchTokensResponse {
     #[serde(rename = "tokenIn", skip_serializing_if = "Option::is_none")]
     pub token_in: Option<TokenSearchResult>,
     #[serde(rename = "tokenOut", skip_serializing_if = "Option::is_none")]
     pub token_out: Option<TokenSearchResult>,
+    #[serde(rename = "tokenInMatches", skip_serializing_if = "Option::is_none")]
+    pub token_in_matches: Option<Vec<ScoredTokenSearchResult>>,
+    #[serde(rename = "tokenOutMatches", skip_serializing_if = "Option::is_none")]
+    pub token_out_matches: Option<Vec<ScoredTokenSearchResult>>,
+}
+
+/// Levenshtein distance between two strings, by character
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Whether all characters of `query` appear in `target` in order
+fn is_subsequence(query: &str, target: &str) -> bool {
+    let mut target = target.chars();
+    query.chars().all(|q| target.any(|t| t == q))
+}
+
+/// Score how well a (lowercase) query matches a (lowercase) symbol or name
+///
+/// Exact matches score 1, then prefixes, substrings and subsequences, with longer
+/// coverage of the target scoring higher within each tier. Anything else falls back
+/// to edit-distance similarity, so small typos still match.
+fn match_score(query: &str, target: &str) -> f64 {
+    if query.is_empty() || target.is_empty() {
+        return 0.0;
+    }
+    if query == target {
+        return 1.0;
+    }
+
+    let coverage = query.chars().count() as f64 / target.chars().count() as f64;
+    if target.starts_with(query) {
+        return 0.8 + 0.15 * coverage;
+    }
+    if target.contains(query) {
+        return 0.6 + 0.15 * coverage;
+    }
+    if is_subsequence(query, target) {
+        return 0.4 + 0.15 * coverage;
+    }
+
+    let longest = query.chars().count().max(target.chars().count()) as f64;
+    let similarity = 1.0 - edit_distance(query, target) as f64 / longest;
+    0.75 * similarity
+}
+
+/// Rank tokens by how well their symbol or name matches `query`
+///
+/// Results are grouped per unified token (the best scoring grouped token wins, NEAR
+/// deployments first on ties) and sorted by score, then symbol.
+fn fuzzy_search_tokens(query: &str, limit: usize) -> Vec<ScoredTokenSearchResult> {
+    let query = query.trim().to_lowercase();
+    let mut results: Vec<ScoredTokenSearchResult> = Vec::new();
+
+    for unified_token in get_tokens_map().values() {
+        let best = unified_token
+            .grouped_tokens
+            .iter()
+            .map(|base_token| {
+                let score = match_score(&query, &base_token.symbol.to_lowercase())
+                    .max(0.9 * match_score(&query, &base_token.name.to_lowercase()));
+                (score, base_token)
+            })
+            .max_by(|(a_score, a), (b_score, b)| {
+                a_score
+                    .total_cmp(b_score)
+                    .then_with(|| {
+                        (a.origin_chain_name == "near").cmp(&(b.origin_chain_name == "near"))
+                    })
+                    .then_with(|| b.defuse_asset_id.cmp(&a.defuse_asset_id))
+            });
+
+        if let Some((score, base_token)) = best
+            && score >= MIN_FUZZY_SCORE
+        {
+            results.push(ScoredTokenSearchResult {
+                token: TokenSearchResult {
+                    defuse_asset_id: base_token.defuse_asset_id.clone(),
+                    symbol: base_token.symbol.clone(),
+                    name: base_token.name.clone(),
+                    decimals: base_token.decimals,
+                    icon: base_token.icon.clone(),
+                    origin_chain_name: base_token.origin_chain_name.clone(),
+                    unified_asset_id: unified_token.unified_asset_id.clone(),
+                    network_info: None,
+                },
+                score,
+            });
+        }
+    }
+
+    results.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then_with(|| a.token.symbol.cmp(&b.token.symbol))
+    });
+    results.truncate(limit);
+    results
 }
 
 /// Search for tokenIn with intentsTokenContractId matching
@@ -100,49 +258,14 @@ fn search_token_in(
                 }
 
                 // Find the network info for the matching deployment
-                let network_info =
-                    base_token
-                        .deployments
-                        .iter()
-                        .find_map(|deployment| match deployment {
-                            TokenDeployment::Native {
-                                chain_name,
-                                decimals,
-                                bridge,
-                                ..
-                            } => {
-                                if chain_name == contract_id {
-                                    Some(NetworkInfo {
-                                        chain_id: chain_name.clone(),
-                                        chain_name: chain_name.clone(),
-                                        contract_address: None,
-                                        decimals: *decimals,
-                                        bridge: bridge.clone(),
-                                    })
-                                } else {
-                                    None
-                                }
-                            }
-                            TokenDeployment::Fungible {
-                                address,
-                                chain_name,
-                                decimals,
-                                bridge,
-                                ..
-                            } => {
-                                if address == contract_id {
-                                    Some(NetworkInfo {
-                                        chain_id: format!("nep141:{}", address),
-                                        chain_name: chain_name.clone(),
-                                        contract_address: Some(address.clone()),
-                                        decimals: *decimals,
-                                        bridge: bridge.clone(),
-                                    })
-                                } else {
-                                    None
-                                }
-                            }
-                        });
+                let network_info = base_token
+                    .deployments
+                    .iter()
+                    .find(|deployment| match deployment {
+                        TokenDeployment::Native { chain_name, .. } => chain_name == contract_id,
+                        TokenDeployment::Fungible { address, .. } => address == contract_id,
+                    })
+                    .map(NetworkInfo::from_deployment);
 
                 return Some(TokenSearchResult {
                     defuse_asset_id: base_token.defuse_asset_id.clone(),
@@ -187,53 +310,13 @@ fn search_token_out(query: &str, destination_network: Option<&str>) -> Option<To
                 || base_token.name.to_lowercase() == query_lower
             {
                 // Find the network deployment matching the destination network (chainId)
-                let network_info = if let Some(chain_id) = destination_network {
-                    base_token.deployments.iter().find_map(|deployment| {
-                        match deployment {
-                            TokenDeployment::Native {
-                                chain_name,
-                                decimals,
-                                bridge,
-                                ..
-                            } => {
-                                // For native tokens, chainId is the chain name
-                                if chain_name == chain_id {
-                                    Some(NetworkInfo {
-                                        chain_id: chain_name.clone(),
-                                        chain_name: chain_name.clone(),
-                                        contract_address: None,
-                                        decimals: *decimals,
-                                        bridge: bridge.clone(),
-                                    })
-                                } else {
-                                    None
-                                }
-                            }
-                            TokenDeployment::Fungible {
-                                address,
-                                chain_name,
-                                decimals,
-                                bridge,
-                                ..
-                            } => {
-                                // For fungible tokens, chainId could be the chain name
-                                if chain_name == chain_id {
-                                    Some(NetworkInfo {
-                                        chain_id: chain_name.clone(),
-                                        chain_name: chain_name.clone(),
-                                        contract_address: Some(address.clone()),
-                                        decimals: *decimals,
-                                        bridge: bridge.clone(),
-                                    })
-                                } else {
-                                    None
-                                }
-                            }
-                        }
-                    })
-                } else {
-                    None
-                };
+                let network_info = destination_network.and_then(|chain_id| {
+                    base_token
+                        .deployments
+                        .iter()
+                        .find(|deployment| deployment.chain_name() == chain_id)
+                        .map(NetworkInfo::from_deployment)
+                });
 
                 return Some(TokenSearchResult {
                     defuse_asset_id: base_token.defuse_asset_id.clone(),
@@ -261,59 +344,116 @@ fn search_token_out(query: &str, destination_network: Option<&str>) -> Option<To
 /// - destinationNetwork: Chain ID to match for tokenOut network
 ///
 /// Returns matching tokens with their defuse asset IDs, metadata, and network info
+#[utoipa::path(
+    get,
+    path = "/api/intents/search-tokens",
+    tag = "intents",
+    params(SearchTokensQuery),
+    responses(
+        (status = 200, description = "Matching intents tokens", body = SearchTokensResponse),
+    )
+)]
 pub async fn search_tokens(
     State(state): State<Arc<AppState>>,
     Query(params): Query<SearchTokensQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<Json<SearchTokensResponse>, ApiError> {
+    let limit = params.limit.unwrap_or(DEFAULT_FUZZY_LIMIT);
+
     // Build cache key from search params
     let cache_key = format!(
-        "token-search:{}:{}:{}:{}",
+        "token-search:{}:{}:{}:{}:{}",
         params.token_in.as_deref().unwrap_or(""),
         params.token_out.as_deref().unwrap_or(""),
         params.intents_token_contract_id.as_deref().unwrap_or(""),
-        params.destination_network.as_deref().unwrap_or("")
+        params.destination_network.as_deref().unwrap_or(""),
+        if params.fuzzy {
+            format!("fuzzy-{}", limit)
+        } else {
+            String::new()
+        }
     );
 
     // Check cache
-    if let Some(cached_result) = state.cache.get(&cache_key).await {
-        return Ok((StatusCode::OK, Json(cached_result)));
+    if let Some(cached) = state.cache.get(&cache_key).await
+        && let Ok(response) = serde_json::from_value(cached)
+    {
+        return Ok(Json(response));
     }
 
-    // Search for tokenIn if provided
-    let token_in_result = params
-        .token_in
-        .as_ref()
-        .and_then(|query| search_token_in(query, params.intents_token_contract_id.as_deref()));
-
-    // Search for tokenOut if provided
-    let token_out_result = params
-        .token_out
-        .as_ref()
-        .and_then(|query| search_token_out(query, params.destination_network.as_deref()));
-
-    let response = SearchTokensResponse {
-        token_in: token_in_result,
-        token_out: token_out_result,
+    let response = if params.fuzzy {
+        SearchTokensResponse {
+            token_in: None,
+            token_out: None,
+            token_in_matches: params
+                .token_in
+                .as_ref()
+                .map(|query| fuzzy_search_tokens(query, limit)),
+            token_out_matches: params
+                .token_out
+                .as_ref()
+                .map(|query| fuzzy_search_tokens(query, limit)),
+        }
+    } else {
+        SearchTokensResponse {
+            token_in: params.token_in.as_ref().and_then(|query| {
+                search_token_in(query, params.intents_token_contract_id.as_deref())
+            }),
+            token_out: params
+                .token_out
+                .as_ref()
+                .and_then(|query| search_token_out(query, params.destination_network.as_deref())),
+            token_in_matches: None,
+            token_out_matches: None,
+        }
     };
 
-    let result_value = serde_json::to_value(&response).map_err(|e| {
-        eprintln!("Error serializing search result: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to serialize result".to_string(),
-        )
-    })?;
-
     // Cache the result
-    state.cache.insert(cache_key, result_value.clone()).await;
+    if let Ok(value) = serde_json::to_value(&response) {
+        state.cache.insert(cache_key, value).await;
+    }
 
-    Ok((StatusCode::OK, Json(result_value)))
+    Ok(Json(response))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn fuzzy_symbols(query: &str, limit: usize) -> Vec<String> {
+        fuzzy_search_tokens(query, limit)
+            .into_iter()
+            .map(|r| r.token.symbol)
+            .collect()
+    }
+
+    #[test]
+    fn test_fuzzy_search_ranks_usd_stablecoins() {
+        let usdc = fuzzy_search_tokens("usdc", 5);
+        assert_eq!(usdc[0].token.symbol, "USDC");
+        assert_eq!(usdc[0].score, 1.0);
+        assert!(
+            fuzzy_symbols("usdc", 5).contains(&"USDT".to_string()),
+            "One-letter typo should still rank"
+        );
+
+        assert_eq!(fuzzy_symbols("usdt", 5)[0], "USDT");
+
+        // A partial query ranks every USD-prefixed token above anything else
+        let usd = fuzzy_search_tokens("usd", 10);
+        let top: Vec<&str> = usd[..4].iter().map(|r| r.token.symbol.as_str()).collect();
+        assert!(top.contains(&"USDC") && top.contains(&"USDT"));
+        assert!(usd[..4].iter().all(|r| r.score > 0.8 && r.score < 1.0));
+        assert!(usd[4..].iter().all(|r| r.score < usd[3].score));
+    }
+
+    #[test]
+    fn test_match_score_tiers() {
+        assert_eq!(match_score("usdc", "usdc"), 1.0);
+        assert!(match_score("usd", "usdc") > match_score("sdc", "usdc"));
+        assert!(match_score("sdc", "usdc") > match_score("udc", "usdc"));
+        assert_eq!(edit_distance("usdc", "usdt"), 1);
+    }
+
     #[test]
     fn test_search_token_in_by_symbol() {
         // Test searching for tokenIn by symbol
@@ -380,6 +520,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_chain_id_is_consistent_across_lookups() {
+        // wrap.near by contract id and NEAR by destination chain are the same deployment
+        let by_contract = search_token_in("NEAR", Some("nep141:wrap.near"))
+            .and_then(|token| token.network_info)
+            .expect("Should find wrap.near by contract id");
+        let by_chain = search_token_out("NEAR", Some("near"))
+            .and_then(|token| token.network_info)
+            .expect("Should find NEAR on the near chain");
+
+        assert_eq!(by_contract, by_chain);
+        assert_eq!(by_contract.chain_id, "near");
+        assert_eq!(by_contract.contract_address.as_deref(), Some("wrap.near"));
+    }
+
     #[test]
     fn test_search_token_out_by_name() {
         // Test searching for tokenOut by name
@@ -466,6 +621,39 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_typed_response_matches_cached_json() {
+        let state = Arc::new(crate::utils::test_utils::init_test_state().await);
+        let contract = "17208628f84f5d6ad33f0da3bbbeb27ffcb398eac501a31bd6ad2011e36133a1";
+        let query = || SearchTokensQuery {
+            token_in: Some("USDC".to_string()),
+            token_out: None,
+            intents_token_contract_id: Some(contract.to_string()),
+            destination_network: None,
+            fuzzy: false,
+            limit: None,
+        };
+
+        let Json(response) = search_tokens(State(state.clone()), Query(query()))
+            .await
+            .unwrap();
+
+        // Same JSON as the handler returned before it was typed
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "tokenIn": search_token_in("USDC", Some(contract)) })
+        );
+        assert_eq!(json["tokenIn"]["symbol"], "USDC");
+        assert!(json.get("tokenOut").is_none() && json.get("tokenInMatches").is_none());
+
+        // The cached value is the same JSON and is served on the next request
+        let cache_key = format!("token-search:USDC::{}::", contract);
+        assert_eq!(state.cache.get(&cache_key).await, Some(json));
+        let Json(cached) = search_tokens(State(state), Query(query())).await.unwrap();
+        assert_eq!(cached, response);
+    }
+
     #[test]
     fn test_search_with_nep141_prefix() {
         // Test searching with nep141: prefix in contract ID
@@ -8,11 +8,16 @@ use axum::{
 use near_api::{AccountId, Contract, NetworkConfig};
 use reqwest::StatusCode;
 use serde::Deserialize;
+use utoipa::IntoParams;
 
 use crate::AppState;
+use crate::utils::api_error::ApiError;
+use crate::utils::timeout::with_timeout;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct PoolLookupQuery {
+    #[param(value_type = String)]
     #[serde(rename = "accountId")]
     pub account_id: AccountId,
 }
@@ -20,7 +25,7 @@ pub struct PoolLookupQuery {
 async fn fetch_pool(
     token_id: AccountId,
     network: &NetworkConfig,
-) -> Result<Option<AccountId>, (StatusCode, String)> {
+) -> Result<Option<AccountId>, ApiError> {
     let pool: Option<AccountId> = Contract(token_id.clone())
         .call_function("get_staking_pool_account_id", ())
         .read_only()
@@ -28,33 +33,41 @@ async fn fetch_pool(
         .await
         .map_err(|e| {
             eprintln!("Error fetching pool for {}: {}", token_id, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch pool: {}", e),
-            )
+            ApiError::internal(format!("Failed to fetch pool: {}", e))
         })?
         .data;
 
     Ok(pool)
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/lockup/pool",
+    tag = "lookup",
+    params(PoolLookupQuery),
+    responses(
+        (status = 200, description = "Staking pool of the lockup account", body = serde_json::Value),
+    )
+)]
 pub async fn get_lockup_pool(
     State(state): State<Arc<AppState>>,
     Query(params): Query<PoolLookupQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let cache_key = format!("pool-lookup:{}", params.account_id);
     if let Some(cached_data) = state.cache.get(&cache_key).await {
         return Ok((StatusCode::OK, Json(cached_data.clone())));
     }
 
-    let pool = fetch_pool(params.account_id.clone(), &state.network).await?;
+    let pool = with_timeout(
+        state.external_timeout(),
+        "RPC",
+        fetch_pool(params.account_id.clone(), &state.network),
+    )
+    .await??;
 
     let result_value = serde_json::to_value(&pool).map_err(|e| {
         eprintln!("Error serializing pool: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to serialize pool: {}", e),
-        )
+        ApiError::internal(format!("Failed to serialize pool: {}", e))
     })?;
 
     state.cache.insert(cache_key, result_value.clone()).await;
@@ -1,4 +1,5 @@
 pub mod balance_changes;
+pub mod block;
 pub mod bulkpayment;
 pub mod intents;
 pub mod lookup;
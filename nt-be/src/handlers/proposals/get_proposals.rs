@@ -7,14 +7,25 @@ use axum::{
 use std::sync::Arc;
 
 use crate::AppState;
+use crate::utils::api_error::ApiError;
+use crate::utils::timeout::with_timeout;
 
+#[utoipa::path(
+    get,
+    path = "/api/proposals/{dao_id}",
+    tag = "proposals",
+    params(("dao_id" = String, Path, description = "Sputnik DAO account")),
+    responses(
+        (status = 200, description = "Filtered proposals", body = serde_json::Value),
+    )
+)]
 pub async fn get_proposals(
     State(state): State<Arc<AppState>>,
     Path(dao_id): Path<String>,
     RawQuery(query): RawQuery,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     if dao_id.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "dao_id is required".to_string()));
+        return Err(ApiError::bad_request("dao_id is required"));
     }
 
     // Build URL with query string
@@ -31,13 +42,13 @@ pub async fn get_proposals(
     };
 
     // Forward request to Sputnik DAO API
-    let response = state.http_client.get(&url).send().await.map_err(|e| {
-        eprintln!("Error fetching proposals from Sputnik DAO API: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to fetch proposals: {}", e),
-        )
-    })?;
+    let request = state.http_client.get(&url).send();
+    let response = with_timeout(state.external_timeout(), "Sputnik DAO API", request)
+        .await?
+        .map_err(|e| {
+            eprintln!("Error fetching proposals from Sputnik DAO API: {}", e);
+            ApiError::internal(format!("Failed to fetch proposals: {}", e))
+        })?;
 
     if !response.status().is_success() {
         let status = response.status();
@@ -46,50 +57,60 @@ pub async fn get_proposals(
             .await
             .unwrap_or_else(|_| "Unknown error".to_string());
         eprintln!("Sputnik DAO API error: {} - {}", status, error_text);
-        return Err((
+        return Err(ApiError::new(
             StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
             format!("Sputnik DAO API error: {}", error_text),
         ));
     }
 
     // Forward response as-is
-    let proposals_response: serde_json::Value = response.json().await.map_err(|e| {
-        eprintln!("Error parsing proposals response: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to parse proposals: {}", e),
-        )
-    })?;
+    let proposals_response: serde_json::Value =
+        with_timeout(state.external_timeout(), "Sputnik DAO API", response.json())
+            .await?
+            .map_err(|e| {
+                eprintln!("Error parsing proposals response: {}", e);
+                ApiError::internal(format!("Failed to parse proposals: {}", e))
+            })?;
 
     Ok((StatusCode::OK, Json(proposals_response)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/proposal/{dao_id}/{proposal_id}",
+    tag = "proposals",
+    params(
+        ("dao_id" = String, Path, description = "Sputnik DAO account"),
+        ("proposal_id" = u64, Path, description = "Proposal ID"),
+    ),
+    responses(
+        (status = 200, description = "Proposal", body = serde_json::Value),
+    )
+)]
 pub async fn get_proposal(
     State(state): State<Arc<AppState>>,
     Path((dao_id, proposal_id)): Path<(String, String)>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     if dao_id.is_empty() || proposal_id.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "proposal_id is required".to_string(),
-        ));
+        return Err(ApiError::bad_request("proposal_id is required"));
     }
 
-    let response = state
-        .http_client
-        .get(format!(
-            "{}/proposal/{}/{}",
-            state.env_vars.sputnik_dao_api_base, dao_id, proposal_id
-        ))
-        .send()
-        .await
-        .map_err(|e| {
-            eprintln!("Error fetching proposal from Sputnik DAO API: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch proposal: {}", e),
-            )
-        })?;
+    let response = with_timeout(
+        state.external_timeout(),
+        "Sputnik DAO API",
+        state
+            .http_client
+            .get(format!(
+                "{}/proposal/{}/{}",
+                state.env_vars.sputnik_dao_api_base, dao_id, proposal_id
+            ))
+            .send(),
+    )
+    .await?
+    .map_err(|e| {
+        eprintln!("Error fetching proposal from Sputnik DAO API: {}", e);
+        ApiError::internal(format!("Failed to fetch proposal: {}", e))
+    })?;
 
     if !response.status().is_success() {
         let status = response.status();
@@ -98,19 +119,19 @@ pub async fn get_proposal(
             .await
             .unwrap_or_else(|_| "Unknown error".to_string());
         eprintln!("Sputnik DAO API error: {} - {}", status, error_text);
-        return Err((
+        return Err(ApiError::new(
             StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
             format!("Sputnik DAO API error: {}", error_text),
         ));
     }
 
-    let proposal_response: serde_json::Value = response.json().await.map_err(|e| {
-        eprintln!("Error parsing proposal response: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to parse proposal: {}", e),
-        )
-    })?;
+    let proposal_response: serde_json::Value =
+        with_timeout(state.external_timeout(), "Sputnik DAO API", response.json())
+            .await?
+            .map_err(|e| {
+                eprintln!("Error parsing proposal response: {}", e);
+                ApiError::internal(format!("Failed to parse proposal: {}", e))
+            })?;
 
     Ok((StatusCode::OK, Json(proposal_response)))
 }
@@ -1 +1,2 @@
 pub mod get_proposals;
+pub mod votes;
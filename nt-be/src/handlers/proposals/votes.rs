@@ -0,0 +1,377 @@
+//! Proposal Vote Tallies
+//!
+//! Computes where a Sputnik DAO proposal stands: who voted what, and whether the
+//! votes reach the threshold of any role allowed to vote on it. The rule mirrors the
+//! contract's `proposal_status`: for each role (except `Everyone`, which has no fixed
+//! size) the threshold is the larger of the vote policy's quorum and its threshold,
+//! where a ratio threshold `[num, denom]` is `min(num * total / denom + 1, total)`.
+
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+use near_api::{AccountId, Contract};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::AppState;
+use crate::handlers::treasury::policy::fetch_treasury_policy;
+use crate::utils::api_error::ApiError;
+use crate::utils::timeout::with_timeout;
+
+/// Accounts that cast each kind of vote
+#[derive(Debug, Default, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Voters {
+    pub approve: Vec<String>,
+    pub reject: Vec<String>,
+    pub remove: Vec<String>,
+}
+
+/// Votes of one role against its threshold
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct RoleTally {
+    pub role: String,
+    /// Weighted votes (members for role-weighted policies, delegated tokens otherwise)
+    pub approve: u128,
+    pub reject: u128,
+    pub remove: u128,
+    pub threshold: u128,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ProposalVotes {
+    pub proposal_id: u64,
+    /// Status recorded by the contract (`InProgress`, `Approved`, ...)
+    pub status: String,
+    /// Number of accounts that voted to approve, reject or remove
+    pub approve: u64,
+    pub reject: u64,
+    pub remove: u64,
+    /// Lowest threshold among the roles that can decide the proposal; `null` if none can
+    pub threshold: Option<u128>,
+    /// Whether the approvals reach the threshold of any role
+    pub would_pass: bool,
+    pub voters: Voters,
+    pub roles: Vec<RoleTally>,
+}
+
+/// Policy label of a proposal kind, as used as key of `vote_policy`
+fn policy_label(kind: &Value) -> Option<&'static str> {
+    let name = match kind {
+        Value::String(name) => name.as_str(),
+        Value::Object(map) => map.keys().next()?.as_str(),
+        _ => return None,
+    };
+    Some(match name {
+        "ChangeConfig" => "config",
+        "ChangePolicy" => "policy",
+        "AddMemberToRole" => "add_member_to_role",
+        "RemoveMemberFromRole" => "remove_member_from_role",
+        "FunctionCall" => "call",
+        "UpgradeSelf" => "upgrade_self",
+        "UpgradeRemote" => "upgrade_remote",
+        "Transfer" => "transfer",
+        "SetStakingContract" => "set_vote_token",
+        "AddBounty" => "add_bounty",
+        "BountyDone" => "bounty_done",
+        "Vote" => "vote",
+        "FactoryInfoUpdate" => "factory_info_update",
+        "ChangePolicyAddOrUpdateRole" => "policy_add_or_update_role",
+        "ChangePolicyRemoveRole" => "policy_remove_role",
+        "ChangePolicyUpdateDefaultVotePolicy" => "policy_update_default_vote_policy",
+        "ChangePolicyUpdateParameters" => "policy_update_parameters",
+        _ => return None,
+    })
+}
+
+/// A U128 given as a JSON string or number
+fn as_u128(value: &Value) -> Option<u128> {
+    match value {
+        Value::String(s) => s.parse().ok(),
+        Value::Number(n) => n.to_string().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Weight needed for a decision out of `total_weight`
+fn threshold_weight(vote_policy: &Value, total_weight: u128) -> u128 {
+    let quorum = vote_policy.get("quorum").and_then(as_u128).unwrap_or(0);
+    let threshold = match vote_policy.get("threshold") {
+        Some(Value::Array(ratio)) => {
+            let num = ratio.first().and_then(as_u128).unwrap_or(1);
+            let denom = ratio.get(1).and_then(as_u128).unwrap_or(1).max(1);
+            (num * total_weight / denom + 1).min(total_weight)
+        }
+        Some(weight) => as_u128(weight).unwrap_or(0),
+        None => 0,
+    };
+    quorum.max(threshold)
+}
+
+/// Tally the votes of a proposal against a DAO policy
+///
+/// # Arguments
+/// * `proposal` - The proposal as returned by the contract's `get_proposal`
+/// * `policy` - The DAO policy as returned by `get_policy`
+/// * `total_supply` - Delegated token supply, for token-weighted vote policies
+pub fn tally_votes(proposal: &Value, policy: &Value, total_supply: u128) -> ProposalVotes {
+    let mut voters = Voters::default();
+    if let Some(votes) = proposal.get("votes").and_then(Value::as_object) {
+        for (account_id, vote) in votes {
+            let list = match vote.as_str() {
+                Some("Approve") => &mut voters.approve,
+                Some("Reject") => &mut voters.reject,
+                Some("Remove") => &mut voters.remove,
+                _ => continue,
+            };
+            list.push(account_id.clone());
+        }
+    }
+    for list in [&mut voters.approve, &mut voters.reject, &mut voters.remove] {
+        list.sort();
+    }
+
+    let label = proposal.get("kind").and_then(policy_label);
+    let default_vote_policy = &policy["default_vote_policy"];
+    let roles: Vec<RoleTally> = policy
+        .get("roles")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|role| {
+            let name = role.get("name")?.as_str()?;
+            let vote_policy = label
+                .and_then(|label| role.get("vote_policy")?.get(label))
+                .unwrap_or(default_vote_policy);
+            let role_weighted =
+                vote_policy.get("weight_kind").and_then(Value::as_str) != Some("TokenWeight");
+            let total_weight = match role.get("kind")? {
+                // `Everyone` has no size, so it can't decide a proposal
+                Value::String(kind) if kind == "Everyone" => return None,
+                Value::Object(kind) => match kind.get("Group") {
+                    Some(Value::Array(members)) if role_weighted => members.len() as u128,
+                    _ => total_supply,
+                },
+                _ => return None,
+            };
+
+            let counts = proposal.get("vote_counts").and_then(|c| c.get(name));
+            let count = |i: usize| counts.and_then(|c| c.get(i)).and_then(as_u128).unwrap_or(0);
+            Some(RoleTally {
+                role: name.to_string(),
+                approve: count(0),
+                reject: count(1),
+                remove: count(2),
+                threshold: threshold_weight(vote_policy, total_weight),
+            })
+        })
+        .collect();
+
+    ProposalVotes {
+        proposal_id: proposal
+            .get("id")
+            .and_then(Value::as_u64)
+            .unwrap_or_default(),
+        status: proposal
+            .get("status")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        approve: voters.approve.len() as u64,
+        reject: voters.reject.len() as u64,
+        remove: voters.remove.len() as u64,
+        threshold: roles.iter().map(|r| r.threshold).min(),
+        would_pass: roles.iter().any(|r| r.approve >= r.threshold),
+        voters,
+        roles,
+    }
+}
+
+/// Whether any role of the policy weighs votes by delegated tokens
+fn uses_token_weight(policy: &Value) -> bool {
+    let is_token_weight = |vote_policy: &Value| {
+        vote_policy.get("weight_kind").and_then(Value::as_str) == Some("TokenWeight")
+    };
+    is_token_weight(&policy["default_vote_policy"])
+        || policy["roles"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .any(|role| {
+                role["vote_policy"]
+                    .as_object()
+                    .is_some_and(|policies| policies.values().any(is_token_weight))
+            })
+}
+
+/// Get the vote tallies of a proposal
+///
+/// The proposal is read from the DAO contract; the policy comes from the shared
+/// cache (see `/api/treasury/policy`).
+#[utoipa::path(
+    get,
+    path = "/api/proposal/{dao_id}/{proposal_id}/votes",
+    tag = "proposals",
+    params(
+        ("dao_id" = String, Path, description = "Sputnik DAO account"),
+        ("proposal_id" = u64, Path, description = "Proposal ID"),
+    ),
+    responses(
+        (status = 200, description = "Vote tallies", body = ProposalVotes),
+        (status = 400, description = "Invalid DAO account id"),
+        (status = 404, description = "Proposal not found"),
+    )
+)]
+pub async fn get_proposal_votes(
+    State(state): State<Arc<AppState>>,
+    Path((dao_id, proposal_id)): Path<(String, u64)>,
+) -> Result<Json<ProposalVotes>, ApiError> {
+    let dao_id: AccountId = dao_id
+        .parse()
+        .map_err(|e| ApiError::bad_request(format!("Invalid dao_id: {}", e)))?;
+
+    let proposal: Value = with_timeout(
+        state.external_timeout(),
+        "RPC",
+        Contract(dao_id.clone())
+            .call_function("get_proposal", serde_json::json!({ "id": proposal_id }))
+            .read_only()
+            .fetch_from(&state.network),
+    )
+    .await?
+    .map_err(|e| {
+        log::warn!(
+            "Failed to fetch proposal {} of {}: {}",
+            proposal_id,
+            dao_id,
+            e
+        );
+        ApiError::not_found(format!("Proposal {} not found", proposal_id))
+            .with_details(e.to_string())
+    })?
+    .data;
+
+    let policy = fetch_treasury_policy(&state, &dao_id).await?;
+
+    let total_supply = if uses_token_weight(&policy) {
+        let supply: Value = with_timeout(
+            state.external_timeout(),
+            "RPC",
+            Contract(dao_id.clone())
+                .call_function("delegation_total_supply", ())
+                .read_only()
+                .fetch_from(&state.network),
+        )
+        .await?
+        .map_err(|e| {
+            ApiError::internal("Failed to fetch delegation supply").with_details(e.to_string())
+        })?
+        .data;
+        as_u128(&supply).unwrap_or(0)
+    } else {
+        0
+    };
+
+    Ok(Json(tally_votes(&proposal, &policy, total_supply)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policy() -> Value {
+        json!({
+            "roles": [
+                {
+                    "name": "all",
+                    "kind": "Everyone",
+                    "permissions": ["*:AddProposal"],
+                    "vote_policy": {}
+                },
+                {
+                    "name": "Approver",
+                    "kind": { "Group": ["alice.near", "bob.near", "carol.near", "dave.near"] },
+                    "permissions": ["*:VoteApprove", "*:VoteReject", "*:VoteRemove"],
+                    "vote_policy": {
+                        "transfer": { "weight_kind": "RoleWeight", "quorum": "0", "threshold": "3" }
+                    }
+                }
+            ],
+            "default_vote_policy": { "weight_kind": "RoleWeight", "quorum": "0", "threshold": [1, 2] }
+        })
+    }
+
+    fn proposal(kind: Value, votes: Value, approve: u64, reject: u64) -> Value {
+        json!({
+            "id": 42,
+            "proposer": "alice.near",
+            "description": "Pay the designer",
+            "kind": kind,
+            "status": "InProgress",
+            "vote_counts": { "Approver": [approve, reject, 0] },
+            "votes": votes,
+            "submission_time": "1750000000000000000"
+        })
+    }
+
+    #[test]
+    fn test_in_progress_proposal_below_threshold() {
+        let transfer = json!({ "Transfer": { "token_id": "", "receiver_id": "designer.near", "amount": "1" } });
+        let votes = tally_votes(
+            &proposal(
+                transfer,
+                json!({ "bob.near": "Approve", "alice.near": "Approve", "carol.near": "Reject" }),
+                2,
+                1,
+            ),
+            &policy(),
+            0,
+        );
+
+        assert_eq!(votes.proposal_id, 42);
+        assert_eq!(votes.status, "InProgress");
+        assert_eq!((votes.approve, votes.reject, votes.remove), (2, 1, 0));
+        assert_eq!(votes.voters.approve, vec!["alice.near", "bob.near"]);
+        assert_eq!(votes.voters.reject, vec!["carol.near"]);
+        // The transfer policy needs 3 approvals; `Everyone` can't decide
+        assert_eq!(votes.roles.len(), 1);
+        assert_eq!(votes.threshold, Some(3));
+        assert!(!votes.would_pass);
+    }
+
+    #[test]
+    fn test_ratio_threshold_from_default_policy() {
+        // Half of 4 members: 4 * 1 / 2 + 1 = 3 approvals
+        let call = json!({ "FunctionCall": { "receiver_id": "app.near", "actions": [] } });
+        let votes = tally_votes(&proposal(call.clone(), json!({}), 2, 0), &policy(), 0);
+        assert_eq!(votes.threshold, Some(3));
+        assert!(!votes.would_pass);
+
+        let votes = tally_votes(&proposal(call, json!({}), 3, 0), &policy(), 0);
+        assert!(votes.would_pass);
+    }
+
+    #[test]
+    fn test_token_weighted_threshold_uses_supply() {
+        let policy = json!({
+            "roles": [{
+                "name": "council",
+                "kind": { "Group": ["alice.near"] },
+                "vote_policy": {}
+            }],
+            "default_vote_policy": { "weight_kind": "TokenWeight", "quorum": "10", "threshold": [1, 2] }
+        });
+        assert!(uses_token_weight(&policy));
+
+        let votes = tally_votes(
+            &json!({ "id": 1, "kind": "Vote", "status": "InProgress", "vote_counts": { "council": ["600", "0", "0"] }, "votes": {} }),
+            &policy,
+            1000,
+        );
+        assert_eq!(votes.roles[0].threshold, 501);
+        assert!(votes.would_pass);
+    }
+}
@@ -1,18 +1,114 @@
 use axum::{
     Json,
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::IntoResponse,
+    body::Bytes,
+    extract::{Path, RawQuery, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
 };
-use moka::future::Cache;
 use reqwest::Client;
 use serde_json::Value;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, fmt, sync::Arc, time::Duration};
 
-use crate::AppState;
+use crate::{
+    AppState,
+    utils::{api_error::ApiError, cache::AppCache, env::EnvVars},
+};
 
 pub const REF_SDK_BASE_URL: &str = "https://ref-sdk-test-cold-haze-1300-2.fly.dev/api";
 
+/// Timeout and body size limits applied to proxied requests
+#[derive(Clone, Debug)]
+pub struct ProxyLimits {
+    pub timeout: Duration,
+    pub max_response_bytes: usize,
+    pub max_request_bytes: usize,
+}
+
+impl ProxyLimits {
+    pub fn from_env_vars(env_vars: &EnvVars) -> Self {
+        Self {
+            timeout: Duration::from_secs(env_vars.proxy_timeout_seconds),
+            max_response_bytes: env_vars.proxy_max_response_bytes,
+            max_request_bytes: env_vars.proxy_max_request_bytes,
+        }
+    }
+}
+
+/// Errors returned when proxying a request to an external API
+#[derive(Debug)]
+pub enum ProxyError {
+    /// The upstream did not respond within the configured timeout
+    Timeout,
+    /// The upstream response body exceeded the configured size limit
+    ResponseTooLarge,
+    /// The forwarded request body exceeded the configured size limit
+    RequestTooLarge,
+    /// The upstream returned a non-success status code
+    Upstream(reqwest::StatusCode),
+    /// The request could not be sent or the body could not be read
+    Request,
+    /// The upstream response was not valid JSON
+    Parse,
+}
+
+impl ProxyError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            ProxyError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            ProxyError::ResponseTooLarge | ProxyError::RequestTooLarge => {
+                StatusCode::PAYLOAD_TOO_LARGE
+            }
+            ProxyError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            ProxyError::Request | ProxyError::Parse => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProxyError::Timeout => write!(f, "External API timed out"),
+            ProxyError::ResponseTooLarge => write!(f, "External API response too large"),
+            ProxyError::RequestTooLarge => write!(f, "Request body too large"),
+            ProxyError::Upstream(status) => write!(f, "External API error: {}", status),
+            ProxyError::Request => write!(f, "Failed to fetch from external API"),
+            ProxyError::Parse => write!(f, "Failed to parse response"),
+        }
+    }
+}
+
+impl From<reqwest::Error> for ProxyError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            ProxyError::Timeout
+        } else {
+            ProxyError::Request
+        }
+    }
+}
+
+/// Reads a response body, giving up as soon as it grows past `max_bytes`
+async fn read_body_capped(
+    mut response: reqwest::Response,
+    max_bytes: usize,
+) -> Result<Vec<u8>, ProxyError> {
+    if response
+        .content_length()
+        .is_some_and(|len| len > max_bytes as u64)
+    {
+        return Err(ProxyError::ResponseTooLarge);
+    }
+
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > max_bytes {
+            return Err(ProxyError::ResponseTooLarge);
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    Ok(body)
+}
+
 /// Fetches JSON data from an external API with caching
 ///
 /// # Arguments
@@ -21,32 +117,46 @@ pub const REF_SDK_BASE_URL: &str = "https://ref-sdk-test-cold-haze-1300-2.fly.de
 /// * `base_url` - The base URL of the API
 /// * `path` - The path to append to the base URL
 /// * `params` - Query parameters to include in the request
+/// * `limits` - Timeout and maximum response size for the upstream request
 ///
 /// # Returns
 /// * `Ok(Value)` - The parsed JSON response
-/// * `Err(String)` - An error message describing what went wrong
+/// * `Err(ProxyError)` - What went wrong, mapped to a status code by the caller
 pub async fn fetch_proxy_api(
     client: &Client,
-    cache: &Cache<String, Value>,
+    cache: &AppCache,
     base_url: &str,
     path: &str,
     params: &HashMap<String, String>,
-) -> Result<Value, String> {
-    // Construct the full URL for both fetching and cache key
-    let mut url = format!("{}/{}", base_url, path);
-
-    // Add query parameters if any
-    let query_string = if !params.is_empty() {
-        let qs = params
-            .iter()
-            .map(|(k, v)| format!("{}={}", k, v))
-            .collect::<Vec<_>>()
-            .join("&");
-        url = format!("{}?{}", url, qs);
-        qs
-    } else {
-        String::new()
-    };
+    limits: &ProxyLimits,
+) -> Result<Value, ProxyError> {
+    let query_string = params
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    fetch_proxy_api_with_query(client, cache, base_url, path, &query_string, limits).await
+}
+
+/// Fetches JSON data from an external API with caching, forwarding a raw query string
+///
+/// # Arguments
+/// * `client` - The HTTP client to use for the request
+/// * `cache` - The cache to store responses in
+/// * `base_url` - The base URL of the API
+/// * `path` - The path to append to the base URL
+/// * `query_string` - Query string forwarded as-is (empty for none)
+/// * `limits` - Timeout and maximum response size for the upstream request
+pub async fn fetch_proxy_api_with_query(
+    client: &Client,
+    cache: &AppCache,
+    base_url: &str,
+    path: &str,
+    query_string: &str,
+    limits: &ProxyLimits,
+) -> Result<Value, ProxyError> {
+    let url = build_proxy_url(base_url, path, query_string);
 
     // Create cache key from base_url, path, and query params
     let cache_key = format!("proxy:{}:{}:{}", base_url, path, query_string);
@@ -59,68 +169,308 @@ pub async fn fetch_proxy_api(
 
     println!("Cache miss, proxying request to: {}", url);
 
-    // Proxy the request to the external API
-    match client
-        .get(&url)
+    let data = send_proxy_request(client.get(&url), &url, limits).await?;
+
+    // Store in cache
+    cache.insert(cache_key, data.clone()).await;
+    Ok(data)
+}
+
+/// Posts a body to an external API and returns the JSON response
+///
+/// POST responses are never cached. The request body is rejected before
+/// anything is sent upstream if it exceeds `limits.max_request_bytes`.
+///
+/// # Arguments
+/// * `client` - The HTTP client to use for the request
+/// * `base_url` - The base URL of the API
+/// * `path` - The path to append to the base URL
+/// * `query_string` - Query string forwarded as-is (empty for none)
+/// * `body` - The request body to forward
+/// * `content_type` - Content type of the body, if the caller sent one
+/// * `limits` - Timeout and size limits for the upstream request
+pub async fn post_proxy_api(
+    client: &Client,
+    base_url: &str,
+    path: &str,
+    query_string: &str,
+    body: Bytes,
+    content_type: Option<&HeaderValue>,
+    limits: &ProxyLimits,
+) -> Result<Value, ProxyError> {
+    if body.len() > limits.max_request_bytes {
+        return Err(ProxyError::RequestTooLarge);
+    }
+
+    let url = build_proxy_url(base_url, path, query_string);
+    println!("Proxying POST request to: {}", url);
+
+    let mut request = client.post(&url).body(body);
+    if let Some(content_type) = content_type {
+        request = request.header(header::CONTENT_TYPE, content_type);
+    }
+
+    send_proxy_request(request, &url, limits).await
+}
+
+/// Joins the base URL, path and an optional query string
+///
+/// The host always comes from `base_url`, so a caller-supplied path can't
+/// redirect the request to another server.
+fn build_proxy_url(base_url: &str, path: &str, query_string: &str) -> String {
+    if query_string.is_empty() {
+        format!("{}/{}", base_url, path)
+    } else {
+        format!("{}/{}?{}", base_url, path, query_string)
+    }
+}
+
+/// Sends a prepared upstream request and parses the JSON response, enforcing `limits`
+async fn send_proxy_request(
+    request: reqwest::RequestBuilder,
+    url: &str,
+    limits: &ProxyLimits,
+) -> Result<Value, ProxyError> {
+    let response = request
         .header("accept", "application/json")
+        .timeout(limits.timeout)
         .send()
         .await
-    {
-        Ok(response) => {
-            let status = response.status();
-            if status.is_success() {
-                match response.json::<Value>().await {
-                    Ok(data) => {
-                        // Store in cache
-                        cache.insert(cache_key, data.clone()).await;
-                        Ok(data)
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to parse response from {}: {}", url, e);
-                        Err("Failed to parse response".to_string())
-                    }
-                }
-            } else {
-                eprintln!("External API returned error {}: {}", status, url);
-                Err(format!("External API error: {}", status))
-            }
-        }
-        Err(e) => {
+        .map_err(|e| {
             eprintln!("Failed to fetch from {}: {}", url, e);
-            Err("Failed to fetch from external API".to_string())
-        }
+            ProxyError::from(e)
+        })?;
+
+    let status = response.status();
+    if !status.is_success() {
+        eprintln!("External API returned error {}: {}", status, url);
+        return Err(ProxyError::Upstream(status));
     }
+
+    let body = read_body_capped(response, limits.max_response_bytes)
+        .await
+        .map_err(|e| {
+            eprintln!("Failed to read response from {}: {}", url, e);
+            e
+        })?;
+
+    serde_json::from_slice::<Value>(&body).map_err(|e| {
+        eprintln!("Failed to parse response from {}: {}", url, e);
+        ProxyError::Parse
+    })
 }
 
 /// Generic proxy endpoint for external API calls
-/// Forwards requests to the external API with the given path and query parameters
+/// Forwards GET and POST requests to the external API with the given path,
+/// the original query string and (for POST) the request body
+#[utoipa::path(
+    method(get, post),
+    path = "/api/proxy/{path}",
+    tag = "proxy",
+    params(("path" = String, Path, description = "Path forwarded to the external API")),
+    responses(
+        (status = 200, description = "Upstream response", body = serde_json::Value),
+        (status = 413, description = "Request body or upstream response exceeded the size limit"),
+        (status = 502, description = "Upstream returned an error"),
+        (status = 504, description = "Upstream timed out"),
+    )
+)]
 pub async fn proxy_external_api(
     State(state): State<Arc<AppState>>,
     Path(path): Path<String>,
-    Query(params): Query<HashMap<String, String>>,
-) -> impl IntoResponse {
-    match fetch_proxy_api(
-        &state.http_client,
-        &state.cache,
-        REF_SDK_BASE_URL,
-        &path,
-        &params,
-    )
-    .await
-    {
-        Ok(data) => (StatusCode::OK, Json(data)),
-        Err(error_msg) => {
-            let status_code = if error_msg.starts_with("External API error") {
-                StatusCode::BAD_GATEWAY
-            } else {
-                StatusCode::INTERNAL_SERVER_ERROR
-            };
-            (
-                status_code,
-                Json(serde_json::json!({
-                    "error": error_msg
-                })),
+    method: Method,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Value>, ApiError> {
+    let limits = ProxyLimits::from_env_vars(&state.env_vars);
+    let query_string = query.unwrap_or_default();
+
+    let result = if method == Method::POST {
+        post_proxy_api(
+            &state.http_client,
+            REF_SDK_BASE_URL,
+            &path,
+            &query_string,
+            body,
+            headers.get(header::CONTENT_TYPE),
+            &limits,
+        )
+        .await
+    } else {
+        fetch_proxy_api_with_query(
+            &state.http_client,
+            &state.cache,
+            REF_SDK_BASE_URL,
+            &path,
+            &query_string,
+            &limits,
+        )
+        .await
+    };
+
+    result
+        .map(Json)
+        .map_err(|error| ApiError::new(error.status_code(), error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, routing::get};
+
+    async fn echo(RawQuery(query): RawQuery, body: String) -> Json<Value> {
+        Json(serde_json::json!({"query": query, "body": body}))
+    }
+
+    /// Starts a local upstream server and returns its base URL
+    async fn spawn_upstream() -> String {
+        let app = Router::new()
+            .route(
+                "/ok",
+                get(|| async { Json(serde_json::json!({"ok": true})) }),
+            )
+            .route(
+                "/slow",
+                get(|| async {
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                    Json(serde_json::json!({"ok": true}))
+                }),
             )
+            .route("/large", get(|| async { "x".repeat(10_000) }))
+            .route("/echo", get(echo).post(echo));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        format!("http://{}", addr)
+    }
+
+    fn test_limits() -> ProxyLimits {
+        ProxyLimits {
+            timeout: Duration::from_millis(200),
+            max_response_bytes: 1024,
+            max_request_bytes: 256,
         }
     }
+
+    #[tokio::test]
+    async fn test_proxy_returns_small_response() {
+        let base_url = spawn_upstream().await;
+        let cache = AppCache::memory();
+
+        let data = fetch_proxy_api(
+            &Client::new(),
+            &cache,
+            &base_url,
+            "ok",
+            &HashMap::new(),
+            &test_limits(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(data, serde_json::json!({"ok": true}));
+    }
+
+    #[tokio::test]
+    async fn test_proxy_times_out_slow_upstream() {
+        let base_url = spawn_upstream().await;
+        let cache = AppCache::memory();
+
+        let err = fetch_proxy_api(
+            &Client::new(),
+            &cache,
+            &base_url,
+            "slow",
+            &HashMap::new(),
+            &test_limits(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(err, ProxyError::Timeout));
+        assert_eq!(err.status_code(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_rejects_oversized_upstream_response() {
+        let base_url = spawn_upstream().await;
+        let cache = AppCache::memory();
+
+        let err = fetch_proxy_api(
+            &Client::new(),
+            &cache,
+            &base_url,
+            "large",
+            &HashMap::new(),
+            &test_limits(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(err, ProxyError::ResponseTooLarge));
+        assert_eq!(err.status_code(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_forwards_query_string() {
+        let base_url = spawn_upstream().await;
+        let cache = AppCache::memory();
+
+        let data = fetch_proxy_api_with_query(
+            &Client::new(),
+            &cache,
+            &base_url,
+            "echo",
+            "ids=a&ids=b&name=hello%20world",
+            &test_limits(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(data["query"], "ids=a&ids=b&name=hello%20world");
+    }
+
+    #[tokio::test]
+    async fn test_proxy_forwards_post_body() {
+        let base_url = spawn_upstream().await;
+        let content_type = HeaderValue::from_static("application/json");
+
+        let data = post_proxy_api(
+            &Client::new(),
+            &base_url,
+            "echo",
+            "tokenIn=near",
+            Bytes::from_static(br#"{"amount":"1"}"#),
+            Some(&content_type),
+            &test_limits(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(data["query"], "tokenIn=near");
+        assert_eq!(data["body"], r#"{"amount":"1"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_proxy_rejects_oversized_request_body() {
+        let base_url = spawn_upstream().await;
+
+        let err = post_proxy_api(
+            &Client::new(),
+            &base_url,
+            "echo",
+            "",
+            Bytes::from(vec![b'x'; 1024]),
+            None,
+            &test_limits(),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(err, ProxyError::RequestTooLarge));
+        assert_eq!(err.status_code(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
 }
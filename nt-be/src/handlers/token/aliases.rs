@@ -0,0 +1,120 @@
+//! Token Aliases
+//!
+//! Some FT contracts migrate to a new address, so the balance appears to jump from
+//! one token to another. Aliases registered in `token_aliases` let the balance
+//! history merge all addresses of a token into a single series.
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use sqlx::types::chrono::{DateTime, Utc};
+use utoipa::ToSchema;
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct TokenAlias {
+    pub old_contract: String,
+    pub new_contract: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RegisterTokenAliasRequest {
+    /// Contract the token migrated away from
+    pub old_contract: String,
+    /// Contract the token migrated to
+    pub new_contract: String,
+}
+
+/// Record that `old_contract` migrated to `new_contract`
+///
+/// Registering an existing alias again returns it unchanged.
+pub async fn register_alias(
+    pool: &PgPool,
+    old_contract: &str,
+    new_contract: &str,
+) -> Result<TokenAlias, sqlx::Error> {
+    sqlx::query_as::<_, TokenAlias>(
+        r#"
+        INSERT INTO token_aliases (old_contract, new_contract)
+        VALUES ($1, $2)
+        ON CONFLICT (old_contract, new_contract) DO UPDATE
+        SET old_contract = EXCLUDED.old_contract
+        RETURNING old_contract, new_contract, created_at
+        "#,
+    )
+    .bind(old_contract)
+    .bind(new_contract)
+    .fetch_one(pool)
+    .await
+}
+
+pub async fn list_aliases(pool: &PgPool) -> Result<Vec<TokenAlias>, sqlx::Error> {
+    sqlx::query_as::<_, TokenAlias>(
+        r#"
+        SELECT old_contract, new_contract, created_at
+        FROM token_aliases
+        ORDER BY new_contract, old_contract
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// All contracts of the token `contract` belongs to
+///
+/// Follows aliases in both directions, so a chain of migrations (a -> b -> c)
+/// resolves to the same group from any of its contracts.
+///
+/// # Returns
+/// `contract` first, followed by its aliases in alphabetical order
+pub async fn alias_group(pool: &PgPool, contract: &str) -> Result<Vec<String>, sqlx::Error> {
+    let aliases: Vec<String> = sqlx::query_scalar(
+        r#"
+        WITH RECURSIVE grp(contract) AS (
+            SELECT $1::VARCHAR
+            UNION
+            SELECT CASE WHEN a.old_contract = g.contract THEN a.new_contract ELSE a.old_contract END
+            FROM token_aliases a
+            JOIN grp g ON g.contract IN (a.old_contract, a.new_contract)
+        )
+        SELECT contract FROM grp
+        WHERE contract <> $1
+        ORDER BY contract
+        "#,
+    )
+    .bind(contract)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(std::iter::once(contract.to_string())
+        .chain(aliases)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test]
+    async fn test_alias_group_follows_migration_chain(pool: PgPool) -> sqlx::Result<()> {
+        register_alias(&pool, "v1.token.near", "v2.token.near").await?;
+        register_alias(&pool, "v2.token.near", "v3.token.near").await?;
+        register_alias(&pool, "v2.token.near", "v3.token.near").await?;
+        register_alias(&pool, "other-old.near", "other-new.near").await?;
+
+        assert_eq!(
+            alias_group(&pool, "v3.token.near").await?,
+            vec!["v3.token.near", "v1.token.near", "v2.token.near"]
+        );
+        assert_eq!(
+            alias_group(&pool, "v1.token.near").await?,
+            vec!["v1.token.near", "v2.token.near", "v3.token.near"]
+        );
+        assert_eq!(
+            alias_group(&pool, "unaliased.near").await?,
+            vec!["unaliased.near"]
+        );
+        assert_eq!(list_aliases(&pool).await?.len(), 3);
+
+        Ok(())
+    }
+}
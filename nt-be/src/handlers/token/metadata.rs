@@ -1,27 +1,35 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
 
 use axum::{
     Json,
     extract::{Query, State},
     response::IntoResponse,
 };
+use futures::{StreamExt, stream};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
 
+use crate::utils::api_error::ApiError;
 use crate::{
     AppState,
     constants::intents_chains::{ChainIcons, get_chain_metadata_by_name},
-    handlers::proxy::external::{REF_SDK_BASE_URL, fetch_proxy_api},
+    handlers::balance_changes::counterparty::query_ft_metadata,
+    handlers::proxy::external::{ProxyError, ProxyLimits, REF_SDK_BASE_URL, fetch_proxy_api},
 };
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct TokenMetadataQuery {
     #[serde(rename = "tokenId")]
     pub token_id: String,
     pub network: String,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct TokenMetadata {
     #[serde(rename = "tokenId")]
     pub token_id: String,
@@ -65,11 +73,20 @@ struct RefSdkToken {
 ///
 /// # Returns
 /// * `Ok(Vec<TokenMetadata>)` - List of token metadata with chain information
-/// * `Err((StatusCode, String))` - Error with status code and message
+/// * `Err(ApiError)` - Error with status code and message
 pub async fn fetch_tokens_metadata(
     state: &Arc<AppState>,
     defuse_asset_ids: &[String],
-) -> Result<Vec<TokenMetadata>, (StatusCode, String)> {
+) -> Result<Vec<TokenMetadata>, ApiError> {
+    fetch_ref_sdk_metadata(state, REF_SDK_BASE_URL, defuse_asset_ids).await
+}
+
+/// `fetch_tokens_metadata` against a given Ref SDK base URL
+async fn fetch_ref_sdk_metadata(
+    state: &Arc<AppState>,
+    ref_sdk_base_url: &str,
+    defuse_asset_ids: &[String],
+) -> Result<Vec<TokenMetadata>, ApiError> {
     if defuse_asset_ids.is_empty() {
         return Ok(Vec::new());
     }
@@ -85,25 +102,24 @@ pub async fn fetch_tokens_metadata(
     let response = fetch_proxy_api(
         &state.http_client,
         &state.cache,
-        REF_SDK_BASE_URL,
+        ref_sdk_base_url,
         "token-by-defuse-asset-id",
         &query_params,
+        &ProxyLimits::from_env_vars(&state.env_vars),
     )
     .await
     .map_err(|e| {
-        (
-            StatusCode::BAD_GATEWAY,
-            format!("Failed to fetch token metadata: {}", e),
-        )
+        let status = match e {
+            ProxyError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::BAD_GATEWAY,
+        };
+        ApiError::new(status, format!("Failed to fetch token metadata: {}", e))
     })?;
 
     // Parse the response as an array of tokens
     let tokens: Vec<RefSdkToken> = serde_json::from_value(response).map_err(|e| {
         eprintln!("Failed to parse token response: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to parse token metadata response".to_string(),
-        )
+        ApiError::internal("Failed to parse token metadata response")
     })?;
 
     // Map RefSdkToken to TokenMetadata with chain metadata
@@ -132,10 +148,204 @@ pub async fn fetch_tokens_metadata(
     Ok(metadata_responses)
 }
 
+/// Max token ids of one batch metadata request
+pub const MAX_BATCH_METADATA_TOKENS: usize = 100;
+
+/// Max on-chain `ft_metadata` lookups run at once for a batch
+const ONCHAIN_METADATA_CONCURRENCY: usize = 8;
+
+/// Request body for batch token metadata
+#[derive(Deserialize, ToSchema)]
+pub struct BatchTokenMetadataRequest {
+    /// FT contract ids (e.g. `usdt.tether-token.near`), or `near` for native NEAR
+    #[serde(rename = "tokenIds")]
+    pub token_ids: Vec<String>,
+}
+
+/// Normalize a requested contract id (`nep141:` prefix and case are ignored)
+fn normalize_contract_id(token_id: &str) -> String {
+    let token_id = token_id.trim();
+    token_id
+        .strip_prefix("nep141:")
+        .unwrap_or(token_id)
+        .to_lowercase()
+}
+
+/// NEAR is described by the wNEAR contract, renamed
+fn metadata_contract(contract_id: &str) -> &str {
+    if contract_id == "near" {
+        "wrap.near"
+    } else {
+        contract_id
+    }
+}
+
+/// Token metadata from the contract's on-chain `ft_metadata`
+async fn fetch_onchain_metadata(
+    state: &Arc<AppState>,
+    contract_id: &str,
+) -> Result<TokenMetadata, String> {
+    let metadata = query_ft_metadata(&state.network, contract_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let chain_metadata = get_chain_metadata_by_name("near");
+
+    Ok(TokenMetadata {
+        token_id: format!("nep141:{}", contract_id),
+        name: metadata.name,
+        symbol: metadata.symbol,
+        decimals: metadata.decimals,
+        icon: metadata.icon,
+        price: None,
+        price_updated_at: None,
+        network: Some("near".to_string()),
+        chain_name: chain_metadata.as_ref().map(|m| m.name.clone()),
+        chain_icons: chain_metadata.map(|m| m.icon),
+    })
+}
+
+/// Fetch metadata for many FT contracts, keyed by normalized contract id
+///
+/// Contracts are looked up in the Ref SDK first (in one request), falling back to
+/// the on-chain `ft_metadata` for contracts the Ref SDK doesn't know or when it is
+/// unavailable (at most `ONCHAIN_METADATA_CONCURRENCY` at once). Results are cached
+/// per contract; contracts unknown to both sources are left out of the map.
+pub async fn fetch_contracts_metadata(
+    state: &Arc<AppState>,
+    ref_sdk_base_url: &str,
+    token_ids: &[String],
+) -> BTreeMap<String, TokenMetadata> {
+    let mut result = BTreeMap::new();
+    let mut missing = Vec::new();
+
+    for contract_id in token_ids.iter().map(|id| normalize_contract_id(id)) {
+        if contract_id.is_empty() || result.contains_key(&contract_id) {
+            continue;
+        }
+        let cached = state
+            .cache
+            .get(&format!("token-metadata-contract:{}", contract_id))
+            .await
+            .and_then(|value| serde_json::from_value::<TokenMetadata>(value).ok());
+        match cached {
+            Some(metadata) => {
+                result.insert(contract_id, metadata);
+            }
+            None if !missing.contains(&contract_id) => missing.push(contract_id),
+            None => {}
+        }
+    }
+
+    if missing.is_empty() {
+        return result;
+    }
+
+    let defuse_asset_ids: Vec<String> = missing
+        .iter()
+        .map(|contract_id| format!("nep141:{}", metadata_contract(contract_id)))
+        .collect();
+    let ref_tokens = fetch_ref_sdk_metadata(state, ref_sdk_base_url, &defuse_asset_ids)
+        .await
+        .unwrap_or_else(|e| {
+            eprintln!(
+                "Ref SDK metadata unavailable, using on-chain metadata: {}",
+                e
+            );
+            Vec::new()
+        });
+
+    let lookups = missing.into_iter().map(|contract_id| {
+        let defuse_asset_id = format!("nep141:{}", metadata_contract(&contract_id));
+        let from_ref = ref_tokens
+            .iter()
+            .find(|token| token.token_id == defuse_asset_id)
+            .cloned();
+
+        async move {
+            let metadata = match from_ref {
+                Some(metadata) => Some(metadata),
+                None => fetch_onchain_metadata(state, metadata_contract(&contract_id))
+                    .await
+                    .map_err(|e| {
+                        eprintln!("Error fetching ft_metadata for {}: {}", contract_id, e);
+                    })
+                    .ok(),
+            };
+            (contract_id, metadata)
+        }
+    });
+
+    let fetched: Vec<_> = stream::iter(lookups)
+        .buffer_unordered(ONCHAIN_METADATA_CONCURRENCY)
+        .collect()
+        .await;
+
+    for (contract_id, metadata) in fetched {
+        let Some(mut metadata) = metadata else {
+            continue;
+        };
+        if contract_id == "near" {
+            metadata.name = "NEAR".to_string();
+            metadata.symbol = "NEAR".to_string();
+        }
+
+        if let Ok(value) = serde_json::to_value(&metadata) {
+            state
+                .cache
+                .insert(format!("token-metadata-contract:{}", contract_id), value)
+                .await;
+        }
+        result.insert(contract_id, metadata);
+    }
+
+    result
+}
+
+/// Metadata for many tokens at once
+///
+/// Contract ids unknown to both the Ref SDK and the chain are left out of the map.
+#[utoipa::path(
+    post,
+    path = "/api/token/metadata/batch",
+    tag = "token",
+    request_body = BatchTokenMetadataRequest,
+    responses(
+        (status = 200, description = "Token metadata by contract id", body = HashMap<String, TokenMetadata>),
+        (status = 400, description = "No token ids provided, or more than 100"),
+    )
+)]
+pub async fn get_batch_token_metadata(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<BatchTokenMetadataRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    if payload.token_ids.is_empty() {
+        return Err(ApiError::bad_request("No token ids provided"));
+    }
+    if payload.token_ids.len() > MAX_BATCH_METADATA_TOKENS {
+        return Err(ApiError::bad_request(format!(
+            "At most {} token ids can be queried at once",
+            MAX_BATCH_METADATA_TOKENS
+        )));
+    }
+
+    let metadata = fetch_contracts_metadata(&state, REF_SDK_BASE_URL, &payload.token_ids).await;
+
+    Ok((StatusCode::OK, Json(metadata)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/token/metadata",
+    tag = "token",
+    params(TokenMetadataQuery),
+    responses(
+        (status = 200, description = "Token metadata", body = TokenMetadata),
+    )
+)]
 pub async fn get_token_metadata(
     State(state): State<Arc<AppState>>,
     Query(mut params): Query<TokenMetadataQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let cache_key = format!("token-metadata:{}:{}", params.token_id, params.network);
     if let Some(cached_data) = state.cache.get(&cache_key).await {
         return Ok((StatusCode::OK, Json(cached_data)));
@@ -152,12 +362,7 @@ pub async fn get_token_metadata(
     // Get the first token from the array
     let mut metadata = tokens
         .first()
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                format!("Token not found: {}", params.token_id),
-            )
-        })?
+        .ok_or_else(|| ApiError::not_found(format!("Token not found: {}", params.token_id)))?
         .clone();
 
     if is_near {
@@ -167,13 +372,147 @@ pub async fn get_token_metadata(
 
     let result_value = serde_json::to_value(&metadata).map_err(|e| {
         eprintln!("Error serializing token metadata: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Failed to serialize token metadata: {}", e),
-        )
+        ApiError::internal(format!("Failed to serialize token metadata: {}", e))
     })?;
 
     state.cache.insert(cache_key, result_value.clone()).await;
 
     Ok((StatusCode::OK, Json(result_value)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{init_test_state, spawn_mock_rpc};
+    use axum::{Router, routing::get};
+    use serde_json::{Value, json};
+
+    /// Ref SDK that only knows `ref-known.near`
+    async fn mock_ref_sdk() -> Json<Value> {
+        Json(json!([{
+            "defuse_asset_id": "nep141:ref-known.near",
+            "name": "Ref Known",
+            "symbol": "REF",
+            "decimals": 18,
+            "icon": null,
+            "price": 1.5,
+            "price_updated_at": "2026-01-01T00:00:00Z",
+            "chainName": "near"
+        }]))
+    }
+
+    /// JSON-RPC node answering `ft_metadata` for any contract
+    async fn mock_rpc(Json(request): Json<Value>) -> Json<Value> {
+        let metadata = json!({
+            "spec": "ft-1.0.0",
+            "name": "On-chain Token",
+            "symbol": "ONC",
+            "decimals": 6
+        });
+
+        Json(json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": {
+                "result": serde_json::to_vec(&metadata).unwrap(),
+                "logs": [],
+                "block_height": 1,
+                "block_hash": "11111111111111111111111111111111"
+            }
+        }))
+    }
+
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_batch_metadata_falls_back_to_onchain() {
+        let ref_url =
+            serve(Router::new().route("/token-by-defuse-asset-id", get(mock_ref_sdk))).await;
+        let mut state = init_test_state().await;
+        state.network = spawn_mock_rpc(mock_rpc).await;
+        let state = Arc::new(state);
+
+        let metadata = fetch_contracts_metadata(
+            &state,
+            &ref_url,
+            &[
+                "nep141:ref-known.near".to_string(),
+                "ref-unknown.near".to_string(),
+            ],
+        )
+        .await;
+
+        assert_eq!(metadata.len(), 2);
+        let known = &metadata["ref-known.near"];
+        assert_eq!(known.symbol, "REF");
+        assert_eq!(known.price, Some(1.5));
+        let unknown = &metadata["ref-unknown.near"];
+        assert_eq!(unknown.symbol, "ONC");
+        assert_eq!(unknown.decimals, 6);
+        assert_eq!(unknown.token_id, "nep141:ref-unknown.near");
+
+        assert!(
+            state
+                .cache
+                .get("token-metadata-contract:ref-unknown.near")
+                .await
+                .is_some(),
+            "Metadata should be cached per contract"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_metadata_is_bounded() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let ref_url =
+            serve(Router::new().route("/token-by-defuse-asset-id", get(mock_ref_sdk))).await;
+
+        // Node that tracks how many ft_metadata calls are in flight at once
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let rpc = {
+            let (in_flight, max_in_flight) = (in_flight.clone(), max_in_flight.clone());
+            move |request: Json<Value>| {
+                let (in_flight, max_in_flight) = (in_flight.clone(), max_in_flight.clone());
+                async move {
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    mock_rpc(request).await
+                }
+            }
+        };
+        let mut state = init_test_state().await;
+        state.network = spawn_mock_rpc(rpc).await;
+        let state = Arc::new(state);
+
+        let token_ids: Vec<String> = (0..3 * ONCHAIN_METADATA_CONCURRENCY)
+            .map(|i| format!("bounded-{}.near", i))
+            .collect();
+        let metadata = fetch_contracts_metadata(&state, &ref_url, &token_ids).await;
+        assert_eq!(metadata.len(), token_ids.len());
+        assert!(max_in_flight.load(Ordering::SeqCst) <= ONCHAIN_METADATA_CONCURRENCY);
+
+        let too_many = (0..=MAX_BATCH_METADATA_TOKENS)
+            .map(|i| format!("token-{}.near", i))
+            .collect();
+        let Err(ApiError { status, .. }) = get_batch_token_metadata(
+            State(state),
+            Json(BatchTokenMetadataRequest {
+                token_ids: too_many,
+            }),
+        )
+        .await
+        else {
+            panic!("Oversized batches should be rejected");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
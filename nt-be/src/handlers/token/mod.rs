@@ -1,3 +1,4 @@
+pub mod aliases;
 pub mod metadata;
 pub mod storage_deposit;
 
@@ -4,38 +4,67 @@ use axum::{
     http::StatusCode,
     response::IntoResponse,
 };
-use near_api::{AccountId, Contract};
+use futures::{StreamExt, stream};
+use near_api::{AccountId, Contract, Reference};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::AppState;
+use crate::utils::api_error::ApiError;
+use crate::utils::timeout::with_timeout;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct GetStorageDepositQuery {
+    #[param(value_type = String)]
     #[serde(rename = "accountId")]
     pub account_id: AccountId,
+    #[param(value_type = String)]
     #[serde(rename = "tokenId")]
     pub token_id: AccountId,
+    /// Check registration at this block instead of the latest one
+    #[serde(default, rename = "blockHeight", alias = "block_height")]
+    pub block_height: Option<u64>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct StorageDepositResponse {
     pub account_id: String,
     pub token_id: String,
     pub is_registered: bool,
+    /// Only present when requested with `includeBounds`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub storage_balance_bounds: Option<StorageBalanceBounds>,
+}
+
+/// NEP-145 `storage_balance_bounds` of a contract, in yoctoNEAR
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct StorageBalanceBounds {
+    /// Deposit required to register
+    pub min: String,
+    /// Maximum deposit the contract accepts; `null` if unbounded
+    pub max: Option<String>,
 }
 
 /// Check storage deposit for a single token
+///
+/// With `block_height`, registration is checked at that block on the archival network.
 async fn check_storage_deposit(
     state: &Arc<AppState>,
     account_id: AccountId,
     token_id: AccountId,
-) -> Result<bool, String> {
+    block_height: Option<u64>,
+) -> Result<bool, ApiError> {
     if token_id == "near" || token_id == "NEAR" {
         return Ok(true);
     }
 
-    let cache_key = format!("storage-deposit:{}:{}", account_id, token_id);
+    let mut cache_key = format!("storage-deposit:{}:{}", account_id, token_id);
+    if let Some(block_height) = block_height {
+        cache_key.push_str(&format!(":{}", block_height));
+    }
     if let Some(cached_storage_deposit) = state.cache.get(&cache_key).await {
         println!(
             "🔁 Returning cached storage deposit for {} / {}",
@@ -44,19 +73,29 @@ async fn check_storage_deposit(
         return Ok(cached_storage_deposit == "true");
     }
 
-    let storage_deposit = Contract(token_id.clone())
+    let request = Contract(token_id.clone())
         .storage_deposit()
-        .view_account_storage(account_id.clone())
-        .fetch_from(&state.network)
-        .await
-        .map_err(|e| {
-            eprintln!(
-                "Error fetching storage deposit with account_id: {} and token_id: {}: {e}",
-                account_id, token_id,
-            );
-            e.to_string()
-        })?
-        .data;
+        .view_account_storage(account_id.clone());
+    let storage_deposit = match block_height {
+        Some(block_height) => {
+            let request = request
+                .at(Reference::AtBlock(block_height))
+                .fetch_from(&state.archival_network);
+            with_timeout(state.external_timeout(), "RPC", request).await?
+        }
+        None => {
+            let request = request.fetch_from(&state.network);
+            with_timeout(state.external_timeout(), "RPC", request).await?
+        }
+    }
+    .map_err(|e| {
+        eprintln!(
+            "Error fetching storage deposit with account_id: {} and token_id: {}: {e}",
+            account_id, token_id,
+        );
+        ApiError::internal(e.to_string())
+    })?
+    .data;
 
     let is_registered = storage_deposit.is_some();
     state
@@ -66,69 +105,320 @@ async fn check_storage_deposit(
     Ok(is_registered)
 }
 
+/// Fetch the storage deposit bounds of a token contract
+async fn fetch_storage_balance_bounds(
+    state: &Arc<AppState>,
+    token_id: &AccountId,
+) -> Result<StorageBalanceBounds, String> {
+    let cache_key = format!("storage-balance-bounds:{}", token_id);
+    if let Some(cached) = state.cache.get(&cache_key).await
+        && let Ok(bounds) = serde_json::from_value(cached)
+    {
+        return Ok(bounds);
+    }
+
+    let bounds = with_timeout(
+        state.external_timeout(),
+        "RPC",
+        Contract(token_id.clone())
+            .call_function("storage_balance_bounds", serde_json::json!({}))
+            .read_only::<StorageBalanceBounds>()
+            .fetch_from(&state.network),
+    )
+    .await
+    .map_err(|e| e.message)?
+    .map_err(|e| {
+        eprintln!(
+            "Error fetching storage balance bounds of {}: {}",
+            token_id, e
+        );
+        e.to_string()
+    })?
+    .data;
+
+    if let Ok(value) = serde_json::to_value(&bounds) {
+        state.cache.insert(cache_key, value).await;
+    }
+    Ok(bounds)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/token/storage-deposit/is-registered",
+    tag = "token",
+    params(GetStorageDepositQuery),
+    responses(
+        (status = 200, description = "Whether the account is registered with the token", body = bool),
+    )
+)]
 pub async fn is_storage_deposit_registered(
     State(state): State<Arc<AppState>>,
     Query(params): Query<GetStorageDepositQuery>,
-) -> Result<Json<bool>, (StatusCode, String)> {
+) -> Result<Json<bool>, ApiError> {
     let account_id = params.account_id.clone();
     let token_id = params.token_id;
 
-    check_storage_deposit(&state, account_id, token_id)
+    check_storage_deposit(&state, account_id, token_id, params.block_height)
         .await
         .map(Json)
-        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))
 }
 
 /// Request body for batch storage deposit check
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct StorageDepositRequest {
+    #[schema(value_type = String)]
     #[serde(rename = "accountId")]
     pub account_id: AccountId,
+    #[schema(value_type = String)]
     #[serde(rename = "tokenId")]
     pub token_id: AccountId,
 }
 
+/// Max account-token pairs of one batch storage deposit request
+pub const MAX_BATCH_STORAGE_DEPOSIT_REQUESTS: usize = 100;
+
+/// Max RPC lookups run at once for a batch
+const BATCH_LOOKUP_CONCURRENCY: usize = 8;
+
 /// Batch endpoint to check storage deposit for multiple account-token pairs
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct BatchStorageDepositRequest {
     pub requests: Vec<StorageDepositRequest>,
+    /// Also return the `storage_balance_bounds` of each token contract
+    #[serde(default, rename = "includeBounds", alias = "include_bounds")]
+    pub include_bounds: bool,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/token/storage-deposit/is-registered/batch",
+    tag = "token",
+    request_body = BatchStorageDepositRequest,
+    responses(
+        (status = 200, description = "Registration status per request", body = Vec<StorageDepositResponse>),
+    )
+)]
 pub async fn get_batch_storage_deposit_is_registered(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<BatchStorageDepositRequest>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     if payload.requests.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "No requests provided".to_string()));
+        return Err(ApiError::bad_request("No requests provided"));
+    }
+    if payload.requests.len() > MAX_BATCH_STORAGE_DEPOSIT_REQUESTS {
+        return Err(ApiError::bad_request(format!(
+            "At most {} requests can be checked at once",
+            MAX_BATCH_STORAGE_DEPOSIT_REQUESTS
+        )));
     }
 
-    let mut futures = Vec::new();
-
-    for request in payload.requests {
-        let state_clone = state.clone();
-        let account_id = request.account_id;
-        let token_id = request.token_id;
+    // Bounds are per contract, so each distinct token is fetched once
+    let mut bounds: HashMap<AccountId, StorageBalanceBounds> = HashMap::new();
+    if payload.include_bounds {
+        let mut token_ids: Vec<AccountId> = payload
+            .requests
+            .iter()
+            .map(|request| request.token_id.clone())
+            .filter(|token_id| token_id != "near" && token_id != "NEAR")
+            .collect();
+        token_ids.sort_unstable();
+        token_ids.dedup();
 
-        futures.push(async move {
-            match check_storage_deposit(&state_clone, account_id.clone(), token_id.clone()).await {
-                Ok(is_registered) => Some(StorageDepositResponse {
-                    account_id: account_id.to_string(),
-                    token_id: token_id.to_string(),
-                    is_registered,
-                }),
-                Err(e) => {
-                    eprintln!(
-                        "Error checking storage deposit for {} / {}: {}",
-                        account_id, token_id, e
-                    );
-                    None
+        let fetched: Vec<_> = stream::iter(token_ids)
+            .map(|token_id| {
+                let state = state.clone();
+                async move {
+                    fetch_storage_balance_bounds(&state, &token_id)
+                        .await
+                        .ok()
+                        .map(|b| (token_id, b))
                 }
-            }
-        });
+            })
+            .buffer_unordered(BATCH_LOOKUP_CONCURRENCY)
+            .collect()
+            .await;
+        bounds.extend(fetched.into_iter().flatten());
     }
 
-    let results = futures::future::join_all(futures).await;
+    // Responses keep the order of the requests
+    let results: Vec<_> = stream::iter(payload.requests)
+        .map(|request| {
+            let state_clone = state.clone();
+            let account_id = request.account_id;
+            let token_id = request.token_id;
+            let storage_balance_bounds = bounds.get(&token_id).cloned();
+
+            async move {
+                match check_storage_deposit(
+                    &state_clone,
+                    account_id.clone(),
+                    token_id.clone(),
+                    None,
+                )
+                .await
+                {
+                    Ok(is_registered) => Some(StorageDepositResponse {
+                        account_id: account_id.to_string(),
+                        token_id: token_id.to_string(),
+                        is_registered,
+                        storage_balance_bounds,
+                    }),
+                    Err(e) => {
+                        eprintln!(
+                            "Error checking storage deposit for {} / {}: {}",
+                            account_id, token_id, e.message
+                        );
+                        None
+                    }
+                }
+            }
+        })
+        .buffered(BATCH_LOOKUP_CONCURRENCY)
+        .collect()
+        .await;
     let deposits: Vec<StorageDepositResponse> = results.into_iter().flatten().collect();
 
     Ok((StatusCode::OK, Json(deposits)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{init_test_state, spawn_mock_rpc};
+
+    /// Deposit the mocked token requires to register
+    const MIN_DEPOSIT: &str = "1250000000000000000000";
+
+    /// Block at which the mocked account registered on the token
+    const REGISTER_BLOCK: u64 = 150_000_000;
+
+    /// JSON-RPC node answering `storage_balance_of` for an account registered at `REGISTER_BLOCK`
+    async fn storage_balance_rpc(
+        Json(request): Json<serde_json::Value>,
+    ) -> Json<serde_json::Value> {
+        let params = &request["params"];
+        let block_height = params["block_id"]
+            .as_u64()
+            .unwrap_or(REGISTER_BLOCK + 1_000);
+        let storage_balance = if params["method_name"] == "storage_balance_bounds" {
+            serde_json::json!({"min": MIN_DEPOSIT, "max": MIN_DEPOSIT})
+        } else if block_height >= REGISTER_BLOCK {
+            serde_json::json!({"total": "1250000000000000000000", "available": "0"})
+        } else {
+            serde_json::Value::Null
+        };
+
+        Json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": {
+                "result": serde_json::to_vec(&storage_balance).unwrap(),
+                "logs": [],
+                "block_height": block_height,
+                "block_hash": "11111111111111111111111111111111"
+            }
+        }))
+    }
+
+    async fn mocked_state() -> Arc<AppState> {
+        let network = spawn_mock_rpc(storage_balance_rpc).await;
+        let mut state = init_test_state().await;
+        state.network = network.clone();
+        state.archival_network = network;
+        Arc::new(state)
+    }
+
+    #[tokio::test]
+    async fn test_registration_at_block_before_registering_is_false() {
+        let state = mocked_state().await;
+
+        let is_registered = |block_height| {
+            is_storage_deposit_registered(
+                State(state.clone()),
+                Query(GetStorageDepositQuery {
+                    account_id: "late-registrant.near".parse().unwrap(),
+                    token_id: "usdt.tether-token.near".parse().unwrap(),
+                    block_height,
+                }),
+            )
+        };
+
+        let Json(before) = is_registered(Some(REGISTER_BLOCK - 1)).await.unwrap();
+        assert!(!before);
+        let Json(after) = is_registered(Some(REGISTER_BLOCK)).await.unwrap();
+        assert!(after);
+        let Json(current) = is_registered(None).await.unwrap();
+        assert!(current);
+    }
+
+    #[tokio::test]
+    async fn test_batch_includes_storage_balance_bounds() {
+        let state = mocked_state().await;
+
+        let batch = |include_bounds| {
+            get_batch_storage_deposit_is_registered(
+                State(state.clone()),
+                Json(BatchStorageDepositRequest {
+                    requests: vec![
+                        StorageDepositRequest {
+                            account_id: "late-registrant.near".parse().unwrap(),
+                            token_id: "usdt.tether-token.near".parse().unwrap(),
+                        },
+                        StorageDepositRequest {
+                            account_id: "late-registrant.near".parse().unwrap(),
+                            token_id: "near".parse().unwrap(),
+                        },
+                    ],
+                    include_bounds,
+                }),
+            )
+        };
+        let responses = |response: axum::response::Response| async move {
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<Vec<StorageDepositResponse>>(&body).unwrap()
+        };
+
+        let with_bounds = responses(batch(true).await.unwrap().into_response()).await;
+        assert_eq!(
+            with_bounds[0].storage_balance_bounds,
+            Some(StorageBalanceBounds {
+                min: MIN_DEPOSIT.to_string(),
+                max: Some(MIN_DEPOSIT.to_string()),
+            })
+        );
+        assert!(with_bounds[0].is_registered);
+        assert_eq!(
+            with_bounds[1].storage_balance_bounds, None,
+            "NEAR needs no deposit"
+        );
+
+        let without_bounds = responses(batch(false).await.unwrap().into_response()).await;
+        assert_eq!(without_bounds[0].storage_balance_bounds, None);
+    }
+
+    #[tokio::test]
+    async fn test_batch_rejects_too_many_requests() {
+        let state = mocked_state().await;
+        let requests = (0..=MAX_BATCH_STORAGE_DEPOSIT_REQUESTS)
+            .map(|i| StorageDepositRequest {
+                account_id: "late-registrant.near".parse().unwrap(),
+                token_id: format!("token-{}.near", i).parse().unwrap(),
+            })
+            .collect();
+
+        let Err(ApiError { status, .. }) = get_batch_storage_deposit_is_registered(
+            State(state),
+            Json(BatchStorageDepositRequest {
+                requests,
+                include_bounds: true,
+            }),
+        )
+        .await
+        else {
+            panic!("Oversized batches should be rejected");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,42 +1,58 @@
 use axum::{
     Json,
     extract::{Query, State},
-    http::StatusCode,
 };
 use near_api::{Account, AccountId};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::AppState;
+use crate::utils::api_error::ApiError;
+use crate::utils::timeout::with_timeout;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct CheckHandleUnusedQuery {
+    #[param(value_type = String)]
     #[serde(rename = "treasuryId")]
     pub treasury_id: AccountId,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct CheckHandleUnusedResponse {
     pub unused: bool,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/treasury/check-handle-unused",
+    tag = "treasury",
+    params(CheckHandleUnusedQuery),
+    responses(
+        (status = 200, description = "Whether the treasury handle is available", body = CheckHandleUnusedResponse),
+    )
+)]
 pub async fn check_handle_unused(
     State(state): State<Arc<AppState>>,
     Query(params): Query<CheckHandleUnusedQuery>,
-) -> Result<Json<CheckHandleUnusedResponse>, (StatusCode, String)> {
+) -> Result<Json<CheckHandleUnusedResponse>, ApiError> {
     let treasury_id = params.treasury_id;
 
     if !treasury_id.as_str().ends_with("sputnik-dao.near") {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "Treasury ID must end with sputnik-dao.near".to_string(),
+        return Err(ApiError::bad_request(
+            "Treasury ID must end with sputnik-dao.near",
         ));
     }
 
-    match Account(treasury_id.clone())
-        .view()
-        .fetch_from(&state.network)
-        .await
+    match with_timeout(
+        state.external_timeout(),
+        "RPC",
+        Account(treasury_id.clone())
+            .view()
+            .fetch_from(&state.network),
+    )
+    .await?
     {
         Ok(_) => Ok(Json(CheckHandleUnusedResponse { unused: false })),
         Err(e) => Ok(Json(CheckHandleUnusedResponse {
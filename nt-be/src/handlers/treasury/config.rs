@@ -8,17 +8,22 @@ use near_api::{AccountId, Contract};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::AppState;
+use crate::utils::api_error::ApiError;
 use crate::utils::base64json::Base64Json;
+use crate::utils::timeout::with_timeout;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct GetTreasuryConfigQuery {
+    #[param(value_type = String)]
     #[serde(rename = "treasuryId")]
     pub treasury_id: AccountId,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct TreasuryMetadata {
     #[serde(rename = "primaryColor", default)]
     pub primary_color: Option<String>,
@@ -37,24 +42,33 @@ pub struct TreasuryConfigFromContract {
     pub purpose: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct TreasuryConfig {
     pub metadata: Option<TreasuryMetadata>,
     pub name: Option<String>,
     pub purpose: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct Treasury {
     #[serde(rename = "daoId")]
     pub dao_id: String,
     pub config: TreasuryConfig,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/treasury/config",
+    tag = "treasury",
+    params(GetTreasuryConfigQuery),
+    responses(
+        (status = 200, description = "Treasury config", body = Treasury),
+    )
+)]
 pub async fn get_treasury_config(
     State(state): State<Arc<AppState>>,
     Query(params): Query<GetTreasuryConfigQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let treasury_id = params.treasury_id;
 
     let cache_key = format!("treasury-config:{}", treasury_id);
@@ -63,19 +77,20 @@ pub async fn get_treasury_config(
         return Ok((StatusCode::OK, Json(cached_config)));
     }
 
-    let result = Contract(treasury_id.clone())
-        .call_function("get_config", ())
-        .read_only::<TreasuryConfigFromContract>()
-        .fetch_from(&state.network)
-        .await
-        .map_err(|e| {
-            eprintln!("Error fetching treasury config for {}: {}", treasury_id, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch treasury config: {}", e),
-            )
-        })?
-        .data;
+    let result = with_timeout(
+        state.external_timeout(),
+        "RPC",
+        Contract(treasury_id.clone())
+            .call_function("get_config", ())
+            .read_only::<TreasuryConfigFromContract>()
+            .fetch_from(&state.network),
+    )
+    .await?
+    .map_err(|e| {
+        eprintln!("Error fetching treasury config for {}: {}", treasury_id, e);
+        ApiError::internal(format!("Failed to fetch treasury config: {}", e))
+    })?
+    .data;
 
     let treasury = Treasury {
         dao_id: treasury_id.to_string(),
@@ -88,10 +103,7 @@ pub async fn get_treasury_config(
 
     let treasury_value = serde_json::to_value(&treasury).map_err(|e| {
         eprintln!("Error serializing treasury: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to serialize treasury".to_string(),
-        )
+        ApiError::internal("Failed to serialize treasury")
     })?;
 
     state.cache.insert(cache_key, treasury_value.clone()).await;
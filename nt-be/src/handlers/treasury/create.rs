@@ -3,25 +3,32 @@ use std::sync::Arc;
 use axum::{Json, extract::State};
 use base64::{Engine, prelude::BASE64_STANDARD};
 use near_api::{AccountId, Contract, NearToken};
-use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
+use crate::utils::api_error::ApiError;
+use crate::utils::timeout::with_timeout;
 use crate::{AppState, constants::TREASURY_FACTORY_CONTRACT_ID};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateTreasuryRequest {
     pub name: String,
+    #[schema(value_type = String)]
     #[serde(rename = "accountId")]
     pub account_id: AccountId,
     #[serde(rename = "paymentThreshold")]
     pub payment_threshold: u8,
+    #[schema(value_type = Vec<String>)]
     pub governors: Vec<AccountId>,
+    #[schema(value_type = Vec<String>)]
     pub financiers: Vec<AccountId>,
+    #[schema(value_type = Vec<String>)]
     pub requestors: Vec<AccountId>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct CreateTreasuryResponse {
+    #[schema(value_type = String)]
     pub treasury: AccountId,
 }
 
@@ -141,33 +148,126 @@ fn prepare_args(payload: CreateTreasuryRequest) -> Result<serde_json::Value, ser
     }))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/treasury/create",
+    tag = "treasury",
+    request_body = CreateTreasuryRequest,
+    responses(
+        (status = 200, description = "Treasury created", body = CreateTreasuryResponse),
+    )
+)]
 pub async fn create_treasury(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<CreateTreasuryRequest>,
-) -> Result<Json<CreateTreasuryResponse>, (StatusCode, String)> {
+) -> Result<Json<CreateTreasuryResponse>, ApiError> {
     let treasury = payload.account_id.clone();
     let args = prepare_args(payload).map_err(|e| {
         eprintln!("Error preparing args: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+        ApiError::internal(e.to_string())
     })?;
 
-    Contract(TREASURY_FACTORY_CONTRACT_ID.into())
-        .call_function("create", args)
-        .transaction()
-        .max_gas()
-        .deposit(NearToken::from_near(6))
-        .with_signer(state.signer_id.clone(), state.signer.clone())
-        .send_to(&state.network)
-        .await
-        .map_err(|e| {
-            eprintln!("Error creating treasury: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
-        })?
-        .into_result()
-        .map_err(|e| {
-            eprintln!("Error creating treasury: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
-        })?;
+    with_timeout(
+        state.external_timeout(),
+        "RPC",
+        Contract(TREASURY_FACTORY_CONTRACT_ID.into())
+            .call_function("create", args)
+            .transaction()
+            .max_gas()
+            .deposit(NearToken::from_near(6))
+            .with_signer(state.signer_id.clone(), state.signer.clone())
+            .send_to(&state.network),
+    )
+    .await?
+    .map_err(|e| {
+        eprintln!("Error creating treasury: {}", e);
+        ApiError::internal(e.to_string())
+    })?
+    .into_result()
+    .map_err(|e| {
+        eprintln!("Error creating treasury: {}", e);
+        ApiError::internal(e.to_string())
+    })?;
 
     Ok(Json(CreateTreasuryResponse { treasury }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::signer::build_signer;
+    use crate::utils::test_utils::{init_test_state, spawn_mock_rpc_with_state};
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// JSON-RPC node recording the access keys nonces are fetched for, failing broadcasts
+    async fn access_key_rpc(
+        axum::extract::State(used_keys): axum::extract::State<Arc<Mutex<Vec<String>>>>,
+        Json(request): Json<Value>,
+    ) -> Json<Value> {
+        let params = &request["params"];
+        if params["request_type"] == "view_access_key" {
+            used_keys
+                .lock()
+                .unwrap()
+                .push(params["public_key"].as_str().unwrap().to_string());
+            return Json(json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": {
+                    "nonce": 0,
+                    "permission": "FullAccess",
+                    "block_height": 1000,
+                    "block_hash": "11111111111111111111111111111111"
+                }
+            }));
+        }
+
+        Json(json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "error": {"code": -32000, "message": "Server error", "data": "not broadcasting"}
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_submissions_rotate_across_signer_keys() {
+        let used_keys = Arc::new(Mutex::new(Vec::new()));
+
+        let keys = [
+            near_api::signer::generate_secret_key().unwrap(),
+            near_api::signer::generate_secret_key().unwrap(),
+        ];
+        let mut state = init_test_state().await;
+        state.network = spawn_mock_rpc_with_state(access_key_rpc, used_keys.clone()).await;
+        state.signer = build_signer(&keys).await.unwrap();
+        let state = Arc::new(state);
+
+        for i in 0..4 {
+            let request = CreateTreasuryRequest {
+                name: format!("Treasury {}", i),
+                account_id: format!("rotation-{}.sputnik-dao.near", i).parse().unwrap(),
+                payment_threshold: 1,
+                governors: vec![],
+                financiers: vec![],
+                requestors: vec![],
+            };
+            assert!(
+                create_treasury(State(state.clone()), Json(request))
+                    .await
+                    .is_err()
+            );
+        }
+
+        let mut submissions_per_key: HashMap<String, usize> = HashMap::new();
+        for key in used_keys.lock().unwrap().iter() {
+            *submissions_per_key.entry(key.clone()).or_default() += 1;
+        }
+        let expected: HashMap<String, usize> = keys
+            .iter()
+            .map(|key| (key.public_key().to_string(), 2))
+            .collect();
+        assert_eq!(submissions_per_key, expected);
+    }
+}
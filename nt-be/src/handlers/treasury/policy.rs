@@ -7,39 +7,65 @@ use axum::{
 use near_api::{AccountId, Contract};
 use serde::Deserialize;
 use std::sync::Arc;
+use utoipa::IntoParams;
 
 use crate::AppState;
+use crate::utils::api_error::ApiError;
+use crate::utils::timeout::with_timeout;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct GetTreasuryPolicyQuery {
+    #[param(value_type = String)]
     #[serde(rename = "treasuryId")]
     pub treasury_id: AccountId,
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/treasury/policy",
+    tag = "treasury",
+    params(GetTreasuryPolicyQuery),
+    responses(
+        (status = 200, description = "Sputnik DAO policy", body = serde_json::Value),
+    )
+)]
 pub async fn get_treasury_policy(
     State(state): State<Arc<AppState>>,
     Query(params): Query<GetTreasuryPolicyQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
-    let treasury_id = params.treasury_id;
+) -> Result<impl IntoResponse, ApiError> {
+    let policy = fetch_treasury_policy(&state, &params.treasury_id).await?;
 
+    Ok((StatusCode::OK, Json(policy)))
+}
+
+/// Fetch a DAO's policy, using the shared cache
+pub async fn fetch_treasury_policy(
+    state: &Arc<AppState>,
+    treasury_id: &AccountId,
+) -> Result<serde_json::Value, ApiError> {
     let cache_key = format!("treasury-policy:{}", treasury_id);
     if let Some(cached_policy) = state.cache.get(&cache_key).await {
         println!("🔁 Returning cached policy for {}", treasury_id);
-        return Ok((StatusCode::OK, Json(cached_policy)));
+        return Ok(cached_policy);
     }
 
-    let policy: serde_json::Value = Contract(treasury_id)
-        .call_function("get_policy", ())
-        .read_only()
-        .fetch_from(&state.network)
-        .await
-        .map_err(|e| {
-            eprintln!("Error fetching treasury policy: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
-        })?
-        .data;
+    let policy: serde_json::Value = with_timeout(
+        state.external_timeout(),
+        "RPC",
+        Contract(treasury_id.clone())
+            .call_function("get_policy", ())
+            .read_only()
+            .fetch_from(&state.network),
+    )
+    .await?
+    .map_err(|e| {
+        eprintln!("Error fetching treasury policy: {}", e);
+        ApiError::internal(e.to_string())
+    })?
+    .data;
 
     state.cache.insert(cache_key, policy.clone()).await;
 
-    Ok((StatusCode::OK, Json(policy)))
+    Ok(policy)
 }
@@ -4,23 +4,56 @@ use axum::{
     http::StatusCode,
     response::IntoResponse,
 };
-use near_api::Contract;
+use bigdecimal::{BigDecimal, Zero};
+use near_api::{AccountId, Contract, Tokens};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
+use utoipa::{IntoParams, ToSchema};
 
+use crate::handlers::balance_changes::token_discovery::owned_intents_tokens;
+use crate::utils::account::{AccountKind, account_kind};
+use crate::utils::api_error::ApiError;
+use crate::utils::decimals::NEAR_DECIMALS;
+use crate::utils::timeout::with_timeout;
 use crate::{
     AppState,
-    constants::{
-        INTENTS_CONTRACT_ID, NEAR_ICON, REF_FINANCE_CONTRACT_ID, intents_chains::ChainIcons,
-    },
+    constants::{NEAR_ICON, intents_chains::ChainIcons},
     handlers::token::{TokenMetadata as TokenMetadataResponse, fetch_tokens_metadata},
+    utils::fields::{parse_fields, select_fields},
 };
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct UserAssetsQuery {
     #[serde(rename = "accountId")]
     pub account_id: String,
+    #[serde(rename = "sortBy", default)]
+    pub sort_by: AssetSort,
+    /// Comma-separated token fields to return (e.g. `symbol,balance`); all fields when unset
+    pub fields: Option<String>,
+    /// Comma-separated token ids or contract ids; only these tokens are returned
+    #[serde(rename = "includeTokens", alias = "include_tokens")]
+    pub include_tokens: Option<String>,
+    /// Comma-separated token ids or contract ids to leave out
+    #[serde(rename = "excludeTokens", alias = "exclude_tokens")]
+    pub exclude_tokens: Option<String>,
+    /// Only return native NEAR and tokens whitelisted on Ref Finance
+    #[serde(rename = "whitelistedOnly", alias = "whitelisted_only", default)]
+    pub whitelisted_only: bool,
+}
+
+/// Ordering of the assets list, highest first
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetSort {
+    /// Raw balance in the token's smallest unit
+    #[default]
+    Balance,
+    /// Balance converted to USD using the token's decimals and price
+    Usd,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -34,7 +67,7 @@ pub struct TokenMetadata {
 impl TokenMetadata {
     pub fn near() -> Self {
         Self {
-            decimals: 24,
+            decimals: NEAR_DECIMALS,
             symbol: "NEAR".to_string(),
             name: "NEAR".to_string(),
             icon: NEAR_ICON.to_string(),
@@ -42,14 +75,14 @@ impl TokenMetadata {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub enum TokenResidency {
     Near,
     Ft,
     Intents,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct SimplifiedToken {
     pub id: String,
     #[serde(rename = "contractId")]
@@ -69,118 +102,238 @@ pub struct SimplifiedToken {
     pub chain_icons: Option<ChainIcons>,
 }
 
+impl SimplifiedToken {
+    /// Raw balance in the token's smallest unit; unparseable balances count as zero
+    ///
+    /// Balances can exceed `u128` for tokens with many decimals and large supplies.
+    pub fn balance_decimal(&self) -> BigDecimal {
+        BigDecimal::from_str(&self.balance).unwrap_or_else(|_| BigDecimal::zero())
+    }
+
+    /// Balance converted to USD, or zero if the price is unknown
+    pub fn usd_value(&self) -> BigDecimal {
+        let price = BigDecimal::from_str(&self.price).unwrap_or_else(|_| BigDecimal::zero());
+        let scale = BigDecimal::from_str(&format!("1e{}", self.decimals)).unwrap();
+        self.balance_decimal() * price / scale
+    }
+}
+
+/// Sort tokens by the given measure, highest first
+fn sort_tokens(tokens: &mut [SimplifiedToken], sort: AssetSort) {
+    match sort {
+        AssetSort::Balance => tokens.sort_by_key(|t| std::cmp::Reverse(t.balance_decimal())),
+        AssetSort::Usd => tokens.sort_by_key(|t| std::cmp::Reverse(t.usd_value())),
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct UserAssetsResponse {
+    /// Whether the account is named, implicit or a sub-account
+    #[serde(rename = "accountKind")]
+    pub account_kind: AccountKind,
+    pub tokens: Vec<SimplifiedToken>,
+    /// True when a balance source was unavailable and some tokens may be missing
+    pub partial: bool,
+}
+
 #[derive(Deserialize, Debug)]
-struct FastNearToken {
+pub(crate) struct FastNearToken {
     contract_id: String,
     balance: String,
 }
 
 #[derive(Deserialize, Debug)]
-struct FastNearResponse {
+pub(crate) struct FastNearResponse {
     tokens: Option<Vec<FastNearToken>>,
-    state: Option<FastNearState>,
+    pub(crate) state: Option<FastNearState>,
 }
 
 #[derive(Deserialize, Debug)]
-struct FastNearState {
-    balance: String,
+pub(crate) struct FastNearState {
+    pub(crate) balance: String,
 }
 
 /// Fetches whitelisted token IDs from the Ref Finance contract via RPC
 async fn fetch_whitelisted_tokens_from_rpc(
     state: &Arc<AppState>,
-) -> Result<HashSet<String>, (StatusCode, String)> {
-    let whitelisted_tokens = Contract(REF_FINANCE_CONTRACT_ID.into())
+) -> Result<HashSet<String>, ApiError> {
+    let request = Contract(state.env_vars.ref_finance_contract_id.clone())
         .call_function("get_whitelisted_tokens", ())
         .read_only::<HashSet<String>>()
-        .fetch_from(&state.network)
-        .await
+        .fetch_from(&state.network);
+    let whitelisted_tokens = with_timeout(state.external_timeout(), "RPC", request)
+        .await?
         .map_err(|e| {
             eprintln!("Error fetching whitelisted tokens from RPC: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch whitelisted tokens".to_string(),
-            )
+            ApiError::internal("Failed to fetch whitelisted tokens")
         })?;
 
     Ok(whitelisted_tokens.data)
 }
 
-/// Fetches all Ref Finance tokens and filters them by whitelist
-async fn fetch_whitelisted_tokens(
-    state: &Arc<AppState>,
-) -> Result<HashSet<String>, (StatusCode, String)> {
-    let cache_key = "ref-whitelisted-tokens";
+/// Cache key for the Ref Finance token whitelist
+pub(crate) const FASTNEAR_API_BASE_URL: &str = "https://api.fastnear.com";
 
-    // Check cache first
-    if let Some(cached_tokens) = state.cache.get(cache_key).await {
-        println!("🔁 Returning cached whitelisted tokens");
-        let tokens: HashSet<String> = serde_json::from_value(cached_tokens).map_err(|e| {
-            eprintln!("Error deserializing cached tokens: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to deserialize cached tokens".to_string(),
-            )
-        })?;
-        return Ok(tokens);
-    }
+pub const REF_WHITELIST_CACHE_KEY: &str = "ref-whitelisted-tokens";
 
-    // Fetch whitelist
+/// Fetches the whitelist from RPC and stores it in the cache
+///
+/// Also records the refresh time so it can be reported by the health endpoint.
+pub async fn refresh_whitelisted_tokens(
+    state: &Arc<AppState>,
+) -> Result<HashSet<String>, ApiError> {
     let whitelist_set = fetch_whitelisted_tokens_from_rpc(state).await?;
 
-    // Cache the result
     let tokens_value = serde_json::to_value(&whitelist_set).map_err(|e| {
         eprintln!("Error serializing tokens: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to serialize tokens".to_string(),
-        )
+        ApiError::internal("Failed to serialize tokens")
     })?;
 
     state
         .cache
-        .insert(cache_key.to_string(), tokens_value)
+        .insert(REF_WHITELIST_CACHE_KEY.to_string(), tokens_value)
         .await;
+    *state.ref_whitelist_refreshed_at.write().await = Some(chrono::Utc::now());
 
     Ok(whitelist_set)
 }
 
+/// Spawns a background task that keeps the whitelist cache warm
+///
+/// The interval should be shorter than the cache TTL so user requests never
+/// have to pay for the `get_whitelisted_tokens` RPC call.
+pub fn spawn_whitelist_refresh(
+    state: Arc<AppState>,
+    interval: Duration,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match refresh_whitelisted_tokens(&state).await {
+                Ok(tokens) => {
+                    log::info!("Refreshed Ref whitelist ({} tokens)", tokens.len());
+                }
+                Err(e) => {
+                    log::error!("Failed to refresh Ref whitelist: {}", e.message);
+                }
+            }
+
+            tokio::time::sleep(interval).await;
+        }
+    })
+}
+
+/// Fetches all Ref Finance tokens and filters them by whitelist
+async fn fetch_whitelisted_tokens(state: &Arc<AppState>) -> Result<HashSet<String>, ApiError> {
+    // Check cache first
+    if let Some(cached_tokens) = state.cache.get(REF_WHITELIST_CACHE_KEY).await {
+        println!("🔁 Returning cached whitelisted tokens");
+        let tokens: HashSet<String> = serde_json::from_value(cached_tokens).map_err(|e| {
+            eprintln!("Error deserializing cached tokens: {}", e);
+            ApiError::internal("Failed to deserialize cached tokens")
+        })?;
+        return Ok(tokens);
+    }
+
+    // Cache is cold (e.g. background refresh hasn't run yet), fetch and cache now
+    refresh_whitelisted_tokens(state).await
+}
+
 /// Fetches user balances from FastNear API
-async fn fetch_user_balances(
+///
+/// A 429 benches the key that was used and retries with the next configured key.
+pub(crate) async fn fetch_user_balances(
     state: &Arc<AppState>,
+    base_url: &str,
     account: &str,
-) -> Result<FastNearResponse, (StatusCode, String)> {
-    let response = state
-        .http_client
-        .get(format!(
-            "https://api.fastnear.com/v1/account/{}/full",
-            account
-        ))
-        .header(
-            "Authorization",
-            format!("Bearer {}", state.env_vars.fastnear_api_key),
-        )
-        .send()
-        .await
-        .map_err(|e| {
+) -> Result<FastNearResponse, ApiError> {
+    let url = format!("{}/v1/account/{}/full", base_url, account);
+    let fetch_error = || ApiError::internal("Failed to fetch user balances");
+
+    let mut attempts = state.fastnear_keys.len();
+    let response = loop {
+        let key = state.fastnear_keys.current().ok_or_else(fetch_error)?;
+        let request = state
+            .http_client
+            .get(&url)
+            .header("Authorization", format!("Bearer {}", key))
+            .send();
+        let response = with_timeout(state.external_timeout(), "FastNear", request)
+            .await?
+            .map_err(|e| {
+                eprintln!("Error fetching user balances: {}", e);
+                fetch_error()
+            })?;
+
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            state.fastnear_keys.mark_rate_limited(key);
+            attempts -= 1;
+            if attempts > 0 {
+                continue;
+            }
+        } else {
+            state.fastnear_keys.mark_success(key);
+        }
+
+        break response.error_for_status().map_err(|e| {
             eprintln!("Error fetching user balances: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch user balances".to_string(),
-            )
+            fetch_error()
         })?;
+    };
 
-    response.json().await.map_err(|e| {
-        eprintln!("Error parsing balances: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to parse balances".to_string(),
-        )
-    })
+    with_timeout(state.external_timeout(), "FastNear", response.json())
+        .await?
+        .map_err(|e| {
+            eprintln!("Error parsing balances: {}", e);
+            ApiError::internal("Failed to parse balances")
+        })
+}
+
+/// Fetches user balances, falling back to the NEAR balance from RPC if FastNear fails
+///
+/// Returns the balances and whether the FT balances are missing because of the fallback.
+async fn fetch_user_balances_or_near_only(
+    state: &Arc<AppState>,
+    base_url: &str,
+    account: &str,
+) -> Result<(FastNearResponse, bool), ApiError> {
+    let fastnear_error = match fetch_user_balances(state, base_url, account).await {
+        Ok(balances) => return Ok((balances, false)),
+        Err(e) => e.message,
+    };
+
+    eprintln!(
+        "Warning: FastNear unavailable for {} ({}), returning NEAR balance from RPC without FT balances",
+        account, fastnear_error
+    );
+
+    let account_id: AccountId = account.parse().map_err(|e| {
+        eprintln!("Invalid account id {}: {}", account, e);
+        ApiError::bad_request("Invalid account id")
+    })?;
+
+    let request = Tokens::account(account_id)
+        .near_balance()
+        .fetch_from(&state.network);
+    let balance = with_timeout(state.external_timeout(), "RPC", request)
+        .await?
+        .map_err(|e| {
+            eprintln!("Error fetching NEAR balance for {}: {}", account, e);
+            ApiError::internal("Failed to fetch user balances")
+        })?;
+
+    Ok((
+        FastNearResponse {
+            tokens: None,
+            state: Some(FastNearState {
+                balance: balance.total.as_yoctonear().to_string(),
+            }),
+        },
+        true,
+    ))
 }
 
 /// Builds a map of token balances from FastNear response
-fn build_balance_map(user_balances: &FastNearResponse) -> HashMap<String, String> {
+pub(crate) fn build_balance_map(user_balances: &FastNearResponse) -> HashMap<String, String> {
     let mut balance_map = HashMap::new();
     if let Some(tokens) = &user_balances.tokens {
         for token in tokens {
@@ -190,68 +343,49 @@ fn build_balance_map(user_balances: &FastNearResponse) -> HashMap<String, String
     balance_map
 }
 
-/// Gets the balance for a specific token
-fn get_token_balance(
-    token_id: &str,
-    user_balances: &FastNearResponse,
-    balance_map: &HashMap<String, String>,
-) -> String {
-    if token_id == "nep141:wrap.near" {
-        user_balances
-            .state
-            .as_ref()
-            .map(|s| s.balance.clone())
-            .unwrap_or_else(|| "0".to_string())
-    } else {
-        balance_map
-            .get(token_id)
-            .cloned()
-            .unwrap_or_else(|| "0".to_string())
-    }
-}
-
-#[derive(Deserialize, Debug)]
-struct IntentsToken {
-    token_id: String,
+/// Gets the FT balance for a specific token
+///
+/// Accepts the contract id with or without the `nep141:` prefix. wNEAR
+/// (`wrap.near`) is an FT like any other; its balance is separate from the
+/// account's native NEAR balance in `user_balances.state`.
+fn get_token_balance(token_id: &str, balance_map: &HashMap<String, String>) -> String {
+    let contract_id = token_id.strip_prefix("nep141:").unwrap_or(token_id);
+    balance_map
+        .get(&contract_id.to_lowercase())
+        .cloned()
+        .unwrap_or_else(|| "0".to_string())
 }
 
-/// Fetches tokens owned by an account from intents.near
+/// Fetches tokens owned by an account from the intents contract (cached briefly, see
+/// `token_discovery::owned_intents_tokens`)
 async fn fetch_intents_owned_tokens(
     state: &Arc<AppState>,
     account_id: &str,
-) -> Result<Vec<String>, (StatusCode, String)> {
-    let owned_tokens = Contract(INTENTS_CONTRACT_ID.into())
-        .call_function(
-            "mt_tokens_for_owner",
-            serde_json::json!({
-                "account_id": account_id
-            }),
-        )
-        .read_only::<Vec<IntentsToken>>()
-        .fetch_from(&state.network)
-        .await
+) -> Result<Vec<String>, ApiError> {
+    let request = owned_intents_tokens(
+        &state.network,
+        &state.env_vars.intents_contract_id,
+        account_id,
+    );
+    with_timeout(state.external_timeout(), "RPC", request)
+        .await?
         .map_err(|e| {
             eprintln!("Error fetching owned tokens from intents.near: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch owned tokens from intents.near".to_string(),
-            )
-        })?;
-
-    Ok(owned_tokens.data.into_iter().map(|t| t.token_id).collect())
+            ApiError::internal("Failed to fetch owned tokens from intents.near")
+        })
 }
 
-/// Fetches balances for multiple tokens from intents.near
+/// Fetches balances for multiple tokens from the intents contract
 async fn fetch_intents_balances(
     state: &Arc<AppState>,
     account_id: &str,
     token_ids: &[String],
-) -> Result<Vec<String>, (StatusCode, String)> {
+) -> Result<Vec<String>, ApiError> {
     if token_ids.is_empty() {
         return Ok(Vec::new());
     }
 
-    let balances = Contract(INTENTS_CONTRACT_ID.into())
+    let balances = Contract(state.env_vars.intents_contract_id.clone())
         .call_function(
             "mt_batch_balance_of",
             serde_json::json!({
@@ -260,14 +394,12 @@ async fn fetch_intents_balances(
             }),
         )
         .read_only::<Vec<String>>()
-        .fetch_from(&state.network)
-        .await
+        .fetch_from(&state.network);
+    let balances = with_timeout(state.external_timeout(), "RPC", balances)
+        .await?
         .map_err(|e| {
             eprintln!("Error fetching balances from intents.near: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch balances from intents.near".to_string(),
-            )
+            ApiError::internal("Failed to fetch balances from intents.near")
         })?;
 
     Ok(balances.data)
@@ -312,28 +444,148 @@ fn build_intents_tokens(
         .collect()
 }
 
+/// Tokens a client restricted the assets list to
+#[derive(Debug, Default)]
+struct TokenFilter {
+    include: Option<HashSet<String>>,
+    exclude: HashSet<String>,
+    /// Ref whitelist when only whitelisted tokens are requested
+    whitelist: Option<HashSet<String>>,
+}
+
+/// Parse a comma-separated list of token ids, lowercased
+fn parse_token_list(tokens: Option<&str>) -> HashSet<String> {
+    tokens
+        .unwrap_or_default()
+        .split(',')
+        .map(|t| t.trim().to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+impl TokenFilter {
+    fn new(params: &UserAssetsQuery, whitelist: Option<HashSet<String>>) -> Self {
+        let include = parse_token_list(params.include_tokens.as_deref());
+        Self {
+            include: (!include.is_empty()).then_some(include),
+            exclude: parse_token_list(params.exclude_tokens.as_deref()),
+            whitelist: whitelist.map(|w| w.into_iter().map(|t| t.to_lowercase()).collect()),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.include.is_none() && self.exclude.is_empty() && self.whitelist.is_none()
+    }
+
+    /// A token matches a list by its id or its contract id
+    fn matches(list: &HashSet<String>, token: &SimplifiedToken) -> bool {
+        list.contains(&token.id.to_lowercase())
+            || token
+                .contract_id
+                .as_ref()
+                .is_some_and(|c| list.contains(&c.to_lowercase()))
+    }
+
+    fn allows(&self, token: &SimplifiedToken) -> bool {
+        if let Some(include) = &self.include
+            && !Self::matches(include, token)
+        {
+            return false;
+        }
+        if Self::matches(&self.exclude, token) {
+            return false;
+        }
+        match &self.whitelist {
+            Some(whitelist) => {
+                matches!(token.residency, TokenResidency::Near)
+                    || token
+                        .contract_id
+                        .as_ref()
+                        .is_some_and(|c| whitelist.contains(&c.to_lowercase()))
+            }
+            None => true,
+        }
+    }
+}
+
+/// Drop the tokens of an assets response that the filter doesn't allow
+///
+/// Like `project_tokens`, this runs per request on the full (cached) response.
+fn filter_tokens(response: serde_json::Value, filter: &TokenFilter) -> serde_json::Value {
+    if filter.is_empty() {
+        return response;
+    }
+
+    match serde_json::from_value::<UserAssetsResponse>(response.clone()) {
+        Ok(mut assets) => {
+            assets.tokens.retain(|t| filter.allows(t));
+            serde_json::to_value(assets).unwrap_or(response)
+        }
+        Err(e) => {
+            eprintln!("Error deserializing user assets for filtering: {}", e);
+            response
+        }
+    }
+}
+
+/// Trim each token of an assets response to the requested fields
+///
+/// The full response is what gets cached; projection happens per request.
+fn project_tokens(mut response: serde_json::Value, fields: Option<&str>) -> serde_json::Value {
+    if let (Some(fields), Some(tokens)) = (parse_fields(fields), response.get_mut("tokens")) {
+        select_fields(tokens, &fields);
+    }
+    response
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/user/assets",
+    tag = "user",
+    params(UserAssetsQuery),
+    responses(
+        (status = 200, description = "Tokens held by the account", body = UserAssetsResponse),
+    )
+)]
 pub async fn get_user_assets(
     State(state): State<Arc<AppState>>,
     Query(params): Query<UserAssetsQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let account = &params.account_id;
 
     if account.is_empty() {
-        return Err((StatusCode::BAD_REQUEST, "account is required".to_string()));
+        return Err(ApiError::bad_request("account is required"));
     }
 
-    let cache_key = format!("{}-user-assets", account);
+    let whitelist = if params.whitelisted_only {
+        Some(fetch_whitelisted_tokens(&state).await?)
+    } else {
+        None
+    };
+    let filter = TokenFilter::new(&params, whitelist);
+
+    let cache_key = match params.sort_by {
+        AssetSort::Balance => format!("{}-user-assets", account),
+        AssetSort::Usd => format!("{}-user-assets-by-usd", account),
+    };
 
     // Check cache
     if let Some(cached_tokens) = state.cache.get(&cache_key).await {
         println!("🔁 Returning cached user assets for {}", account);
-        return Ok((StatusCode::OK, Json(cached_tokens)));
+        return Ok((
+            StatusCode::OK,
+            Json(project_tokens(
+                filter_tokens(cached_tokens, &filter),
+                params.fields.as_deref(),
+            )),
+        ));
     }
 
     // Fetch REF Finance data
     let ref_data_future = async {
         let tokens_future = fetch_whitelisted_tokens(&state);
-        let balances_future = fetch_user_balances(&state, account);
+        let balances_future =
+            fetch_user_balances_or_near_only(&state, FASTNEAR_API_BASE_URL, account);
 
         tokio::try_join!(tokens_future, balances_future)
     };
@@ -342,7 +594,7 @@ pub async fn get_user_assets(
     let intents_data_future = async {
         let owned_token_ids = fetch_intents_owned_tokens(&state, account).await?;
         if owned_token_ids.is_empty() {
-            return Ok::<_, (StatusCode, String)>(Vec::new());
+            return Ok::<_, ApiError>(Vec::new());
         }
 
         let balances = fetch_intents_balances(&state, account, &owned_token_ids).await?;
@@ -350,8 +602,10 @@ pub async fn get_user_assets(
         // Filter to only tokens with non-zero balances
         let tokens_with_balances: Vec<(String, String)> = owned_token_ids
             .into_iter()
-            .zip(balances.into_iter())
-            .filter(|(_, balance)| balance.parse::<u128>().unwrap_or(0) > 0)
+            .zip(balances)
+            .filter(|(_, balance)| {
+                BigDecimal::from_str(balance).is_ok_and(|b| b > BigDecimal::zero())
+            })
             .collect();
 
         Ok(tokens_with_balances)
@@ -361,11 +615,12 @@ pub async fn get_user_assets(
     let (ref_data_result, intents_data_result) = tokio::join!(ref_data_future, intents_data_future);
 
     // Get whitelisted tokens and user balances
-    let (whitelist_set, user_balances) = ref_data_result?;
+    let (whitelist_set, (user_balances, mut partial)) = ref_data_result?;
 
     // Get intents balances (already filtered to non-zero)
     let intents_balances = intents_data_result.unwrap_or_else(|e| {
         eprintln!("Warning: Failed to fetch intents tokens: {:?}", e);
+        partial = true;
         Vec::new()
     });
 
@@ -374,7 +629,7 @@ pub async fn get_user_assets(
     let ref_tokens_with_balances: Vec<(String, String)> = whitelist_set
         .into_iter()
         .filter_map(|token_id| {
-            let balance = get_token_balance(&token_id, &user_balances, &balance_map);
+            let balance = get_token_balance(&token_id, &balance_map);
             if balance != "0" {
                 Some((token_id, balance))
             } else {
@@ -403,7 +658,7 @@ pub async fn get_user_assets(
     // Build simplified tokens for REF Finance tokens
     let mut all_simplified_tokens: Vec<SimplifiedToken> = ref_tokens_with_balances
         .into_iter()
-        .zip(token_ids_to_fetch.into_iter())
+        .zip(token_ids_to_fetch)
         .filter_map(|((token_id, balance), token_id_to_fetch)| {
             let token_meta = tokens_metadata
                 .iter()
@@ -457,28 +712,385 @@ pub async fn get_user_assets(
         chain_icons: near_token_meta.chain_icons.clone(),
     });
 
-    // Sort combined list by balance (highest first)
-    all_simplified_tokens = all_simplified_tokens
-        .into_iter()
-        .filter(|t| t.balance.parse::<u128>().unwrap_or(0) > 0)
-        .collect::<Vec<_>>();
-    all_simplified_tokens.sort_by(|a, b| {
-        let a_val: u128 = a.balance.parse().unwrap_or(0);
-        let b_val: u128 = b.balance.parse().unwrap_or(0);
-        b_val
-            .partial_cmp(&a_val)
-            .unwrap_or(std::cmp::Ordering::Equal)
-    });
+    // Drop empty balances and sort the combined list (highest first)
+    all_simplified_tokens.retain(|t| t.balance_decimal() > BigDecimal::zero());
+    sort_tokens(&mut all_simplified_tokens, params.sort_by);
 
-    let result_value = serde_json::to_value(&all_simplified_tokens).map_err(|e| {
+    let result_value = serde_json::to_value(UserAssetsResponse {
+        account_kind: account_kind(account),
+        tokens: all_simplified_tokens,
+        partial,
+    })
+    .map_err(|e| {
         eprintln!("Error serializing result: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to serialize result".to_string(),
-        )
+        ApiError::internal("Failed to serialize result")
     })?;
 
-    state.cache.insert(cache_key, result_value.clone()).await;
+    // Don't cache partial results so the missing balances show up once the source recovers
+    if !partial {
+        state.cache.insert(cache_key, result_value.clone()).await;
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(project_tokens(
+            filter_tokens(result_value, &filter),
+            params.fields.as_deref(),
+        )),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{init_test_state, spawn_mock_rpc_with_state};
+
+    fn token(id: &str, balance: &str, decimals: u8, price: &str) -> SimplifiedToken {
+        SimplifiedToken {
+            id: id.to_string(),
+            contract_id: Some(id.to_string()),
+            residency: TokenResidency::Ft,
+            network: "near".to_string(),
+            chain_name: "Near Protocol".to_string(),
+            symbol: id.to_uppercase(),
+            balance: balance.to_string(),
+            decimals,
+            price: price.to_string(),
+            name: id.to_string(),
+            icon: None,
+            chain_icons: None,
+        }
+    }
+
+    fn assets_query(include: Option<&str>, exclude: Option<&str>) -> UserAssetsQuery {
+        UserAssetsQuery {
+            account_id: "user.near".to_string(),
+            sort_by: AssetSort::Balance,
+            fields: None,
+            include_tokens: include.map(str::to_string),
+            exclude_tokens: exclude.map(str::to_string),
+            whitelisted_only: false,
+        }
+    }
+
+    fn token_ids(response: &serde_json::Value) -> Vec<String> {
+        response["tokens"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["id"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_token_filter_include_exclude_and_whitelist() {
+        let mut spam = token("nep141:spam.near", "5", 18, "0");
+        spam.residency = TokenResidency::Intents;
+        spam.contract_id = Some("spam.near".to_string());
+        let mut near = token("near", "10", 24, "3");
+        near.residency = TokenResidency::Near;
+        near.contract_id = None;
+        let response = serde_json::to_value(UserAssetsResponse {
+            account_kind: AccountKind::Named,
+            tokens: vec![token("usdc", "1000000", 6, "1"), spam, near],
+            partial: false,
+        })
+        .unwrap();
+
+        let excluded = filter_tokens(
+            response.clone(),
+            &TokenFilter::new(&assets_query(None, Some(" SPAM.near ")), None),
+        );
+        assert_eq!(token_ids(&excluded), vec!["usdc", "near"]);
+
+        let included = filter_tokens(
+            response.clone(),
+            &TokenFilter::new(&assets_query(Some("usdc,near"), Some("near")), None),
+        );
+        assert_eq!(token_ids(&included), vec!["usdc"]);
+
+        let whitelist = HashSet::from(["usdc".to_string()]);
+        let whitelisted = filter_tokens(
+            response.clone(),
+            &TokenFilter::new(&assets_query(None, None), Some(whitelist)),
+        );
+        assert_eq!(token_ids(&whitelisted), vec!["usdc", "near"]);
+
+        let unfiltered = filter_tokens(
+            response.clone(),
+            &TokenFilter::new(&assets_query(None, None), None),
+        );
+        assert_eq!(unfiltered, response);
+    }
+
+    #[tokio::test]
+    async fn test_empty_account_returns_structured_error() {
+        let state = Arc::new(init_test_state().await);
+        let error = get_user_assets(
+            State(state),
+            Query(UserAssetsQuery {
+                account_id: String::new(),
+                ..assets_query(None, None)
+            }),
+        )
+        .await
+        .err()
+        .expect("An empty account should be rejected");
+
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "error": {"code": "bad_request", "message": "account is required"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_fields_limit_token_keys() {
+        let response = serde_json::to_value(UserAssetsResponse {
+            account_kind: AccountKind::Named,
+            tokens: vec![token("usdc", "1000000", 6, "1")],
+            partial: false,
+        })
+        .unwrap();
+
+        let projected = project_tokens(response.clone(), Some("symbol,balance"));
+        let token = projected["tokens"][0].as_object().unwrap();
+        assert_eq!(token.len(), 2);
+        assert_eq!(token["symbol"], "USDC");
+        assert_eq!(token["balance"], "1000000");
+        for absent in [
+            "id",
+            "contractId",
+            "decimals",
+            "price",
+            "icon",
+            "chainIcons",
+        ] {
+            assert!(!token.contains_key(absent), "{} should be absent", absent);
+        }
+        assert_eq!(projected["partial"], false);
+
+        assert_eq!(project_tokens(response.clone(), None), response);
+    }
+
+    #[test]
+    fn test_wnear_balance_is_separate_from_native_near() {
+        let user_balances = FastNearResponse {
+            tokens: Some(vec![
+                FastNearToken {
+                    contract_id: "wrap.near".to_string(),
+                    balance: "2000000000000000000000000".to_string(),
+                },
+                FastNearToken {
+                    contract_id: "usdt.tether-token.near".to_string(),
+                    balance: "1000000".to_string(),
+                },
+            ]),
+            state: Some(FastNearState {
+                balance: "5000000000000000000000000".to_string(),
+            }),
+        };
+        let balance_map = build_balance_map(&user_balances);
+
+        let wnear = get_token_balance("wrap.near", &balance_map);
+        assert_eq!(wnear, "2000000000000000000000000");
+        assert_eq!(get_token_balance("nep141:wrap.near", &balance_map), wnear);
+        assert_ne!(wnear, user_balances.state.unwrap().balance);
+        assert_eq!(get_token_balance("missing.near", &balance_map), "0");
+    }
+
+    #[test]
+    fn test_sort_by_balance_handles_values_beyond_u128() {
+        // Larger than u128::MAX, which used to parse as 0 and sort last
+        let huge = "400000000000000000000000000000000000000000";
+        assert!(huge.parse::<u128>().is_err());
+
+        let mut tokens = vec![
+            token("small.near", "1000", 6, "1"),
+            token("huge.near", huge, 24, "0"),
+            token("medium.near", "5000000", 6, "1"),
+        ];
+        sort_tokens(&mut tokens, AssetSort::Balance);
+
+        let ids: Vec<&str> = tokens.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["huge.near", "medium.near", "small.near"]);
+    }
+
+    #[test]
+    fn test_sort_by_usd_value() {
+        let mut tokens = vec![
+            // 1,000,000 tokens worth nothing
+            token("spam.near", "1000000000000000000000000000000", 24, "0"),
+            // 10 USDC at $1
+            token("usdc.near", "10000000", 6, "1"),
+            // 2 NEAR at $3
+            token("near", "2000000000000000000000000", 24, "3"),
+        ];
+        sort_tokens(&mut tokens, AssetSort::Usd);
+
+        let ids: Vec<&str> = tokens.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["usdc.near", "near", "spam.near"]);
+        assert_eq!(tokens[1].usd_value(), BigDecimal::from(6));
+    }
 
-    Ok((StatusCode::OK, Json(result_value)))
+    #[tokio::test]
+    async fn test_fastnear_failure_falls_back_to_rpc_near_balance() {
+        use axum::{Router, routing::get};
+
+        let app = Router::new().route(
+            "/v1/account/{account}/full",
+            get(|| async { StatusCode::SERVICE_UNAVAILABLE }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let state = Arc::new(init_test_state().await);
+        let (balances, partial) = fetch_user_balances_or_near_only(
+            &state,
+            &base_url,
+            "webassemblymusic-treasury.sputnik-dao.near",
+        )
+        .await
+        .expect("NEAR balance should still be returned");
+
+        assert!(partial);
+        assert!(balances.tokens.is_none());
+        let near_balance = balances.state.expect("NEAR balance from RPC").balance;
+        assert!(BigDecimal::from_str(&near_balance).unwrap() > BigDecimal::zero());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_fastnear_key_rotates_to_next() {
+        use crate::utils::api_keys::ApiKeyRotation;
+        use axum::{Router, http::HeaderMap, routing::get};
+
+        let app = Router::new().route(
+            "/v1/account/{account}/full",
+            get(|headers: HeaderMap| async move {
+                match headers.get("Authorization").and_then(|v| v.to_str().ok()) {
+                    Some("Bearer key2") => (
+                        StatusCode::OK,
+                        Json(serde_json::json!({"tokens": [], "state": {"balance": "42"}})),
+                    ),
+                    _ => (StatusCode::TOO_MANY_REQUESTS, Json(serde_json::json!({}))),
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut state = init_test_state().await;
+        state.fastnear_keys = ApiKeyRotation::new(vec!["key1".to_string(), "key2".to_string()]);
+        let state = Arc::new(state);
+
+        let balances = fetch_user_balances(&state, &base_url, "test.near")
+            .await
+            .expect("Second key should succeed");
+
+        assert_eq!(balances.state.unwrap().balance, "42");
+        assert_eq!(state.fastnear_keys.rate_limit_counts(), vec![1, 0]);
+        assert_eq!(state.fastnear_keys.current(), Some("key2"));
+    }
+
+    #[tokio::test]
+    async fn test_stalled_fastnear_times_out_with_504() {
+        use axum::{Router, routing::get};
+
+        let app = Router::new().route(
+            "/v1/account/{account}/full",
+            get(std::future::pending::<Json<serde_json::Value>>),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut state = init_test_state().await;
+        state.env_vars.external_timeout_seconds = 1;
+        let state = Arc::new(state);
+
+        let started = std::time::Instant::now();
+        let error = fetch_user_balances(&state, &base_url, "test.near")
+            .await
+            .expect_err("A stalled upstream should time out");
+
+        assert_eq!(error.status, StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(error.message, "FastNear did not respond within 1 seconds");
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_intents_contract_id_is_configurable() {
+        use axum::extract::State;
+        use std::sync::Mutex;
+
+        async fn rpc(
+            State(targets): State<Arc<Mutex<Vec<String>>>>,
+            Json(request): Json<serde_json::Value>,
+        ) -> Json<serde_json::Value> {
+            let params = &request["params"];
+            assert_eq!(params["method_name"], "mt_tokens_for_owner");
+            targets
+                .lock()
+                .unwrap()
+                .push(params["account_id"].as_str().unwrap().to_string());
+
+            let tokens = serde_json::json!([{"token_id": "nep141:wrap.testnet"}]);
+            Json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": {
+                    "result": serde_json::to_vec(&tokens).unwrap(),
+                    "logs": [],
+                    "block_height": 1,
+                    "block_hash": "11111111111111111111111111111111"
+                }
+            }))
+        }
+
+        let targets = Arc::new(Mutex::new(Vec::new()));
+        let mut state = init_test_state().await;
+        state.network = spawn_mock_rpc_with_state(rpc, targets.clone()).await;
+        state.env_vars.intents_contract_id = "intents.testnet".parse().unwrap();
+        let state = Arc::new(state);
+
+        let tokens = fetch_intents_owned_tokens(&state, "configured-intents.testnet")
+            .await
+            .unwrap();
+
+        assert_eq!(tokens, vec!["nep141:wrap.testnet"]);
+        assert_eq!(*targets.lock().unwrap(), vec!["intents.testnet"]);
+    }
+
+    #[tokio::test]
+    async fn test_background_refresh_populates_whitelist_cache() {
+        let state = Arc::new(init_test_state().await);
+        assert!(state.cache.get(REF_WHITELIST_CACHE_KEY).await.is_none());
+
+        let handle = spawn_whitelist_refresh(state.clone(), Duration::from_secs(3600));
+
+        // Wait for the first refresh without making any user request
+        let mut cached = None;
+        for _ in 0..50 {
+            cached = state.cache.get(REF_WHITELIST_CACHE_KEY).await;
+            if cached.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(200)).await;
+        }
+        handle.abort();
+
+        let tokens: HashSet<String> =
+            serde_json::from_value(cached.expect("Whitelist should be cached")).unwrap();
+        assert!(tokens.contains("wrap.near"));
+        assert!(state.ref_whitelist_refreshed_at.read().await.is_some());
+    }
 }
@@ -1,29 +1,73 @@
 use axum::{
     Json,
     extract::{Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
 };
-use near_api::{AccountId, Contract, Tokens, types::json::U128};
+use near_api::{AccountId, Contract, NetworkConfig, Tokens, types::json::U128};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
+use crate::handlers::balance_changes::balance::{get_balance_at_block, get_balance_at_time};
+use crate::handlers::balance_changes::counterparty::convert_decimal_to_raw;
+use crate::handlers::block::timestamp::parse_timestamp;
+use crate::utils::admin_auth::admin_rpc_network;
+use crate::utils::api_error::ApiError;
+use crate::utils::decimals::{NEAR_DECIMALS, decimals};
+use crate::utils::numeric::NumericQuery;
+use crate::utils::timeout::with_timeout;
 use crate::{AppState, constants::INTENTS_CONTRACT_ID};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct TokenBalanceQuery {
+    #[param(value_type = String)]
     #[serde(rename = "accountId")]
     pub account_id: AccountId,
     #[serde(rename = "tokenId")]
     pub token_id: String,
+    /// Return the balance at this block (queried on the archival network) instead of now
+    #[serde(rename = "blockHeight", alias = "block_height")]
+    pub block_height: Option<u64>,
+    /// Admin only: query `blockHeight` on this RPC endpoint instead of the archival
+    /// network. Must be listed in `RPC_URL_ALLOWLIST`.
+    pub rpc_url: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct TokenBalanceResponse {
     pub account_id: String,
     pub token_id: String,
     pub balance: String,
     pub decimals: u8,
+    /// Block the balance was queried at; omitted for current balances
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_height: Option<u64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TokenBalanceAtTimeQuery {
+    #[serde(rename = "accountId")]
+    pub account_id: String,
+    #[serde(rename = "tokenId")]
+    pub token_id: String,
+    /// RFC 3339 date-time (e.g. `2025-06-16T18:05:44Z`) or nanoseconds since the Unix epoch
+    pub timestamp: String,
+    /// Admin only: query this RPC endpoint instead of the archival network.
+    /// Must be listed in `RPC_URL_ALLOWLIST`.
+    pub rpc_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct TokenBalanceAtTimeResponse {
+    pub account_id: String,
+    pub token_id: String,
+    /// Last block at or before the requested time
+    pub block_height: u64,
+    /// Balance in whole token units (decimals applied)
+    pub balance: String,
 }
 
 /// Fetch NEAR balance for an account
@@ -44,7 +88,8 @@ async fn fetch_near_balance(
         account_id: account_id.to_string(),
         token_id: "near".to_string(),
         balance: balance.total.as_yoctonear().to_string(),
-        decimals: 24,
+        decimals: NEAR_DECIMALS,
+        block_height: None,
     })
 }
 
@@ -71,6 +116,7 @@ async fn fetch_ft_balance(
         token_id: token_id.to_string(),
         balance: balance.amount().to_string(),
         decimals: balance.decimals(),
+        block_height: None,
     })
 }
 
@@ -79,7 +125,7 @@ pub async fn fetch_intents_balance(
     account_id: AccountId,
     token_id: String,
 ) -> Result<TokenBalanceResponse, String> {
-    let balance: U128 = Contract(INTENTS_CONTRACT_ID.into())
+    let balance: U128 = Contract(state.env_vars.intents_contract_id.clone())
         .call_function(
             "mt_balance_of",
             serde_json::json!({
@@ -120,20 +166,107 @@ pub async fn fetch_intents_balance(
         token_id: token_id.to_string(),
         balance: balance.0.to_string(),
         decimals: metadata.data.decimals,
+        block_height: None,
+    })
+}
+
+/// Fetch the balance of any supported token at a past block
+///
+/// Queries `network` (normally the archival network) through
+/// `balance::get_balance_at_block` and returns raw units, like the current balance lookups.
+async fn fetch_balance_at_block(
+    state: &Arc<AppState>,
+    network: &NetworkConfig,
+    account_id: &AccountId,
+    token_id: &str,
+    block_height: u64,
+) -> Result<TokenBalanceResponse, String> {
+    let query_balance = |balance_token_id: String| async move {
+        get_balance_at_block(
+            &state.db_pool,
+            network,
+            account_id.as_str(),
+            &balance_token_id,
+            block_height,
+        )
+        .await
+        .map_err(|e| {
+            eprintln!(
+                "Error fetching balance of {} / {} at block {}: {}",
+                account_id, balance_token_id, block_height, e
+            );
+            format!("Failed to fetch balance at block {}: {}", block_height, e)
+        })
+    };
+    let decimals_of = |token_id: String| async move {
+        decimals(&state.db_pool, network, &token_id)
+            .await
+            .map_err(|e| format!("Failed to fetch metadata: {}", e))
+    };
+
+    let (balance, decimals) = if token_id == "near" || token_id == "NEAR" {
+        let balance = query_balance("near".to_string()).await?;
+        let raw = convert_decimal_to_raw(&balance, NEAR_DECIMALS).map_err(|e| e.to_string())?;
+        (raw, NEAR_DECIMALS)
+    } else if token_id.starts_with("nep141:") {
+        // Intents balances are stored as raw amounts already
+        let intents_token_id = format!("{}:{}", INTENTS_CONTRACT_ID, token_id);
+        let raw = query_balance(intents_token_id.clone()).await?;
+        (raw, decimals_of(intents_token_id).await?)
+    } else {
+        let decimals = decimals_of(token_id.to_string()).await?;
+        let balance = query_balance(token_id.to_string()).await?;
+        let raw = convert_decimal_to_raw(&balance, decimals).map_err(|e| e.to_string())?;
+        (raw, decimals)
+    };
+
+    Ok(TokenBalanceResponse {
+        account_id: account_id.to_string(),
+        token_id: token_id.to_string(),
+        balance,
+        decimals,
+        block_height: Some(block_height),
     })
 }
 
 /// Main handler for token balance endpoint
+///
+/// Returns the current balance, or the balance at `blockHeight` when given.
+/// With `rpc_url`, the `blockHeight` lookup goes to that endpoint and isn't cached;
+/// this requires `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    get,
+    path = "/api/user/balance",
+    tag = "user",
+    params(TokenBalanceQuery, NumericQuery),
+    responses(
+        (status = 200, description = "Token balance", body = TokenBalanceResponse),
+        (status = 400, description = "Invalid token ID, or rpc_url not allowed or given without blockHeight"),
+        (status = 401, description = "rpc_url given without valid admin credentials"),
+        (status = 502, description = "Historical balance lookup failed"),
+    )
+)]
 pub async fn get_token_balance(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Query(params): Query<TokenBalanceQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let account_id = params.account_id;
     let token_id = params.token_id.trim();
 
+    if params.rpc_url.is_some() && params.block_height.is_none() {
+        return Err(ApiError::bad_request("rpc_url requires blockHeight"));
+    }
+    let custom_network = admin_rpc_network(&state.env_vars, &headers, params.rpc_url.as_deref())?;
+
     // Check cache first (short cache for balances as they change frequently)
-    let cache_key = format!("token-balance:{}:{}", account_id, token_id);
-    if let Some(cached_data) = state.cache.get(&cache_key).await {
+    let cache_key = match params.block_height {
+        Some(block_height) => format!("token-balance:{}:{}:{}", account_id, token_id, block_height),
+        None => format!("token-balance:{}:{}", account_id, token_id),
+    };
+    if custom_network.is_none()
+        && let Some(cached_data) = state.cache.get(&cache_key).await
+    {
         println!(
             "🔁 Returning cached balance for {} / {}",
             account_id, token_id
@@ -144,43 +277,318 @@ pub async fn get_token_balance(
     // Determine if it's NEAR or FT token
     let is_near = token_id == "near" || token_id == "NEAR";
 
-    let response = if is_near {
-        fetch_near_balance(&state, account_id).await.map_err(|e| {
+    let response = if let Some(block_height) = params.block_height {
+        if !is_near && !token_id.starts_with("nep141:") {
+            token_id.parse::<AccountId>().map_err(|e| {
+                eprintln!("Invalid token ID '{}': {}", token_id, e);
+                ApiError::bad_request(format!("Invalid token ID: {}", e))
+            })?;
+        }
+        with_timeout(
+            state.external_timeout(),
+            "RPC",
+            fetch_balance_at_block(
+                &state,
+                custom_network.as_ref().unwrap_or(&state.archival_network),
+                &account_id,
+                token_id,
+                block_height,
+            ),
+        )
+        .await?
+        .map_err(|e| ApiError::new(StatusCode::BAD_GATEWAY, e))?
+    } else if is_near {
+        with_timeout(
+            state.external_timeout(),
+            "RPC",
+            fetch_near_balance(&state, account_id),
+        )
+        .await?
+        .map_err(|e| {
             eprintln!("Error fetching NEAR balance: {}", e);
-            (StatusCode::INTERNAL_SERVER_ERROR, e)
+            ApiError::internal(e)
         })?
     } else if token_id.starts_with("nep141:") {
-        fetch_intents_balance(&state, account_id, token_id.to_string())
-            .await
-            .map_err(|e| {
-                eprintln!("Error fetching Intents balance: {}", e);
-                (StatusCode::INTERNAL_SERVER_ERROR, e)
-            })?
+        with_timeout(
+            state.external_timeout(),
+            "RPC",
+            fetch_intents_balance(&state, account_id, token_id.to_string()),
+        )
+        .await?
+        .map_err(|e| {
+            eprintln!("Error fetching Intents balance: {}", e);
+            ApiError::internal(e)
+        })?
     } else {
         // Parse token_id as AccountId
         let token_account_id: AccountId = token_id.parse().map_err(|e| {
             eprintln!("Invalid token ID '{}': {}", token_id, e);
-            (StatusCode::BAD_REQUEST, format!("Invalid token ID: {}", e))
+            ApiError::bad_request(format!("Invalid token ID: {}", e))
         })?;
 
-        fetch_ft_balance(&state, account_id, token_account_id)
-            .await
-            .map_err(|e| {
-                eprintln!("Error fetching token balance: {}", e);
-                (StatusCode::INTERNAL_SERVER_ERROR, e)
-            })?
+        with_timeout(
+            state.external_timeout(),
+            "RPC",
+            fetch_ft_balance(&state, account_id, token_account_id),
+        )
+        .await?
+        .map_err(|e| {
+            eprintln!("Error fetching token balance: {}", e);
+            ApiError::internal(e)
+        })?
     };
 
     let result_value = serde_json::to_value(&response).map_err(|e| {
         eprintln!("Error serializing token balance: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to serialize token balance".to_string(),
-        )
+        ApiError::internal("Failed to serialize token balance")
     })?;
 
     // Cache for 30 seconds (balances change frequently)
-    state.cache.insert(cache_key, result_value.clone()).await;
+    if custom_network.is_none() {
+        state.cache.insert(cache_key, result_value.clone()).await;
+    }
 
     Ok((StatusCode::OK, Json(result_value)))
 }
+
+/// Token balance at a point in time
+///
+/// Resolves the last block at or before `timestamp` and returns the balance there.
+/// With `rpc_url`, both lookups go to that endpoint; this requires
+/// `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    get,
+    path = "/api/user/balance/at-time",
+    tag = "user",
+    params(TokenBalanceAtTimeQuery, NumericQuery),
+    responses(
+        (status = 200, description = "Token balance at the given time", body = TokenBalanceAtTimeResponse),
+        (status = 400, description = "Invalid timestamp, before the first block, or rpc_url not allowed"),
+        (status = 401, description = "rpc_url given without valid admin credentials"),
+        (status = 502, description = "Block or balance lookups failed"),
+    )
+)]
+pub async fn get_token_balance_at_time(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<TokenBalanceAtTimeQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let custom_network = admin_rpc_network(&state.env_vars, &headers, params.rpc_url.as_deref())?;
+    let network = custom_network.as_ref().unwrap_or(&state.archival_network);
+
+    let timestamp = parse_timestamp(&params.timestamp).ok_or(ApiError::bad_request(
+        "timestamp must be RFC 3339 or nanoseconds since the Unix epoch",
+    ))?;
+    let token_id = params.token_id.trim();
+
+    let (block_height, balance) = get_balance_at_time(
+        &state.db_pool,
+        network,
+        &params.account_id,
+        token_id,
+        timestamp,
+    )
+    .await
+    .map_err(|e| {
+        eprintln!(
+            "Error fetching balance of {} / {} at {}: {}",
+            params.account_id, token_id, timestamp, e
+        );
+        ApiError::new(
+            StatusCode::BAD_GATEWAY,
+            format!("Failed to fetch balance: {}", e),
+        )
+    })?
+    .ok_or(ApiError::bad_request("timestamp is before the first block"))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(TokenBalanceAtTimeResponse {
+            account_id: params.account_id,
+            token_id: token_id.to_string(),
+            block_height,
+            balance,
+        }),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{init_test_state, spawn_mock_rpc};
+    use axum::http::header;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// RPC endpoint that counts requests and fails all of them
+    async fn spawn_counting_rpc() -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let network = spawn_mock_rpc(move || {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+        })
+        .await;
+        (network.rpc_endpoints[0].url.to_string(), requests)
+    }
+
+    /// Block from which the mocked account holds 11 NEAR instead of 5
+    const CHANGE_BLOCK: u64 = 150_000_000;
+
+    /// JSON-RPC node answering `view_account` for an account that received 6 NEAR
+    async fn view_account_rpc(Json(request): Json<serde_json::Value>) -> Json<serde_json::Value> {
+        let params = &request["params"];
+        // Queries without a block_id are for the latest (final/optimistic) block
+        let block_height = params["block_id"].as_u64().unwrap_or(CHANGE_BLOCK + 1_000);
+        let amount = if block_height >= CHANGE_BLOCK {
+            "11000000000000000000000000"
+        } else {
+            "5000000000000000000000000"
+        };
+
+        Json(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": request["id"],
+            "result": {
+                "amount": amount,
+                "locked": "0",
+                "code_hash": "11111111111111111111111111111111",
+                "storage_usage": 100,
+                "storage_paid_at": 0,
+                "block_height": block_height,
+                "block_hash": "11111111111111111111111111111111"
+            }
+        }))
+    }
+
+    async fn balance_body(state: &Arc<AppState>, block_height: Option<u64>) -> serde_json::Value {
+        let response = get_token_balance(
+            State(state.clone()),
+            HeaderMap::new(),
+            Query(TokenBalanceQuery {
+                account_id: "receiver.near".parse().unwrap(),
+                token_id: "near".to_string(),
+                block_height,
+                rpc_url: None,
+            }),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_balance_at_block_differs_from_current() {
+        let network = spawn_mock_rpc(view_account_rpc).await;
+        let mut state = init_test_state().await;
+        state.network = network.clone();
+        state.archival_network = network;
+        let state = Arc::new(state);
+
+        let current = balance_body(&state, None).await;
+        assert_eq!(current["balance"], "11000000000000000000000000");
+        assert_eq!(current["decimals"], 24);
+        assert!(current.get("block_height").is_none());
+
+        let historical = balance_body(&state, Some(CHANGE_BLOCK - 1)).await;
+        assert_eq!(historical["balance"], "5000000000000000000000000");
+        assert_eq!(historical["decimals"], 24);
+        assert_eq!(historical["block_height"], CHANGE_BLOCK - 1);
+    }
+
+    #[tokio::test]
+    async fn test_custom_rpc_url_is_admin_only_and_allowlisted() {
+        let (allowed_url, requests) = spawn_counting_rpc().await;
+
+        let mut state = init_test_state().await;
+        state.env_vars.admin_api_key = Some("admin-key".to_string());
+        state.env_vars.rpc_url_allowlist = vec![allowed_url.clone()];
+        let state = Arc::new(state);
+
+        let mut admin_headers = HeaderMap::new();
+        admin_headers.insert(header::AUTHORIZATION, "Bearer admin-key".parse().unwrap());
+
+        let query = |rpc_url: &str| {
+            Query(TokenBalanceAtTimeQuery {
+                account_id: "webassemblymusic-treasury.sputnik-dao.near".to_string(),
+                token_id: "near".to_string(),
+                timestamp: "1750097144159145697".to_string(),
+                rpc_url: Some(rpc_url.to_string()),
+            })
+        };
+
+        // Without admin credentials
+        let Err(ApiError { status, .. }) =
+            get_token_balance_at_time(State(state.clone()), HeaderMap::new(), query(&allowed_url))
+                .await
+        else {
+            panic!("rpc_url requires admin credentials");
+        };
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Not on the allowlist
+        let Err(ApiError { status, .. }) = get_token_balance_at_time(
+            State(state.clone()),
+            admin_headers.clone(),
+            query("https://rpc.example.com/"),
+        )
+        .await
+        else {
+            panic!("rpc_url must be allowlisted");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+
+        // The allowed endpoint is queried (and its failure surfaces as 502)
+        let Err(ApiError { status, .. }) =
+            get_token_balance_at_time(State(state.clone()), admin_headers, query(&allowed_url))
+                .await
+        else {
+            panic!("The mocked endpoint fails every request");
+        };
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert!(requests.load(Ordering::SeqCst) > 0);
+
+        // The blockHeight balance accepts the same parameter, only for historical lookups
+        let at_block = |block_height| {
+            Query(TokenBalanceQuery {
+                account_id: "webassemblymusic-treasury.sputnik-dao.near"
+                    .parse()
+                    .unwrap(),
+                token_id: "near".to_string(),
+                block_height,
+                rpc_url: Some(allowed_url.clone()),
+            })
+        };
+        let Err(ApiError { status, .. }) =
+            get_token_balance(State(state.clone()), HeaderMap::new(), at_block(Some(1))).await
+        else {
+            panic!("rpc_url requires admin credentials");
+        };
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let mut admin_headers = HeaderMap::new();
+        admin_headers.insert(header::AUTHORIZATION, "Bearer admin-key".parse().unwrap());
+        let Err(ApiError { status, .. }) =
+            get_token_balance(State(state.clone()), admin_headers.clone(), at_block(None)).await
+        else {
+            panic!("rpc_url requires blockHeight");
+        };
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let queried = requests.load(Ordering::SeqCst);
+        let Err(ApiError { status, .. }) =
+            get_token_balance(State(state.clone()), admin_headers, at_block(Some(1))).await
+        else {
+            panic!("The mocked endpoint fails every request");
+        };
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert!(requests.load(Ordering::SeqCst) > queried);
+    }
+}
@@ -1,25 +1,40 @@
 use axum::{
     Json,
     extract::{Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
 };
-use near_api::{AccountId, Chain, FTBalance, Reference, Tokens, W_NEAR_BALANCE};
+use near_api::{AccountId, Chain, FTBalance, NetworkConfig, Reference, Tokens, W_NEAR_BALANCE};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::{AppState, constants::BLOCKS_PER_HOUR};
+use crate::AppState;
+use crate::handlers::token::aliases;
+use crate::utils::admin_auth::admin_rpc_network;
+use crate::utils::api_error::ApiError;
+use crate::utils::numeric::NumericQuery;
+use crate::utils::timeout::with_timeout;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct TokenBalanceHistoryQuery {
+    #[param(value_type = String)]
     #[serde(rename = "accountId")]
     pub account_id: AccountId,
+    #[param(value_type = String)]
     #[serde(rename = "tokenId")]
     pub token_id: AccountId,
+    /// Merge the balances of aliased contracts (see `/api/admin/token-aliases`) into one series
+    #[serde(default, rename = "mergeAliases", alias = "merge_aliases")]
+    pub merge_aliases: bool,
+    /// Admin only: query every point on this RPC endpoint instead of the regular and
+    /// archival networks. Must be listed in `RPC_URL_ALLOWLIST`.
+    pub rpc_url: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct BalanceHistoryEntry {
     pub timestamp: u64,
     pub date: String,
@@ -94,18 +109,34 @@ impl Period {
     }
 }
 
+/// Network to query a block on
+///
+/// The admin-specified network if any, otherwise the regular or archival network
+/// depending on how far behind the head the block is.
+fn network_at<'a>(
+    state: &'a AppState,
+    custom_network: Option<&'a NetworkConfig>,
+    block_height: u64,
+    current_block: u64,
+) -> &'a NetworkConfig {
+    custom_network.unwrap_or_else(|| state.network_for_block(block_height, current_block))
+}
+
 /// Fetches current block height and timestamp
-async fn fetch_current_block(state: &Arc<AppState>) -> Result<(u64, u64), (StatusCode, String)> {
-    let block = Chain::block()
-        .fetch_from(&state.network)
-        .await
-        .map_err(|e| {
-            eprintln!("Error fetching current block: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch current block: {}", e),
-            )
-        })?;
+async fn fetch_current_block(
+    state: &Arc<AppState>,
+    custom_network: Option<&NetworkConfig>,
+) -> Result<(u64, u64), ApiError> {
+    let block = with_timeout(
+        state.external_timeout(),
+        "RPC",
+        Chain::block().fetch_from(custom_network.unwrap_or(&state.network)),
+    )
+    .await?
+    .map_err(|e| {
+        eprintln!("Error fetching current block: {}", e);
+        ApiError::internal(format!("Failed to fetch current block: {}", e))
+    })?;
 
     Ok((block.header.height, block.header.timestamp / 1_000_000))
 }
@@ -113,19 +144,27 @@ async fn fetch_current_block(state: &Arc<AppState>) -> Result<(u64, u64), (Statu
 /// Fetches the timestamp for a specific block
 async fn fetch_block_timestamp(
     state: &Arc<AppState>,
+    custom_network: Option<&NetworkConfig>,
     block_height: u64,
-) -> Result<u64, (StatusCode, String)> {
-    let block = Chain::block()
-        .at(Reference::AtBlock(block_height))
-        .fetch_from(&state.archival_network)
-        .await
-        .map_err(|e| {
-            eprintln!("Error fetching block {}: {}", block_height, e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch block {}: {}", block_height, e),
-            )
-        })?;
+    current_block: u64,
+) -> Result<u64, ApiError> {
+    let block = with_timeout(
+        state.external_timeout(),
+        "RPC",
+        Chain::block()
+            .at(Reference::AtBlock(block_height))
+            .fetch_from(network_at(
+                state,
+                custom_network,
+                block_height,
+                current_block,
+            )),
+    )
+    .await?
+    .map_err(|e| {
+        eprintln!("Error fetching block {}: {}", block_height, e);
+        ApiError::internal(format!("Failed to fetch block {}: {}", block_height, e))
+    })?;
 
     Ok(block.header.timestamp / 1_000_000)
 }
@@ -133,92 +172,206 @@ async fn fetch_block_timestamp(
 /// Fetches NEAR balance for an account at a specific block
 async fn fetch_near_balance(
     state: &Arc<AppState>,
+    custom_network: Option<&NetworkConfig>,
     account_id: AccountId,
     block_height: u64,
-) -> Result<FTBalance, (StatusCode, String)> {
-    let balance = Tokens::account(account_id.clone())
-        .near_balance()
-        .at(Reference::AtBlock(block_height))
-        .fetch_from(&state.archival_network)
-        .await
-        .map_err(|e| {
-            eprintln!(
-                "Error fetching near balance for {} at block {}: {}",
-                account_id, block_height, e
-            );
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch token balance: {}", e),
-            )
-        })?;
+    current_block: u64,
+) -> Result<FTBalance, ApiError> {
+    let balance = with_timeout(
+        state.external_timeout(),
+        "RPC",
+        Tokens::account(account_id.clone())
+            .near_balance()
+            .at(Reference::AtBlock(block_height))
+            .fetch_from(network_at(
+                state,
+                custom_network,
+                block_height,
+                current_block,
+            )),
+    )
+    .await?
+    .map_err(|e| {
+        eprintln!(
+            "Error fetching near balance for {} at block {}: {}",
+            account_id, block_height, e
+        );
+        ApiError::internal(format!("Failed to fetch token balance: {}", e))
+    })?;
 
     Ok(W_NEAR_BALANCE.with_amount(balance.total.as_yoctonear()))
 }
 
+/// Whether an RPC error means the token contract wasn't deployed at the queried block
+fn is_contract_missing(error: &str) -> bool {
+    error.contains("NoContractCode")
+        || error.contains("CodeDoesNotExist")
+        || error.contains("UnknownAccount")
+}
+
 /// Fetches fungible token balance for an account at a specific block
+///
+/// # Returns
+/// * `Some(balance)` - The balance at the block
+/// * `None` - The token contract wasn't deployed at the block
 async fn fetch_ft_balance(
     state: &Arc<AppState>,
+    custom_network: Option<&NetworkConfig>,
     account_id: AccountId,
     token_id: AccountId,
     block_height: u64,
-) -> Result<FTBalance, (StatusCode, String)> {
-    let balance = Tokens::account(account_id.clone())
-        .ft_balance(token_id.clone())
-        .at(Reference::AtBlock(block_height))
-        .fetch_from(&state.archival_network)
-        .await
-        .map_err(|e| {
+    current_block: u64,
+) -> Result<Option<FTBalance>, ApiError> {
+    let result = with_timeout(
+        state.external_timeout(),
+        "RPC",
+        Tokens::account(account_id.clone())
+            .ft_balance(token_id.clone())
+            .at(Reference::AtBlock(block_height))
+            .fetch_from(network_at(
+                state,
+                custom_network,
+                block_height,
+                current_block,
+            )),
+    )
+    .await?;
+
+    match result {
+        Ok(balance) => Ok(Some(balance)),
+        Err(e) if is_contract_missing(&e.to_string()) => Ok(None),
+        Err(e) => {
             eprintln!(
                 "Error fetching ft_balance_of for {} on {} at block {}: {}",
                 account_id, token_id, block_height, e
             );
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Failed to fetch token balance: {}", e),
-            )
-        })?;
+            Err(ApiError::internal(format!(
+                "Failed to fetch token balance: {}",
+                e
+            )))
+        }
+    }
+}
 
-    Ok(balance)
+/// Sums balances of the contracts of an aliased token
+///
+/// Balances are scaled to the largest decimals among them, so a migration that
+/// changed the decimals doesn't distort the series.
+fn merge_balances(balances: &[FTBalance]) -> Option<FTBalance> {
+    let decimals = balances.iter().map(|b| b.decimals()).max()?;
+    let amount = balances.iter().fold(0u128, |total, balance| {
+        let scale = 10u128.saturating_pow((decimals - balance.decimals()) as u32);
+        total.saturating_add(balance.amount().saturating_mul(scale))
+    });
+
+    Some(FTBalance::with_decimals(decimals).with_amount(amount))
+}
+
+/// Fetches the combined fungible token balance of all contracts of a token at a specific block
+///
+/// Contracts not deployed yet at the block are left out. Any other failure fails the
+/// balance, so a partial sum is never reported.
+///
+/// # Returns
+/// * `Some(balance)` - The summed balance of the deployed contracts
+/// * `None` - None of the contracts was deployed at the block
+async fn fetch_merged_ft_balance(
+    state: &Arc<AppState>,
+    custom_network: Option<&NetworkConfig>,
+    account_id: AccountId,
+    token_ids: Vec<AccountId>,
+    block_height: u64,
+    current_block: u64,
+) -> Result<Option<FTBalance>, ApiError> {
+    let results = futures::future::join_all(token_ids.into_iter().map(|token_id| {
+        fetch_ft_balance(
+            state,
+            custom_network,
+            account_id.clone(),
+            token_id,
+            block_height,
+            current_block,
+        )
+    }))
+    .await;
+
+    let balances: Vec<FTBalance> = results
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+
+    Ok(merge_balances(&balances))
+}
+
+/// Balance history of a single period
+struct PeriodHistory {
+    entries: Vec<BalanceHistoryEntry>,
+    /// Whether any point was left out because fetching it failed
+    has_failed_points: bool,
 }
 
 /// Fetches balance history for a single period
+///
+/// `token_ids` holds either `near`, or the FT contracts whose balances are summed.
+/// Points before the token existed are left out; points that failed to fetch are
+/// left out too, and flagged so the history isn't cached.
 async fn fetch_period_history(
     state: &Arc<AppState>,
+    custom_network: Option<&NetworkConfig>,
     account_id: AccountId,
-    token_id: AccountId,
+    token_ids: Vec<AccountId>,
     period: &Period,
     current_block: u64,
-) -> Result<Vec<BalanceHistoryEntry>, (StatusCode, String)> {
+) -> Result<PeriodHistory, ApiError> {
     let hours_per_step = (period.hours as f64) / (period.interval as f64);
-    let blocks_per_step = (hours_per_step * BLOCKS_PER_HOUR as f64).floor() as u64;
+    let blocks_per_step = (hours_per_step * state.blocks_per_hour.get() as f64).floor() as u64;
 
     let block_heights = (0..period.interval).map(|i| current_block - (blocks_per_step * i));
 
     // Fetch timestamps and balances for all blocks in parallel
-    let is_near = token_id == "near";
+    let is_near = token_ids.iter().any(|token_id| token_id == "near");
     let futures: Vec<_> = block_heights
         .map(|block_height| {
             let state = state.clone();
             let account_id = account_id.clone();
-            let token_id = token_id.clone();
+            let token_ids = token_ids.clone();
             async move {
                 let (timestamp_result, balance_result) = if is_near {
                     tokio::join!(
-                        fetch_block_timestamp(&state, block_height),
-                        fetch_near_balance(&state, account_id, block_height)
+                        fetch_block_timestamp(&state, custom_network, block_height, current_block),
+                        async {
+                            fetch_near_balance(
+                                &state,
+                                custom_network,
+                                account_id,
+                                block_height,
+                                current_block,
+                            )
+                            .await
+                            .map(Some)
+                        }
                     )
                 } else {
                     tokio::join!(
-                        fetch_block_timestamp(&state, block_height),
-                        fetch_ft_balance(&state, account_id, token_id, block_height)
+                        fetch_block_timestamp(&state, custom_network, block_height, current_block),
+                        fetch_merged_ft_balance(
+                            &state,
+                            custom_network,
+                            account_id,
+                            token_ids,
+                            block_height,
+                            current_block
+                        )
                     )
                 };
 
                 match (timestamp_result, balance_result) {
-                    (Ok(timestamp), Ok(balance)) => Some((timestamp, balance)),
+                    (Ok(timestamp), Ok(balance)) => Ok(balance.map(|balance| (timestamp, balance))),
                     (a, b) => {
                         eprintln!("ERROR: {a:?}\n{b:?}");
-                        None
+                        Err(())
                     }
                 }
             }
@@ -226,9 +379,11 @@ async fn fetch_period_history(
         .collect();
 
     let results = futures::future::join_all(futures).await;
+    let has_failed_points = results.iter().any(Result::is_err);
     let entries: Vec<BalanceHistoryEntry> = results
         .into_iter()
         .flatten()
+        .flatten()
         .map(|(timestamp, balance)| BalanceHistoryEntry {
             timestamp,
             date: period.format_timestamp(timestamp),
@@ -237,21 +392,76 @@ async fn fetch_period_history(
         })
         .collect();
 
-    Ok(entries)
+    Ok(PeriodHistory {
+        entries,
+        has_failed_points,
+    })
+}
+
+/// Contracts whose balances make up the series of `token_id`
+async fn resolve_token_ids(
+    state: &Arc<AppState>,
+    token_id: &AccountId,
+    merge_aliases: bool,
+) -> Result<Vec<AccountId>, ApiError> {
+    if !merge_aliases || token_id == "near" {
+        return Ok(vec![token_id.clone()]);
+    }
+
+    let group = aliases::alias_group(&state.db_pool, token_id.as_str())
+        .await
+        .map_err(|e| {
+            log::error!("Failed to load aliases of {}: {}", token_id, e);
+            ApiError::internal("Failed to load token aliases").with_details(e.to_string())
+        })?;
+
+    Ok(group
+        .into_iter()
+        .filter_map(|contract| match contract.parse::<AccountId>() {
+            Ok(contract) => Some(contract),
+            Err(e) => {
+                log::warn!("Skipping invalid alias {} of {}: {}", contract, token_id, e);
+                None
+            }
+        })
+        .collect())
 }
 
 /// Main handler for token balance history endpoint
+///
+/// With `mergeAliases=true`, the balances of all contracts aliased to `tokenId` are
+/// summed into one series. With `rpc_url`, every point is queried on that endpoint
+/// and the history isn't cached; this requires `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    get,
+    path = "/api/user/balance/history",
+    tag = "user",
+    params(TokenBalanceHistoryQuery, NumericQuery),
+    responses(
+        (status = 200, description = "Balance history keyed by period", body = HashMap<String, Vec<BalanceHistoryEntry>>),
+        (status = 400, description = "rpc_url not allowed"),
+        (status = 401, description = "rpc_url given without valid admin credentials"),
+    )
+)]
 pub async fn get_token_balance_history(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Query(params): Query<TokenBalanceHistoryQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let account_id = &params.account_id;
     let token_id = &params.token_id;
+    let custom_network = admin_rpc_network(&state.env_vars, &headers, params.rpc_url.as_deref())?;
+    let custom_network = custom_network.as_ref();
 
-    let cache_key = format!("balance-history:{}:{}", account_id, token_id);
+    let mut cache_key = format!("balance-history:{}:{}", account_id, token_id);
+    if params.merge_aliases {
+        cache_key.push_str(":merged");
+    }
 
     // Check cache
-    if let Some(cached_data) = state.cache.get(&cache_key).await {
+    if custom_network.is_none()
+        && let Some(cached_data) = state.cache.get(&cache_key).await
+    {
         println!(
             "🔁 Returning cached balance history for {} / {}",
             account_id, token_id
@@ -259,20 +469,28 @@ pub async fn get_token_balance_history(
         return Ok((StatusCode::OK, Json(cached_data)));
     }
 
+    let token_ids = resolve_token_ids(&state, token_id, params.merge_aliases).await?;
+
     // Fetch current block
-    let (current_block, _current_timestamp) = fetch_current_block(&state).await?;
+    let (current_block, _current_timestamp) = fetch_current_block(&state, custom_network).await?;
 
     // Fetch balance history for all periods concurrently
     let mut period_futures = Vec::new();
     for period in Period::DEFAULT {
         let state_clone = state.clone();
         let account_id = account_id.clone();
-        let token_id = token_id.clone();
+        let token_ids = token_ids.clone();
 
         period_futures.push(async move {
-            let result =
-                fetch_period_history(&state_clone, account_id, token_id, period, current_block)
-                    .await;
+            let result = fetch_period_history(
+                &state_clone,
+                custom_network,
+                account_id,
+                token_ids,
+                period,
+                current_block,
+            )
+            .await;
             (period.name, result)
         });
     }
@@ -282,15 +500,21 @@ pub async fn get_token_balance_history(
 
     // Build response map
     let mut response: HashMap<String, Vec<BalanceHistoryEntry>> = HashMap::new();
+    let mut complete = true;
 
     for (period_name, result) in period_results {
         match result {
-            Ok(mut entries) => {
+            Ok(PeriodHistory {
+                mut entries,
+                has_failed_points,
+            }) => {
+                complete &= !has_failed_points;
                 entries.sort_by_key(|e| e.timestamp);
                 response.insert(period_name.to_string(), entries);
             }
             Err(_) => {
                 // If a period fails, insert empty array
+                complete = false;
                 response.insert(period_name.to_string(), Vec::new());
             }
         }
@@ -298,13 +522,187 @@ pub async fn get_token_balance_history(
 
     let result_value = serde_json::to_value(&response).map_err(|e| {
         eprintln!("Error serializing balance history: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to serialize balance history".to_string(),
-        )
+        ApiError::internal("Failed to serialize balance history")
     })?;
 
-    state.cache.insert(cache_key, result_value.clone()).await;
+    // An incomplete history is served but not cached, so the next request retries it
+    if complete && custom_network.is_none() {
+        state.cache.insert(cache_key, result_value.clone()).await;
+    }
 
     Ok((StatusCode::OK, Json(result_value)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::{init_test_state, spawn_mock_rpc};
+    use sqlx::PgPool;
+
+    const HEAD_BLOCK: u64 = 200_000_000;
+    /// Block from which the token lives on `new-token.near` instead of `old-token.near`
+    const MIGRATION_BLOCK: u64 = 199_000_000;
+
+    /// JSON-RPC node for a token that migrated from a 6 to an 18 decimals contract
+    async fn migrated_token_rpc(Json(request): Json<serde_json::Value>) -> Json<serde_json::Value> {
+        use near_primitives::views::{BlockHeaderView, BlockView};
+        use serde_json::json;
+
+        let params = &request["params"];
+        let block_height = params["block_id"].as_u64().unwrap_or(HEAD_BLOCK);
+        let migrated = block_height >= MIGRATION_BLOCK;
+
+        // Deployed only after the head block
+        if params["account_id"] == "pre-token.near" {
+            return Json(json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": {
+                    "error": "wasm execution failed with error: CompilationError(CodeDoesNotExist { account_id: AccountId(\"pre-token.near\") })",
+                    "logs": [],
+                    "block_height": block_height,
+                    "block_hash": "11111111111111111111111111111111"
+                }
+            }));
+        }
+
+        let result = if request["method"] == "block" {
+            serde_json::to_value(BlockView {
+                author: "validator.near".parse().unwrap(),
+                header: BlockHeaderView {
+                    height: block_height,
+                    timestamp: block_height * 1_000_000_000,
+                    timestamp_nanosec: block_height * 1_000_000_000,
+                    ..Default::default()
+                },
+                chunks: vec![],
+            })
+            .unwrap()
+        } else {
+            let value = match (
+                params["account_id"].as_str(),
+                params["method_name"].as_str(),
+            ) {
+                (Some(contract), Some("ft_metadata")) => json!({
+                    "spec": "ft-1.0.0",
+                    "name": contract,
+                    "symbol": "TKN",
+                    "icon": null,
+                    "reference": null,
+                    "reference_hash": null,
+                    "decimals": if contract == "old-token.near" { 6 } else { 18 }
+                }),
+                (Some("old-token.near"), Some("ft_balance_of")) => {
+                    json!(if migrated { "0" } else { "1000000" })
+                }
+                (Some("new-token.near"), Some("ft_balance_of")) => {
+                    json!(if migrated { "3000000000000000000" } else { "0" })
+                }
+                _ => {
+                    return Json(json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": {"code": -32000, "message": "Server error", "data": "unsupported"}
+                    }));
+                }
+            };
+            json!({
+                "result": serde_json::to_vec(&value).unwrap(),
+                "logs": [],
+                "block_height": block_height,
+                "block_hash": "11111111111111111111111111111111"
+            })
+        };
+
+        Json(json!({"jsonrpc": "2.0", "id": request["id"], "result": result}))
+    }
+
+    async fn yearly_balances(state: &Arc<AppState>, merge_aliases: bool) -> Vec<(String, u8)> {
+        let response = get_token_balance_history(
+            State(state.clone()),
+            HeaderMap::new(),
+            Query(TokenBalanceHistoryQuery {
+                account_id: "holder.near".parse().unwrap(),
+                token_id: "new-token.near".parse().unwrap(),
+                merge_aliases,
+                rpc_url: None,
+            }),
+        )
+        .await
+        .unwrap()
+        .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let history: HashMap<String, Vec<BalanceHistoryEntry>> =
+            serde_json::from_slice(&body).unwrap();
+
+        history["1Y"]
+            .iter()
+            .map(|entry| (entry.balance.clone(), entry.decimals))
+            .collect()
+    }
+
+    #[sqlx::test]
+    async fn test_aliased_contracts_merge_into_one_series(pool: PgPool) -> sqlx::Result<()> {
+        aliases::register_alias(&pool, "old-token.near", "new-token.near").await?;
+
+        let network = spawn_mock_rpc(migrated_token_rpc).await;
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        state.network = network.clone();
+        state.archival_network = network;
+        let state = Arc::new(state);
+
+        let merged = yearly_balances(&state, true).await;
+        assert_eq!(merged.len(), 12);
+        assert_eq!(
+            merged.first().unwrap(),
+            &("1000000000000000000".to_string(), 18),
+            "Balance on the old contract is scaled to the new decimals"
+        );
+        assert_eq!(
+            merged.last().unwrap(),
+            &("3000000000000000000".to_string(), 18)
+        );
+
+        let unmerged = yearly_balances(&state, false).await;
+        assert_eq!(unmerged.first().unwrap(), &("0".to_string(), 18));
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_failing_alias_fails_merged_points(pool: PgPool) -> sqlx::Result<()> {
+        // Not deployed yet: counts as no balance
+        aliases::register_alias(&pool, "pre-token.near", "new-token.near").await?;
+
+        let network = spawn_mock_rpc(migrated_token_rpc).await;
+        let mut state = init_test_state().await;
+        state.db_pool = pool.clone();
+        state.network = network.clone();
+        state.archival_network = network;
+        let state = Arc::new(state);
+
+        let merged = yearly_balances(&state, true).await;
+        assert_eq!(merged.len(), 12);
+        assert_eq!(
+            merged.last().unwrap(),
+            &("3000000000000000000".to_string(), 18)
+        );
+        state.cache.invalidate_prefix("balance-history:").await;
+
+        // The RPC node fails for this contract: no partial sums, and nothing cached
+        aliases::register_alias(&pool, "broken-token.near", "new-token.near").await?;
+        assert!(yearly_balances(&state, true).await.is_empty());
+        assert!(
+            state
+                .cache
+                .get("balance-history:holder.near:new-token.near:merged")
+                .await
+                .is_none()
+        );
+
+        Ok(())
+    }
+}
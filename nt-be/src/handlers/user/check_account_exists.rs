@@ -1,46 +1,301 @@
 use axum::{
     Json,
     extract::{Query, State},
-    http::StatusCode,
 };
+use futures::{StreamExt, stream};
 use near_api::{Account, AccountId};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use serde_json::json;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+use utoipa::{IntoParams, ToSchema};
 
 use crate::AppState;
+use crate::utils::api_error::ApiError;
+use crate::utils::timeout::with_timeout;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct CheckAccountExistsQuery {
+    #[param(value_type = String)]
     #[serde(rename = "accountId")]
     pub account_id: AccountId,
 }
 
-#[derive(Serialize)]
+/// Maximum number of RPC lookups run concurrently by the batch endpoint
+const BATCH_LOOKUP_CONCURRENCY: usize = 10;
+
+/// Max account ids of one batch existence request
+pub const MAX_BATCH_ACCOUNT_IDS: usize = 100;
+
+/// How long a "does not exist" result is trusted, since the account may be created at any time
+///
+/// Positive results live for the regular cache TTL.
+pub const NEGATIVE_ACCOUNT_CACHE_TTL_SECONDS: i64 = 30;
+
+#[derive(Serialize, ToSchema)]
 pub struct CheckAccountExistsResponse {
     pub exists: bool,
+    /// Whether the result was served from cache
+    pub cached: bool,
 }
 
-pub async fn check_account_exists(
-    State(state): State<Arc<AppState>>,
-    Query(params): Query<CheckAccountExistsQuery>,
-) -> Result<Json<CheckAccountExistsResponse>, (StatusCode, String)> {
-    let account_id = params.account_id;
+fn account_exists_cache_key(account_id: &AccountId) -> String {
+    format!("account-exists:{}", account_id)
+}
 
-    match Account(account_id.clone())
-        .view()
-        .fetch_from(&state.network)
-        .await
+/// Returns the cached existence of an account, if present and not stale
+///
+/// Negative results are stored with the time they were cached and are ignored
+/// once they are older than `NEGATIVE_ACCOUNT_CACHE_TTL_SECONDS`.
+async fn get_cached_account_exists(state: &Arc<AppState>, account_id: &AccountId) -> Option<bool> {
+    let cached = state
+        .cache
+        .get(&account_exists_cache_key(account_id))
+        .await?;
+    let exists = cached.get("exists")?.as_bool()?;
+
+    if !exists {
+        let cached_at = cached.get("cached_at")?.as_i64()?;
+        if chrono::Utc::now().timestamp() - cached_at > NEGATIVE_ACCOUNT_CACHE_TTL_SECONDS {
+            return None;
+        }
+    }
+
+    Some(exists)
+}
+
+async fn cache_account_exists(state: &Arc<AppState>, account_id: &AccountId, exists: bool) {
+    state
+        .cache
+        .insert(
+            account_exists_cache_key(account_id),
+            json!({
+                "exists": exists,
+                "cached_at": chrono::Utc::now().timestamp(),
+            }),
+        )
+        .await;
+}
+
+/// Checks whether an account exists, using the cache for both positive and negative results
+pub async fn lookup_account_exists(
+    state: &Arc<AppState>,
+    account_id: &AccountId,
+) -> Result<CheckAccountExistsResponse, ApiError> {
+    if let Some(exists) = get_cached_account_exists(state, account_id).await {
+        println!("🔁 Returning cached account existence for {}", account_id);
+        return Ok(CheckAccountExistsResponse {
+            exists,
+            cached: true,
+        });
+    }
+
+    let exists = match with_timeout(
+        state.external_timeout(),
+        "RPC",
+        Account(account_id.clone())
+            .view()
+            .fetch_from(&state.network),
+    )
+    .await?
     {
-        Ok(_) => Ok(Json(CheckAccountExistsResponse { exists: true })),
+        Ok(_) => true,
         Err(e) => {
             if e.to_string().contains("UnknownAccount") {
-                Ok(Json(CheckAccountExistsResponse { exists: false }))
+                false
             } else {
-                Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    format!("Failed to check account: {}", e),
-                ))
+                return Err(ApiError::internal(format!(
+                    "Failed to check account: {}",
+                    e
+                )));
             }
         }
+    };
+
+    cache_account_exists(state, account_id, exists).await;
+
+    Ok(CheckAccountExistsResponse {
+        exists,
+        cached: false,
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/user/check-account-exists",
+    tag = "user",
+    params(CheckAccountExistsQuery),
+    responses(
+        (status = 200, description = "Whether the account exists", body = CheckAccountExistsResponse),
+    )
+)]
+pub async fn check_account_exists(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<CheckAccountExistsQuery>,
+) -> Result<Json<CheckAccountExistsResponse>, ApiError> {
+    lookup_account_exists(&state, &params.account_id)
+        .await
+        .map(Json)
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct BatchCheckAccountExistsRequest {
+    #[schema(value_type = Vec<String>)]
+    #[serde(rename = "accountIds")]
+    pub account_ids: Vec<AccountId>,
+}
+
+/// Checks existence of many accounts at once
+///
+/// Lookups go through the existence cache and run with bounded concurrency.
+/// Accounts whose lookup fails are left out of the result.
+/// At most `MAX_BATCH_ACCOUNT_IDS` accounts can be checked per request.
+#[utoipa::path(
+    post,
+    path = "/api/user/check-account-exists/batch",
+    tag = "user",
+    request_body = BatchCheckAccountExistsRequest,
+    responses(
+        (status = 200, description = "Existence keyed by account ID", body = HashMap<String, bool>),
+    )
+)]
+pub async fn check_accounts_exist_batch(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<BatchCheckAccountExistsRequest>,
+) -> Result<Json<HashMap<String, bool>>, ApiError> {
+    if payload.account_ids.is_empty() {
+        return Err(ApiError::bad_request("No account IDs provided"));
+    }
+    if payload.account_ids.len() > MAX_BATCH_ACCOUNT_IDS {
+        return Err(ApiError::bad_request(format!(
+            "At most {} account IDs can be checked at once",
+            MAX_BATCH_ACCOUNT_IDS
+        )));
+    }
+
+    let unique_ids: HashSet<AccountId> = payload.account_ids.into_iter().collect();
+
+    let results: Vec<(AccountId, Option<bool>)> = stream::iter(unique_ids)
+        .map(|account_id| {
+            let state = state.clone();
+            async move {
+                match lookup_account_exists(&state, &account_id).await {
+                    Ok(response) => (account_id, Some(response.exists)),
+                    Err(e) => {
+                        eprintln!("Error checking account {}: {}", account_id, e.message);
+                        (account_id, None)
+                    }
+                }
+            }
+        })
+        .buffer_unordered(BATCH_LOOKUP_CONCURRENCY)
+        .collect()
+        .await;
+
+    let existence = results
+        .into_iter()
+        .filter_map(|(account_id, exists)| exists.map(|exists| (account_id.to_string(), exists)))
+        .collect();
+
+    Ok(Json(existence))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::init_test_state;
+
+    #[tokio::test]
+    async fn test_second_lookup_is_served_from_cache() {
+        let state = Arc::new(init_test_state().await);
+        let account_id: AccountId = "webassemblymusic-treasury.sputnik-dao.near"
+            .parse()
+            .unwrap();
+
+        let first = lookup_account_exists(&state, &account_id).await.unwrap();
+        assert!(first.exists);
+        assert!(!first.cached);
+
+        let second = lookup_account_exists(&state, &account_id).await.unwrap();
+        assert!(second.exists);
+        assert!(second.cached);
+    }
+
+    #[tokio::test]
+    async fn test_stale_negative_result_is_not_served_from_cache() {
+        let state = Arc::new(init_test_state().await);
+        let fresh: AccountId = "fresh-missing.near".parse().unwrap();
+        let stale: AccountId = "stale-missing.near".parse().unwrap();
+
+        cache_account_exists(&state, &fresh, false).await;
+        state
+            .cache
+            .insert(
+                account_exists_cache_key(&stale),
+                json!({
+                    "exists": false,
+                    "cached_at": chrono::Utc::now().timestamp()
+                        - NEGATIVE_ACCOUNT_CACHE_TTL_SECONDS
+                        - 1,
+                }),
+            )
+            .await;
+
+        assert_eq!(get_cached_account_exists(&state, &fresh).await, Some(false));
+        assert_eq!(get_cached_account_exists(&state, &stale).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_batch_rejects_too_many_account_ids() {
+        let state = Arc::new(init_test_state().await);
+        let too_many = (0..=MAX_BATCH_ACCOUNT_IDS)
+            .map(|i| format!("account-{}.near", i).parse().unwrap())
+            .collect();
+
+        let Err(ApiError { status, .. }) = check_accounts_exist_batch(
+            State(state),
+            Json(BatchCheckAccountExistsRequest {
+                account_ids: too_many,
+            }),
+        )
+        .await
+        else {
+            panic!("Oversized batch should be rejected");
+        };
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_batch_check_accounts_exist() {
+        let state = Arc::new(init_test_state().await);
+
+        let Json(existence) = check_accounts_exist_batch(
+            State(state),
+            Json(BatchCheckAccountExistsRequest {
+                account_ids: vec![
+                    "webassemblymusic-treasury.sputnik-dao.near"
+                        .parse()
+                        .unwrap(),
+                    "this-account-definitely-does-not-exist-1234567890.near"
+                        .parse()
+                        .unwrap(),
+                ],
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(existence.len(), 2);
+        assert_eq!(
+            existence.get("webassemblymusic-treasury.sputnik-dao.near"),
+            Some(&true)
+        );
+        assert_eq!(
+            existence.get("this-account-definitely-does-not-exist-1234567890.near"),
+            Some(&false)
+        );
     }
 }
@@ -3,4 +3,5 @@ pub mod balance;
 pub mod balance_history;
 pub mod check_account_exists;
 pub mod profile;
+pub mod tokens;
 pub mod treasuries;
@@ -7,23 +7,33 @@ use axum::{
 use near_api::Contract;
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, sync::Arc};
+use utoipa::{IntoParams, ToSchema};
 
 use crate::AppState;
+use crate::utils::account::{AccountKind, account_kind};
+use crate::utils::api_error::ApiError;
+use crate::utils::timeout::with_timeout;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ProfileQuery {
     #[serde(rename = "accountId")]
     pub account_id: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct BatchProfileQuery {
     #[serde(rename = "accountIds")]
     pub account_ids: String, // Comma-separated account IDs
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// Normalized NEAR Social profile; fields missing or empty in Social DB are `null`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, ToSchema)]
 pub struct ProfileData {
+    /// Whether the account is named, implicit or a sub-account
+    #[serde(rename = "accountKind", default)]
+    pub account_kind: Option<AccountKind>,
     pub name: Option<String>,
     pub image: Option<serde_json::Value>,
     #[serde(rename = "backgroundImage")]
@@ -36,19 +46,23 @@ pub struct ProfileData {
 const SOCIAL_DB_CONTRACT: &str = "social.near";
 
 /// Fetch profile data from NEAR Social DB for a single account
-async fn fetch_profile(state: &Arc<AppState>, account_id: &str) -> Result<ProfileData, String> {
+async fn fetch_profile(state: &Arc<AppState>, account_id: &str) -> Result<ProfileData, ApiError> {
     let keys = vec![format!("{}/profile/**", account_id)];
 
-    let result: serde_json::Value = Contract(SOCIAL_DB_CONTRACT.parse().unwrap())
-        .call_function("get", serde_json::json!({ "keys": keys }))
-        .read_only()
-        .fetch_from(&state.network)
-        .await
-        .map_err(|e| {
-            eprintln!("Error fetching profile for {}: {}", account_id, e);
-            format!("Failed to fetch profile: {}", e)
-        })?
-        .data;
+    let result: serde_json::Value = with_timeout(
+        state.external_timeout(),
+        "Social DB",
+        Contract(SOCIAL_DB_CONTRACT.parse().unwrap())
+            .call_function("get", serde_json::json!({ "keys": keys }))
+            .read_only()
+            .fetch_from(&state.network),
+    )
+    .await?
+    .map_err(|e| {
+        eprintln!("Error fetching profile for {}: {}", account_id, e);
+        ApiError::internal(format!("Failed to fetch profile: {}", e))
+    })?
+    .data;
 
     // Extract profile data from the result
     let profile = result
@@ -57,39 +71,74 @@ async fn fetch_profile(state: &Arc<AppState>, account_id: &str) -> Result<Profil
         .cloned()
         .unwrap_or(serde_json::json!({}));
 
-    let profile_data = ProfileData {
-        name: profile
-            .get("name")
-            .and_then(|v| v.as_str())
-            .map(String::from),
-        image: profile.get("image").cloned(),
-        background_image: profile
-            .get("backgroundImage")
-            .and_then(|v| v.as_str())
-            .map(String::from),
-        description: profile
-            .get("description")
-            .and_then(|v| v.as_str())
-            .map(String::from),
-        linktree: profile.get("linktree").cloned(),
-        tags: profile.get("tags").cloned(),
-    };
-
-    Ok(profile_data)
+    Ok(ProfileData {
+        account_kind: Some(account_kind(account_id)),
+        ..ProfileData::from_social_profile(&profile)
+    })
+}
+
+/// A non-empty string field
+fn string_field(profile: &serde_json::Value, key: &str) -> Option<String> {
+    profile
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+}
+
+/// An object field with at least one non-empty value (e.g. `image` or `linktree`)
+fn object_field(profile: &serde_json::Value, key: &str) -> Option<serde_json::Value> {
+    let object = profile.get(key)?.as_object()?;
+    let has_content = object.values().any(|v| match v {
+        serde_json::Value::Null => false,
+        serde_json::Value::String(s) => !s.trim().is_empty(),
+        serde_json::Value::Object(o) => !o.is_empty(),
+        _ => true,
+    });
+    has_content.then(|| serde_json::Value::Object(object.clone()))
+}
+
+impl ProfileData {
+    /// Build a profile from the `profile` object stored in Social DB
+    ///
+    /// Social DB stores whatever the user's client wrote, so empty strings and empty
+    /// objects are common; they are normalized to `None`.
+    pub fn from_social_profile(profile: &serde_json::Value) -> Self {
+        Self {
+            account_kind: None,
+            name: string_field(profile, "name"),
+            image: object_field(profile, "image"),
+            background_image: string_field(profile, "backgroundImage"),
+            description: string_field(profile, "description"),
+            linktree: object_field(profile, "linktree"),
+            // Tags are stored as keys with empty values, e.g. {"dao": ""}
+            tags: profile
+                .get("tags")
+                .filter(|v| v.as_object().is_some_and(|o| !o.is_empty()))
+                .cloned(),
+        }
+    }
 }
 
 /// Main handler for single profile endpoint
+#[utoipa::path(
+    get,
+    path = "/api/user/profile",
+    tag = "user",
+    params(ProfileQuery),
+    responses(
+        (status = 200, description = "NEAR Social profile", body = ProfileData),
+    )
+)]
 pub async fn get_profile(
     State(state): State<Arc<AppState>>,
     Query(params): Query<ProfileQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let account_id = params.account_id.trim();
 
     if account_id.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "account_id is required".to_string(),
-        ));
+        return Err(ApiError::bad_request("account_id is required"));
     }
 
     let cache_key = format!("profile:{}", account_id);
@@ -102,17 +151,11 @@ pub async fn get_profile(
 
     println!("🚨 Fetching profile from Social DB for: {}", account_id);
 
-    let profile = fetch_profile(&state, account_id).await.map_err(|e| {
-        eprintln!("Error fetching profile: {}", e);
-        (StatusCode::INTERNAL_SERVER_ERROR, e)
-    })?;
+    let profile = fetch_profile(&state, account_id).await?;
 
     let result_value = serde_json::to_value(&profile).map_err(|e| {
         eprintln!("Error serializing profile: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to serialize profile".to_string(),
-        )
+        ApiError::internal("Failed to serialize profile")
     })?;
 
     state.cache.insert(cache_key, result_value.clone()).await;
@@ -121,17 +164,23 @@ pub async fn get_profile(
 }
 
 /// Batch handler for multiple profiles endpoint
+#[utoipa::path(
+    get,
+    path = "/api/user/profile/batch",
+    tag = "user",
+    params(BatchProfileQuery),
+    responses(
+        (status = 200, description = "Profiles keyed by account ID", body = HashMap<String, ProfileData>),
+    )
+)]
 pub async fn get_batch_profiles(
     State(state): State<Arc<AppState>>,
     Query(params): Query<BatchProfileQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let account_ids: Vec<&str> = params.account_ids.split(',').map(|s| s.trim()).collect();
 
     if account_ids.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "No account IDs provided".to_string(),
-        ));
+        return Err(ApiError::bad_request("No account IDs provided"));
     }
 
     // Check which accounts are not in cache
@@ -176,16 +225,15 @@ pub async fn get_batch_profiles(
                     Some((account_id_owned, profile))
                 }
                 Err(e) => {
-                    eprintln!("Error fetching profile for {}: {}", account_id_owned, e);
+                    eprintln!(
+                        "Error fetching profile for {}: {}",
+                        account_id_owned, e.message
+                    );
                     // Cache empty profile to prevent retries
                     let cache_key = format!("profile:{}", account_id_owned);
                     let empty_profile = ProfileData {
-                        name: None,
-                        image: None,
-                        background_image: None,
-                        description: None,
-                        linktree: None,
-                        tags: None,
+                        account_kind: Some(account_kind(&account_id_owned)),
+                        ..Default::default()
                     };
                     if let Ok(value) = serde_json::to_value(&empty_profile) {
                         state_clone.cache.insert(cache_key, value).await;
@@ -205,3 +253,46 @@ pub async fn get_batch_profiles(
 
     Ok((StatusCode::OK, Json(cached_profiles)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_profile_with_name_and_missing_image() {
+        let profile = ProfileData::from_social_profile(&json!({
+            "name": "Peter",
+            "description": "",
+            "linktree": { "twitter": "", "github": "petersalomonsen" }
+        }));
+
+        assert_eq!(profile.name.as_deref(), Some("Peter"));
+        assert_eq!(profile.image, None);
+        assert_eq!(profile.description, None);
+        assert_eq!(
+            profile.linktree,
+            Some(json!({ "twitter": "", "github": "petersalomonsen" }))
+        );
+
+        // Every field is present in the response, missing ones as null
+        let value = serde_json::to_value(&profile).unwrap();
+        assert_eq!(value["image"], serde_json::Value::Null);
+        assert_eq!(value["backgroundImage"], serde_json::Value::Null);
+        assert_eq!(value.as_object().unwrap().len(), 7);
+    }
+
+    #[test]
+    fn test_empty_image_object_is_null() {
+        let profile = ProfileData::from_social_profile(&json!({
+            "image": { "ipfs_cid": "" },
+            "linktree": {},
+            "tags": {}
+        }));
+
+        assert_eq!(profile, ProfileData::default());
+
+        let profile = ProfileData::from_social_profile(&json!({ "tags": { "dao": "" } }));
+        assert_eq!(profile.tags, Some(json!({ "dao": "" })));
+    }
+}
@@ -0,0 +1,149 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::AppState;
+use crate::utils::api_error::ApiError;
+use crate::utils::numeric::NumericQuery;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserTokensQuery {
+    #[serde(rename = "accountId", alias = "account_id")]
+    pub account_id: String,
+}
+
+/// A token the account has had balance changes for
+#[derive(Serialize, Deserialize, Clone, Debug, sqlx::FromRow, ToSchema)]
+pub struct UserToken {
+    pub token_id: String,
+    /// Block of the earliest recorded change
+    pub first_block: i64,
+    /// Block of the latest recorded change
+    pub last_block: i64,
+    /// Balance after the latest recorded change (decimals applied); `0` once fully withdrawn
+    pub current_balance: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+pub struct UserTokensResponse {
+    pub account_id: String,
+    pub tokens: Vec<UserToken>,
+}
+
+/// List every token an account has ever had a balance change for
+///
+/// Tokens whose balance has since dropped to zero are included, so portfolio
+/// history can show them.
+#[utoipa::path(
+    get,
+    path = "/api/user/tokens",
+    tag = "user",
+    params(UserTokensQuery, NumericQuery),
+    responses(
+        (status = 200, description = "Tokens with recorded balance changes", body = UserTokensResponse),
+    )
+)]
+pub async fn get_user_tokens(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<UserTokensQuery>,
+) -> Result<Json<UserTokensResponse>, ApiError> {
+    let tokens = sqlx::query_as::<_, UserToken>(
+        r#"
+        SELECT token_id,
+               MIN(block_height) AS first_block,
+               MAX(block_height) AS last_block,
+               (ARRAY_AGG(balance_after::TEXT ORDER BY block_height DESC, id DESC))[1] AS current_balance
+        FROM balance_changes
+        WHERE account_id = $1 AND token_id IS NOT NULL
+        GROUP BY token_id
+        ORDER BY token_id
+        "#,
+    )
+    .bind(&params.account_id)
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        log::error!("Failed to fetch tokens of {}: {}", params.account_id, e);
+        ApiError::internal("Failed to fetch tokens").with_details(e.to_string())
+    })?;
+
+    Ok(Json(UserTokensResponse {
+        account_id: params.account_id,
+        tokens,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::init_test_state;
+    use sqlx::PgPool;
+
+    async fn insert_change(
+        pool: &PgPool,
+        token_id: &str,
+        block_height: i64,
+        before: i64,
+        after: i64,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO balance_changes
+            (account_id, token_id, block_height, block_timestamp, block_time, amount, balance_before, balance_after, counterparty, actions, raw_data)
+            VALUES ('holder.near', $1, $2, 1, NOW(), $3, $4, $5, 'sender.near', '{}', '{}')
+            "#,
+        )
+        .bind(token_id)
+        .bind(block_height)
+        .bind(after - before)
+        .bind(before)
+        .bind(after)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_withdrawn_token_is_listed_with_zero_balance(pool: PgPool) -> sqlx::Result<()> {
+        insert_change(&pool, "near", 100, 0, 5).await?;
+        insert_change(&pool, "usdc.near", 150, 0, 20).await?;
+        insert_change(&pool, "usdc.near", 300, 20, 0).await?;
+        insert_change(&pool, "near", 200, 5, 7).await?;
+
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+
+        let Json(response) = get_user_tokens(
+            State(Arc::new(state)),
+            Query(UserTokensQuery {
+                account_id: "holder.near".to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+
+        let tokens: Vec<(&str, i64, i64, &str)> = response
+            .tokens
+            .iter()
+            .map(|t| {
+                (
+                    t.token_id.as_str(),
+                    t.first_block,
+                    t.last_block,
+                    t.current_balance.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![("near", 100, 200, "7"), ("usdc.near", 150, 300, "0")]
+        );
+
+        Ok(())
+    }
+}
@@ -7,18 +7,27 @@ use axum::{
 };
 use near_api::AccountId;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use serde_with::serde_as;
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::AppState;
+use crate::handlers::treasury::policy::fetch_treasury_policy;
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{Pagination, PaginationQuery, paginate};
+use crate::utils::timeout::with_timeout;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct UserTreasuriesQuery {
     #[serde(rename = "accountId")]
     pub account_id: String,
+    /// Only return DAOs where the account is in the policy role with this name
+    pub role: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct TreasuryMetadata {
     #[serde(rename = "primaryColor", default)]
     pub primary_color: Option<String>,
@@ -37,94 +46,172 @@ pub struct TreasuryConfigFromContract {
     pub purpose: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct TreasuryConfig {
     pub metadata: Option<TreasuryMetadata>,
     pub name: Option<String>,
     pub purpose: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
 pub struct Treasury {
+    #[schema(value_type = String)]
     #[serde(rename = "daoId")]
     pub dao_id: AccountId,
     pub config: TreasuryConfig,
 }
 
+#[derive(Serialize, Clone, Debug, ToSchema)]
+pub struct UserTreasuriesResponse {
+    pub treasuries: Vec<Treasury>,
+    /// Number of matching DAOs across all pages
+    pub total: usize,
+    /// The page size actually used, after clamping the requested `limit`
+    pub limit_applied: i64,
+    pub offset: i64,
+}
+
+/// Whether the account holds the named role in a Sputnik DAO policy
+///
+/// `Everyone` roles match any account, `Group` roles match their listed members.
+fn has_role(policy: &Value, account_id: &str, role: &str) -> bool {
+    let Some(roles) = policy.get("roles").and_then(|r| r.as_array()) else {
+        return false;
+    };
+
+    roles.iter().any(|r| {
+        let name_matches = r
+            .get("name")
+            .and_then(|n| n.as_str())
+            .is_some_and(|n| n.eq_ignore_ascii_case(role));
+
+        let is_member = match r.get("kind") {
+            Some(Value::String(kind)) => kind == "Everyone",
+            Some(kind) => kind
+                .get("Group")
+                .and_then(|g| g.as_array())
+                .is_some_and(|members| members.iter().any(|m| m.as_str() == Some(account_id))),
+            None => false,
+        };
+
+        name_matches && is_member
+    })
+}
+
+/// Keep only the DAOs whose policy gives the account the named role
+fn filter_by_role(daos: Vec<(AccountId, Value)>, account_id: &str, role: &str) -> Vec<AccountId> {
+    daos.into_iter()
+        .filter(|(_, policy)| has_role(policy, account_id, role))
+        .map(|(dao_id, _)| dao_id)
+        .collect()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/user/treasuries",
+    tag = "user",
+    params(UserTreasuriesQuery, PaginationQuery),
+    responses(
+        (status = 200, description = "Treasuries the account is a member of", body = UserTreasuriesResponse),
+    )
+)]
 pub async fn get_user_treasuries(
     State(state): State<Arc<AppState>>,
+    Pagination { limit, offset, .. }: Pagination,
     Query(params): Query<UserTreasuriesQuery>,
-) -> Result<impl IntoResponse, (StatusCode, String)> {
+) -> Result<impl IntoResponse, ApiError> {
     let account_id = &params.account_id;
 
     if account_id.is_empty() {
-        return Err((
-            StatusCode::BAD_REQUEST,
-            "account_id is required".to_string(),
-        ));
+        return Err(ApiError::bad_request("account_id is required"));
     }
 
-    let cache_key = format!("user-treasuries:{}", account_id);
+    let cache_key = format!(
+        "user-treasuries:{}:{}:{}:{}",
+        account_id,
+        params.role.as_deref().unwrap_or(""),
+        offset,
+        limit
+    );
 
     if let Some(cached_treasuries) = state.cache.get(&cache_key).await {
         println!("🔁 Returning cached treasuries for {}", account_id);
         return Ok((StatusCode::OK, Json(cached_treasuries)));
     }
 
-    let response = state
-        .http_client
-        .get("https://api.pikespeak.ai/daos/members")
-        .header("x-api-key", state.env_vars.pikespeak_key.clone())
-        .send()
-        .await
-        .map_err(|e| {
-            eprintln!("Error fetching user daos: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to fetch user daos".to_string(),
-            )
-        })?;
-
-    let data: serde_json::Value = response.json().await.map_err(|e| {
-        eprintln!("Error parsing response: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to parse response".to_string(),
-        )
+    let response = with_timeout(
+        state.external_timeout(),
+        "Pikespeak",
+        state
+            .http_client
+            .get("https://api.pikespeak.ai/daos/members")
+            .header("x-api-key", state.env_vars.pikespeak_key.clone())
+            .send(),
+    )
+    .await?
+    .map_err(|e| {
+        eprintln!("Error fetching user daos: {}", e);
+        ApiError::internal("Failed to fetch user daos")
     })?;
 
+    let data: serde_json::Value =
+        with_timeout(state.external_timeout(), "Pikespeak", response.json())
+            .await?
+            .map_err(|e| {
+                eprintln!("Error parsing response: {}", e);
+                ApiError::internal("Failed to parse response")
+            })?;
+
     let user_daos = data
         .get(account_id)
         .and_then(|v| v.get("daos"))
         .and_then(|v| v.as_array())
-        .ok_or((StatusCode::NOT_FOUND, "No DAOs found for user".to_string()))?;
-
-    let mut treasuries = Vec::new();
+        .ok_or(ApiError::not_found("No DAOs found for user"))?;
 
+    let mut dao_ids = Vec::new();
     for dao in user_daos {
         let dao_id: AccountId = match dao.as_str() {
             Some(id) => id.parse().map_err(|e| {
                 eprintln!("Error parsing DAO ID: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to parse DAO ID".to_string(),
-                )
+                ApiError::internal("Failed to parse DAO ID")
             })?,
             None => continue,
         };
-        let result = near_api::Contract(dao_id.clone())
-            .call_function("get_config", ())
-            .read_only::<TreasuryConfigFromContract>()
-            .fetch_from(&state.network)
-            .await
-            .map_err(|e| {
-                eprintln!("Error fetching DAO config: {}", e);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Failed to fetch DAO config".to_string(),
-                )
-            })?
-            .data;
+        dao_ids.push(dao_id);
+    }
+
+    if let Some(role) = &params.role {
+        let policies = futures::future::try_join_all(
+            dao_ids
+                .iter()
+                .map(|dao_id| fetch_treasury_policy(&state, dao_id)),
+        )
+        .await?;
+        dao_ids = filter_by_role(
+            dao_ids.into_iter().zip(policies).collect(),
+            account_id,
+            role,
+        );
+    }
+
+    let total = dao_ids.len();
+    let mut treasuries = Vec::new();
+
+    for dao_id in paginate(dao_ids, offset, limit) {
+        let result = with_timeout(
+            state.external_timeout(),
+            "RPC",
+            near_api::Contract(dao_id.clone())
+                .call_function("get_config", ())
+                .read_only::<TreasuryConfigFromContract>()
+                .fetch_from(&state.network),
+        )
+        .await?
+        .map_err(|e| {
+            eprintln!("Error fetching DAO config: {}", e);
+            ApiError::internal("Failed to fetch DAO config")
+        })?
+        .data;
 
         let dao_config: TreasuryConfig = TreasuryConfig {
             metadata: result.metadata,
@@ -137,12 +224,15 @@ pub async fn get_user_treasuries(
         });
     }
 
-    let treasuries_value = serde_json::to_value(&treasuries).map_err(|e| {
+    let treasuries_value = serde_json::to_value(UserTreasuriesResponse {
+        treasuries,
+        total,
+        limit_applied: limit,
+        offset,
+    })
+    .map_err(|e| {
         eprintln!("Error serializing treasuries: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            "Failed to serialize treasuries".to_string(),
-        )
+        ApiError::internal("Failed to serialize treasuries")
     })?;
 
     state
@@ -152,3 +242,53 @@ pub async fn get_user_treasuries(
 
     Ok((StatusCode::OK, Json(treasuries_value)))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn policy(roles: Value) -> Value {
+        json!({ "roles": roles })
+    }
+
+    #[test]
+    fn test_filter_by_role_and_page_size() {
+        let user = "user.near";
+        let daos: Vec<(AccountId, Value)> = vec![
+            (
+                "a.sputnik-dao.near".parse().unwrap(),
+                policy(json!([{ "name": "Admin", "kind": { "Group": [user] } }])),
+            ),
+            (
+                "b.sputnik-dao.near".parse().unwrap(),
+                policy(json!([
+                    { "name": "Admin", "kind": { "Group": ["other.near"] } },
+                    { "name": "Approver", "kind": { "Group": [user] } }
+                ])),
+            ),
+            (
+                "c.sputnik-dao.near".parse().unwrap(),
+                policy(json!([{ "name": "admin", "kind": { "Group": [user, "other.near"] } }])),
+            ),
+            (
+                "d.sputnik-dao.near".parse().unwrap(),
+                policy(json!([{ "name": "all", "kind": "Everyone" }])),
+            ),
+        ];
+
+        let admins = filter_by_role(daos.clone(), user, "Admin");
+        let admin_ids: Vec<&str> = admins.iter().map(|d| d.as_str()).collect();
+        assert_eq!(admin_ids, vec!["a.sputnik-dao.near", "c.sputnik-dao.near"]);
+
+        let everyone = filter_by_role(daos, user, "all");
+        assert_eq!(everyone.len(), 1);
+
+        let first_page = paginate(admins.clone(), 0, 1);
+        assert_eq!(first_page.len(), 1);
+        assert_eq!(first_page[0].as_str(), "a.sputnik-dao.near");
+
+        let second_page = paginate(admins, 1, 1);
+        assert_eq!(second_page[0].as_str(), "c.sputnik-dao.near");
+    }
+}
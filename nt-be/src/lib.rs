@@ -3,20 +3,62 @@ pub mod handlers;
 pub mod routes;
 pub mod utils;
 
-use moka::future::Cache;
-use near_api::{AccountId, NetworkConfig, RPCEndpoint, Signer};
+use near_api::{AccountId, NetworkConfig, Signer};
 use sqlx::PgPool;
+use sqlx::types::chrono::{DateTime, Utc};
 use std::{sync::Arc, time::Duration};
+use tokio::sync::RwLock;
 
 pub struct AppState {
     pub http_client: reqwest::Client,
-    pub cache: Cache<String, serde_json::Value>,
+    pub cache: utils::cache::AppCache,
     pub signer: Arc<Signer>,
     pub signer_id: AccountId,
     pub network: NetworkConfig,
     pub archival_network: NetworkConfig,
     pub env_vars: utils::env::EnvVars,
     pub db_pool: PgPool,
+    pub ref_whitelist_refreshed_at: RwLock<Option<DateTime<Utc>>>,
+    /// FastNear API keys for HTTP calls, rotated when one is rate-limited
+    pub fastnear_keys: utils::api_keys::ApiKeyRotation,
+    /// Progress events published by the monitoring cycle
+    pub monitor_progress: handlers::balance_changes::monitor_progress::ProgressSender,
+    /// When the monitoring cycle last completed
+    pub monitor_liveness: handlers::balance_changes::monitor_liveness::MonitorLiveness,
+    /// Measured block rate, for mapping durations to block counts
+    pub blocks_per_hour: handlers::block::rate::BlocksPerHour,
+    /// Block the monitor processes up to when pinned, instead of the live head
+    pub monitor_ceiling: handlers::balance_changes::monitor_ceiling::MonitorCeiling,
+    /// Kill switch for in-flight fills, per account
+    pub fill_cancellations: Arc<handlers::balance_changes::fill_cancellation::FillCancellations>,
+    /// Outbound RPC rate shared by the monitor and all requests (`RPC_RATE_LIMIT_RPS`)
+    pub rpc_rate_limit: Option<Arc<handlers::balance_changes::rpc_rate_limit::TokenBucket>>,
+}
+
+impl AppState {
+    /// Pick the regular or archival network for a query at `block_height`
+    ///
+    /// Blocks within `REGULAR_RPC_BLOCK_WINDOW` of `head_height` are served by the
+    /// regular RPC; anything older falls back to archival.
+    pub fn network_for_block(&self, block_height: u64, head_height: u64) -> &NetworkConfig {
+        utils::network::select_network_for_block(
+            &self.network,
+            &self.archival_network,
+            block_height,
+            head_height,
+            self.env_vars.regular_rpc_block_window,
+        )
+    }
+
+    /// How long handlers wait on an external HTTP/RPC call (`EXTERNAL_TIMEOUT_SECONDS`)
+    pub fn external_timeout(&self) -> Duration {
+        Duration::from_secs(self.env_vars.external_timeout_seconds)
+    }
+
+    /// Settings passed into gap fills
+    pub fn fill_config(&self) -> utils::fill_config::FillConfig {
+        utils::fill_config::FillConfig::from(&self.env_vars)
+    }
 }
 
 /// Initialize the application state with database connection and migrations
@@ -26,8 +68,9 @@ pub async fn init_app_state() -> Result<AppState, Box<dyn std::error::Error>> {
     // Database connection
     log::info!("Connecting to database...");
     let db_pool = sqlx::postgres::PgPoolOptions::new()
-        .max_connections(20)
-        .acquire_timeout(Duration::from_secs(3))
+        .max_connections(env_vars.db_max_connections)
+        .acquire_timeout(Duration::from_secs(env_vars.db_acquire_timeout_secs))
+        .idle_timeout(Duration::from_secs(env_vars.db_idle_timeout_secs))
         .connect(&env_vars.database_url)
         .await?;
 
@@ -36,36 +79,47 @@ pub async fn init_app_state() -> Result<AppState, Box<dyn std::error::Error>> {
 
     log::info!("Database connection established successfully");
 
-    let cache = Cache::builder()
-        .max_capacity(10_000)
-        .time_to_live(Duration::from_secs(600))
-        .build();
+    let seeded = utils::decimals::seed_intents_decimals(&db_pool).await?;
+    log::info!("Seeded decimals of {} intents tokens", seeded);
+
+    if let Some(block) = env_vars.monitor_up_to_block {
+        log::info!("Monitor pinned to block {} (MONITOR_UP_TO_BLOCK)", block);
+    }
+    let monitor_ceiling = handlers::balance_changes::monitor_ceiling::MonitorCeiling::new(
+        env_vars.monitor_up_to_block,
+    );
+
+    let cache =
+        utils::cache::build_cache(env_vars.cache_backend, env_vars.redis_url.as_deref()).await?;
 
     Ok(AppState {
         http_client: reqwest::Client::new(),
         cache,
-        signer: Signer::from_secret_key(env_vars.signer_key.clone())
+        signer: utils::signer::build_signer(&env_vars.signer_keys)
+            .await
             .expect("Failed to create signer."),
         signer_id: env_vars.signer_id.clone(),
-        network: NetworkConfig {
-            rpc_endpoints: vec![
-                RPCEndpoint::new("https://rpc.mainnet.fastnear.com/".parse().unwrap())
-                    .with_api_key(env_vars.fastnear_api_key.clone()),
-            ],
-            ..NetworkConfig::mainnet()
-        },
-        archival_network: NetworkConfig {
-            rpc_endpoints: vec![
-                RPCEndpoint::new(
-                    "https://archival-rpc.mainnet.fastnear.com/"
-                        .parse()
-                        .unwrap(),
-                )
-                .with_api_key(env_vars.fastnear_api_key.clone()),
-            ],
-            ..NetworkConfig::mainnet()
-        },
-        env_vars,
+        network: utils::network::build_network(
+            utils::network::NetworkKind::Regular,
+            &env_vars.fastnear_api_keys,
+        ),
+        archival_network: utils::network::build_network(
+            utils::network::NetworkKind::Archival,
+            &env_vars.fastnear_api_keys,
+        ),
+        fastnear_keys: utils::api_keys::ApiKeyRotation::new(env_vars.fastnear_api_keys.clone()),
         db_pool,
+        ref_whitelist_refreshed_at: RwLock::new(None),
+        monitor_progress: handlers::balance_changes::monitor_progress::progress_channel(),
+        monitor_liveness: handlers::balance_changes::monitor_liveness::MonitorLiveness::new(),
+        blocks_per_hour: handlers::block::rate::BlocksPerHour::new(),
+        monitor_ceiling,
+        fill_cancellations: Arc::new(
+            handlers::balance_changes::fill_cancellation::FillCancellations::new(),
+        ),
+        rpc_rate_limit: handlers::balance_changes::rpc_rate_limit::rate_limit(
+            env_vars.rpc_rate_limit_rps,
+        ),
+        env_vars,
     })
 }
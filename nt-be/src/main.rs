@@ -1,7 +1,6 @@
 use axum::Router;
 use std::sync::Arc;
 use std::time::Duration;
-use tower_http::cors::{Any, CorsLayer};
 
 #[tokio::main]
 async fn main() {
@@ -26,16 +25,48 @@ async fn main() {
     if !state.env_vars.disable_balance_monitoring {
         let state_clone = state.clone();
         tokio::spawn(async move {
-            use near_api::Chain;
-            use nt_be::handlers::balance_changes::account_monitor::run_monitor_cycle;
-
-            // Get monitoring interval from env or default to 5 minutes
-            let interval_minutes: u64 = std::env::var("MONITOR_INTERVAL_MINUTES")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(5);
-
-            let interval = Duration::from_secs(interval_minutes * 60);
+            use nt_be::handlers::balance_changes::indexer_source::FastNearIndexer;
+            use nt_be::handlers::balance_changes::monitor_supervisor::{
+                INITIAL_RESTART_BACKOFF, supervise,
+            };
+            use nt_be::handlers::balance_changes::rpc_tape::{RpcTapeMode, start_rpc_tape};
+
+            let interval_minutes = state_clone.env_vars.monitor_interval_minutes;
+
+            let indexer = state_clone
+                .env_vars
+                .fastnear_indexer_url
+                .as_deref()
+                .map(|url| {
+                    log::info!("Narrowing gap searches with the indexer at {}", url);
+                    Arc::new(FastNearIndexer::new(
+                        state_clone.http_client.clone(),
+                        url,
+                        Some(state_clone.env_vars.fastnear_api_key.clone()),
+                    ))
+                });
+
+            // Optionally record or replay the monitor's RPC traffic; replaying wins
+            let tape = match (
+                &state_clone.env_vars.rpc_replay_file,
+                &state_clone.env_vars.rpc_record_file,
+            ) {
+                (Some(path), _) => Some((RpcTapeMode::Replay, path)),
+                (None, Some(path)) => Some((RpcTapeMode::Record, path)),
+                (None, None) => None,
+            };
+            let monitor_network = match tape {
+                Some((mode, path)) => {
+                    match start_rpc_tape(mode, path, &state_clone.archival_network).await {
+                        Ok(network) => network,
+                        Err(e) => {
+                            log::error!("Failed to start RPC tape at {}: {}", path, e);
+                            return;
+                        }
+                    }
+                }
+                None => state_clone.archival_network.clone(),
+            };
 
             log::info!(
                 "Starting background monitoring service (interval: {} minutes)",
@@ -45,47 +76,45 @@ async fn main() {
             // Wait a bit before first run to let server fully start
             tokio::time::sleep(Duration::from_secs(10)).await;
 
-            loop {
-                log::info!("Running monitoring cycle...");
-
-                // Get current block height from the network
-                let up_to_block = match Chain::block().fetch_from(&state_clone.network).await {
-                    Ok(block) => block.header.height as i64,
-                    Err(e) => {
-                        log::error!("Failed to get current block height: {}", e);
-                        log::info!("Retrying in {} minutes", interval_minutes);
-                        tokio::time::sleep(interval).await;
-                        continue;
-                    }
-                };
-
-                log::info!("Processing up to block {}", up_to_block);
-
-                match run_monitor_cycle(
-                    &state_clone.db_pool,
-                    &state_clone.archival_network,
-                    up_to_block,
-                )
-                .await
-                {
-                    Ok(()) => {
-                        log::info!("Monitoring cycle completed successfully");
-                    }
-                    Err(e) => {
-                        log::error!("Monitoring cycle failed: {}", e);
-                    }
-                }
-
-                log::info!("Next monitoring cycle in {} minutes", interval_minutes);
-                tokio::time::sleep(interval).await;
-            }
+            // Restart the monitor if a cycle panics
+            supervise(
+                &state_clone.monitor_liveness,
+                INITIAL_RESTART_BACKOFF,
+                || {
+                    run_monitor(
+                        state_clone.clone(),
+                        monitor_network.clone(),
+                        indexer.clone(),
+                    )
+                },
+            )
+            .await;
         });
     }
 
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    // Retry webhook deliveries that failed when the balance change was recorded
+    nt_be::handlers::balance_changes::webhooks::spawn_delivery_retries(
+        state.db_pool.clone(),
+        Duration::from_secs(60),
+        state.env_vars.webhook_allow_private_urls,
+    );
+
+    // Measure the block rate now and then, so durations map to accurate block counts
+    nt_be::handlers::block::rate::spawn_blocks_per_hour_refresh(
+        state.clone(),
+        Duration::from_secs(60 * 60),
+    );
+
+    // Keep the Ref Finance whitelist cache warm so user requests never wait on RPC
+    nt_be::handlers::user::assets::spawn_whitelist_refresh(
+        state.clone(),
+        Duration::from_secs(state.env_vars.ref_whitelist_refresh_seconds),
+    );
+
+    let cors = nt_be::utils::cors::cors_layer(
+        &state.env_vars.cors_origins,
+        state.env_vars.cors_allow_credentials,
+    );
 
     let app = Router::new()
         .merge(nt_be::routes::create_routes(state))
@@ -100,3 +129,67 @@ async fn main() {
 
     axum::serve(listener, app).await.unwrap();
 }
+
+/// Run monitoring cycles every `MONITOR_INTERVAL_MINUTES`, forever
+async fn run_monitor(
+    state: Arc<nt_be::AppState>,
+    monitor_network: near_api::NetworkConfig,
+    indexer: Option<Arc<nt_be::handlers::balance_changes::indexer_source::FastNearIndexer>>,
+) {
+    use near_api::Chain;
+    use nt_be::handlers::balance_changes::account_monitor::run_monitor_cycle;
+    use nt_be::handlers::balance_changes::fill_cancellation::with_cancellations;
+    use nt_be::handlers::balance_changes::indexer_source::IndexerSource;
+    use nt_be::handlers::balance_changes::rpc_budget::{RpcBudget, with_budget};
+    use nt_be::handlers::balance_changes::rpc_rate_limit::with_rate_limit;
+
+    let interval_minutes = state.env_vars.monitor_interval_minutes;
+    let interval = Duration::from_secs(interval_minutes * 60);
+
+    loop {
+        log::info!("Running monitoring cycle...");
+
+        // Get current block height from the network, unless pinned below it
+        let up_to_block = match Chain::block().fetch_from(&monitor_network).await {
+            Ok(block) => state.monitor_ceiling.up_to_block(block.header.height) as i64,
+            Err(e) => {
+                log::error!("Failed to get current block height: {}", e);
+                log::info!("Retrying in {} minutes", interval_minutes);
+                tokio::time::sleep(interval).await;
+                continue;
+            }
+        };
+
+        log::info!("Processing up to block {}", up_to_block);
+
+        let config = state.fill_config();
+        let cycle = run_monitor_cycle(
+            &state.db_pool,
+            &monitor_network,
+            &config,
+            up_to_block,
+            state.env_vars.head_lag_blocks,
+            indexer.as_deref().map(|i| i as &dyn IndexerSource),
+            Some(&state.monitor_progress),
+        );
+        let budget = Arc::new(RpcBudget::new(state.env_vars.monitor_rpc_budget));
+        let cycle = with_budget(budget.clone(), cycle);
+        let cycle = with_rate_limit(state.rpc_rate_limit.clone(), cycle);
+        match with_cancellations(state.fill_cancellations.clone(), cycle).await {
+            Ok(()) => {
+                log::info!(
+                    "Monitoring cycle completed successfully ({} RPC calls)",
+                    budget.spent()
+                );
+            }
+            Err(e) => {
+                log::error!("Monitoring cycle failed: {}", e);
+            }
+        }
+        // A failed cycle still shows the monitor is alive
+        state.monitor_liveness.record_cycle_completed();
+
+        log::info!("Next monitoring cycle in {} minutes", interval_minutes);
+        tokio::time::sleep(interval).await;
+    }
+}
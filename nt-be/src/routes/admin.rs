@@ -0,0 +1,744 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use utoipa::ToSchema;
+
+use crate::AppState;
+use crate::handlers::balance_changes::fill_cancellation::with_cancellations;
+use crate::handlers::balance_changes::fill_failures::{self, FillFailure};
+use crate::handlers::balance_changes::gap_detector::{GapsSummary, summarize_gaps};
+use crate::handlers::balance_changes::gap_filler;
+use crate::handlers::balance_changes::monitor_progress::MonitorProgress;
+use crate::handlers::token::aliases::{self, RegisterTokenAliasRequest, TokenAlias};
+use crate::utils::admin_auth::require_admin;
+use crate::utils::api_error::ApiError;
+use crate::utils::cache::CacheStats;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RebuildRequest {
+    pub account_id: String,
+    pub token_id: String,
+    pub up_to_block: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RebuildResponse {
+    pub account_id: String,
+    pub token_id: String,
+    pub up_to_block: i64,
+    pub records_deleted: u64,
+    pub records_filled: usize,
+}
+
+/// Purge and rebuild the balance change chain for an account and token
+///
+/// Requires `Authorization: Bearer <ADMIN_API_KEY>`. Returns 409 if the account
+/// is currently being processed by a monitoring cycle or another rebuild.
+#[utoipa::path(
+    post,
+    path = "/api/admin/rebuild",
+    tag = "admin",
+    request_body = RebuildRequest,
+    responses(
+        (status = 200, description = "Chain rebuilt", body = RebuildResponse),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+        (status = 409, description = "Account is locked by another process"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn rebuild_chain(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(params): Json<RebuildRequest>,
+) -> Result<Json<RebuildResponse>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    log::info!(
+        "rebuild request: account={}, token={}, up_to_block={}",
+        params.account_id,
+        params.token_id,
+        params.up_to_block
+    );
+
+    let config = state.fill_config();
+    let rebuild = gap_filler::rebuild_chain(
+        &state.db_pool,
+        &state.archival_network,
+        &config,
+        &params.account_id,
+        &params.token_id,
+        params.up_to_block,
+    );
+    let summary = with_cancellations(state.fill_cancellations.clone(), rebuild)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to rebuild chain: {}", e);
+            ApiError::internal("Failed to rebuild chain").with_details(e.to_string())
+        })?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                "Account is locked by another process, try again later",
+            )
+        })?;
+
+    Ok(Json(RebuildResponse {
+        account_id: params.account_id,
+        token_id: params.token_id,
+        up_to_block: params.up_to_block,
+        records_deleted: summary.deleted,
+        records_filled: summary.filled.len(),
+    }))
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CollapseDuplicatesRequest {
+    pub account_id: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DuplicateKeyResponse {
+    /// `null` for legacy NEAR rows stored without a token id
+    pub token_id: Option<String>,
+    pub block_height: i64,
+    /// Ids of all rows that shared the key before collapsing
+    pub ids: Vec<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CollapseDuplicatesResponse {
+    pub account_id: String,
+    pub duplicates: Vec<DuplicateKeyResponse>,
+    pub records_deleted: u64,
+}
+
+/// Collapse duplicate balance change rows for an account
+///
+/// Rows sharing the same account, block and token are reduced to the most
+/// enriched one. Requires `Authorization: Bearer <ADMIN_API_KEY>`. Returns 409 if
+/// the account is currently being processed.
+#[utoipa::path(
+    post,
+    path = "/api/admin/collapse-duplicates",
+    tag = "admin",
+    request_body = CollapseDuplicatesRequest,
+    responses(
+        (status = 200, description = "Duplicates collapsed", body = CollapseDuplicatesResponse),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+        (status = 409, description = "Account is locked by another process"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn collapse_duplicates(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(params): Json<CollapseDuplicatesRequest>,
+) -> Result<Json<CollapseDuplicatesResponse>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    log::info!("collapse duplicates request: account={}", params.account_id);
+
+    let collapsed = gap_filler::collapse_duplicates(&state.db_pool, &params.account_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to collapse duplicates: {}", e);
+            ApiError::internal("Failed to collapse duplicates").with_details(e.to_string())
+        })?
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::CONFLICT,
+                "Account is locked by another process, try again later",
+            )
+        })?;
+
+    Ok(Json(CollapseDuplicatesResponse {
+        account_id: params.account_id,
+        duplicates: collapsed
+            .duplicates
+            .into_iter()
+            .map(|d| DuplicateKeyResponse {
+                token_id: d.token_id,
+                block_height: d.block_height,
+                ids: d.ids,
+            })
+            .collect(),
+        records_deleted: collapsed.deleted,
+    }))
+}
+
+/// Stream monitoring cycle progress as Server-Sent Events
+///
+/// Emits a `progress` event with a `MonitorProgress` payload after each
+/// account/token is processed. Subscribers that fall too far behind skip the
+/// missed events rather than disconnecting. Requires `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    get,
+    path = "/api/admin/monitor/progress",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Stream of progress events", content_type = "text/event-stream", body = MonitorProgress),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn monitor_progress(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    let receiver = state.monitor_progress.subscribe();
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(progress) => {
+                    let event = Event::default()
+                        .event("progress")
+                        .json_data(&progress)
+                        .unwrap_or_else(|_| Event::default().comment("unserializable event"));
+                    return Some((Ok(event), receiver));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("Progress subscriber lagged, skipped {} events", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CancelFillResponse {
+    pub account_id: String,
+    pub cancelled: bool,
+}
+
+/// Cancel the in-flight fills of an account
+///
+/// Running gap searches for the account stop before their next RPC query and the
+/// monitoring cycle moves on to the next account. Only fills in flight are
+/// cancelled; the next cycle fills the account again. Requires
+/// `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    post,
+    path = "/api/admin/monitor/cancel/{account_id}",
+    tag = "admin",
+    params(("account_id" = String, Path, description = "Account whose fills to cancel")),
+    responses(
+        (status = 200, description = "Fills cancelled", body = CancelFillResponse),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+        (status = 404, description = "No fill in flight for the account"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn cancel_fill(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(account_id): Path<String>,
+) -> Result<Json<CancelFillResponse>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    if !state.fill_cancellations.cancel(&account_id) {
+        return Err(ApiError::not_found(format!(
+            "No fill in flight for {}",
+            account_id
+        )));
+    }
+    log::warn!("Cancelled in-flight fills for {}", account_id);
+
+    Ok(Json(CancelFillResponse {
+        account_id,
+        cancelled: true,
+    }))
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct MonitorCeilingBody {
+    /// Block the monitor processes up to; `null` follows the live head again
+    pub up_to_block: Option<u64>,
+}
+
+/// Pin the block the monitoring cycle processes up to
+///
+/// Overrides `MONITOR_UP_TO_BLOCK` until the next restart. The ceiling applies from
+/// the next cycle; it is capped at the chain head. Requires
+/// `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    post,
+    path = "/api/admin/monitor/ceiling",
+    tag = "admin",
+    request_body = MonitorCeilingBody,
+    responses(
+        (status = 200, description = "Ceiling updated", body = MonitorCeilingBody),
+        (status = 400, description = "Block 0 can't be pinned"),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn set_monitor_ceiling(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<MonitorCeilingBody>,
+) -> Result<Json<MonitorCeilingBody>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    if body.up_to_block == Some(0) {
+        return Err(ApiError::bad_request("up_to_block must be positive"));
+    }
+
+    state.monitor_ceiling.set(body.up_to_block);
+    match body.up_to_block {
+        Some(block) => log::warn!("Monitor pinned to block {}", block),
+        None => log::info!("Monitor follows the chain head again"),
+    }
+
+    Ok(Json(MonitorCeilingBody {
+        up_to_block: state.monitor_ceiling.get(),
+    }))
+}
+
+/// Register a token alias
+///
+/// Records that `old_contract` migrated to `new_contract`, so balance history
+/// requested with `mergeAliases=true` merges both into one series. Requires
+/// `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    post,
+    path = "/api/admin/token-aliases",
+    tag = "admin",
+    request_body = RegisterTokenAliasRequest,
+    responses(
+        (status = 200, description = "Alias registered", body = TokenAlias),
+        (status = 400, description = "A contract can't alias itself"),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn register_token_alias(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(body): Json<RegisterTokenAliasRequest>,
+) -> Result<Json<TokenAlias>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    let old_contract = body.old_contract.trim();
+    let new_contract = body.new_contract.trim();
+    if old_contract.is_empty() || new_contract.is_empty() {
+        return Err(ApiError::bad_request(
+            "old_contract and new_contract are required",
+        ));
+    }
+    if old_contract == new_contract {
+        return Err(ApiError::bad_request(
+            "old_contract and new_contract must differ",
+        ));
+    }
+
+    let alias = aliases::register_alias(&state.db_pool, old_contract, new_contract)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to register token alias: {}", e);
+            ApiError::internal("Failed to register token alias").with_details(e.to_string())
+        })?;
+    log::info!(
+        "Registered token alias {} -> {}",
+        alias.old_contract,
+        alias.new_contract
+    );
+
+    Ok(Json(alias))
+}
+
+/// List registered token aliases
+///
+/// Requires `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    get,
+    path = "/api/admin/token-aliases",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Registered token aliases", body = Vec<TokenAlias>),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn list_token_aliases(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<TokenAlias>>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    let aliases = aliases::list_aliases(&state.db_pool).await.map_err(|e| {
+        log::error!("Failed to list token aliases: {}", e);
+        ApiError::internal("Failed to list token aliases").with_details(e.to_string())
+    })?;
+
+    Ok(Json(aliases))
+}
+
+#[derive(Debug, Serialize, ToSchema, sqlx::FromRow)]
+pub struct AppliedMigration {
+    pub version: i64,
+    pub description: String,
+    pub installed_on: DateTime<Utc>,
+    pub success: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MigrationsResponse {
+    pub applied: Vec<AppliedMigration>,
+    /// Versions bundled with this build that have not been applied yet
+    pub pending: Vec<i64>,
+}
+
+/// Read the applied migrations from `_sqlx_migrations`, oldest first
+async fn load_migrations(pool: &PgPool) -> Result<MigrationsResponse, sqlx::Error> {
+    let applied: Vec<AppliedMigration> = sqlx::query_as(
+        r#"
+        SELECT version, description, installed_on, success
+        FROM _sqlx_migrations
+        ORDER BY version
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let pending = sqlx::migrate!("./migrations")
+        .iter()
+        .map(|m| m.version)
+        .filter(|version| !applied.iter().any(|a| a.version == *version))
+        .collect();
+
+    Ok(MigrationsResponse { applied, pending })
+}
+
+/// List applied database migrations
+///
+/// Requires `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    get,
+    path = "/api/admin/migrations",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Applied and pending migrations", body = MigrationsResponse),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn list_migrations(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<MigrationsResponse>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    let migrations = load_migrations(&state.db_pool).await.map_err(|e| {
+        log::error!("Failed to load migrations: {}", e);
+        ApiError::internal("Failed to load migrations").with_details(e.to_string())
+    })?;
+
+    Ok(Json(migrations))
+}
+
+/// Most monitored accounts one gaps report checks
+pub const MAX_GAPS_REPORT_ACCOUNTS: usize = 500;
+
+/// Response cache key of the gaps report
+const GAPS_REPORT_CACHE_KEY: &str = "gaps-report";
+
+/// Report gaps across all enabled monitored accounts
+///
+/// For each account (up to `MAX_GAPS_REPORT_ACCOUNTS`, in account id order) and each
+/// of its tracked tokens, reports the number of gaps in the balance change chain and
+/// the blocks they span. Reports are kept in the response cache. Requires
+/// `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    get,
+    path = "/api/admin/gaps",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Gaps per account and token", body = GapsSummary),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn list_gaps(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<GapsSummary>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    if let Some(cached) = state.cache.get(GAPS_REPORT_CACHE_KEY).await
+        && let Ok(summary) = serde_json::from_value(cached)
+    {
+        return Ok(Json(summary));
+    }
+
+    let summary = summarize_gaps(&state.db_pool, MAX_GAPS_REPORT_ACCOUNTS)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to summarize gaps: {}", e);
+            ApiError::internal("Failed to summarize gaps").with_details(e.to_string())
+        })?;
+    if let Ok(value) = serde_json::to_value(&summary) {
+        state
+            .cache
+            .insert(GAPS_REPORT_CACHE_KEY.to_string(), value)
+            .await;
+    }
+
+    Ok(Json(summary))
+}
+
+/// List gaps whose fill keeps failing
+///
+/// Each failed fill pushes the gap's next retry further out (see `fill_failures`);
+/// gaps with many attempts need an operator's attention. Requires
+/// `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    get,
+    path = "/api/admin/fill-failures",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Failing gaps, most attempted first", body = Vec<FillFailure>),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn list_fill_failures(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<FillFailure>>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    let failures = fill_failures::list_failures(&state.db_pool)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to list fill failures: {}", e);
+            ApiError::internal("Failed to list fill failures").with_details(e.to_string())
+        })?;
+
+    Ok(Json(failures))
+}
+
+/// Inspect the response cache
+///
+/// Reports the backend, the number of cached entries and the hits and misses since
+/// startup. Requires `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    get,
+    path = "/api/admin/cache/stats",
+    tag = "admin",
+    responses(
+        (status = 200, description = "Cache usage", body = CacheStats),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn cache_stats(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<CacheStats>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    Ok(Json(state.cache.stats().await))
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct InvalidateCacheRequest {
+    /// Evict every entry whose key starts with this, e.g. `profile:`; empty clears the cache
+    pub prefix: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct InvalidateCacheResponse {
+    pub prefix: String,
+    /// Number of entries evicted
+    pub invalidated: u64,
+}
+
+/// Evict response cache entries by key prefix
+///
+/// Lets operators drop stale upstream data (e.g. `profile:alice.near`) without a
+/// restart. Requires `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    post,
+    path = "/api/admin/cache/invalidate",
+    tag = "admin",
+    request_body = InvalidateCacheRequest,
+    responses(
+        (status = 200, description = "Entries evicted", body = InvalidateCacheResponse),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn invalidate_cache(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(request): Json<InvalidateCacheRequest>,
+) -> Result<Json<InvalidateCacheResponse>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    let invalidated = state.cache.invalidate_prefix(&request.prefix).await;
+    log::warn!(
+        "Invalidated {} cache entries with prefix '{}'",
+        invalidated,
+        request.prefix
+    );
+
+    Ok(Json(InvalidateCacheResponse {
+        prefix: request.prefix,
+        invalidated,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{HeaderValue, header};
+
+    fn headers_with(auth: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(auth).unwrap());
+        headers
+    }
+
+    #[tokio::test]
+    async fn test_set_monitor_ceiling() {
+        let mut state = crate::utils::test_utils::init_test_state().await;
+        state.env_vars.admin_api_key = Some("secret".to_string());
+        let state = Arc::new(state);
+
+        let pin = |up_to_block| {
+            set_monitor_ceiling(
+                State(state.clone()),
+                headers_with("Bearer secret"),
+                Json(MonitorCeilingBody { up_to_block }),
+            )
+        };
+
+        let Json(pinned) = pin(Some(150_000_000)).await.unwrap();
+        assert_eq!(pinned.up_to_block, Some(150_000_000));
+        assert_eq!(state.monitor_ceiling.up_to_block(180_000_000), 150_000_000);
+
+        let ApiError { status, .. } = pin(Some(0)).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let Json(unpinned) = pin(None).await.unwrap();
+        assert_eq!(unpinned.up_to_block, None);
+        assert_eq!(state.monitor_ceiling.up_to_block(180_000_000), 180_000_000);
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_cache_by_prefix() {
+        let mut state = crate::utils::test_utils::init_test_state().await;
+        state.env_vars.admin_api_key = Some("secret".to_string());
+        let state = Arc::new(state);
+
+        state
+            .cache
+            .insert("profile:alice.near".to_string(), serde_json::json!({}))
+            .await;
+        state
+            .cache
+            .insert("receipt-outcome:abc".to_string(), serde_json::json!({}))
+            .await;
+
+        let Json(response) = invalidate_cache(
+            State(state.clone()),
+            headers_with("Bearer secret"),
+            Json(InvalidateCacheRequest {
+                prefix: "profile:".to_string(),
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.invalidated, 1);
+        assert_eq!(state.cache.get("profile:alice.near").await, None);
+        assert!(state.cache.get("receipt-outcome:abc").await.is_some());
+
+        let Json(stats) = cache_stats(State(state.clone()), headers_with("Bearer secret"))
+            .await
+            .unwrap();
+        assert_eq!(stats.entries, 1);
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+
+        let ApiError { status, .. } = cache_stats(State(state), HeaderMap::new())
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[sqlx::test]
+    async fn test_gaps_report_is_kept_in_response_cache(pool: PgPool) -> sqlx::Result<()> {
+        let mut state = crate::utils::test_utils::init_test_state().await;
+        state.db_pool = pool.clone();
+        state.env_vars.admin_api_key = Some("secret".to_string());
+        let state = Arc::new(state);
+
+        let Json(empty) = list_gaps(State(state.clone()), headers_with("Bearer secret"))
+            .await
+            .unwrap();
+        assert!(empty.accounts.is_empty());
+        assert!(state.cache.get(GAPS_REPORT_CACHE_KEY).await.is_some());
+
+        // A newly monitored account shows up only once the cached report is invalidated
+        sqlx::query("INSERT INTO monitored_accounts (account_id) VALUES ('treasury.near')")
+            .execute(&pool)
+            .await?;
+        let Json(cached) = list_gaps(State(state.clone()), headers_with("Bearer secret"))
+            .await
+            .unwrap();
+        assert_eq!(cached, empty);
+
+        state.cache.invalidate_prefix(GAPS_REPORT_CACHE_KEY).await;
+        let Json(fresh) = list_gaps(State(state), headers_with("Bearer secret"))
+            .await
+            .unwrap();
+        assert_eq!(fresh.accounts.len(), 1);
+        assert_eq!(fresh.accounts[0].account_id, "treasury.near");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_load_migrations_lists_known_migrations(pool: PgPool) -> sqlx::Result<()> {
+        let migrations = load_migrations(&pool).await?;
+
+        let known: Vec<i64> = sqlx::migrate!("./migrations")
+            .iter()
+            .map(|m| m.version)
+            .collect();
+        let applied: Vec<i64> = migrations.applied.iter().map(|m| m.version).collect();
+
+        assert_eq!(applied, known);
+        assert!(migrations.pending.is_empty());
+        assert!(migrations.applied.iter().all(|m| m.success));
+        assert_eq!(migrations.applied[0].description, "create balance changes");
+
+        Ok(())
+    }
+}
@@ -1,26 +1,49 @@
 use axum::{
     Json,
-    extract::{Query, State},
-    http::StatusCode,
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
 };
+use bigdecimal::Zero;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use sqlx::types::BigDecimal;
 use sqlx::types::chrono::{DateTime, Utc};
+use std::str::FromStr;
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::AppState;
+use crate::handlers::balance_changes::counterparty::{
+    CounterpartyType, classify_counterparties, convert_raw_to_decimal, known_counterparty_types,
+};
+use crate::handlers::balance_changes::fill_cancellation::with_cancellations;
 use crate::handlers::balance_changes::gap_filler;
+use crate::handlers::balance_changes::lockup_vesting::LOCKUP_LOCKED_TOKEN_ID;
+use crate::handlers::balance_changes::reconciliation::{self, ReconciliationWarning};
+use crate::handlers::balance_changes::transaction_detail::{
+    TransactionDetail, fetch_transaction_detail,
+};
+use crate::handlers::user::assets::{
+    FASTNEAR_API_BASE_URL, build_balance_map, fetch_user_balances,
+};
+use crate::utils::admin_auth::require_admin;
+use crate::utils::api_error::ApiError;
+use crate::utils::decimals::{decimals, known_decimals};
+use crate::utils::numeric::NumericQuery;
+use crate::utils::pagination::{Pagination, PaginationQuery};
+use crate::utils::timeout::with_timeout;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct BalanceChangesQuery {
     pub account_id: String,
     pub token_id: Option<String>,
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
+    /// Leave out changes whose absolute amount, in the token's base units (e.g.
+    /// yoctoNEAR), is below this threshold; hides dust such as spam airdrops
+    #[param(value_type = Option<String>)]
+    pub min_abs_amount: Option<BigDecimal>,
 }
 
-#[derive(Debug, Serialize, sqlx::FromRow)]
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
 pub struct BalanceChange {
     pub id: i64,
     pub account_id: String,
@@ -29,82 +52,595 @@ pub struct BalanceChange {
     pub token_id: String,
     pub receipt_id: Vec<String>,
     pub transaction_hashes: Vec<String>,
+    /// Explorer link to the first of `transaction_hashes`; `null` for records without
+    /// a transaction (e.g. SNAPSHOT)
+    #[sqlx(skip)]
+    pub transaction_url: Option<String>,
     pub counterparty: Option<String>,
+    /// Whether the counterparty is a contract, a user or a system event; `null` when
+    /// it couldn't be determined
+    #[sqlx(skip)]
+    pub counterparty_type: Option<CounterpartyType>,
     pub signer_id: Option<String>,
     pub receiver_id: Option<String>,
+    #[schema(value_type = String)]
     pub amount: BigDecimal,
+    #[schema(value_type = String)]
     pub balance_before: BigDecimal,
+    #[schema(value_type = String)]
     pub balance_after: BigDecimal,
     pub created_at: DateTime<Utc>,
 }
 
+/// Explorer link of a transaction
+pub fn transaction_url(explorer_base: &str, tx_hash: &str) -> String {
+    format!("{}/{}", explorer_base.trim_end_matches('/'), tx_hash)
+}
+
+/// Fill in `transaction_url` for a page of balance changes
+fn annotate_transaction_urls(state: &AppState, changes: &mut [BalanceChange]) {
+    for change in changes {
+        change.transaction_url = change
+            .transaction_hashes
+            .first()
+            .map(|tx_hash| transaction_url(&state.env_vars.explorer_tx_url_base, tx_hash));
+    }
+}
+
+/// Fill in `counterparty_type` for a page of balance changes
+///
+/// Only classifications already stored are used, so listing never waits on RPC.
+/// Counterparties seen for the first time are left unset and classified in the
+/// background for later requests.
+async fn annotate_counterparty_types(state: &AppState, changes: &mut [BalanceChange]) {
+    let mut counterparties: Vec<&str> = changes
+        .iter()
+        .filter_map(|change| change.counterparty.as_deref())
+        .collect();
+    counterparties.sort_unstable();
+    counterparties.dedup();
+
+    let types = match known_counterparty_types(&state.db_pool, &counterparties).await {
+        Ok(types) => types,
+        Err(e) => {
+            log::warn!("Failed to load counterparty types: {}", e);
+            return;
+        }
+    };
+
+    let unclassified: Vec<String> = counterparties
+        .iter()
+        .filter(|counterparty| !types.contains_key(**counterparty))
+        .map(|counterparty| counterparty.to_string())
+        .collect();
+    if !unclassified.is_empty() {
+        let (pool, network) = (state.db_pool.clone(), state.network.clone());
+        tokio::spawn(async move { classify_counterparties(&pool, &network, unclassified).await });
+    }
+
+    for change in changes {
+        change.counterparty_type = change
+            .counterparty
+            .as_ref()
+            .and_then(|counterparty| types.get(counterparty).copied());
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BalanceChangesResponse {
+    pub changes: Vec<BalanceChange>,
+    /// The page size actually used, after clamping the requested `limit`
+    pub limit_applied: i64,
+    pub offset: i64,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/balance-changes",
+    tag = "balance-changes",
+    params(BalanceChangesQuery, PaginationQuery, NumericQuery),
+    responses(
+        (status = 200, description = "Balance changes, newest first", body = BalanceChangesResponse),
+    )
+)]
 pub async fn get_balance_changes(
     State(state): State<Arc<AppState>>,
+    Pagination { limit, offset, .. }: Pagination,
     Query(params): Query<BalanceChangesQuery>,
-) -> Result<Json<Vec<BalanceChange>>, (StatusCode, Json<Value>)> {
-    let limit = params.limit.unwrap_or(100).min(1000);
-    let offset = params.offset.unwrap_or(0);
+) -> Result<Json<BalanceChangesResponse>, ApiError> {
+    if params
+        .min_abs_amount
+        .as_ref()
+        .is_some_and(|min| min < &BigDecimal::zero())
+    {
+        return Err(ApiError::bad_request("min_abs_amount must not be negative"));
+    }
 
-    let changes = if let Some(token_id) = params.token_id {
-        sqlx::query_as::<_, BalanceChange>(
-            r#"
-            SELECT id, account_id, block_height, block_time, token_id, 
-                   receipt_id, transaction_hashes, counterparty, signer_id, receiver_id,
-                   amount, balance_before, balance_after, created_at
-            FROM balance_changes
-            WHERE account_id = $1 AND token_id = $2
-            ORDER BY block_height DESC, id DESC
-            LIMIT $3 OFFSET $4
-            "#,
-        )
-        .bind(&params.account_id)
-        .bind(&token_id)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(&state.db_pool)
-        .await
-    } else {
-        sqlx::query_as::<_, BalanceChange>(
-            r#"
-            SELECT id, account_id, block_height, block_time, token_id, 
-                   receipt_id, transaction_hashes, counterparty, signer_id, receiver_id,
-                   amount, balance_before, balance_after, created_at
-            FROM balance_changes
-            WHERE account_id = $1
-            ORDER BY block_height DESC, id DESC
-            LIMIT $2 OFFSET $3
-            "#,
-        )
-        .bind(&params.account_id)
-        .bind(limit)
-        .bind(offset)
-        .fetch_all(&state.db_pool)
-        .await
+    // NEAR and FT amounts are stored in whole tokens and are scaled by the token's
+    // decimals; intents amounts (`contract:token` ids) are stored in base units already.
+    // Changes of tokens with unknown decimals are kept.
+    let changes = sqlx::query_as::<_, BalanceChange>(
+        r#"
+        SELECT id, account_id, block_height, block_time, token_id, 
+               receipt_id, transaction_hashes, counterparty, signer_id, receiver_id,
+               amount, balance_before, balance_after, created_at
+        FROM balance_changes
+        WHERE account_id = $1
+          AND ($2::TEXT IS NULL OR token_id = $2)
+          AND ($5::NUMERIC IS NULL OR (ABS(amount) * POWER(10::NUMERIC, CASE
+                WHEN token_id LIKE '%:%' THEN 0
+                WHEN LOWER(token_id) IN ('near', 'near-lockup-locked') THEN 24
+                ELSE (SELECT decimals FROM token_decimals d WHERE d.token_id = balance_changes.token_id)
+              END) >= $5) IS NOT FALSE)
+        ORDER BY block_height DESC, id DESC
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(&params.account_id)
+    .bind(&params.token_id)
+    .bind(limit)
+    .bind(offset)
+    .bind(&params.min_abs_amount)
+    .fetch_all(&state.db_pool)
+    .await;
+
+    let mut changes = changes.map_err(|e| {
+        log::error!("Failed to fetch balance changes: {}", e);
+        ApiError::internal("Failed to fetch balance changes").with_details(e.to_string())
+    })?;
+    annotate_counterparty_types(&state, &mut changes).await;
+    annotate_transaction_urls(&state, &mut changes);
+
+    Ok(Json(BalanceChangesResponse {
+        changes,
+        limit_applied: limit,
+        offset,
+    }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TokenChangesQuery {
+    /// Only include changes at or after this block
+    pub from_block: Option<i64>,
+    /// Only include changes at or before this block
+    pub to_block: Option<i64>,
+    /// Only include changes at or after this time
+    pub from_time: Option<DateTime<Utc>>,
+    /// Only include changes at or before this time
+    pub to_time: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenChangesResponse {
+    pub token_id: String,
+    pub changes: Vec<BalanceChange>,
+    /// The page size actually used, after clamping the requested `limit`
+    pub limit_applied: i64,
+    pub offset: i64,
+}
+
+/// Get balance changes of a token across all accounts
+///
+/// Lets a token issuer follow their token through every monitored treasury.
+#[utoipa::path(
+    get,
+    path = "/api/token/{token_id}/changes",
+    tag = "balance-changes",
+    params(
+        ("token_id" = String, Path, description = "Token to aggregate changes for"),
+        TokenChangesQuery,
+        PaginationQuery,
+        NumericQuery
+    ),
+    responses(
+        (status = 200, description = "Balance changes for the token, newest first", body = TokenChangesResponse),
+    )
+)]
+pub async fn get_token_changes(
+    State(state): State<Arc<AppState>>,
+    Path(token_id): Path<String>,
+    Pagination { limit, offset, .. }: Pagination,
+    Query(params): Query<TokenChangesQuery>,
+) -> Result<Json<TokenChangesResponse>, ApiError> {
+    let mut changes = sqlx::query_as::<_, BalanceChange>(
+        r#"
+        SELECT id, account_id, block_height, block_time, token_id, 
+               receipt_id, transaction_hashes, counterparty, signer_id, receiver_id,
+               amount, balance_before, balance_after, created_at
+        FROM balance_changes
+        WHERE token_id = $1
+          AND ($2::BIGINT IS NULL OR block_height >= $2)
+          AND ($3::BIGINT IS NULL OR block_height <= $3)
+          AND ($4::TIMESTAMPTZ IS NULL OR block_time >= $4)
+          AND ($5::TIMESTAMPTZ IS NULL OR block_time <= $5)
+        ORDER BY block_height DESC, id DESC
+        LIMIT $6 OFFSET $7
+        "#,
+    )
+    .bind(&token_id)
+    .bind(params.from_block)
+    .bind(params.to_block)
+    .bind(params.from_time)
+    .bind(params.to_time)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        log::error!("Failed to fetch token balance changes: {}", e);
+        ApiError::internal("Failed to fetch token balance changes").with_details(e.to_string())
+    })?;
+    annotate_counterparty_types(&state, &mut changes).await;
+    annotate_transaction_urls(&state, &mut changes);
+
+    Ok(Json(TokenChangesResponse {
+        token_id,
+        changes,
+        limit_applied: limit,
+        offset,
+    }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TransfersBetweenQuery {
+    pub account_id: String,
+    /// Account on the other side of the transfers
+    pub counterparty: String,
+    /// Only include changes at or after this time
+    pub start_time: Option<DateTime<Utc>>,
+    /// Only include changes at or before this time
+    pub end_time: Option<DateTime<Utc>>,
+}
+
+/// Transfer totals of one token, in whole token units (decimals applied)
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct TokenTransferTotals {
+    pub token_id: String,
+    /// Received from the counterparty
+    #[schema(value_type = String)]
+    pub inflow: BigDecimal,
+    /// Sent to the counterparty
+    #[schema(value_type = String)]
+    pub outflow: BigDecimal,
+    /// `inflow - outflow`
+    #[schema(value_type = String)]
+    pub net: BigDecimal,
+    pub transfers: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TransfersBetweenResponse {
+    pub account_id: String,
+    pub counterparty: String,
+    pub start_time: Option<DateTime<Utc>>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub tokens: Vec<TokenTransferTotals>,
+}
+
+/// Net amounts transferred between an account and a counterparty, per token
+///
+/// Sums the recorded balance changes whose `counterparty` is the given account, so
+/// auditors can reconcile payments over a period.
+#[utoipa::path(
+    get,
+    path = "/api/balance-changes/between",
+    tag = "balance-changes",
+    params(TransfersBetweenQuery),
+    responses(
+        (status = 200, description = "Transfer totals per token", body = TransfersBetweenResponse),
+        (status = 400, description = "start_time is after end_time"),
+    )
+)]
+pub async fn get_transfers_between(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<TransfersBetweenQuery>,
+) -> Result<Json<TransfersBetweenResponse>, ApiError> {
+    if let (Some(start), Some(end)) = (params.start_time, params.end_time)
+        && start > end
+    {
+        return Err(ApiError::bad_request(
+            "start_time must not be after end_time",
+        ));
+    }
+
+    let tokens = sqlx::query_as::<_, TokenTransferTotals>(
+        r#"
+        SELECT token_id,
+               COALESCE(SUM(amount) FILTER (WHERE amount > 0), 0) AS inflow,
+               COALESCE(-SUM(amount) FILTER (WHERE amount < 0), 0) AS outflow,
+               SUM(amount) AS net,
+               COUNT(*) AS transfers
+        FROM balance_changes
+        WHERE account_id = $1
+          AND counterparty = $2
+          AND token_id IS NOT NULL
+          AND ($3::TIMESTAMPTZ IS NULL OR block_time >= $3)
+          AND ($4::TIMESTAMPTZ IS NULL OR block_time <= $4)
+        GROUP BY token_id
+        ORDER BY token_id
+        "#,
+    )
+    .bind(&params.account_id)
+    .bind(&params.counterparty)
+    .bind(params.start_time)
+    .bind(params.end_time)
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        log::error!("Failed to sum transfers between accounts: {}", e);
+        ApiError::internal("Failed to sum transfers").with_details(e.to_string())
+    })?;
+
+    Ok(Json(TransfersBetweenResponse {
+        account_id: params.account_id,
+        counterparty: params.counterparty,
+        start_time: params.start_time,
+        end_time: params.end_time,
+        tokens,
+    }))
+}
+
+/// Max accounts of one latest-balances request
+pub const MAX_LATEST_BALANCES_ACCOUNTS: usize = 200;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LatestBalancesRequest {
+    pub account_ids: Vec<String>,
+}
+
+/// Latest recorded balance of one token of an account
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct LatestTokenBalance {
+    #[serde(skip)]
+    pub account_id: String,
+    pub token_id: String,
+    /// `balance_after` of the latest change
+    #[schema(value_type = String)]
+    pub balance: BigDecimal,
+    pub block_height: i64,
+    pub block_time: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AccountLatestBalances {
+    pub account_id: String,
+    pub tokens: Vec<LatestTokenBalance>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct LatestBalancesResponse {
+    pub accounts: Vec<AccountLatestBalances>,
+}
+
+/// Latest recorded balance of every token, for many accounts at once
+///
+/// Reads the newest record per account and token from the database, so dashboards
+/// can show many treasuries without RPC or FastNear calls. Balances are only as fresh
+/// as the last monitoring cycle.
+#[utoipa::path(
+    post,
+    path = "/api/balance-changes/latest",
+    tag = "balance-changes",
+    params(NumericQuery),
+    request_body = LatestBalancesRequest,
+    responses(
+        (status = 200, description = "Latest balances per account, sorted by account and token", body = LatestBalancesResponse),
+        (status = 400, description = "No accounts, or more than 200"),
+    )
+)]
+pub async fn get_latest_balances(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<LatestBalancesRequest>,
+) -> Result<Json<LatestBalancesResponse>, ApiError> {
+    let mut account_ids = payload.account_ids;
+    account_ids.sort_unstable();
+    account_ids.dedup();
+    if account_ids.is_empty() {
+        return Err(ApiError::bad_request("No account IDs provided"));
+    }
+    if account_ids.len() > MAX_LATEST_BALANCES_ACCOUNTS {
+        return Err(ApiError::bad_request(format!(
+            "At most {} accounts can be queried at once",
+            MAX_LATEST_BALANCES_ACCOUNTS
+        )));
+    }
+
+    let balances = sqlx::query_as::<_, LatestTokenBalance>(
+        r#"
+        SELECT DISTINCT ON (account_id, token_id)
+               account_id, token_id, balance_after AS balance, block_height, block_time
+        FROM balance_changes
+        WHERE account_id = ANY($1) AND token_id IS NOT NULL
+        ORDER BY account_id, token_id, block_height DESC, id DESC
+        "#,
+    )
+    .bind(&account_ids)
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        log::error!("Failed to load latest balances: {}", e);
+        ApiError::internal("Failed to load latest balances").with_details(e.to_string())
+    })?;
+
+    let mut accounts: Vec<AccountLatestBalances> = account_ids
+        .into_iter()
+        .map(|account_id| AccountLatestBalances {
+            account_id,
+            tokens: Vec::new(),
+        })
+        .collect();
+    for balance in balances {
+        if let Ok(index) = accounts.binary_search_by(|a| a.account_id.cmp(&balance.account_id)) {
+            accounts[index].tokens.push(balance);
+        }
+    }
+
+    Ok(Json(LatestBalancesResponse { accounts }))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct VerifyCurrentQuery {
+    pub account_id: String,
+}
+
+/// Latest recorded balance of one token next to FastNear's current balance
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TokenBalanceCheck {
+    pub token_id: String,
+    /// `balance_after` of the latest change; `null` if none was recorded
+    #[schema(value_type = Option<String>)]
+    pub recorded_balance: Option<BigDecimal>,
+    /// Current balance reported by FastNear, in whole token units
+    #[schema(value_type = String)]
+    pub fastnear_balance: BigDecimal,
+    pub matches: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VerifyCurrentResponse {
+    pub account_id: String,
+    /// Tokens whose recorded balance differs from FastNear's
+    pub mismatches: Vec<TokenBalanceCheck>,
+    /// Number of tokens compared
+    pub checked: usize,
+    /// Recorded tokens FastNear doesn't report (intents tokens, FTs without known decimals)
+    pub unchecked: Vec<String>,
+}
+
+/// Compare the latest recorded balances of an account with FastNear's full account
+///
+/// FastNear's raw balances are converted with `utils::decimals`. Tokens
+/// FastNear holds a non-zero balance of but that were never recorded are reported as
+/// mismatches too.
+pub(crate) async fn verify_current_balances(
+    state: &Arc<AppState>,
+    fastnear_base_url: &str,
+    account_id: &str,
+) -> Result<VerifyCurrentResponse, ApiError> {
+    let recorded: Vec<(String, BigDecimal)> = sqlx::query_as(
+        r#"
+        SELECT DISTINCT ON (token_id) token_id, balance_after
+        FROM balance_changes
+        WHERE account_id = $1 AND token_id IS NOT NULL
+        ORDER BY token_id, block_height DESC
+        "#,
+    )
+    .bind(account_id)
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| {
+        log::error!("Failed to load latest balances for {}: {}", account_id, e);
+        ApiError::internal("Failed to load latest balances").with_details(e.to_string())
+    })?;
+
+    let fastnear = fetch_user_balances(state, fastnear_base_url, account_id).await?;
+    let mut fastnear_raw = build_balance_map(&fastnear);
+    if let Some(near) = &fastnear.state {
+        fastnear_raw.insert("near".to_string(), near.balance.clone());
+    }
+
+    let to_decimal = |raw: &str, decimals: u8| {
+        convert_raw_to_decimal(raw, decimals)
+            .ok()
+            .and_then(|value| BigDecimal::from_str(&value).ok())
     };
 
-    match changes {
-        Ok(data) => Ok(Json(data)),
-        Err(e) => {
-            log::error!("Failed to fetch balance changes: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to fetch balance changes",
-                    "details": e.to_string()
-                })),
-            ))
+    let mut checks = Vec::new();
+    let mut unchecked = Vec::new();
+    for (token_id, recorded_balance) in recorded {
+        // FastNear only reports native NEAR and FT contracts
+        let token_decimals = if token_id == LOCKUP_LOCKED_TOKEN_ID || token_id.contains(':') {
+            None
+        } else {
+            decimals(&state.db_pool, &state.network, &token_id)
+                .await
+                .ok()
+        };
+        let raw = fastnear_raw.remove(&token_id.to_lowercase());
+        let fastnear_balance =
+            token_decimals.and_then(|decimals| to_decimal(raw.as_deref().unwrap_or("0"), decimals));
+        let Some(fastnear_balance) = fastnear_balance else {
+            unchecked.push(token_id);
+            continue;
+        };
+
+        checks.push(TokenBalanceCheck {
+            matches: recorded_balance == fastnear_balance,
+            token_id,
+            recorded_balance: Some(recorded_balance),
+            fastnear_balance,
+        });
+    }
+
+    // Tokens FastNear reports that were never recorded. Only decimals already known are
+    // used, as FastNear lists every spam token the account was sent.
+    let mut unrecorded: Vec<(String, String)> = fastnear_raw.into_iter().collect();
+    unrecorded.sort();
+    for (token_id, raw) in unrecorded {
+        if raw.trim_start_matches('0').is_empty() {
+            continue;
+        }
+        let Some(decimals) = known_decimals(&state.db_pool, &token_id)
+            .await
+            .ok()
+            .flatten()
+        else {
+            unchecked.push(token_id);
+            continue;
+        };
+        if let Some(fastnear_balance) = to_decimal(&raw, decimals) {
+            checks.push(TokenBalanceCheck {
+                token_id,
+                recorded_balance: None,
+                fastnear_balance,
+                matches: false,
+            });
         }
     }
+
+    let checked = checks.len();
+    Ok(VerifyCurrentResponse {
+        account_id: account_id.to_string(),
+        mismatches: checks.into_iter().filter(|check| !check.matches).collect(),
+        checked,
+        unchecked,
+    })
+}
+
+/// Cross-check the latest recorded balances of an account against FastNear
+///
+/// A mismatch means the reconstructed history is missing a change, either because the
+/// monitor hasn't caught up with a recent transfer yet or because of a bug.
+#[utoipa::path(
+    get,
+    path = "/api/balance-changes/verify-current",
+    tag = "balance-changes",
+    params(VerifyCurrentQuery),
+    responses(
+        (status = 200, description = "Tokens whose recorded balance differs from FastNear", body = VerifyCurrentResponse),
+        (status = 500, description = "FastNear or the database is unavailable"),
+    )
+)]
+pub async fn verify_current(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<VerifyCurrentQuery>,
+) -> Result<Json<VerifyCurrentResponse>, ApiError> {
+    verify_current_balances(&state, FASTNEAR_API_BASE_URL, &params.account_id)
+        .await
+        .map(Json)
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct FillGapsRequest {
     pub account_id: String,
     pub token_id: String,
     pub up_to_block: Option<i64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct FillGapsResponse {
     pub gaps_filled: usize,
     pub account_id: String,
@@ -112,26 +648,31 @@ pub struct FillGapsResponse {
     pub up_to_block: i64,
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/balance-changes/fill-gaps",
+    tag = "balance-changes",
+    request_body = FillGapsRequest,
+    responses(
+        (status = 200, description = "Gaps filled", body = FillGapsResponse),
+    )
+)]
 pub async fn fill_gaps(
     State(state): State<Arc<AppState>>,
     Json(params): Json<FillGapsRequest>,
-) -> Result<Json<FillGapsResponse>, (StatusCode, Json<Value>)> {
+) -> Result<Json<FillGapsResponse>, ApiError> {
     // Get current block height from RPC if not specified
     let up_to_block = if let Some(block) = params.up_to_block {
         block
     } else {
         // Query current block height from RPC
-        match get_current_block_height(&state.network).await {
+        let head = get_current_block_height(&state.network);
+        match with_timeout(state.external_timeout(), "RPC", head).await? {
             Ok(height) => height as i64,
             Err(e) => {
                 log::error!("Failed to get current block height: {}", e);
-                return Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Json(serde_json::json!({
-                        "error": "Failed to get current block height",
-                        "details": e.to_string()
-                    })),
-                ));
+                return Err(ApiError::internal("Failed to get current block height")
+                    .with_details(e.to_string()));
             }
         }
     };
@@ -143,15 +684,16 @@ pub async fn fill_gaps(
         up_to_block
     );
 
-    match gap_filler::fill_gaps(
+    let config = state.fill_config();
+    let fill = gap_filler::fill_gaps(
         &state.db_pool,
         &state.archival_network,
+        &config,
         &params.account_id,
         &params.token_id,
         up_to_block,
-    )
-    .await
-    {
+    );
+    match with_cancellations(state.fill_cancellations.clone(), fill).await {
         Ok(filled) => Ok(Json(FillGapsResponse {
             gaps_filled: filled.len(),
             account_id: params.account_id,
@@ -160,15 +702,235 @@ pub async fn fill_gaps(
         })),
         Err(e) => {
             log::error!("Failed to fill gaps: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(serde_json::json!({
-                    "error": "Failed to fill gaps",
-                    "details": e.to_string()
-                })),
-            ))
+            Err(ApiError::internal("Failed to fill gaps").with_details(e.to_string()))
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ReprocessRequest {
+    pub account_id: String,
+    pub token_id: String,
+    pub block_height: i64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReprocessResponse {
+    pub record: BalanceChange,
+    /// Mismatches with the neighboring records; empty when the chain is continuous
+    pub warnings: Vec<String>,
+}
+
+/// Recompute the balance change record at a single block
+///
+/// Replaces the existing record with one recomputed from RPC data. The recomputed
+/// balances are compared with the neighboring records and any mismatch is
+/// returned as a warning rather than failing the request. Requires the admin key.
+#[utoipa::path(
+    post,
+    path = "/api/balance-changes/reprocess",
+    tag = "balance-changes",
+    request_body = ReprocessRequest,
+    responses(
+        (status = 200, description = "Record recomputed", body = ReprocessResponse),
+        (status = 400, description = "Block height is not positive"),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn reprocess_balance_change(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(params): Json<ReprocessRequest>,
+) -> Result<Json<ReprocessResponse>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    if params.block_height <= 0 {
+        return Err(ApiError::bad_request(format!(
+            "Invalid block height: {}",
+            params.block_height
+        )));
+    }
+
+    log::info!(
+        "reprocess request: account={}, token={}, block={}",
+        params.account_id,
+        params.token_id,
+        params.block_height
+    );
+
+    let reprocessed = gap_filler::reprocess_block(
+        &state.db_pool,
+        &state.archival_network,
+        &state.fill_config(),
+        &params.account_id,
+        &params.token_id,
+        params.block_height as u64,
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Failed to reprocess block: {}", e);
+        ApiError::internal("Failed to reprocess block").with_details(e.to_string())
+    })?;
+
+    let mut record = sqlx::query_as::<_, BalanceChange>(
+        r#"
+        SELECT id, account_id, block_height, block_time, token_id, 
+               receipt_id, transaction_hashes, counterparty, signer_id, receiver_id,
+               amount, balance_before, balance_after, created_at
+        FROM balance_changes
+        WHERE account_id = $1 AND token_id = $2 AND block_height = $3
+        "#,
+    )
+    .bind(&params.account_id)
+    .bind(&params.token_id)
+    .bind(params.block_height)
+    .fetch_one(&state.db_pool)
+    .await
+    .map_err(|e| {
+        log::error!("Failed to fetch reprocessed record: {}", e);
+        ApiError::internal("Failed to fetch reprocessed record").with_details(e.to_string())
+    })?;
+    annotate_transaction_urls(&state, std::slice::from_mut(&mut record));
+
+    Ok(Json(ReprocessResponse {
+        record,
+        warnings: reprocessed.continuity_warnings,
+    }))
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BalanceChangeDetailResponse {
+    pub record: BalanceChange,
+    /// One entry per hash in the record's `transaction_hashes`
+    pub transactions: Vec<TransactionDetail>,
+    /// Disagreements between the record's FT `amount` and the NEP-141 events its
+    /// transactions logged; empty when they agree or nothing was logged
+    pub reconciliation_warnings: Vec<ReconciliationWarning>,
+}
+
+/// Get a balance change record with its transactions decoded
+///
+/// Transactions are fetched from RPC and cached by hash. For FT records, the amount is
+/// reconciled with the NEP-141 events the transactions logged.
+#[utoipa::path(
+    get,
+    path = "/api/balance-changes/{account_id}/{block_height}/{token_id}/detail",
+    tag = "balance-changes",
+    params(
+        ("account_id" = String, Path, description = "Account the balance change belongs to"),
+        ("block_height" = i64, Path, description = "Block of the balance change"),
+        ("token_id" = String, Path, description = "Token of the balance change"),
+        NumericQuery,
+    ),
+    responses(
+        (status = 200, description = "Record with decoded transactions", body = BalanceChangeDetailResponse),
+        (status = 404, description = "No balance change at this block"),
+    )
+)]
+pub async fn get_balance_change_detail(
+    State(state): State<Arc<AppState>>,
+    Path((account_id, block_height, token_id)): Path<(String, i64, String)>,
+) -> Result<Json<BalanceChangeDetailResponse>, ApiError> {
+    let mut record = sqlx::query_as::<_, BalanceChange>(
+        r#"
+        SELECT id, account_id, block_height, block_time, token_id, 
+               receipt_id, transaction_hashes, counterparty, signer_id, receiver_id,
+               amount, balance_before, balance_after, created_at
+        FROM balance_changes
+        WHERE account_id = $1 AND token_id = $2 AND block_height = $3
+        "#,
+    )
+    .bind(&account_id)
+    .bind(&token_id)
+    .bind(block_height)
+    .fetch_optional(&state.db_pool)
+    .await
+    .map_err(|e| {
+        log::error!("Failed to fetch balance change: {}", e);
+        ApiError::internal("Failed to fetch balance change").with_details(e.to_string())
+    })?
+    .ok_or_else(|| ApiError::not_found("Balance change not found"))?;
+    annotate_transaction_urls(&state, std::slice::from_mut(&mut record));
+
+    // The signer routes the lookup to the right shard; fall back to the account itself
+    let sender_id = record.signer_id.as_deref().unwrap_or(&account_id);
+
+    let mut transactions = Vec::new();
+    for tx_hash in &record.transaction_hashes {
+        let cache_key = format!("tx-detail:{}", tx_hash);
+        if let Some(cached) = state.cache.get(&cache_key).await
+            && let Ok(detail) = serde_json::from_value(cached)
+        {
+            transactions.push(detail);
+            continue;
         }
+
+        let detail = fetch_transaction_detail(&state.archival_network, tx_hash, sender_id)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch transaction {}: {}", tx_hash, e);
+                ApiError::new(StatusCode::BAD_GATEWAY, "Failed to fetch transaction")
+                    .with_details(e.to_string())
+            })?;
+
+        if let Ok(value) = serde_json::to_value(&detail) {
+            state.cache.insert(cache_key, value).await;
+        }
+        transactions.push(detail);
     }
+
+    let reconciliation_warnings = reconcile_record(&state, &record, &transactions).await;
+
+    Ok(Json(BalanceChangeDetailResponse {
+        record,
+        transactions,
+        reconciliation_warnings,
+    }))
+}
+
+/// Reconcile an FT record's amount with the events logged by its transactions
+async fn reconcile_record(
+    state: &AppState,
+    record: &BalanceChange,
+    transactions: &[TransactionDetail],
+) -> Vec<ReconciliationWarning> {
+    if !reconciliation::is_ft_token(&record.token_id) {
+        return Vec::new();
+    }
+    let events: Vec<_> = transactions
+        .iter()
+        .flat_map(|tx| tx.ft_events.iter().cloned())
+        .collect();
+    if reconciliation::net_event_amount(&record.account_id, &record.token_id, &events).is_none() {
+        return Vec::new();
+    }
+
+    let decimals = match decimals(&state.db_pool, &state.network, &record.token_id)
+        .await
+        .map_err(|e| e.to_string())
+    {
+        Ok(decimals) => decimals,
+        Err(e) => {
+            log::warn!(
+                "Skipping reconciliation of {}: unknown decimals ({})",
+                record.token_id,
+                e
+            );
+            return Vec::new();
+        }
+    };
+
+    reconciliation::reconcile_ft_amount(
+        &record.account_id,
+        &record.token_id,
+        &record.amount,
+        decimals,
+        &events,
+    )
+    .into_iter()
+    .collect()
 }
 
 async fn get_current_block_height(
@@ -177,3 +939,771 @@ async fn get_current_block_height(
     let block = near_api::Chain::block().fetch_from_mainnet().await?;
     Ok(block.header.height)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::pagination::ListLimits;
+    use crate::utils::test_utils::init_test_state;
+    use sqlx::PgPool;
+
+    /// Page with the configured limits, as the `Pagination` extractor resolves it
+    fn page(state: &AppState, limit: Option<i64>) -> Pagination {
+        Pagination::resolve(
+            PaginationQuery {
+                limit,
+                ..Default::default()
+            },
+            ListLimits::from_env_vars(&state.env_vars),
+        )
+    }
+
+    #[sqlx::test]
+    async fn test_balance_changes_limit_is_clamped(pool: PgPool) -> sqlx::Result<()> {
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        let pagination = page(&state, Some(100_000));
+
+        let response = get_balance_changes(
+            State(Arc::new(state)),
+            pagination,
+            Query(BalanceChangesQuery {
+                account_id: "test.near".to_string(),
+                token_id: None,
+                min_abs_amount: None,
+            }),
+        )
+        .await
+        .expect("Query should succeed");
+
+        assert_eq!(response.limit_applied, 1000);
+        assert!(response.changes.is_empty());
+
+        Ok(())
+    }
+
+    async fn insert_record_with_tx(
+        pool: &PgPool,
+        account_id: &str,
+        token_id: &str,
+        block_height: i64,
+        tx_hash: &str,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO balance_changes
+            (account_id, token_id, block_height, block_timestamp, block_time, amount, balance_before,
+             balance_after, transaction_hashes, counterparty, actions, raw_data)
+            VALUES ($1, $2, $3, 1, NOW(), 1, 0, 1, $4, 'sender.near', '{}', '{}')
+            "#,
+        )
+        .bind(account_id)
+        .bind(token_id)
+        .bind(block_height)
+        .bind(vec![tx_hash.to_string()])
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_transaction_url_only_for_records_with_tx(pool: PgPool) -> sqlx::Result<()> {
+        insert_record_with_tx(
+            &pool,
+            "linked.near",
+            "near",
+            200,
+            "9fQyXbzMRm3fMXtjzSpbgzRTnqhT6FGrk4K6dBpiQQ1G",
+        )
+        .await?;
+        sqlx::query(
+            r#"
+            INSERT INTO balance_changes
+            (account_id, token_id, block_height, block_timestamp, block_time, amount, balance_before,
+             balance_after, counterparty, actions, raw_data)
+            VALUES ('linked.near', 'near', 100, 1, NOW(), 0, 1, 1, 'SNAPSHOT', '{}', '{}')
+            "#,
+        )
+        .execute(&pool)
+        .await?;
+
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        state.env_vars.explorer_tx_url_base = "https://explorer.example/tx/".to_string();
+
+        let pagination = page(&state, None);
+        let Json(response) = get_balance_changes(
+            State(Arc::new(state)),
+            pagination,
+            Query(BalanceChangesQuery {
+                account_id: "linked.near".to_string(),
+                token_id: None,
+                min_abs_amount: None,
+            }),
+        )
+        .await
+        .unwrap();
+
+        let urls: Vec<(i64, Option<&str>)> = response
+            .changes
+            .iter()
+            .map(|c| (c.block_height, c.transaction_url.as_deref()))
+            .collect();
+        assert_eq!(
+            urls,
+            vec![
+                (
+                    200,
+                    Some(
+                        "https://explorer.example/tx/9fQyXbzMRm3fMXtjzSpbgzRTnqhT6FGrk4K6dBpiQQ1G"
+                    )
+                ),
+                (100, None),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_detail_missing_record_is_not_found(pool: PgPool) -> sqlx::Result<()> {
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+
+        let ApiError { status, .. } = get_balance_change_detail(
+            State(Arc::new(state)),
+            Path(("test.near".to_string(), 100, "near".to_string())),
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_reprocess_requires_admin_key(pool: PgPool) -> sqlx::Result<()> {
+        insert_record_with_tx(&pool, "test.near", "near", 100, "tx1").await?;
+
+        let mut state = init_test_state().await;
+        state.db_pool = pool.clone();
+        state.env_vars.admin_api_key = Some("admin-key".to_string());
+
+        let ApiError { status, .. } = reprocess_balance_change(
+            State(Arc::new(state)),
+            HeaderMap::new(),
+            Json(ReprocessRequest {
+                account_id: "test.near".to_string(),
+                token_id: "near".to_string(),
+                block_height: 100,
+            }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let remaining: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM balance_changes WHERE account_id = 'test.near'",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(remaining, 1);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_reprocess_rejects_non_positive_block_height(pool: PgPool) -> sqlx::Result<()> {
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        state.env_vars.admin_api_key = Some("admin-key".to_string());
+        let state = Arc::new(state);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            axum::http::header::AUTHORIZATION,
+            "Bearer admin-key".parse().unwrap(),
+        );
+        for block_height in [0, -1] {
+            let ApiError { status, .. } = reprocess_balance_change(
+                State(state.clone()),
+                headers.clone(),
+                Json(ReprocessRequest {
+                    account_id: "test.near".to_string(),
+                    token_id: "near".to_string(),
+                    block_height,
+                }),
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+        }
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_detail_uses_cached_transaction(pool: PgPool) -> sqlx::Result<()> {
+        insert_record_with_tx(&pool, "test.near", "near", 100, "CachedTxHash").await?;
+
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        state
+            .cache
+            .insert(
+                "tx-detail:CachedTxHash".to_string(),
+                serde_json::json!({
+                    "transaction_hash": "CachedTxHash",
+                    "signer_id": "sender.near",
+                    "receiver_id": "test.near",
+                    "actions": [{"type": "transfer", "deposit": "1"}]
+                }),
+            )
+            .await;
+
+        let response = get_balance_change_detail(
+            State(Arc::new(state)),
+            Path(("test.near".to_string(), 100, "near".to_string())),
+        )
+        .await
+        .expect("Cached transaction should not need RPC");
+
+        assert_eq!(response.transactions.len(), 1);
+        assert_eq!(response.transactions[0].signer_id, "sender.near");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_detail_flags_ft_amount_mismatch(pool: PgPool) -> sqlx::Result<()> {
+        // The record says 1 token arrived, the logged event says 2
+        insert_record_with_tx(&pool, "test.near", "reconcile.near", 100, "MismatchTx").await?;
+        sqlx::query(
+            "INSERT INTO token_decimals (token_id, decimals, source) VALUES ('reconcile.near', 6, 'ft_metadata')",
+        )
+        .execute(&pool)
+        .await?;
+
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        state
+            .cache
+            .insert(
+                "tx-detail:MismatchTx".to_string(),
+                serde_json::json!({
+                    "transaction_hash": "MismatchTx",
+                    "signer_id": "sender.near",
+                    "receiver_id": "reconcile.near",
+                    "actions": [],
+                    "ft_events": [{
+                        "token_contract": "reconcile.near",
+                        "event": {
+                            "event": "ft_transfer",
+                            "data": [{
+                                "old_owner_id": "sender.near",
+                                "new_owner_id": "test.near",
+                                "amount": "2000000"
+                            }]
+                        }
+                    }]
+                }),
+            )
+            .await;
+
+        let response = get_balance_change_detail(
+            State(Arc::new(state)),
+            Path(("test.near".to_string(), 100, "reconcile.near".to_string())),
+        )
+        .await
+        .expect("Cached transaction should not need RPC");
+
+        assert_eq!(response.reconciliation_warnings.len(), 1);
+        let warning = &response.reconciliation_warnings[0];
+        assert_eq!(warning.recorded_amount, "1");
+        assert_eq!(warning.event_amount, "2");
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_detail_decodes_ft_transfer(pool: PgPool) -> sqlx::Result<()> {
+        use crate::handlers::balance_changes::transaction_detail::ActionSummary;
+
+        // Block 178148636 has an arizcredits.near FT transfer to the treasury
+        let account_id = "webassemblymusic-treasury.sputnik-dao.near";
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+
+        gap_filler::insert_balance_change_record(
+            &state.db_pool,
+            &state.archival_network,
+            &state.fill_config(),
+            account_id,
+            "arizcredits.near",
+            178148636,
+        )
+        .await
+        .expect("Should insert the FT transfer record");
+
+        let response = get_balance_change_detail(
+            State(Arc::new(state)),
+            Path((
+                account_id.to_string(),
+                178148636,
+                "arizcredits.near".to_string(),
+            )),
+        )
+        .await
+        .expect("Should decode the transaction");
+
+        let methods: Vec<&str> = response
+            .transactions
+            .iter()
+            .flat_map(|tx| &tx.actions)
+            .filter_map(|action| match action {
+                ActionSummary::FunctionCall { method_name, .. } => Some(method_name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert!(
+            methods
+                .iter()
+                .any(|m| *m == "ft_transfer" || *m == "ft_transfer_call"),
+            "Expected an FT transfer call, got {:?}",
+            methods
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_token_changes_span_accounts(pool: PgPool) -> sqlx::Result<()> {
+        insert_record_with_tx(&pool, "alice.near", "usdc.near", 100, "TxA").await?;
+        insert_record_with_tx(&pool, "bob.near", "usdc.near", 200, "TxB").await?;
+        insert_record_with_tx(&pool, "bob.near", "near", 300, "TxC").await?;
+
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        let state = Arc::new(state);
+
+        let query = |from_block| TokenChangesQuery {
+            from_block,
+            to_block: None,
+            from_time: None,
+            to_time: None,
+        };
+
+        let response = get_token_changes(
+            State(state.clone()),
+            Path("usdc.near".to_string()),
+            page(&state, None),
+            Query(query(None)),
+        )
+        .await
+        .expect("Query should succeed");
+
+        let accounts: Vec<&str> = response
+            .changes
+            .iter()
+            .map(|c| c.account_id.as_str())
+            .collect();
+        assert_eq!(accounts, vec!["bob.near", "alice.near"]);
+
+        let response = get_token_changes(
+            State(state.clone()),
+            Path("usdc.near".to_string()),
+            page(&state, None),
+            Query(query(Some(150))),
+        )
+        .await
+        .expect("Query should succeed");
+
+        assert_eq!(response.changes.len(), 1);
+        assert_eq!(response.changes[0].account_id, "bob.near");
+
+        Ok(())
+    }
+
+    async fn insert_transfer(
+        pool: &PgPool,
+        token_id: &str,
+        block_time: &str,
+        amount: &str,
+        counterparty: &str,
+    ) -> sqlx::Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO balance_changes
+            (account_id, token_id, block_height, block_timestamp, block_time, amount, balance_before,
+             balance_after, counterparty, actions, raw_data)
+            VALUES ('treasury.near', $1, EXTRACT(EPOCH FROM $2::TIMESTAMPTZ)::BIGINT, 1, $2::TIMESTAMPTZ,
+                    $3::NUMERIC, 0, $3::NUMERIC, $4, '{}', '{}')
+            "#,
+        )
+        .bind(token_id)
+        .bind(block_time)
+        .bind(amount)
+        .bind(counterparty)
+        .execute(pool)
+        .await?;
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_transfers_between_nets_per_token(pool: PgPool) -> sqlx::Result<()> {
+        insert_transfer(
+            &pool,
+            "usdc.near",
+            "2025-01-05T00:00:00Z",
+            "100.5",
+            "vendor.near",
+        )
+        .await?;
+        insert_transfer(
+            &pool,
+            "usdc.near",
+            "2025-01-10T00:00:00Z",
+            "-250",
+            "vendor.near",
+        )
+        .await?;
+        insert_transfer(&pool, "near", "2025-01-12T00:00:00Z", "3", "vendor.near").await?;
+        // Other counterparties and transfers outside the period are left out
+        insert_transfer(
+            &pool,
+            "usdc.near",
+            "2025-01-11T00:00:00Z",
+            "-40",
+            "other.near",
+        )
+        .await?;
+        insert_transfer(
+            &pool,
+            "usdc.near",
+            "2025-03-01T00:00:00Z",
+            "-1000",
+            "vendor.near",
+        )
+        .await?;
+
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        let state = Arc::new(state);
+
+        let query = |start_time: &str, end_time: &str| {
+            Query(TransfersBetweenQuery {
+                account_id: "treasury.near".to_string(),
+                counterparty: "vendor.near".to_string(),
+                start_time: Some(start_time.parse().unwrap()),
+                end_time: Some(end_time.parse().unwrap()),
+            })
+        };
+
+        let Json(response) = get_transfers_between(
+            State(state.clone()),
+            query("2025-01-01T00:00:00Z", "2025-01-31T23:59:59Z"),
+        )
+        .await
+        .unwrap();
+
+        let totals: Vec<(&str, String, String, String, i64)> = response
+            .tokens
+            .iter()
+            .map(|t| {
+                (
+                    t.token_id.as_str(),
+                    t.inflow.normalized().to_string(),
+                    t.outflow.normalized().to_string(),
+                    t.net.normalized().to_string(),
+                    t.transfers,
+                )
+            })
+            .collect();
+        assert_eq!(
+            totals,
+            vec![
+                ("near", "3".into(), "0".into(), "3".into(), 1),
+                (
+                    "usdc.near",
+                    "100.5".into(),
+                    "250".into(),
+                    "-149.5".into(),
+                    2
+                ),
+            ]
+        );
+
+        let ApiError { status, .. } = get_transfers_between(
+            State(state),
+            query("2025-02-01T00:00:00Z", "2025-01-01T00:00:00Z"),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_verify_current_reports_stale_balance(pool: PgPool) -> sqlx::Result<()> {
+        use axum::{Router, routing::get};
+
+        sqlx::query(
+            "INSERT INTO counterparties (account_id, account_type, token_decimals) VALUES ('usdc.near', 'ft_token', 6)",
+        )
+        .execute(&pool)
+        .await?;
+        insert_transfer(&pool, "near", "2025-01-05T00:00:00Z", "2", "vendor.near").await?;
+        // Stale: a later withdrawal of 5 USDC was never recorded
+        insert_transfer(
+            &pool,
+            "usdc.near",
+            "2025-01-05T00:00:00Z",
+            "10",
+            "vendor.near",
+        )
+        .await?;
+        insert_transfer(
+            &pool,
+            "intents.near:nep141:btc.omft.near",
+            "2025-01-05T00:00:00Z",
+            "1",
+            "vendor.near",
+        )
+        .await?;
+
+        let app = Router::new().route(
+            "/v1/account/{account}/full",
+            get(|| async {
+                Json(serde_json::json!({
+                    "tokens": [
+                        {"contract_id": "usdc.near", "balance": "5000000"},
+                        {"contract_id": "dust.near", "balance": "0"},
+                        {"contract_id": "unknown.near", "balance": "7"},
+                    ],
+                    "state": {"balance": "2000000000000000000000000"}
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        let response = verify_current_balances(&Arc::new(state), &base_url, "treasury.near")
+            .await
+            .unwrap();
+
+        assert_eq!(response.checked, 2);
+        let mismatches: Vec<(&str, String, String)> = response
+            .mismatches
+            .iter()
+            .map(|m| {
+                (
+                    m.token_id.as_str(),
+                    m.recorded_balance
+                        .as_ref()
+                        .unwrap()
+                        .normalized()
+                        .to_string(),
+                    m.fastnear_balance.normalized().to_string(),
+                )
+            })
+            .collect();
+        assert_eq!(mismatches, vec![("usdc.near", "10".into(), "5".into())]);
+        assert_eq!(
+            response.unchecked,
+            vec!["intents.near:nep141:btc.omft.near", "unknown.near"]
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_min_abs_amount_filters_dust(pool: PgPool) -> sqlx::Result<()> {
+        let records = [
+            // 0.000001 NEAR = 10^18 yoctoNEAR
+            ("near", 1, "-0.000001"),
+            ("near", 2, "5"),
+            // Intents amounts are stored in base units
+            ("intents.near:nep141:btc.omft.near", 3, "100"),
+            (
+                "intents.near:nep141:btc.omft.near",
+                4,
+                "100000000000000000000000",
+            ),
+            // 0.5 USDC = 500000 base units
+            ("usdc.near", 5, "0.5"),
+            ("usdc.near", 6, "-2000000000000000000"),
+            // Unknown decimals: kept
+            ("unknown-decimals.near", 7, "1"),
+        ];
+        for (token_id, block_height, amount) in records {
+            sqlx::query(
+                r#"
+                INSERT INTO balance_changes
+                (account_id, token_id, block_height, block_timestamp, block_time, amount,
+                 balance_before, balance_after, counterparty, actions, raw_data)
+                VALUES ('dust.near', $1, $2, 1, NOW(), $3::NUMERIC, 0, 0, 'sender.near', '{}', '{}')
+                "#,
+            )
+            .bind(token_id)
+            .bind(block_height)
+            .bind(amount)
+            .execute(&pool)
+            .await?;
+        }
+        // USDC metadata as discovered earlier; resolving its decimals, as recording its
+        // balances does, puts them in the registry the filter reads
+        sqlx::query(
+            "INSERT INTO counterparties (account_id, account_type, token_decimals) VALUES ('usdc.near', 'ft_token', 6)",
+        )
+        .execute(&pool)
+        .await?;
+
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        let state = Arc::new(state);
+        assert_eq!(
+            decimals(&state.db_pool, &state.network, "usdc.near")
+                .await
+                .unwrap(),
+            6
+        );
+        let heights = |token_id: Option<&str>, min_abs_amount: Option<&str>| {
+            let state = state.clone();
+            let query = BalanceChangesQuery {
+                account_id: "dust.near".to_string(),
+                token_id: token_id.map(str::to_string),
+                min_abs_amount: min_abs_amount.map(|min| BigDecimal::from_str(min).unwrap()),
+            };
+            async move {
+                let Json(response) =
+                    get_balance_changes(State(state.clone()), page(&state, None), Query(query))
+                        .await
+                        .unwrap();
+                response
+                    .changes
+                    .iter()
+                    .map(|c| c.block_height)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(heights(None, None).await, vec![7, 6, 5, 4, 3, 2, 1]);
+        // 10^21 base units: 0.001 NEAR, 10^21 raw intents units, 10^15 USDC
+        assert_eq!(
+            heights(None, Some("1000000000000000000000")).await,
+            vec![7, 6, 4, 2]
+        );
+        assert_eq!(
+            heights(Some("near"), Some("1000000000000000000")).await,
+            vec![2, 1]
+        );
+        assert_eq!(heights(Some("usdc.near"), Some("1000000")).await, vec![6]);
+
+        let error = get_balance_changes(
+            State(state.clone()),
+            page(&state, None),
+            Query(BalanceChangesQuery {
+                account_id: "dust.near".to_string(),
+                token_id: None,
+                min_abs_amount: Some(BigDecimal::from(-1)),
+            }),
+        )
+        .await
+        .expect_err("A negative threshold should be rejected");
+        assert_eq!(error.status, StatusCode::BAD_REQUEST);
+
+        Ok(())
+    }
+
+    #[sqlx::test]
+    async fn test_latest_balances_for_many_accounts(pool: PgPool) -> sqlx::Result<()> {
+        let records = [
+            ("alpha.near", "near", 100, "1"),
+            ("alpha.near", "near", 300, "3"),
+            ("alpha.near", "near", 200, "2"),
+            ("alpha.near", "usdc.near", 150, "10.5"),
+            ("beta.near", "near", 50, "7"),
+            ("other.near", "near", 400, "9"),
+        ];
+        for (account_id, token_id, block_height, balance) in records {
+            sqlx::query(
+                r#"
+                INSERT INTO balance_changes
+                (account_id, token_id, block_height, block_timestamp, block_time, amount,
+                 balance_before, balance_after, counterparty, actions, raw_data)
+                VALUES ($1, $2, $3, 1, NOW(), 0, 0, $4::NUMERIC, 'sender.near', '{}', '{}')
+                "#,
+            )
+            .bind(account_id)
+            .bind(token_id)
+            .bind(block_height)
+            .bind(balance)
+            .execute(&pool)
+            .await?;
+        }
+
+        // Unreachable RPC: the balances must come from the database alone
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        state.network = near_api::NetworkConfig {
+            rpc_endpoints: vec![near_api::RPCEndpoint::new(
+                "http://127.0.0.1:1/".parse().unwrap(),
+            )],
+            ..near_api::NetworkConfig::mainnet()
+        };
+        state.archival_network = state.network.clone();
+
+        let Json(response) = get_latest_balances(
+            State(Arc::new(state)),
+            Json(LatestBalancesRequest {
+                account_ids: vec![
+                    "beta.near".to_string(),
+                    "alpha.near".to_string(),
+                    "empty.near".to_string(),
+                    "beta.near".to_string(),
+                ],
+            }),
+        )
+        .await
+        .unwrap();
+
+        let balances: Vec<_> = response
+            .accounts
+            .iter()
+            .map(|account| {
+                let tokens = account
+                    .tokens
+                    .iter()
+                    .map(|t| {
+                        (
+                            t.token_id.as_str(),
+                            t.balance.normalized().to_string(),
+                            t.block_height,
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                (account.account_id.as_str(), tokens)
+            })
+            .collect();
+        assert_eq!(
+            balances,
+            vec![
+                (
+                    "alpha.near",
+                    vec![
+                        ("near", "3".to_string(), 300),
+                        ("usdc.near", "10.5".to_string(), 150)
+                    ]
+                ),
+                ("beta.near", vec![("near", "7".to_string(), 50)]),
+                ("empty.near", vec![]),
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -2,15 +2,22 @@ use axum::{
     Json, Router,
     extract::State,
     http::StatusCode,
+    middleware,
     routing::{get, patch, post},
 };
 use serde_json::{Value, json};
 use std::sync::Arc;
+use std::time::Duration;
 
+use crate::handlers::balance_changes::rpc_rate_limit::rate_limit_layer;
+use crate::utils::numeric::numeric_balances_layer;
 use crate::{AppState, handlers};
 
+pub(crate) mod admin;
 mod balance_changes;
 mod monitored_accounts;
+mod openapi;
+mod webhooks;
 
 async fn health_check(
     State(state): State<Arc<AppState>>,
@@ -23,6 +30,20 @@ async fn health_check(
 
     let pool_size = state.db_pool.size();
     let idle_connections = state.db_pool.num_idle();
+    let whitelist_refreshed_at = state
+        .ref_whitelist_refreshed_at
+        .read()
+        .await
+        .map(|t| t.to_rfc3339());
+
+    let monitor_enabled = !state.env_vars.disable_balance_monitoring;
+    let monitor_interval = Duration::from_secs(state.env_vars.monitor_interval_minutes * 60);
+    let now = chrono::Utc::now();
+    let monitor_stale = monitor_enabled && state.monitor_liveness.is_stale(monitor_interval, now);
+    let last_cycle_completed_at = state
+        .monitor_liveness
+        .last_cycle_completed_at()
+        .map(|t| t.to_rfc3339());
 
     if !db_connected {
         return Err((
@@ -39,12 +60,22 @@ async fn health_check(
     }
 
     Ok(Json(json!({
-        "status": "healthy",
-        "timestamp": chrono::Utc::now().to_rfc3339(),
+        // Stale monitoring data doesn't stop the API from serving
+        "status": if monitor_stale { "degraded" } else { "healthy" },
+        "timestamp": now.to_rfc3339(),
         "database": {
             "connected": true,
             "pool_size": pool_size,
             "idle_connections": idle_connections
+        },
+        "ref_whitelist": {
+            "last_refreshed_at": whitelist_refreshed_at
+        },
+        "monitor": {
+            "enabled": monitor_enabled,
+            "last_cycle_completed_at": last_cycle_completed_at,
+            "stale": monitor_stale,
+            "restarts": state.monitor_liveness.restarts()
         }
     })))
 }
@@ -53,20 +84,81 @@ pub fn create_routes(state: Arc<AppState>) -> Router {
     Router::new()
         // Health check
         .route("/api/health", get(health_check))
+        // OpenAPI document
+        .route("/api/openapi.json", get(openapi::get_openapi))
         // Balance changes endpoint
         .route(
             "/api/balance-changes",
-            get(balance_changes::get_balance_changes),
+            get(balance_changes::get_balance_changes)
+                .layer(middleware::from_fn(numeric_balances_layer)),
+        )
+        .route(
+            "/api/balance-changes/latest",
+            post(balance_changes::get_latest_balances)
+                .layer(middleware::from_fn(numeric_balances_layer)),
+        )
+        .route(
+            "/api/balance-changes/between",
+            get(balance_changes::get_transfers_between),
+        )
+        .route(
+            "/api/balance-changes/verify-current",
+            get(balance_changes::verify_current),
         )
         .route(
             "/api/balance-changes/fill-gaps",
             post(balance_changes::fill_gaps),
         )
+        .route(
+            "/api/balance-changes/reprocess",
+            post(balance_changes::reprocess_balance_change),
+        )
+        .route(
+            "/api/balance-changes/{account_id}/{block_height}/{token_id}/detail",
+            get(balance_changes::get_balance_change_detail)
+                .layer(middleware::from_fn(numeric_balances_layer)),
+        )
+        // Admin endpoints
+        .route("/api/admin/rebuild", post(admin::rebuild_chain))
+        .route(
+            "/api/admin/collapse-duplicates",
+            post(admin::collapse_duplicates),
+        )
+        .route("/api/admin/monitor/progress", get(admin::monitor_progress))
+        .route(
+            "/api/admin/monitor/cancel/{account_id}",
+            post(admin::cancel_fill),
+        )
+        .route(
+            "/api/admin/monitor/ceiling",
+            post(admin::set_monitor_ceiling),
+        )
+        .route(
+            "/api/admin/token-aliases",
+            get(admin::list_token_aliases).post(admin::register_token_alias),
+        )
+        .route("/api/admin/migrations", get(admin::list_migrations))
+        .route("/api/admin/gaps", get(admin::list_gaps))
+        .route("/api/admin/fill-failures", get(admin::list_fill_failures))
+        .route("/api/admin/cache/stats", get(admin::cache_stats))
+        .route(
+            "/api/admin/cache/invalidate",
+            post(admin::invalidate_cache),
+        )
         // Token endpoints
+        .route(
+            "/api/token/{token_id}/changes",
+            get(balance_changes::get_token_changes)
+                .layer(middleware::from_fn(numeric_balances_layer)),
+        )
         .route(
             "/api/token/metadata",
             get(handlers::token::metadata::get_token_metadata),
         )
+        .route(
+            "/api/token/metadata/batch",
+            post(handlers::token::metadata::get_batch_token_metadata),
+        )
         .route(
             "/api/token/storage-deposit/is-registered",
             get(handlers::token::storage_deposit::is_registered::is_storage_deposit_registered),
@@ -94,16 +186,28 @@ pub fn create_routes(state: Arc<AppState>) -> Router {
         // User endpoints
         .route(
             "/api/user/balance",
-            get(handlers::user::balance::get_token_balance),
+            get(handlers::user::balance::get_token_balance)
+                .layer(middleware::from_fn(numeric_balances_layer)),
+        )
+        .route(
+            "/api/user/balance/at-time",
+            get(handlers::user::balance::get_token_balance_at_time)
+                .layer(middleware::from_fn(numeric_balances_layer)),
         )
         .route(
             "/api/user/balance/history",
-            get(handlers::user::balance_history::get_token_balance_history),
+            get(handlers::user::balance_history::get_token_balance_history)
+                .layer(middleware::from_fn(numeric_balances_layer)),
         )
         .route(
             "/api/user/treasuries",
             get(handlers::user::treasuries::get_user_treasuries),
         )
+        .route(
+            "/api/user/tokens",
+            get(handlers::user::tokens::get_user_tokens)
+                .layer(middleware::from_fn(numeric_balances_layer)),
+        )
         .route(
             "/api/user/assets",
             get(handlers::user::assets::get_user_assets),
@@ -120,6 +224,10 @@ pub fn create_routes(state: Arc<AppState>) -> Router {
             "/api/user/check-account-exists",
             get(handlers::user::check_account_exists::check_account_exists),
         )
+        .route(
+            "/api/user/check-account-exists/batch",
+            post(handlers::user::check_account_exists::check_accounts_exist_batch),
+        )
         // Proposals endpoints
         .route(
             "/api/proposals/{dao_id}",
@@ -129,7 +237,23 @@ pub fn create_routes(state: Arc<AppState>) -> Router {
             "/api/proposal/{dao_id}/{proposal_id}",
             get(handlers::proposals::get_proposals::get_proposal),
         )
+        .route(
+            "/api/proposal/{dao_id}/{proposal_id}/votes",
+            get(handlers::proposals::votes::get_proposal_votes),
+        )
         // Lookup endpoints
+        .route(
+            "/api/block/at-time",
+            get(handlers::block::timestamp::get_block_at_time),
+        )
+        .route(
+            "/api/block/{height}/timestamp",
+            get(handlers::block::timestamp::get_block_timestamp),
+        )
+        .route(
+            "/api/receipt/{receipt_id}/outcome",
+            get(handlers::block::receipt::get_receipt_outcome),
+        )
         .route(
             "/api/lockup/pool",
             get(handlers::lookup::pool::get_lockup_pool),
@@ -150,15 +274,69 @@ pub fn create_routes(state: Arc<AppState>) -> Router {
             patch(monitored_accounts::update_monitored_account)
                 .delete(monitored_accounts::delete_monitored_account),
         )
+        .route(
+            "/api/monitored-accounts/{account_id}/tokens/{token_id}",
+            patch(monitored_accounts::update_monitored_token),
+        )
+        // Webhook endpoints
+        .route(
+            "/api/webhooks",
+            post(webhooks::register_webhook).get(webhooks::list_webhooks),
+        )
         // Intents endpoints
         .route(
             "/api/intents/search-tokens",
             get(handlers::intents::search_tokens::search_tokens),
         )
+        .route(
+            "/api/intents/tokens",
+            get(handlers::intents::list_tokens::list_tokens),
+        )
+        .route(
+            "/api/intents/resolve",
+            get(handlers::intents::resolve::resolve_token),
+        )
         // Proxy endpoints - catch-all for external API
         .route(
             "/api/proxy/{*path}",
-            get(handlers::proxy::external::proxy_external_api),
+            get(handlers::proxy::external::proxy_external_api)
+                .post(handlers::proxy::external::proxy_external_api),
         )
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit_layer,
+        ))
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::init_test_state;
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn test_health_reports_stale_monitor(pool: PgPool) -> sqlx::Result<()> {
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        state.env_vars.disable_balance_monitoring = false;
+        state.env_vars.monitor_interval_minutes = 5;
+        state
+            .monitor_liveness
+            .record_cycle_completed_at(chrono::Utc::now() - chrono::Duration::minutes(30));
+        let state = Arc::new(state);
+
+        let Json(body) = health_check(State(state.clone())).await.unwrap();
+        assert_eq!(body["status"], "degraded");
+        assert_eq!(body["monitor"]["stale"], true);
+        assert!(body["monitor"]["last_cycle_completed_at"].is_string());
+
+        state.monitor_liveness.record_cycle_completed();
+        let Json(body) = health_check(State(state)).await.unwrap();
+        assert_eq!(body["status"], "healthy");
+        assert_eq!(body["monitor"]["stale"], false);
+        assert_eq!(body["monitor"]["restarts"], 0);
+
+        Ok(())
+    }
+}
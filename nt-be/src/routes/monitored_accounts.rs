@@ -4,22 +4,26 @@ use axum::{
     http::StatusCode,
 };
 use serde::{Deserialize, Serialize};
-use serde_json::{Value, json};
 use sqlx::types::chrono::{DateTime, Utc};
 use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
 
 use crate::AppState;
+use crate::utils::api_error::ApiError;
+use crate::utils::pagination::{Pagination, PaginationQuery};
 
-#[derive(Debug, Serialize, sqlx::FromRow)]
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
 pub struct MonitoredAccount {
     pub account_id: String,
     pub enabled: bool,
     pub last_synced_at: Option<DateTime<Utc>>,
+    /// Record gas-reward-only NEAR changes as one record per run
+    pub coalesce_gas_rewards: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct AddAccountRequest {
     pub account_id: String,
     #[serde(default = "default_enabled")]
@@ -30,21 +34,48 @@ fn default_enabled() -> bool {
     true
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct ListAccountsQuery {
     pub enabled: Option<bool>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateAccountRequest {
+    pub enabled: Option<bool>,
+    /// Record gas-reward-only NEAR changes as one record per run
+    pub coalesce_gas_rewards: Option<bool>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct DiscoveredToken {
+    pub account_id: String,
+    pub token_id: String,
+    pub enabled: bool,
+    pub classification: String,
+    pub discovered_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct UpdateTokenRequest {
     pub enabled: bool,
 }
 
 /// Add a new monitored account
+#[utoipa::path(
+    post,
+    path = "/api/monitored-accounts",
+    tag = "monitored-accounts",
+    request_body = AddAccountRequest,
+    responses(
+        (status = 200, description = "Account registered", body = MonitoredAccount),
+    )
+)]
 pub async fn add_monitored_account(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<AddAccountRequest>,
-) -> Result<Json<MonitoredAccount>, (StatusCode, Json<Value>)> {
+) -> Result<Json<MonitoredAccount>, ApiError> {
     let account = sqlx::query_as::<_, MonitoredAccount>(
         r#"
         INSERT INTO monitored_accounts (account_id, enabled)
@@ -52,102 +83,111 @@ pub async fn add_monitored_account(
         ON CONFLICT (account_id) DO UPDATE
         SET enabled = EXCLUDED.enabled,
             updated_at = NOW()
-        RETURNING account_id, enabled, last_synced_at, created_at, updated_at
+        RETURNING account_id, enabled, last_synced_at, coalesce_gas_rewards, created_at, updated_at
         "#,
     )
     .bind(&payload.account_id)
     .bind(payload.enabled)
     .fetch_one(&state.db_pool)
     .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({ "error": format!("Database error: {}", e) })),
-        )
-    })?;
+    .map_err(|e| ApiError::internal(format!("Database error: {}", e)))?;
 
     Ok(Json(account))
 }
 
 /// List monitored accounts
+///
+/// Accounts are ordered by id and paged with `limit`/`offset`; `cursor` starts the
+/// page after the given account id (pass the last id of the previous page).
+#[utoipa::path(
+    get,
+    path = "/api/monitored-accounts",
+    tag = "monitored-accounts",
+    params(ListAccountsQuery, PaginationQuery),
+    responses(
+        (status = 200, description = "Monitored accounts", body = Vec<MonitoredAccount>),
+    )
+)]
 pub async fn list_monitored_accounts(
     State(state): State<Arc<AppState>>,
+    pagination: Pagination,
     Query(params): Query<ListAccountsQuery>,
-) -> Result<Json<Vec<MonitoredAccount>>, (StatusCode, Json<Value>)> {
-    let accounts = if let Some(enabled) = params.enabled {
-        sqlx::query_as::<_, MonitoredAccount>(
-            r#"
-            SELECT account_id, enabled, last_synced_at, created_at, updated_at
-            FROM monitored_accounts
-            WHERE enabled = $1
-            ORDER BY account_id
-            "#,
-        )
-        .bind(enabled)
-        .fetch_all(&state.db_pool)
-        .await
-    } else {
-        sqlx::query_as::<_, MonitoredAccount>(
-            r#"
-            SELECT account_id, enabled, last_synced_at, created_at, updated_at
-            FROM monitored_accounts
-            ORDER BY account_id
-            "#,
-        )
-        .fetch_all(&state.db_pool)
-        .await
-    }
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({ "error": format!("Database error: {}", e) })),
-        )
-    })?;
+) -> Result<Json<Vec<MonitoredAccount>>, ApiError> {
+    let accounts = sqlx::query_as::<_, MonitoredAccount>(
+        r#"
+        SELECT account_id, enabled, last_synced_at, coalesce_gas_rewards, created_at, updated_at
+        FROM monitored_accounts
+        WHERE ($1::BOOLEAN IS NULL OR enabled = $1)
+          AND ($2::TEXT IS NULL OR account_id > $2)
+        ORDER BY account_id
+        LIMIT $3 OFFSET $4
+        "#,
+    )
+    .bind(params.enabled)
+    .bind(&pagination.cursor)
+    .bind(pagination.limit)
+    .bind(pagination.offset)
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(|e| ApiError::internal(format!("Database error: {}", e)))?;
 
     Ok(Json(accounts))
 }
 
-/// Update a monitored account (enable/disable)
+/// Update a monitored account (enable/disable, gas reward coalescing)
+#[utoipa::path(
+    patch,
+    path = "/api/monitored-accounts/{account_id}",
+    tag = "monitored-accounts",
+    params(("account_id" = String, Path, description = "Monitored account")),
+    request_body = UpdateAccountRequest,
+    responses(
+        (status = 200, description = "Account updated", body = MonitoredAccount),
+        (status = 404, description = "Account not found"),
+    )
+)]
 pub async fn update_monitored_account(
     State(state): State<Arc<AppState>>,
     Path(account_id): Path<String>,
     Json(payload): Json<UpdateAccountRequest>,
-) -> Result<Json<MonitoredAccount>, (StatusCode, Json<Value>)> {
+) -> Result<Json<MonitoredAccount>, ApiError> {
     let account = sqlx::query_as::<_, MonitoredAccount>(
         r#"
         UPDATE monitored_accounts
-        SET enabled = $2,
+        SET enabled = COALESCE($2, enabled),
+            coalesce_gas_rewards = COALESCE($3, coalesce_gas_rewards),
             updated_at = NOW()
         WHERE account_id = $1
-        RETURNING account_id, enabled, last_synced_at, created_at, updated_at
+        RETURNING account_id, enabled, last_synced_at, coalesce_gas_rewards, created_at, updated_at
         "#,
     )
     .bind(&account_id)
     .bind(payload.enabled)
+    .bind(payload.coalesce_gas_rewards)
     .fetch_optional(&state.db_pool)
     .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({ "error": format!("Database error: {}", e) })),
-        )
-    })?;
+    .map_err(|e| ApiError::internal(format!("Database error: {}", e)))?;
 
     account
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(json!({ "error": "Account not found" })),
-            )
-        })
+        .ok_or_else(|| ApiError::not_found("Account not found"))
         .map(Json)
 }
 
 /// Delete a monitored account
+#[utoipa::path(
+    delete,
+    path = "/api/monitored-accounts/{account_id}",
+    tag = "monitored-accounts",
+    params(("account_id" = String, Path, description = "Monitored account")),
+    responses(
+        (status = 204, description = "Account deleted"),
+        (status = 404, description = "Account not found"),
+    )
+)]
 pub async fn delete_monitored_account(
     State(state): State<Arc<AppState>>,
     Path(account_id): Path<String>,
-) -> Result<StatusCode, (StatusCode, Json<Value>)> {
+) -> Result<StatusCode, ApiError> {
     let result = sqlx::query!(
         r#"
         DELETE FROM monitored_accounts
@@ -157,19 +197,110 @@ pub async fn delete_monitored_account(
     )
     .execute(&state.db_pool)
     .await
-    .map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(json!({ "error": format!("Database error: {}", e) })),
-        )
-    })?;
+    .map_err(|e| ApiError::internal(format!("Database error: {}", e)))?;
 
     if result.rows_affected() == 0 {
-        return Err((
-            StatusCode::NOT_FOUND,
-            Json(json!({ "error": "Account not found" })),
-        ));
+        return Err(ApiError::not_found("Account not found"));
     }
 
     Ok(StatusCode::NO_CONTENT)
 }
+
+/// Enable or disable monitoring of a specific token for a monitored account
+#[utoipa::path(
+    patch,
+    path = "/api/monitored-accounts/{account_id}/tokens/{token_id}",
+    tag = "monitored-accounts",
+    params(
+        ("account_id" = String, Path, description = "Monitored account"),
+        ("token_id" = String, Path, description = "Token identifier"),
+    ),
+    request_body = UpdateTokenRequest,
+    responses(
+        (status = 200, description = "Token updated", body = DiscoveredToken),
+        (status = 404, description = "Account not found"),
+    )
+)]
+pub async fn update_monitored_token(
+    State(state): State<Arc<AppState>>,
+    Path((account_id, token_id)): Path<(String, String)>,
+    Json(payload): Json<UpdateTokenRequest>,
+) -> Result<Json<DiscoveredToken>, ApiError> {
+    let account_exists: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM monitored_accounts WHERE account_id = $1)")
+            .bind(&account_id)
+            .fetch_one(&state.db_pool)
+            .await
+            .map_err(|e| ApiError::internal(format!("Database error: {}", e)))?;
+
+    if !account_exists {
+        return Err(ApiError::not_found("Account not found"));
+    }
+
+    let token = sqlx::query_as::<_, DiscoveredToken>(
+        r#"
+        INSERT INTO discovered_tokens (account_id, token_id, enabled)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (account_id, token_id) DO UPDATE
+        SET enabled = EXCLUDED.enabled
+        RETURNING account_id, token_id, enabled, classification, discovered_at, updated_at
+        "#,
+    )
+    .bind(&account_id)
+    .bind(&token_id)
+    .bind(payload.enabled)
+    .fetch_one(&state.db_pool)
+    .await
+    .map_err(|e| ApiError::internal(format!("Database error: {}", e)))?;
+
+    Ok(Json(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::init_test_state;
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn test_list_monitored_accounts_pages(pool: PgPool) -> sqlx::Result<()> {
+        for (account_id, enabled) in [("a.near", true), ("b.near", false), ("c.near", true)] {
+            sqlx::query("INSERT INTO monitored_accounts (account_id, enabled) VALUES ($1, $2)")
+                .bind(account_id)
+                .bind(enabled)
+                .execute(&pool)
+                .await?;
+        }
+
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        let state = Arc::new(state);
+        let list = |limit: i64, cursor: Option<&str>, enabled: Option<bool>| {
+            let state = state.clone();
+            let pagination = Pagination {
+                limit,
+                offset: 0,
+                cursor: cursor.map(str::to_string),
+            };
+            async move {
+                let Json(accounts) = list_monitored_accounts(
+                    State(state),
+                    pagination,
+                    Query(ListAccountsQuery { enabled }),
+                )
+                .await
+                .unwrap();
+                accounts
+                    .into_iter()
+                    .map(|a| a.account_id)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(list(2, None, None).await, vec!["a.near", "b.near"]);
+        assert_eq!(list(2, Some("b.near"), None).await, vec!["c.near"]);
+        assert_eq!(list(10, Some("a.near"), Some(true)).await, vec!["c.near"]);
+
+        Ok(())
+    }
+}
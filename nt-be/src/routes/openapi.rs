@@ -0,0 +1,106 @@
+use axum::Json;
+use utoipa::{
+    Modify, OpenApi,
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+};
+
+use crate::handlers;
+
+use super::{admin, balance_changes, monitored_accounts, webhooks};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Treasury26 API"),
+    paths(
+        balance_changes::get_balance_changes,
+        balance_changes::fill_gaps,
+        balance_changes::reprocess_balance_change,
+        balance_changes::get_balance_change_detail,
+        balance_changes::get_token_changes,
+        balance_changes::get_transfers_between,
+        balance_changes::get_latest_balances,
+        balance_changes::verify_current,
+        admin::rebuild_chain,
+        admin::collapse_duplicates,
+        admin::monitor_progress,
+        admin::cancel_fill,
+        admin::set_monitor_ceiling,
+        admin::register_token_alias,
+        admin::list_token_aliases,
+        admin::list_migrations,
+        admin::list_gaps,
+        admin::list_fill_failures,
+        admin::cache_stats,
+        admin::invalidate_cache,
+        monitored_accounts::add_monitored_account,
+        monitored_accounts::list_monitored_accounts,
+        monitored_accounts::update_monitored_account,
+        monitored_accounts::delete_monitored_account,
+        monitored_accounts::update_monitored_token,
+        webhooks::register_webhook,
+        webhooks::list_webhooks,
+        handlers::token::metadata::get_token_metadata,
+        handlers::token::metadata::get_batch_token_metadata,
+        handlers::token::storage_deposit::is_registered::is_storage_deposit_registered,
+        handlers::token::storage_deposit::is_registered::get_batch_storage_deposit_is_registered,
+        handlers::treasury::policy::get_treasury_policy,
+        handlers::treasury::config::get_treasury_config,
+        handlers::treasury::check_handle_unused::check_handle_unused,
+        handlers::treasury::create::create_treasury,
+        handlers::user::balance::get_token_balance,
+        handlers::user::balance::get_token_balance_at_time,
+        handlers::user::balance_history::get_token_balance_history,
+        handlers::user::treasuries::get_user_treasuries,
+        handlers::user::tokens::get_user_tokens,
+        handlers::user::assets::get_user_assets,
+        handlers::user::profile::get_profile,
+        handlers::user::profile::get_batch_profiles,
+        handlers::user::check_account_exists::check_account_exists,
+        handlers::user::check_account_exists::check_accounts_exist_batch,
+        handlers::proposals::get_proposals::get_proposals,
+        handlers::proposals::get_proposals::get_proposal,
+        handlers::proposals::votes::get_proposal_votes,
+        handlers::lookup::pool::get_lockup_pool,
+        handlers::block::timestamp::get_block_at_time,
+        handlers::block::timestamp::get_block_timestamp,
+        handlers::block::receipt::get_receipt_outcome,
+        handlers::bulkpayment::get::get_batch_payment,
+        handlers::intents::search_tokens::search_tokens,
+        handlers::intents::list_tokens::list_tokens,
+        handlers::intents::resolve::resolve_token,
+        handlers::proxy::external::proxy_external_api,
+    ),
+    modifiers(&SecurityAddon)
+)]
+pub struct ApiDoc;
+
+/// Registers the bearer token used by the admin endpoints
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "admin_key",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// Serves the OpenAPI document generated from the handler annotations
+pub async fn get_openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_document_contains_user_assets() {
+        let doc = ApiDoc::openapi().to_json().unwrap();
+
+        assert!(doc.contains("/api/user/assets"));
+        assert!(doc.contains("SimplifiedToken"));
+    }
+}
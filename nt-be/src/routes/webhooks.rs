@@ -0,0 +1,225 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::HeaderMap,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::types::chrono::{DateTime, Utc};
+use std::sync::Arc;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::AppState;
+use crate::utils::admin_auth::require_admin;
+use crate::utils::api_error::ApiError;
+use crate::utils::webhook::resolve_public_url;
+
+/// A registered webhook (the secret is never returned)
+#[derive(Debug, Serialize, sqlx::FromRow, ToSchema)]
+pub struct Webhook {
+    pub id: i64,
+    pub account_id: String,
+    pub url: String,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RegisterWebhookRequest {
+    pub account_id: String,
+    /// http(s) URL the balance change payloads are POSTed to
+    pub url: String,
+    /// Key for the `X-Signature` HMAC-SHA256 of each payload
+    pub secret: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListWebhooksQuery {
+    pub account_id: Option<String>,
+}
+
+fn database_error(e: sqlx::Error) -> ApiError {
+    log::error!("Webhook database error: {}", e);
+    ApiError::internal(format!("Database error: {}", e))
+}
+
+/// Register a webhook for balance changes of an account
+///
+/// Registering the same account and URL again updates its secret and enabled flag.
+/// Requires `Authorization: Bearer <ADMIN_API_KEY>`. URLs resolving to private,
+/// loopback or link-local addresses are refused.
+#[utoipa::path(
+    post,
+    path = "/api/webhooks",
+    tag = "webhooks",
+    request_body = RegisterWebhookRequest,
+    responses(
+        (status = 200, description = "Webhook registered", body = Webhook),
+        (status = 400, description = "Invalid or non-public URL, or empty secret"),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn register_webhook(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<RegisterWebhookRequest>,
+) -> Result<Json<Webhook>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    resolve_public_url(&payload.url, state.env_vars.webhook_allow_private_urls)
+        .await
+        .map_err(ApiError::bad_request)?;
+    if payload.secret.is_empty() {
+        return Err(ApiError::bad_request("secret is required"));
+    }
+
+    let webhook = sqlx::query_as::<_, Webhook>(
+        r#"
+        INSERT INTO webhooks (account_id, url, secret, enabled)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (account_id, url) DO UPDATE
+        SET secret = EXCLUDED.secret,
+            enabled = EXCLUDED.enabled,
+            updated_at = NOW()
+        RETURNING id, account_id, url, enabled, created_at, updated_at
+        "#,
+    )
+    .bind(&payload.account_id)
+    .bind(&payload.url)
+    .bind(&payload.secret)
+    .bind(payload.enabled)
+    .fetch_one(&state.db_pool)
+    .await
+    .map_err(database_error)?;
+
+    Ok(Json(webhook))
+}
+
+/// List registered webhooks, optionally for one account
+///
+/// Requires `Authorization: Bearer <ADMIN_API_KEY>`.
+#[utoipa::path(
+    get,
+    path = "/api/webhooks",
+    tag = "webhooks",
+    params(ListWebhooksQuery),
+    responses(
+        (status = 200, description = "Registered webhooks", body = Vec<Webhook>),
+        (status = 401, description = "Missing or invalid admin key"),
+        (status = 403, description = "Admin endpoints are disabled"),
+    ),
+    security(("admin_key" = []))
+)]
+pub async fn list_webhooks(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<ListWebhooksQuery>,
+) -> Result<Json<Vec<Webhook>>, ApiError> {
+    require_admin(state.env_vars.admin_api_key.as_deref(), &headers)?;
+
+    let webhooks = sqlx::query_as::<_, Webhook>(
+        r#"
+        SELECT id, account_id, url, enabled, created_at, updated_at
+        FROM webhooks
+        WHERE $1::TEXT IS NULL OR account_id = $1
+        ORDER BY account_id, id
+        "#,
+    )
+    .bind(params.account_id)
+    .fetch_all(&state.db_pool)
+    .await
+    .map_err(database_error)?;
+
+    Ok(Json(webhooks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_utils::init_test_state;
+    use axum::http::{StatusCode, header};
+    use sqlx::PgPool;
+
+    #[sqlx::test]
+    async fn test_register_and_list_webhooks(pool: PgPool) -> sqlx::Result<()> {
+        let mut state = init_test_state().await;
+        state.db_pool = pool;
+        state.env_vars.admin_api_key = Some("admin-key".to_string());
+        let state = Arc::new(state);
+
+        let mut admin_headers = HeaderMap::new();
+        admin_headers.insert(header::AUTHORIZATION, "Bearer admin-key".parse().unwrap());
+
+        let request = |url: &str, secret: &str| RegisterWebhookRequest {
+            account_id: "hooked.near".to_string(),
+            url: url.to_string(),
+            secret: secret.to_string(),
+            enabled: true,
+        };
+
+        let ApiError { status, .. } = register_webhook(
+            State(state.clone()),
+            HeaderMap::new(),
+            Json(request("https://93.184.215.14/hook", "s3cret")),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let Json(webhook) = register_webhook(
+            State(state.clone()),
+            admin_headers.clone(),
+            Json(request("https://93.184.215.14/hook", "s3cret")),
+        )
+        .await
+        .unwrap();
+        assert_eq!(webhook.account_id, "hooked.near");
+
+        let ApiError { status, .. } = register_webhook(
+            State(state.clone()),
+            admin_headers.clone(),
+            Json(request("ftp://example.com", "s3cret")),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let ApiError { status, .. } = list_webhooks(
+            State(state.clone()),
+            HeaderMap::new(),
+            Query(ListWebhooksQuery { account_id: None }),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let Json(listed) = list_webhooks(
+            State(state.clone()),
+            admin_headers,
+            Query(ListWebhooksQuery {
+                account_id: Some("hooked.near".to_string()),
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, webhook.id);
+        assert!(
+            !serde_json::to_string(&listed[0])
+                .unwrap()
+                .contains("s3cret"),
+            "Secrets must not be listed"
+        );
+
+        Ok(())
+    }
+}
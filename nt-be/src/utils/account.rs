@@ -0,0 +1,84 @@
+//! Account Kinds
+//!
+//! NEAR has implicit accounts, whose id is derived from a key (64 hex characters, or
+//! `0x` and 40 hex characters for Ethereum-style ones), and named accounts registered
+//! under a top-level account. UIs show them differently, e.g. shortening implicit ids.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountKind {
+    /// A top-level account or an account directly under one (`near`, `alice.near`)
+    Named,
+    /// An account id derived from a public key or an Ethereum address
+    Implicit,
+    /// An account created under a named account (`treasury.sputnik-dao.near`)
+    SubAccount,
+}
+
+fn is_hex(s: &str) -> bool {
+    s.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Kind of an account id
+pub fn account_kind(account_id: &str) -> AccountKind {
+    let is_near_implicit = account_id.len() == 64 && is_hex(account_id);
+    let is_eth_implicit = account_id
+        .strip_prefix("0x")
+        .is_some_and(|address| address.len() == 40 && is_hex(address));
+
+    if is_near_implicit || is_eth_implicit {
+        AccountKind::Implicit
+    } else if account_id.matches('.').count() > 1 {
+        AccountKind::SubAccount
+    } else {
+        AccountKind::Named
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_accounts() {
+        assert_eq!(account_kind("near"), AccountKind::Named);
+        assert_eq!(account_kind("alice.near"), AccountKind::Named);
+        assert_eq!(account_kind("alice.tg"), AccountKind::Named);
+    }
+
+    #[test]
+    fn test_implicit_accounts() {
+        assert_eq!(
+            account_kind("98793cd91a3f870fb126f66285808c7e094afcfc4eda8a970f6648cdf0dbd6de"),
+            AccountKind::Implicit
+        );
+        assert_eq!(
+            account_kind("0x06012c8cf97bead5deae237070f9587f8e7a266d"),
+            AccountKind::Implicit
+        );
+        // Wrong length or uppercase hex isn't implicit
+        assert_eq!(
+            account_kind("98793cd91a3f870fb126f6628580"),
+            AccountKind::Named
+        );
+        assert_eq!(
+            account_kind("98793CD91A3F870FB126F66285808C7E094AFCFC4EDA8A970F6648CDF0DBD6DE"),
+            AccountKind::Named
+        );
+    }
+
+    #[test]
+    fn test_sub_accounts() {
+        assert_eq!(
+            account_kind("treasury.sputnik-dao.near"),
+            AccountKind::SubAccount
+        );
+        assert_eq!(
+            account_kind("petersalomonsen.lockup.near"),
+            AccountKind::SubAccount
+        );
+    }
+}
@@ -0,0 +1,107 @@
+//! Admin Authentication
+//!
+//! Admin-only endpoints and parameters are gated on a shared `ADMIN_API_KEY`,
+//! passed as a bearer token.
+
+use axum::http::{HeaderMap, StatusCode, header};
+use hmac::{Hmac, Mac};
+use near_api::NetworkConfig;
+use sha2::Sha256;
+
+use crate::utils::api_error::ApiError;
+use crate::utils::env::EnvVars;
+use crate::utils::network::custom_rpc_network;
+
+/// HMAC of `value` keyed with the admin key
+fn admin_key_mac(admin_api_key: &str, value: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(admin_api_key.as_bytes())
+        .expect("HMAC accepts keys of any size");
+    mac.update(value.as_bytes());
+    mac
+}
+
+/// Check the `Authorization: Bearer <ADMIN_API_KEY>` header
+///
+/// Admin endpoints are disabled entirely when no admin key is configured. The key is
+/// compared through its HMAC in constant time, so response timing doesn't leak how
+/// much of a guess was right.
+pub fn require_admin(admin_api_key: Option<&str>, headers: &HeaderMap) -> Result<(), ApiError> {
+    let Some(expected) = admin_api_key else {
+        return Err(ApiError::new(
+            StatusCode::FORBIDDEN,
+            "Admin endpoints are disabled",
+        ));
+    };
+
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    let expected_mac = admin_key_mac(expected, expected).finalize().into_bytes();
+    let matches = provided.is_some_and(|provided| {
+        admin_key_mac(expected, provided)
+            .verify_slice(&expected_mac)
+            .is_ok()
+    });
+    if !matches {
+        return Err(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "Invalid admin credentials",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Build the network for an admin-only `rpc_url` query parameter
+///
+/// # Returns
+/// `None` without `rpc_url`, otherwise the network once the caller is verified as
+/// admin and the URL as listed in `RPC_URL_ALLOWLIST`
+pub fn admin_rpc_network(
+    env_vars: &EnvVars,
+    headers: &HeaderMap,
+    rpc_url: Option<&str>,
+) -> Result<Option<NetworkConfig>, ApiError> {
+    let Some(rpc_url) = rpc_url else {
+        return Ok(None);
+    };
+
+    require_admin(env_vars.admin_api_key.as_deref(), headers)?;
+    custom_rpc_network(rpc_url, &env_vars.rpc_url_allowlist)
+        .map(Some)
+        .map_err(ApiError::bad_request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers_with(auth: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(auth).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_require_admin() {
+        assert!(require_admin(Some("secret"), &headers_with("Bearer secret")).is_ok());
+
+        let ApiError { status, .. } =
+            require_admin(Some("secret"), &headers_with("Bearer wrong")).unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let ApiError { status, .. } =
+            require_admin(Some("secret"), &headers_with("Bearer secret2")).unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let ApiError { status, .. } = require_admin(Some("secret"), &HeaderMap::new()).unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let ApiError { status, .. } =
+            require_admin(None, &headers_with("Bearer secret")).unwrap_err();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+}
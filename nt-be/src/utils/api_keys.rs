@@ -0,0 +1,152 @@
+//! API Key Rotation
+//!
+//! FastNear rate-limits per API key. With several keys configured, a key that gets
+//! a 429 is benched for a cooldown and requests move on to the next healthy key.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a key is skipped after being rate-limited
+pub const RATE_LIMIT_COOLDOWN: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Default, Clone)]
+struct KeyHealth {
+    rate_limited_until: Option<Instant>,
+    rate_limit_count: u32,
+}
+
+#[derive(Debug)]
+pub struct ApiKeyRotation {
+    keys: Vec<String>,
+    health: Mutex<Vec<KeyHealth>>,
+    cooldown: Duration,
+}
+
+impl ApiKeyRotation {
+    pub fn new(keys: Vec<String>) -> Self {
+        Self::with_cooldown(keys, RATE_LIMIT_COOLDOWN)
+    }
+
+    pub fn with_cooldown(keys: Vec<String>, cooldown: Duration) -> Self {
+        let health = vec![KeyHealth::default(); keys.len()];
+        Self {
+            keys,
+            health: Mutex::new(health),
+            cooldown,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Pick the key to use for the next request
+    ///
+    /// # Returns
+    /// The first key that isn't cooling down, or the one whose cooldown ends soonest
+    /// if all of them are rate-limited. `None` if no keys are configured.
+    pub fn current(&self) -> Option<&str> {
+        self.current_at(Instant::now())
+    }
+
+    /// Bench a key after it got a 429
+    pub fn mark_rate_limited(&self, key: &str) {
+        self.mark_rate_limited_at(key, Instant::now())
+    }
+
+    /// Clear a key's rate limit after a successful request
+    pub fn mark_success(&self, key: &str) {
+        let mut health = self.health.lock().unwrap();
+        if let Some(index) = self.index_of(key) {
+            health[index].rate_limited_until = None;
+        }
+    }
+
+    /// How many times each key has been rate-limited, in configuration order
+    pub fn rate_limit_counts(&self) -> Vec<u32> {
+        self.health
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|h| h.rate_limit_count)
+            .collect()
+    }
+
+    fn index_of(&self, key: &str) -> Option<usize> {
+        self.keys.iter().position(|k| k == key)
+    }
+
+    fn current_at(&self, now: Instant) -> Option<&str> {
+        let health = self.health.lock().unwrap();
+
+        let index = health
+            .iter()
+            .position(|h| h.rate_limited_until.is_none_or(|until| until <= now))
+            .or_else(|| {
+                health
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, h)| h.rate_limited_until)
+                    .map(|(index, _)| index)
+            })?;
+
+        Some(&self.keys[index])
+    }
+
+    fn mark_rate_limited_at(&self, key: &str, now: Instant) {
+        let mut health = self.health.lock().unwrap();
+        if let Some(index) = self.index_of(key) {
+            health[index].rate_limited_until = Some(now + self.cooldown);
+            health[index].rate_limit_count += 1;
+            log::warn!(
+                "API key #{} rate-limited, skipping it for {}s",
+                index + 1,
+                self.cooldown.as_secs()
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rotation() -> ApiKeyRotation {
+        ApiKeyRotation::with_cooldown(
+            vec!["key1".to_string(), "key2".to_string()],
+            Duration::from_secs(60),
+        )
+    }
+
+    #[test]
+    fn test_rate_limited_key_is_skipped_until_cooldown_ends() {
+        let keys = rotation();
+        let start = Instant::now();
+
+        assert_eq!(keys.current_at(start), Some("key1"));
+
+        keys.mark_rate_limited_at("key1", start);
+        assert_eq!(keys.current_at(start), Some("key2"));
+        assert_eq!(keys.rate_limit_counts(), vec![1, 0]);
+
+        assert_eq!(
+            keys.current_at(start + Duration::from_secs(61)),
+            Some("key1")
+        );
+    }
+
+    #[test]
+    fn test_all_keys_rate_limited_uses_soonest_available() {
+        let keys = rotation();
+        let start = Instant::now();
+
+        keys.mark_rate_limited_at("key2", start);
+        keys.mark_rate_limited_at("key1", start + Duration::from_secs(10));
+
+        assert_eq!(keys.current_at(start), Some("key2"));
+    }
+}
@@ -0,0 +1,398 @@
+//! Response Cache
+//!
+//! Handlers cache upstream responses (the Ref whitelist, token metadata, profiles, ...)
+//! in `AppState::cache`. By default entries live in process memory (moka). With
+//! `CACHE_BACKEND=redis` they are kept in Redis at `REDIS_URL` instead, so horizontally
+//! scaled instances share them rather than each paying for the same upstream calls.
+//! Redis errors are logged and treated as misses: an outage only costs extra calls.
+//!
+//! Operators can inspect the cache and evict entries by key prefix through the
+//! `/api/admin/cache/*` endpoints.
+
+use futures::future::BoxFuture;
+use moka::future::Cache;
+use redis::AsyncCommands;
+use redis::aio::ConnectionManager;
+use serde::Serialize;
+use serde_json::Value;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use utoipa::ToSchema;
+
+/// How long a cached response is reused
+pub const CACHE_TTL: Duration = Duration::from_secs(600);
+
+/// Max entries of the in-memory cache
+pub const CACHE_MAX_CAPACITY: u64 = 10_000;
+
+/// Prefix of cache keys in Redis, to keep them apart from other data in the database
+const REDIS_KEY_PREFIX: &str = "nt-be:cache:";
+
+/// Storage behind `AppCache`
+pub trait CacheStore: Send + Sync {
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Value>>;
+    fn insert(&self, key: String, value: Value) -> BoxFuture<'_, ()>;
+    /// Number of cached entries
+    fn entry_count(&self) -> BoxFuture<'_, u64>;
+    /// Evict every entry whose key starts with `prefix`, returning how many matched
+    fn invalidate_prefix<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, u64>;
+}
+
+/// Process-local cache
+pub struct MemoryStore(Cache<String, Value>);
+
+impl MemoryStore {
+    pub fn new(max_capacity: u64, ttl: Duration) -> Self {
+        Self(
+            Cache::builder()
+                .max_capacity(max_capacity)
+                .time_to_live(ttl)
+                .support_invalidation_closures()
+                .build(),
+        )
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Value>> {
+        Box::pin(self.0.get(key))
+    }
+
+    fn insert(&self, key: String, value: Value) -> BoxFuture<'_, ()> {
+        Box::pin(self.0.insert(key, value))
+    }
+
+    fn entry_count(&self) -> BoxFuture<'_, u64> {
+        Box::pin(async move {
+            self.0.run_pending_tasks().await;
+            self.0.entry_count()
+        })
+    }
+
+    fn invalidate_prefix<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, u64> {
+        Box::pin(async move {
+            let matched = self
+                .0
+                .iter()
+                .filter(|(key, _)| key.starts_with(prefix))
+                .count();
+            let prefix = prefix.to_string();
+            if let Err(e) = self
+                .0
+                .invalidate_entries_if(move |key, _| key.starts_with(&prefix))
+            {
+                log::warn!("Failed to invalidate cache entries: {}", e);
+                return 0;
+            }
+            matched as u64
+        })
+    }
+}
+
+/// Cache shared by all instances through Redis; values are stored as JSON strings
+pub struct RedisStore {
+    connection: ConnectionManager,
+    ttl: Duration,
+}
+
+impl RedisStore {
+    pub async fn connect(url: &str, ttl: Duration) -> redis::RedisResult<Self> {
+        let client = redis::Client::open(url)?;
+        let connection = ConnectionManager::new(client).await?;
+        Ok(Self { connection, ttl })
+    }
+
+    /// Redis keys of the entries whose key starts with `prefix`
+    async fn scan_keys(&self, prefix: &str) -> redis::RedisResult<Vec<String>> {
+        // Escape glob characters so the prefix is matched literally
+        let mut pattern = String::from(REDIS_KEY_PREFIX);
+        for c in prefix.chars() {
+            if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+        }
+        pattern.push('*');
+
+        let mut connection = self.connection.clone();
+        let mut iter = connection.scan_match::<_, String>(pattern).await?;
+        let mut keys = Vec::new();
+        while let Some(key) = iter.next_item().await {
+            keys.push(key?);
+        }
+        Ok(keys)
+    }
+}
+
+impl CacheStore for RedisStore {
+    fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Value>> {
+        Box::pin(async move {
+            let mut connection = self.connection.clone();
+            let cached: Option<String> = connection
+                .get(format!("{}{}", REDIS_KEY_PREFIX, key))
+                .await
+                .map_err(|e| log::warn!("Redis GET {} failed: {}", key, e))
+                .ok()
+                .flatten();
+            cached.and_then(|json| serde_json::from_str(&json).ok())
+        })
+    }
+
+    fn insert(&self, key: String, value: Value) -> BoxFuture<'_, ()> {
+        Box::pin(async move {
+            let mut connection = self.connection.clone();
+            let result: redis::RedisResult<()> = connection
+                .set_ex(
+                    format!("{}{}", REDIS_KEY_PREFIX, key),
+                    value.to_string(),
+                    self.ttl.as_secs(),
+                )
+                .await;
+            if let Err(e) = result {
+                log::warn!("Redis SET {} failed: {}", key, e);
+            }
+        })
+    }
+
+    fn entry_count(&self) -> BoxFuture<'_, u64> {
+        Box::pin(async move {
+            match self.scan_keys("").await {
+                Ok(keys) => keys.len() as u64,
+                Err(e) => {
+                    log::warn!("Redis SCAN failed: {}", e);
+                    0
+                }
+            }
+        })
+    }
+
+    fn invalidate_prefix<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, u64> {
+        Box::pin(async move {
+            let keys = match self.scan_keys(prefix).await {
+                Ok(keys) if keys.is_empty() => return 0,
+                Ok(keys) => keys,
+                Err(e) => {
+                    log::warn!("Redis SCAN {}* failed: {}", prefix, e);
+                    return 0;
+                }
+            };
+            let mut connection = self.connection.clone();
+            connection
+                .del::<_, u64>(&keys)
+                .await
+                .map_err(|e| log::warn!("Redis DEL {}* failed: {}", prefix, e))
+                .unwrap_or(0)
+        })
+    }
+}
+
+/// Cache usage reported by `/api/admin/cache/stats`
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, ToSchema)]
+pub struct CacheStats {
+    pub backend: CacheBackend,
+    pub entries: u64,
+    /// Lookups answered from the cache since startup
+    pub hits: u64,
+    /// Lookups that found nothing since startup
+    pub misses: u64,
+}
+
+/// Cache of JSON responses keyed by string, backed by a `CacheStore`
+#[derive(Clone)]
+pub struct AppCache {
+    store: Arc<dyn CacheStore>,
+    backend: CacheBackend,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl AppCache {
+    pub fn new(store: impl CacheStore + 'static) -> Self {
+        Self::with_backend(store, CacheBackend::Memory)
+    }
+
+    fn with_backend(store: impl CacheStore + 'static, backend: CacheBackend) -> Self {
+        Self {
+            store: Arc::new(store),
+            backend,
+            hits: Arc::default(),
+            misses: Arc::default(),
+        }
+    }
+
+    /// In-memory cache with the default capacity and TTL
+    pub fn memory() -> Self {
+        Self::new(MemoryStore::new(CACHE_MAX_CAPACITY, CACHE_TTL))
+    }
+
+    pub async fn get(&self, key: &str) -> Option<Value> {
+        let value = self.store.get(key).await;
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    pub async fn insert(&self, key: String, value: Value) {
+        self.store.insert(key, value).await
+    }
+
+    pub async fn stats(&self) -> CacheStats {
+        CacheStats {
+            backend: self.backend,
+            entries: self.store.entry_count().await,
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Evict every entry whose key starts with `prefix`, returning how many matched
+    pub async fn invalidate_prefix(&self, prefix: &str) -> u64 {
+        self.store.invalidate_prefix(prefix).await
+    }
+}
+
+/// Where `AppCache` keeps its entries (`CACHE_BACKEND`)
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheBackend {
+    Memory,
+    Redis,
+}
+
+impl FromStr for CacheBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "memory" | "moka" => Ok(CacheBackend::Memory),
+            "redis" => Ok(CacheBackend::Redis),
+            other => Err(format!("Unknown cache backend '{}'", other)),
+        }
+    }
+}
+
+/// Build the cache of the configured backend
+pub async fn build_cache(
+    backend: CacheBackend,
+    redis_url: Option<&str>,
+) -> Result<AppCache, Box<dyn std::error::Error>> {
+    match backend {
+        CacheBackend::Memory => Ok(AppCache::memory()),
+        CacheBackend::Redis => {
+            let url = redis_url.ok_or("CACHE_BACKEND=redis requires REDIS_URL")?;
+            log::info!("Connecting to the Redis cache...");
+            let store = RedisStore::connect(url, CACHE_TTL).await?;
+            Ok(AppCache::with_backend(store, CacheBackend::Redis))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    /// Store keeping entries in a map and counting lookups
+    #[derive(Default)]
+    struct FakeStore {
+        entries: Mutex<HashMap<String, Value>>,
+        lookups: Mutex<Vec<String>>,
+    }
+
+    impl CacheStore for Arc<FakeStore> {
+        fn get<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Value>> {
+            self.lookups.lock().unwrap().push(key.to_string());
+            let value = self.entries.lock().unwrap().get(key).cloned();
+            Box::pin(async move { value })
+        }
+
+        fn insert(&self, key: String, value: Value) -> BoxFuture<'_, ()> {
+            self.entries.lock().unwrap().insert(key, value);
+            Box::pin(async {})
+        }
+
+        fn entry_count(&self) -> BoxFuture<'_, u64> {
+            let count = self.entries.lock().unwrap().len() as u64;
+            Box::pin(async move { count })
+        }
+
+        fn invalidate_prefix<'a>(&'a self, prefix: &'a str) -> BoxFuture<'a, u64> {
+            let mut entries = self.entries.lock().unwrap();
+            let before = entries.len();
+            entries.retain(|key, _| !key.starts_with(prefix));
+            let removed = (before - entries.len()) as u64;
+            Box::pin(async move { removed })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_app_cache_goes_through_the_store() {
+        let store = Arc::new(FakeStore::default());
+        let cache = AppCache::new(store.clone());
+
+        assert_eq!(cache.get("profile:alice.near").await, None);
+        cache
+            .insert(
+                "profile:alice.near".to_string(),
+                serde_json::json!({ "name": "Alice" }),
+            )
+            .await;
+
+        // Clones share the store
+        let shared = cache.clone();
+        assert_eq!(
+            shared.get("profile:alice.near").await,
+            Some(serde_json::json!({ "name": "Alice" }))
+        );
+        assert_eq!(
+            *store.lookups.lock().unwrap(),
+            vec!["profile:alice.near", "profile:alice.near"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_round_trip() {
+        let cache = AppCache::memory();
+        cache
+            .insert("key".to_string(), serde_json::json!([1, 2]))
+            .await;
+
+        assert_eq!(cache.get("key").await, Some(serde_json::json!([1, 2])));
+        assert_eq!(cache.get("missing").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_invalidates_by_prefix() {
+        let cache = AppCache::memory();
+        for key in [
+            "profile:alice.near",
+            "profile:bob.near",
+            "receipt-outcome:abc",
+        ] {
+            cache.insert(key.to_string(), serde_json::json!(key)).await;
+        }
+
+        assert_eq!(cache.invalidate_prefix("profile:").await, 2);
+        assert_eq!(cache.get("profile:alice.near").await, None);
+        assert_eq!(cache.get("profile:bob.near").await, None);
+        assert!(cache.get("receipt-outcome:abc").await.is_some());
+
+        let stats = cache.stats().await;
+        assert_eq!(stats.entries, 1);
+        assert_eq!((stats.hits, stats.misses), (1, 2));
+    }
+
+    #[test]
+    fn test_parse_cache_backend() {
+        assert_eq!("redis".parse(), Ok(CacheBackend::Redis));
+        assert_eq!(" Memory ".parse(), Ok(CacheBackend::Memory));
+        assert!("memcached".parse::<CacheBackend>().is_err());
+    }
+}
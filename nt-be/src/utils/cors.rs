@@ -0,0 +1,135 @@
+//! CORS Configuration
+//!
+//! Allowed origins come from `CORS_ORIGINS` (comma-separated). Any origin is only
+//! allowed when it is explicitly set to `*`, so authenticated endpoints are not
+//! exposed to arbitrary sites by default.
+
+use axum::http::HeaderValue;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
+
+/// Origin allowed when `CORS_ORIGINS` is not set (the frontend dev server)
+pub const DEFAULT_CORS_ORIGIN: &str = "http://localhost:3000";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CorsOrigins {
+    Any,
+    List(Vec<String>),
+}
+
+/// Parse the `CORS_ORIGINS` value
+pub fn parse_cors_origins(value: Option<&str>) -> CorsOrigins {
+    let Some(value) = value else {
+        return CorsOrigins::List(vec![DEFAULT_CORS_ORIGIN.to_string()]);
+    };
+
+    if value.trim() == "*" {
+        return CorsOrigins::Any;
+    }
+
+    CorsOrigins::List(
+        value
+            .split(',')
+            .map(|origin| origin.trim().trim_end_matches('/'))
+            .filter(|origin| !origin.is_empty())
+            .map(String::from)
+            .collect(),
+    )
+}
+
+/// Build the CORS layer for the configured origins
+///
+/// Browsers refuse credentialed requests to wildcard origins, so credentials are
+/// ignored (with a warning) when any origin is allowed.
+pub fn cors_layer(origins: &CorsOrigins, allow_credentials: bool) -> CorsLayer {
+    match origins {
+        CorsOrigins::Any => {
+            if allow_credentials {
+                log::warn!("CORS_ALLOW_CREDENTIALS is ignored when CORS_ORIGINS is *");
+            }
+            CorsLayer::new()
+                .allow_origin(Any)
+                .allow_methods(Any)
+                .allow_headers(Any)
+        }
+        CorsOrigins::List(list) => {
+            let origins: Vec<HeaderValue> = list
+                .iter()
+                .filter_map(|origin| match origin.parse() {
+                    Ok(value) => Some(value),
+                    Err(_) => {
+                        log::warn!("Ignoring invalid CORS origin: {}", origin);
+                        None
+                    }
+                })
+                .collect();
+
+            CorsLayer::new()
+                .allow_origin(AllowOrigin::list(origins))
+                .allow_methods(AllowMethods::mirror_request())
+                .allow_headers(AllowHeaders::mirror_request())
+                .allow_credentials(allow_credentials)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, body::Body, http::Request, routing::get};
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_parse_cors_origins() {
+        assert_eq!(
+            parse_cors_origins(None),
+            CorsOrigins::List(vec![DEFAULT_CORS_ORIGIN.to_string()])
+        );
+        assert_eq!(parse_cors_origins(Some(" * ")), CorsOrigins::Any);
+        assert_eq!(
+            parse_cors_origins(Some("https://a.example/, ,https://b.example")),
+            CorsOrigins::List(vec![
+                "https://a.example".to_string(),
+                "https://b.example".to_string()
+            ])
+        );
+    }
+
+    async fn allowed_origin_header(layer: CorsLayer, origin: &str) -> Option<HeaderValue> {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(layer);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header("origin", origin)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        response
+            .headers()
+            .get("access-control-allow-origin")
+            .cloned()
+    }
+
+    #[tokio::test]
+    async fn test_disallowed_origin_is_rejected() {
+        let origins = parse_cors_origins(Some("https://treasury.example"));
+
+        let allowed =
+            allowed_origin_header(cors_layer(&origins, true), "https://treasury.example").await;
+        assert_eq!(allowed.unwrap(), "https://treasury.example");
+
+        let rejected =
+            allowed_origin_header(cors_layer(&origins, true), "https://evil.example").await;
+        assert!(rejected.is_none());
+
+        let any =
+            allowed_origin_header(cors_layer(&CorsOrigins::Any, true), "https://evil.example")
+                .await;
+        assert_eq!(any.unwrap(), "*");
+    }
+}
@@ -0,0 +1,200 @@
+//! Token Decimals Registry
+//!
+//! Every token id balances are recorded for (`near`, FT contracts and intents tokens
+//! like `intents.near:nep141:btc.omft.near`) resolves to its decimals through
+//! `decimals`. Resolved values are kept in the `token_decimals` table, which is seeded
+//! from the intents tokens map at startup (`seed_intents_decimals`) and filled from
+//! `ft_metadata` for other FT contracts, plus an in-memory cache in front of it.
+//!
+//! This is the single source of decimals: the gap filler, balance queries and the
+//! balance change routes all go through it. `known_decimals` answers from what is
+//! already stored, for callers that must not fall back to RPC.
+
+use moka::future::Cache;
+use near_api::NetworkConfig;
+use once_cell::sync::Lazy;
+use sqlx::PgPool;
+
+use crate::constants::INTENTS_CONTRACT_ID;
+use crate::constants::intents_tokens::get_tokens_map;
+use crate::handlers::balance_changes::counterparty::ensure_ft_metadata;
+
+/// Decimals of native NEAR (yoctoNEAR)
+pub const NEAR_DECIMALS: u8 = 24;
+
+/// Token id -> decimals; decimals of a token never change
+static DECIMALS_CACHE: Lazy<Cache<String, u8>> = Lazy::new(|| Cache::new(10_000));
+
+/// Defuse asset id (`nep141:x`) of an intents token id (`intents.near:nep141:x`)
+fn defuse_asset_id(token_id: &str) -> Option<&str> {
+    token_id
+        .strip_prefix(INTENTS_CONTRACT_ID.as_str())
+        .and_then(|rest| rest.strip_prefix(':'))
+}
+
+/// Decimals of an intents token from the intents tokens map
+///
+/// Accepts the intents token id or the bare defuse asset id.
+pub fn intents_decimals(token_id: &str) -> Option<u8> {
+    let asset_id = defuse_asset_id(token_id).unwrap_or(token_id);
+    get_tokens_map().values().find_map(|unified| {
+        unified
+            .grouped_tokens
+            .iter()
+            .find(|base| base.defuse_asset_id.eq_ignore_ascii_case(asset_id))
+            .map(|base| base.decimals)
+    })
+}
+
+async fn store(pool: &PgPool, token_id: &str, decimals: u8, source: &str) -> sqlx::Result<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO token_decimals (token_id, decimals, source)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (token_id) DO UPDATE
+        SET decimals = EXCLUDED.decimals, source = EXCLUDED.source, updated_at = NOW()
+        "#,
+    )
+    .bind(token_id)
+    .bind(decimals as i16)
+    .bind(source)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Store the decimals of every token in the intents tokens map
+///
+/// # Returns
+/// The number of tokens seeded
+pub async fn seed_intents_decimals(pool: &PgPool) -> sqlx::Result<usize> {
+    let tokens: Vec<(String, u8)> = get_tokens_map()
+        .values()
+        .flat_map(|unified| &unified.grouped_tokens)
+        .map(|base| {
+            (
+                format!("{}:{}", INTENTS_CONTRACT_ID, base.defuse_asset_id),
+                base.decimals,
+            )
+        })
+        .collect();
+
+    for (token_id, decimals) in &tokens {
+        store(pool, token_id, *decimals, "intents").await?;
+    }
+    Ok(tokens.len())
+}
+
+/// Decimals of a token id, if already known without RPC calls
+///
+/// Resolution order: NEAR, the cache, `token_decimals` and the intents tokens map.
+pub async fn known_decimals(pool: &PgPool, token_id: &str) -> sqlx::Result<Option<u8>> {
+    if token_id.eq_ignore_ascii_case("near") {
+        return Ok(Some(NEAR_DECIMALS));
+    }
+    if let Some(decimals) = DECIMALS_CACHE.get(token_id).await {
+        return Ok(Some(decimals));
+    }
+
+    let stored: Option<i16> =
+        sqlx::query_scalar("SELECT decimals FROM token_decimals WHERE token_id = $1")
+            .bind(token_id)
+            .fetch_optional(pool)
+            .await?;
+
+    let decimals = match stored {
+        Some(decimals) => decimals as u8,
+        None => match intents_decimals(token_id) {
+            Some(decimals) => {
+                store(pool, token_id, decimals, "intents").await?;
+                decimals
+            }
+            None => return Ok(None),
+        },
+    };
+
+    DECIMALS_CACHE.insert(token_id.to_string(), decimals).await;
+    Ok(Some(decimals))
+}
+
+/// Decimals of a token id
+///
+/// Resolves through `known_decimals`, and finally `ft_metadata` of the FT contract (for
+/// `nep141:` intents tokens missing from the map, of the underlying contract). Results
+/// are stored in `token_decimals`.
+pub async fn decimals(
+    pool: &PgPool,
+    network: &NetworkConfig,
+    token_id: &str,
+) -> Result<u8, Box<dyn std::error::Error>> {
+    if let Some(decimals) = known_decimals(pool, token_id).await? {
+        return Ok(decimals);
+    }
+
+    let asset_id = defuse_asset_id(token_id).unwrap_or(token_id);
+    let contract = asset_id.strip_prefix("nep141:").unwrap_or(asset_id);
+    if contract.contains(':') {
+        return Err(format!("Unknown decimals for token {}", token_id).into());
+    }
+    let decimals = ensure_ft_metadata(pool, network, contract).await?;
+    store(pool, token_id, decimals, "ft_metadata").await?;
+
+    DECIMALS_CACHE.insert(token_id.to_string(), decimals).await;
+    Ok(decimals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test]
+    async fn test_decimals_of_near_ft_and_intents_tokens(pool: PgPool) -> sqlx::Result<()> {
+        assert!(seed_intents_decimals(&pool).await? > 0);
+
+        // Known ft_metadata; no RPC needed once it's in counterparties
+        sqlx::query(
+            "INSERT INTO counterparties (account_id, account_type, token_decimals) VALUES ('decimals-test.near', 'ft_token', 6)",
+        )
+        .execute(&pool)
+        .await?;
+        let network = NetworkConfig::mainnet();
+        let decimals_of = |token_id: &'static str| {
+            let pool = pool.clone();
+            let network = network.clone();
+            async move { decimals(&pool, &network, token_id).await.unwrap() }
+        };
+
+        // Not known until resolved through ft_metadata
+        assert_eq!(known_decimals(&pool, "decimals-test.near").await?, None);
+
+        assert_eq!(decimals_of("near").await, 24);
+        assert_eq!(decimals_of("NEAR").await, 24);
+        assert_eq!(decimals_of("decimals-test.near").await, 6);
+        assert_eq!(decimals_of("intents.near:nep141:btc.omft.near").await, 8);
+        assert_eq!(intents_decimals("nep141:btc.omft.near"), Some(8));
+        assert_eq!(known_decimals(&pool, "decimals-test.near").await?, Some(6));
+
+        let stored: Vec<(String, i16, String)> = sqlx::query_as(
+            "SELECT token_id, decimals, source FROM token_decimals WHERE token_id IN ('decimals-test.near', 'intents.near:nep141:btc.omft.near') ORDER BY token_id",
+        )
+        .fetch_all(&pool)
+        .await?;
+        assert_eq!(
+            stored,
+            vec![
+                (
+                    "decimals-test.near".to_string(),
+                    6,
+                    "ft_metadata".to_string()
+                ),
+                (
+                    "intents.near:nep141:btc.omft.near".to_string(),
+                    8,
+                    "intents".to_string()
+                ),
+            ]
+        );
+
+        Ok(())
+    }
+}
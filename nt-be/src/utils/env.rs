@@ -1,40 +1,325 @@
+use near_account_id::AccountIdRef;
 use near_api::{AccountId, SecretKey};
+use std::str::FromStr;
+
+use crate::constants::{INTENTS_CONTRACT_ID, REF_FINANCE_CONTRACT_ID};
+use crate::utils::cache::CacheBackend;
+
+use crate::utils::fill_config::{
+    DEFAULT_FINALITY_CONFIRMATIONS, DEFAULT_TO_PAST_MAX_LOOKBACK_BLOCKS, LookbackStrategy,
+};
+
+/// Default size of the database connection pool
+pub const DEFAULT_DB_MAX_CONNECTIONS: u32 = 20;
+
+/// Default time to wait for a free database connection
+pub const DEFAULT_DB_ACQUIRE_TIMEOUT_SECS: u64 = 3;
+
+/// Default time after which an unused database connection is closed
+pub const DEFAULT_DB_IDLE_TIMEOUT_SECS: u64 = 600;
+
+pub const DEFAULT_EXPLORER_TX_URL_BASE: &str = "https://nearblocks.io/txns/";
 
 #[derive(Clone, Debug)]
 pub struct EnvVars {
     pub database_url: String,
     pub pikespeak_key: String,
     pub fastnear_api_key: String,
+    /// `FASTNEAR_API_KEY` followed by any extra keys from `FASTNEAR_API_KEYS`, used in rotation
+    pub fastnear_api_keys: Vec<String>,
     pub sputnik_dao_api_base: String,
     pub bridge_rpc_url: String,
     pub signer_key: SecretKey,
+    /// `SIGNER_KEY` followed by any extra access keys of `SIGNER_ID` from `SIGNER_KEYS`
+    pub signer_keys: Vec<SecretKey>,
     pub signer_id: AccountId,
+    /// Intents multi-token contract queried for balances and owned tokens
+    pub intents_contract_id: AccountId,
+    /// Ref Finance contract the token whitelist is read from
+    pub ref_finance_contract_id: AccountId,
     pub disable_balance_monitoring: bool,
+    pub monitor_interval_minutes: u64,
+    /// Pinned block the monitor processes up to instead of the live head
+    pub monitor_up_to_block: Option<u64>,
+    /// Max RPC calls of one monitoring cycle; unset for no limit
+    pub monitor_rpc_budget: Option<u64>,
+    /// Max outbound RPC calls per second of `block_info` queries; unset for no limit
+    pub rpc_rate_limit_rps: Option<u32>,
+    /// File the monitor's RPC traffic is recorded to
+    pub rpc_record_file: Option<String>,
+    /// File the monitor's RPC traffic is replayed from instead of the network
+    pub rpc_replay_file: Option<String>,
+    /// FastNear explorer API used to narrow gap searches; unset disables it
+    pub fastnear_indexer_url: Option<String>,
+    pub regular_rpc_block_window: u64,
+    /// Blocks below the chain head that the monitor's gap-to-present search stays away from
+    pub head_lag_blocks: u64,
+    /// Blocks a newly found change needs on top of it before it's persisted
+    pub finality_confirmations: u64,
+    /// Growth of the gap-to-past window across monitor cycles (see `gap_filler`)
+    pub to_past_lookback_strategy: LookbackStrategy,
+    pub to_past_max_lookback_blocks: u64,
+    /// Seed tokens the account no longer holds from the change that emptied them
+    pub seed_from_history: bool,
+    /// Let webhooks target private and loopback addresses (local development only)
+    pub webhook_allow_private_urls: bool,
+    pub ref_whitelist_refresh_seconds: u64,
+    pub proxy_timeout_seconds: u64,
+    /// Max seconds a handler waits on an external HTTP/RPC call before returning 504
+    pub external_timeout_seconds: u64,
+    pub proxy_max_response_bytes: usize,
+    pub proxy_max_request_bytes: usize,
+    pub admin_api_key: Option<String>,
+    /// RPC URLs admins may query through the `rpc_url` parameter
+    pub rpc_url_allowlist: Vec<String>,
+    /// Explorer page of a transaction, followed by its hash
+    pub explorer_tx_url_base: String,
+    pub list_default_limit: i64,
+    pub list_max_limit: i64,
+    pub cors_origins: super::cors::CorsOrigins,
+    pub cors_allow_credentials: bool,
+    /// Store full receipt JSON alongside balance changes (see `receipt_audit`)
+    pub audit_mode: bool,
+    /// Where the response cache lives (`CACHE_BACKEND`: `memory` or `redis`)
+    pub cache_backend: CacheBackend,
+    pub redis_url: Option<String>,
+    pub db_max_connections: u32,
+    pub db_acquire_timeout_secs: u64,
+    pub db_idle_timeout_secs: u64,
+}
+
+/// Parse an optional setting, falling back to `default` when unset or invalid
+fn parse_or<T: FromStr>(value: Option<&str>, default: T) -> T {
+    value.and_then(|s| s.trim().parse().ok()).unwrap_or(default)
+}
+
+/// Combine the primary key with a comma-separated list of extra keys, without duplicates
+fn parse_api_keys(primary: &str, extra: Option<&str>) -> Vec<String> {
+    let mut keys = vec![primary.to_string()];
+    for key in extra.unwrap_or_default().split(',').map(str::trim) {
+        if !key.is_empty() && !keys.iter().any(|k| k == key) {
+            keys.push(key.to_string());
+        }
+    }
+    keys
+}
+
+/// Split a comma-separated list, dropping empty entries
+fn parse_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Read an account id from `var`, falling back to `default` when unset
+fn parse_account_id(var: &str, default: &AccountIdRef) -> AccountId {
+    std::env::var(var)
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| {
+            s.trim()
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid {}", var))
+        })
+        .unwrap_or_else(|| default.into())
 }
 
 impl Default for EnvVars {
     fn default() -> Self {
+        let fastnear_api_key =
+            std::env::var("FASTNEAR_API_KEY").expect("FASTNEAR_API_KEY is not set");
+        let fastnear_api_keys = parse_api_keys(
+            &fastnear_api_key,
+            std::env::var("FASTNEAR_API_KEYS").ok().as_deref(),
+        );
+
+        let signer_keys = parse_api_keys(
+            &std::env::var("SIGNER_KEY").expect("SIGNER_KEY is not set"),
+            std::env::var("SIGNER_KEYS").ok().as_deref(),
+        )
+        .iter()
+        .map(|key| key.parse().expect("Invalid signer key"))
+        .collect::<Vec<SecretKey>>();
+
         Self {
             database_url: std::env::var("DATABASE_URL").expect("DATABASE_URL is not set"),
             pikespeak_key: std::env::var("PIKESPEAK_KEY").expect("PIKESPEAK_KEY is not set"),
-            fastnear_api_key: std::env::var("FASTNEAR_API_KEY")
-                .expect("FASTNEAR_API_KEY is not set"),
+            fastnear_api_key,
+            fastnear_api_keys,
             sputnik_dao_api_base: std::env::var("SPUTNIK_DAO_API_BASE")
                 .unwrap_or_else(|_| "https://sputnik-indexer.fly.dev".to_string()),
             bridge_rpc_url: std::env::var("BRIDGE_RPC_URL")
                 .unwrap_or_else(|_| "https://bridge.chaindefuser.com/rpc".to_string()),
-            signer_key: std::env::var("SIGNER_KEY")
-                .expect("SIGNER_KEY is not set")
-                .parse()
-                .unwrap(),
+            signer_key: signer_keys[0].clone(),
+            signer_keys,
             signer_id: std::env::var("SIGNER_ID")
                 .expect("SIGNER_ID is not set")
                 .parse()
                 .unwrap(),
+            intents_contract_id: parse_account_id("INTENTS_CONTRACT_ID", INTENTS_CONTRACT_ID),
+            ref_finance_contract_id: parse_account_id(
+                "REF_FINANCE_CONTRACT_ID",
+                REF_FINANCE_CONTRACT_ID,
+            ),
             disable_balance_monitoring: std::env::var("DISABLE_BALANCE_MONITORING")
                 .unwrap_or_else(|_| "false".to_string())
                 .parse()
                 .unwrap_or(false),
+            monitor_interval_minutes: parse_or(
+                std::env::var("MONITOR_INTERVAL_MINUTES").ok().as_deref(),
+                5,
+            ),
+            monitor_up_to_block: std::env::var("MONITOR_UP_TO_BLOCK")
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+                .filter(|&block| block > 0),
+            monitor_rpc_budget: std::env::var("MONITOR_RPC_BUDGET")
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+                .filter(|&budget| budget > 0),
+            rpc_rate_limit_rps: std::env::var("RPC_RATE_LIMIT_RPS")
+                .ok()
+                .and_then(|s| s.trim().parse().ok())
+                .filter(|&rps| rps > 0),
+            rpc_record_file: std::env::var("RPC_RECORD_FILE")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            rpc_replay_file: std::env::var("RPC_REPLAY_FILE")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            fastnear_indexer_url: std::env::var("FASTNEAR_INDEXER_URL")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            regular_rpc_block_window: std::env::var("REGULAR_RPC_BLOCK_WINDOW")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(super::network::DEFAULT_REGULAR_RPC_BLOCK_WINDOW),
+            head_lag_blocks: std::env::var("HEAD_LAG_BLOCKS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(super::fill_config::DEFAULT_HEAD_LAG_BLOCKS),
+            // Zero would refresh in a tight loop
+            ref_whitelist_refresh_seconds: std::env::var("REF_WHITELIST_REFRESH_SECONDS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|&seconds| seconds > 0)
+                .unwrap_or(300),
+            proxy_timeout_seconds: parse_or(
+                std::env::var("PROXY_TIMEOUT_SECONDS").ok().as_deref(),
+                10,
+            ),
+            external_timeout_seconds: std::env::var("EXTERNAL_TIMEOUT_SECONDS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|&seconds| seconds > 0)
+                .unwrap_or(super::timeout::DEFAULT_EXTERNAL_TIMEOUT_SECONDS),
+            proxy_max_response_bytes: parse_or(
+                std::env::var("PROXY_MAX_RESPONSE_BYTES").ok().as_deref(),
+                5 * 1024 * 1024,
+            ),
+            proxy_max_request_bytes: parse_or(
+                std::env::var("PROXY_MAX_REQUEST_BYTES").ok().as_deref(),
+                64 * 1024,
+            ),
+            admin_api_key: std::env::var("ADMIN_API_KEY")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            rpc_url_allowlist: parse_list(std::env::var("RPC_URL_ALLOWLIST").ok().as_deref()),
+            explorer_tx_url_base: std::env::var("EXPLORER_TX_URL_BASE")
+                .ok()
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| DEFAULT_EXPLORER_TX_URL_BASE.to_string()),
+            list_default_limit: parse_or(
+                std::env::var("LIST_DEFAULT_LIMIT").ok().as_deref(),
+                super::pagination::DEFAULT_LIST_LIMIT,
+            ),
+            list_max_limit: parse_or(
+                std::env::var("LIST_MAX_LIMIT").ok().as_deref(),
+                super::pagination::MAX_LIST_LIMIT,
+            ),
+            cors_origins: super::cors::parse_cors_origins(
+                std::env::var("CORS_ORIGINS").ok().as_deref(),
+            ),
+            cors_allow_credentials: std::env::var("CORS_ALLOW_CREDENTIALS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            audit_mode: std::env::var("AUDIT_MODE")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            cache_backend: parse_or(
+                std::env::var("CACHE_BACKEND").ok().as_deref(),
+                CacheBackend::Memory,
+            ),
+            redis_url: std::env::var("REDIS_URL").ok(),
+            // A pool needs at least one connection
+            db_max_connections: parse_or(
+                std::env::var("DB_MAX_CONNECTIONS").ok().as_deref(),
+                DEFAULT_DB_MAX_CONNECTIONS,
+            )
+            .max(1),
+            db_acquire_timeout_secs: parse_or(
+                std::env::var("DB_ACQUIRE_TIMEOUT_SECS").ok().as_deref(),
+                DEFAULT_DB_ACQUIRE_TIMEOUT_SECS,
+            ),
+            db_idle_timeout_secs: parse_or(
+                std::env::var("DB_IDLE_TIMEOUT_SECS").ok().as_deref(),
+                DEFAULT_DB_IDLE_TIMEOUT_SECS,
+            ),
+            finality_confirmations: parse_or(
+                std::env::var("FINALITY_CONFIRMATIONS").ok().as_deref(),
+                DEFAULT_FINALITY_CONFIRMATIONS,
+            ),
+            to_past_lookback_strategy: parse_or(
+                std::env::var("TO_PAST_LOOKBACK_STRATEGY").ok().as_deref(),
+                LookbackStrategy::Linear,
+            ),
+            to_past_max_lookback_blocks: parse_or(
+                std::env::var("TO_PAST_MAX_LOOKBACK_BLOCKS").ok().as_deref(),
+                DEFAULT_TO_PAST_MAX_LOOKBACK_BLOCKS,
+            ),
+            seed_from_history: std::env::var("SEED_FROM_HISTORY")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
+            webhook_allow_private_urls: std::env::var("WEBHOOK_ALLOW_PRIVATE_URLS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse()
+                .unwrap_or(false),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_api_keys() {
+        assert_eq!(parse_api_keys("a", None), vec!["a"]);
+        assert_eq!(parse_api_keys("a", Some("b, a,,c")), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert!(parse_list(None).is_empty());
+        assert_eq!(
+            parse_list(Some("https://a.example/, ,https://b.example/rpc")),
+            vec!["https://a.example/", "https://b.example/rpc"]
+        );
+    }
+
+    #[test]
+    fn test_parse_db_pool_settings() {
+        assert_eq!(parse_or(Some("50"), DEFAULT_DB_MAX_CONNECTIONS), 50);
+        assert_eq!(parse_or(Some(" 10 "), DEFAULT_DB_ACQUIRE_TIMEOUT_SECS), 10);
+        assert_eq!(parse_or(None, DEFAULT_DB_IDLE_TIMEOUT_SECS), 600);
+        assert_eq!(parse_or(Some("many"), DEFAULT_DB_MAX_CONNECTIONS), 20);
+        assert_eq!(parse_or(Some("-1"), DEFAULT_DB_ACQUIRE_TIMEOUT_SECS), 3);
+    }
+}
@@ -0,0 +1,74 @@
+//! Field Selection
+//!
+//! Support for a `fields` query parameter that trims JSON responses down to the
+//! keys a client asks for (e.g. `?fields=symbol,balance`), so clients on slow
+//! connections don't download data they never show.
+
+use serde_json::Value;
+
+/// Parse a comma-separated `fields` parameter
+///
+/// # Returns
+/// The requested keys, or `None` when the parameter is absent or lists no keys,
+/// meaning the full response should be returned
+pub fn parse_fields(fields: Option<&str>) -> Option<Vec<String>> {
+    let fields: Vec<String> = fields?
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(str::to_string)
+        .collect();
+
+    (!fields.is_empty()).then_some(fields)
+}
+
+/// Keep only the given top-level keys of a JSON object
+///
+/// Arrays are projected element by element, so a list of items can be trimmed the
+/// same way as a single item. Other values are left unchanged.
+pub fn select_fields(value: &mut Value, fields: &[String]) {
+    match value {
+        Value::Object(map) => map.retain(|key, _| fields.iter().any(|f| f == key)),
+        Value::Array(items) => {
+            for item in items {
+                select_fields(item, fields);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_fields() {
+        assert_eq!(
+            parse_fields(Some("symbol, balance,,")),
+            Some(vec!["symbol".to_string(), "balance".to_string()])
+        );
+        assert_eq!(parse_fields(Some(" , ")), None);
+        assert_eq!(parse_fields(None), None);
+    }
+
+    #[test]
+    fn test_select_fields_projects_objects_and_arrays() {
+        let fields = parse_fields(Some("symbol,balance")).unwrap();
+
+        let mut list = json!([
+            {"symbol": "NEAR", "balance": "1", "icon": "data:..."},
+            {"symbol": "USDC", "decimals": 6},
+        ]);
+        select_fields(&mut list, &fields);
+        assert_eq!(
+            list,
+            json!([{"symbol": "NEAR", "balance": "1"}, {"symbol": "USDC"}])
+        );
+
+        let mut scalar = json!("unchanged");
+        select_fields(&mut scalar, &fields);
+        assert_eq!(scalar, json!("unchanged"));
+    }
+}
@@ -0,0 +1,96 @@
+//! Gap Fill Settings
+//!
+//! Defaults and types of the settings that tune gap filling, read from the
+//! environment by `EnvVars` and applied by `gap_filler`.
+
+use std::str::FromStr;
+
+use super::env::EnvVars;
+
+/// Default number of blocks below the chain head skipped by the gap-to-present search
+///
+/// Archival nodes can lag the head by a few blocks and return errors for them.
+pub const DEFAULT_HEAD_LAG_BLOCKS: u64 = 100;
+
+/// Default number of blocks a gap-to-present change needs on top of it to be persisted
+///
+/// Blocks near the head can (rarely) be reorged; NEAR finalizes blocks a couple of
+/// heights behind the head.
+pub const DEFAULT_FINALITY_CONFIRMATIONS: u64 = 3;
+
+/// Blocks searched back by a gap-to-past fill (~7 days)
+pub const DEFAULT_TO_PAST_LOOKBACK_BLOCKS: u64 = 600_000;
+
+/// Default cap on the exponential gap-to-past window (~7 months)
+pub const DEFAULT_TO_PAST_MAX_LOOKBACK_BLOCKS: u64 = 19_200_000;
+
+/// How the gap-to-past window grows when history keeps reaching further back
+///
+/// Each fill that finds no change inserts a SNAPSHOT at its lookback boundary, and
+/// the next cycle searches back from there.
+/// - `Linear`: every fill searches `DEFAULT_TO_PAST_LOOKBACK_BLOCKS`
+/// - `Exponential`: every successive fill doubles the window, up to a cap, so deep
+///   histories are reached in fewer cycles
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LookbackStrategy {
+    Linear,
+    Exponential,
+}
+
+impl FromStr for LookbackStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "linear" => Ok(LookbackStrategy::Linear),
+            "exponential" => Ok(LookbackStrategy::Exponential),
+            other => Err(format!("Unknown lookback strategy '{}'", other)),
+        }
+    }
+}
+
+/// Settings of a gap fill, taken from `EnvVars` (see `AppState::fill_config`)
+///
+/// Passed into every fill rather than kept in process-wide state, so callers (and
+/// tests) with different settings don't affect each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FillConfig {
+    /// Growth of the gap-to-past window across fills
+    pub to_past_lookback_strategy: LookbackStrategy,
+    /// Cap on the exponential gap-to-past window
+    pub to_past_max_lookback_blocks: u64,
+    /// Blocks a gap-to-present change needs on top of it to be persisted
+    pub finality_confirmations: u64,
+    /// Whether a token the account no longer holds is seeded from its history
+    pub seed_from_history: bool,
+    /// Whether the receipts of every recorded change are stored (see `receipt_audit`)
+    pub audit_mode: bool,
+    /// Whether webhooks notified of recorded changes may target private addresses
+    pub webhook_allow_private_urls: bool,
+}
+
+impl Default for FillConfig {
+    fn default() -> Self {
+        Self {
+            to_past_lookback_strategy: LookbackStrategy::Linear,
+            to_past_max_lookback_blocks: DEFAULT_TO_PAST_MAX_LOOKBACK_BLOCKS,
+            finality_confirmations: DEFAULT_FINALITY_CONFIRMATIONS,
+            seed_from_history: false,
+            audit_mode: false,
+            webhook_allow_private_urls: false,
+        }
+    }
+}
+
+impl From<&EnvVars> for FillConfig {
+    fn from(env_vars: &EnvVars) -> Self {
+        Self {
+            to_past_lookback_strategy: env_vars.to_past_lookback_strategy,
+            to_past_max_lookback_blocks: env_vars.to_past_max_lookback_blocks,
+            finality_confirmations: env_vars.finality_confirmations,
+            seed_from_history: env_vars.seed_from_history,
+            audit_mode: env_vars.audit_mode,
+            webhook_allow_private_urls: env_vars.webhook_allow_private_urls,
+        }
+    }
+}
@@ -1,6 +1,21 @@
+pub mod account;
+pub mod admin_auth;
+pub mod api_error;
+pub mod api_keys;
 pub mod base64json;
+pub mod cache;
+pub mod cors;
+pub mod decimals;
 pub mod env;
+pub mod fields;
+pub mod fill_config;
 pub mod jsonrpc;
+pub mod network;
+pub mod numeric;
+pub mod pagination;
+pub mod signer;
+pub mod timeout;
+pub mod webhook;
 
 #[cfg(test)]
 pub mod test_utils;
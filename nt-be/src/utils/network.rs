@@ -0,0 +1,212 @@
+//! Network Selection
+//!
+//! Helpers for building the regular and archival RPC networks and choosing between them.
+//! Regular RPC nodes only keep a few epochs of state, so recent blocks can be
+//! served by the cheaper regular network while older blocks need archival.
+
+use near_api::{NetworkConfig, RPCEndpoint};
+
+/// Default number of blocks behind the chain head still served by the regular RPC
+///
+/// Regular nodes garbage-collect state older than ~5 epochs (43,200 blocks each),
+/// so two epochs leaves a comfortable safety margin.
+pub const DEFAULT_REGULAR_RPC_BLOCK_WINDOW: u64 = 86_400;
+
+/// FastNear mainnet RPC serving recent state
+pub const REGULAR_RPC_URL: &str = "https://rpc.mainnet.fastnear.com/";
+
+/// FastNear mainnet archival RPC serving historical state
+pub const ARCHIVAL_RPC_URL: &str = "https://archival-rpc.mainnet.fastnear.com/";
+
+/// Retries per endpoint when several API keys are configured
+///
+/// Kept low so a rate-limited key fails over to the next one quickly.
+const RETRIES_PER_KEY: u8 = 2;
+
+/// Build one RPC endpoint per API key for the same URL
+///
+/// near-api retries 429 responses and moves on to the next endpoint once an
+/// endpoint's retries are exhausted, so a rate-limited key rotates to the next key.
+pub fn rpc_endpoints_with_keys(url: &str, api_keys: &[String]) -> Vec<RPCEndpoint> {
+    api_keys
+        .iter()
+        .map(|key| {
+            let endpoint =
+                RPCEndpoint::new(url.parse().expect("Invalid RPC url")).with_api_key(key.clone());
+            if api_keys.len() > 1 {
+                endpoint.with_retries(RETRIES_PER_KEY)
+            } else {
+                endpoint
+            }
+        })
+        .collect()
+}
+
+/// Which RPC network to build
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkKind {
+    Regular,
+    Archival,
+}
+
+impl NetworkKind {
+    pub fn rpc_url(self) -> &'static str {
+        match self {
+            NetworkKind::Regular => REGULAR_RPC_URL,
+            NetworkKind::Archival => ARCHIVAL_RPC_URL,
+        }
+    }
+}
+
+/// Build the mainnet network configuration of a kind
+///
+/// One endpoint is added per API key (see `rpc_endpoints_with_keys`); with no
+/// keys a single unauthenticated endpoint is used.
+pub fn build_network(kind: NetworkKind, api_keys: &[String]) -> NetworkConfig {
+    let rpc_endpoints = if api_keys.is_empty() {
+        vec![RPCEndpoint::new(
+            kind.rpc_url().parse().expect("Invalid RPC url"),
+        )]
+    } else {
+        rpc_endpoints_with_keys(kind.rpc_url(), api_keys)
+    };
+
+    NetworkConfig {
+        rpc_endpoints,
+        ..NetworkConfig::mainnet()
+    }
+}
+
+/// Build a one-off network for an operator-specified RPC endpoint
+///
+/// Only http(s) URLs on `allowlist` (compared after URL normalization) are accepted,
+/// so the backend can't be pointed at arbitrary hosts.
+///
+/// # Returns
+/// The network, or a message explaining why the URL was rejected
+pub fn custom_rpc_network(rpc_url: &str, allowlist: &[String]) -> Result<NetworkConfig, String> {
+    let url = reqwest::Url::parse(rpc_url).map_err(|e| format!("Invalid rpc_url: {}", e))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err("rpc_url must be an http(s) URL".to_string());
+    }
+
+    let allowed = allowlist
+        .iter()
+        .filter_map(|entry| reqwest::Url::parse(entry).ok())
+        .any(|entry| entry == url);
+    if !allowed {
+        return Err(format!("rpc_url {} is not in RPC_URL_ALLOWLIST", url));
+    }
+
+    Ok(NetworkConfig {
+        rpc_endpoints: vec![RPCEndpoint::new(url)],
+        ..NetworkConfig::mainnet()
+    })
+}
+
+/// URL of a network's first RPC endpoint
+///
+/// Identifies the network for process-wide caches and circuit breakers, so
+/// results from one endpoint (e.g. an operator-specified one) never leak into another.
+pub fn primary_rpc_url(network: &NetworkConfig) -> String {
+    network
+        .rpc_endpoints
+        .first()
+        .map(|endpoint| endpoint.url.to_string())
+        .unwrap_or_default()
+}
+
+/// Pick the network to use for a query at a specific block height
+///
+/// # Arguments
+/// * `regular` - The regular (non-archival) network configuration
+/// * `archival` - The archival network configuration
+/// * `block_height` - The block height being queried
+/// * `head_height` - The current chain head height
+/// * `window` - How many blocks behind the head are still considered recent
+///
+/// # Returns
+/// The regular network if `block_height` is within `window` blocks of the head,
+/// otherwise the archival network
+pub fn select_network_for_block<'a>(
+    regular: &'a NetworkConfig,
+    archival: &'a NetworkConfig,
+    block_height: u64,
+    head_height: u64,
+    window: u64,
+) -> &'a NetworkConfig {
+    if head_height.saturating_sub(block_height) <= window {
+        regular
+    } else {
+        archival
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn networks() -> (NetworkConfig, NetworkConfig) {
+        (
+            build_network(NetworkKind::Regular, &[]),
+            build_network(NetworkKind::Archival, &[]),
+        )
+    }
+
+    #[test]
+    fn test_recent_block_uses_regular_network() {
+        let (regular, archival) = networks();
+
+        let selected =
+            select_network_for_block(&regular, &archival, 177_999_000, 178_000_000, 86_400);
+
+        assert!(std::ptr::eq(selected, &regular));
+    }
+
+    #[test]
+    fn test_old_block_uses_archival_network() {
+        let (regular, archival) = networks();
+
+        let selected =
+            select_network_for_block(&regular, &archival, 151_386_339, 178_000_000, 86_400);
+
+        assert!(std::ptr::eq(selected, &archival));
+    }
+
+    #[test]
+    fn test_block_ahead_of_head_uses_regular_network() {
+        let (regular, archival) = networks();
+
+        // A head fetched slightly earlier than the queried block must not underflow
+        let selected =
+            select_network_for_block(&regular, &archival, 178_000_010, 178_000_000, 86_400);
+
+        assert!(std::ptr::eq(selected, &regular));
+    }
+
+    #[test]
+    fn test_archival_network_uses_archival_url_and_api_key() {
+        let network = build_network(NetworkKind::Archival, &["secret".to_string()]);
+
+        assert_eq!(network.rpc_endpoints.len(), 1);
+        let endpoint = &network.rpc_endpoints[0];
+        assert_eq!(endpoint.url.as_str(), ARCHIVAL_RPC_URL);
+        assert_eq!(endpoint.bearer_header.as_deref(), Some("Bearer secret"));
+
+        let unauthenticated = build_network(NetworkKind::Regular, &[]);
+        assert_eq!(
+            unauthenticated.rpc_endpoints[0].url.as_str(),
+            REGULAR_RPC_URL
+        );
+        assert!(unauthenticated.rpc_endpoints[0].bearer_header.is_none());
+    }
+
+    #[test]
+    fn test_one_endpoint_per_api_key() {
+        let keys = vec!["key1".to_string(), "key2".to_string()];
+        let endpoints = rpc_endpoints_with_keys("https://rpc.mainnet.fastnear.com/", &keys);
+
+        assert_eq!(endpoints.len(), 2);
+        assert!(endpoints.iter().all(|e| e.retries == RETRIES_PER_KEY));
+    }
+}
@@ -0,0 +1,197 @@
+//! Numeric Balances
+//!
+//! Balances are serialized as strings so no precision is lost. Clients that only
+//! show small-decimal tokens can pass `?numeric=true` to balance endpoints to get
+//! JSON numbers instead. A balance is only converted when a double represents it
+//! exactly; otherwise it stays a string and the object is flagged with
+//! `"balances_numeric": false`, so clients know to parse it themselves.
+
+use axum::{
+    body::Body,
+    extract::{Query, Request},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use serde_json::{Number, Value};
+use utoipa::IntoParams;
+
+/// Keys of balance amounts in balance responses
+const BALANCE_KEYS: &[&str] = &[
+    "balance",
+    "amount",
+    "balance_before",
+    "balance_after",
+    "current_balance",
+];
+
+/// Key flagging whether every balance of an object was converted
+pub const NUMERIC_FLAG: &str = "balances_numeric";
+
+/// Significant digits a double reproduces exactly
+const MAX_SAFE_DIGITS: usize = 15;
+
+/// Query parameter of endpoints wrapped in `numeric_balances_layer`
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct NumericQuery {
+    /// Return balances as JSON numbers where a double holds them exactly
+    #[serde(default)]
+    pub numeric: bool,
+}
+
+/// Parse a decimal string into a JSON number, if a double holds it exactly
+///
+/// # Returns
+/// The number, or `None` for values with more than `MAX_SAFE_DIGITS` significant
+/// digits and strings that aren't plain decimals
+pub fn safe_number(value: &str) -> Option<Number> {
+    let unsigned = value.strip_prefix('-').unwrap_or(value);
+    let (int, frac) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let is_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if int.is_empty() || !is_digits(int) || !is_digits(frac) {
+        return None;
+    }
+
+    let frac = frac.trim_end_matches('0');
+    let significant = format!("{}{}", int, frac);
+    if significant.trim_start_matches('0').len() > MAX_SAFE_DIGITS {
+        return None;
+    }
+
+    if frac.is_empty() {
+        let int: i64 = int.parse().ok()?;
+        Some(Number::from(if unsigned.len() < value.len() {
+            -int
+        } else {
+            int
+        }))
+    } else {
+        value.parse::<f64>().ok().and_then(Number::from_f64)
+    }
+}
+
+/// Convert the balances of every object in `value` to numbers where safe
+pub fn numeric_balances(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            let mut has_balance = false;
+            let mut all_numeric = true;
+            for key in BALANCE_KEYS {
+                if let Some(balance) = map.get_mut(*key) {
+                    has_balance = true;
+                    match balance.as_str().and_then(safe_number) {
+                        Some(number) => *balance = Value::Number(number),
+                        None => all_numeric &= balance.is_number(),
+                    }
+                }
+            }
+            if has_balance {
+                map.insert(NUMERIC_FLAG.to_string(), Value::Bool(all_numeric));
+            }
+
+            for (_, nested) in map.iter_mut() {
+                numeric_balances(nested);
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(numeric_balances),
+        _ => {}
+    }
+}
+
+/// Middleware applying `numeric_balances` to successful responses of `?numeric=true` requests
+pub async fn numeric_balances_layer(request: Request, next: Next) -> Response {
+    let numeric = Query::<NumericQuery>::try_from_uri(request.uri())
+        .map(|Query(query)| query.numeric)
+        .unwrap_or(false);
+
+    let response = next.run(request).await;
+    if !numeric || !response.status().is_success() {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::error!("Failed to read response body: {}", e);
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    let Ok(mut value) = serde_json::from_slice::<Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+
+    numeric_balances(&mut value);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(parts, Body::from(value.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Json, Router, middleware, routing::get};
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    #[test]
+    fn test_safe_number() {
+        assert_eq!(safe_number("5000000"), Some(Number::from(5_000_000)));
+        assert_eq!(safe_number("-20000"), Some(Number::from(-20_000)));
+        assert_eq!(safe_number("1.50"), Number::from_f64(1.5));
+        assert_eq!(safe_number("0.000001"), Number::from_f64(0.000001));
+        assert_eq!(safe_number("12.0"), Some(Number::from(12)));
+
+        // 11 NEAR in yoctoNEAR needs 26 digits
+        assert_eq!(safe_number("11000000000000000000000000"), None);
+        assert_eq!(safe_number("1234567890.1234567"), None);
+        assert_eq!(safe_number("1e5"), None);
+        assert_eq!(safe_number(".5"), None);
+    }
+
+    #[tokio::test]
+    async fn test_large_yocto_near_stays_string_in_numeric_mode() {
+        let app = Router::new()
+            .route(
+                "/balance",
+                get(|| async {
+                    Json(json!({
+                        "changes": [
+                            {"token_id": "near", "amount": "11000000000000000000000000", "balance_before": "0", "balance_after": "11000000000000000000000000"},
+                            {"token_id": "usdc.near", "amount": "-20000", "balance_before": "584253", "balance_after": "564253"},
+                        ]
+                    }))
+                }),
+            )
+            .layer(middleware::from_fn(numeric_balances_layer));
+
+        let body = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<Value>(&bytes).unwrap()
+            }
+        };
+
+        let numeric = body("/balance?numeric=true").await;
+        let near = &numeric["changes"][0];
+        assert_eq!(near["amount"], "11000000000000000000000000");
+        assert_eq!(near["balance_before"], 0);
+        assert_eq!(near[NUMERIC_FLAG], false);
+        let usdc = &numeric["changes"][1];
+        assert_eq!(usdc["amount"], -20000);
+        assert_eq!(usdc["balance_after"], 564253);
+        assert_eq!(usdc[NUMERIC_FLAG], true);
+
+        let strings = body("/balance").await;
+        assert_eq!(strings["changes"][1]["amount"], "-20000");
+        assert!(strings["changes"][1].get(NUMERIC_FLAG).is_none());
+    }
+}
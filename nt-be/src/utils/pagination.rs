@@ -0,0 +1,197 @@
+//! Pagination Limits
+//!
+//! Clamping for the `limit` parameter of list endpoints, so a client can't request
+//! an unbounded page. List handlers take a `Pagination` extractor, which reads the
+//! `limit`, `offset` and `cursor` query parameters and applies the configured limits.
+
+use axum::extract::{FromRequestParts, Query};
+use axum::http::request::Parts;
+use serde::Deserialize;
+use std::sync::Arc;
+use utoipa::IntoParams;
+
+use super::api_error::ApiError;
+use super::env::EnvVars;
+use crate::AppState;
+
+/// Default page size when a request doesn't specify `limit`
+pub const DEFAULT_LIST_LIMIT: i64 = 100;
+
+/// Largest page size a request may ask for
+pub const MAX_LIST_LIMIT: i64 = 1000;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ListLimits {
+    pub default_limit: i64,
+    pub max_limit: i64,
+}
+
+impl ListLimits {
+    pub fn from_env_vars(env_vars: &EnvVars) -> Self {
+        Self {
+            default_limit: env_vars.list_default_limit,
+            max_limit: env_vars.list_max_limit,
+        }
+    }
+
+    /// Resolve the page size to use for a request
+    ///
+    /// # Returns
+    /// `requested` clamped to `1..=max_limit`, or the default limit when not given
+    pub fn apply(&self, requested: Option<i64>) -> i64 {
+        requested
+            .unwrap_or(self.default_limit)
+            .clamp(1, self.max_limit.max(1))
+    }
+}
+
+impl Default for ListLimits {
+    fn default() -> Self {
+        Self {
+            default_limit: DEFAULT_LIST_LIMIT,
+            max_limit: MAX_LIST_LIMIT,
+        }
+    }
+}
+
+/// Page query parameters shared by list endpoints
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PaginationQuery {
+    /// Page size; defaults to `LIST_DEFAULT_LIMIT` and is capped at `LIST_MAX_LIMIT`
+    pub limit: Option<i64>,
+    /// Number of items to skip
+    pub offset: Option<i64>,
+    /// Start after this item, for endpoints with a stable sort key (see the endpoint)
+    pub cursor: Option<String>,
+}
+
+/// Resolved page of a list request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pagination {
+    /// Page size, within `1..=max_limit`
+    pub limit: i64,
+    /// Number of items to skip, never negative
+    pub offset: i64,
+    /// Non-empty cursor, if given
+    pub cursor: Option<String>,
+}
+
+impl Pagination {
+    pub fn resolve(query: PaginationQuery, limits: ListLimits) -> Self {
+        Self {
+            limit: limits.apply(query.limit),
+            offset: query.offset.unwrap_or(0).max(0),
+            cursor: query.cursor.filter(|cursor| !cursor.is_empty()),
+        }
+    }
+}
+
+impl FromRequestParts<Arc<AppState>> for Pagination {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let Query(query) = Query::<PaginationQuery>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| ApiError::bad_request(e.body_text()))?;
+        Ok(Self::resolve(
+            query,
+            ListLimits::from_env_vars(&state.env_vars),
+        ))
+    }
+}
+
+/// Take a single page out of an in-memory list
+pub fn paginate<T>(items: Vec<T>, offset: i64, limit: i64) -> Vec<T> {
+    items
+        .into_iter()
+        .skip(offset.max(0) as usize)
+        .take(limit.max(0) as usize)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_defaults_and_clamps() {
+        let limits = ListLimits::default();
+
+        assert_eq!(limits.apply(None), 100);
+        assert_eq!(limits.apply(Some(50)), 50);
+        assert_eq!(limits.apply(Some(100_000)), 1000);
+        assert_eq!(limits.apply(Some(0)), 1);
+        assert_eq!(limits.apply(Some(-5)), 1);
+    }
+
+    #[test]
+    fn test_pagination_resolve_clamps() {
+        let limits = ListLimits {
+            default_limit: 20,
+            max_limit: 50,
+        };
+        let resolve = |limit, offset, cursor: Option<&str>| {
+            Pagination::resolve(
+                PaginationQuery {
+                    limit,
+                    offset,
+                    cursor: cursor.map(str::to_string),
+                },
+                limits,
+            )
+        };
+
+        assert_eq!(
+            resolve(None, None, None),
+            Pagination {
+                limit: 20,
+                offset: 0,
+                cursor: None
+            }
+        );
+        assert_eq!(resolve(Some(500), Some(-3), None).limit, 50);
+        assert_eq!(resolve(Some(500), Some(-3), None).offset, 0);
+        assert_eq!(resolve(Some(0), Some(40), None).limit, 1);
+        assert_eq!(resolve(None, None, Some("")).cursor, None);
+        assert_eq!(
+            resolve(None, None, Some("b.near")).cursor.as_deref(),
+            Some("b.near")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pagination_extractor() {
+        use axum::http::Request;
+
+        let mut state = crate::utils::test_utils::init_test_state().await;
+        state.env_vars.list_max_limit = 10;
+        let state = Arc::new(state);
+
+        let extract = |uri: &str| {
+            let (mut parts, _) = Request::get(uri).body(()).unwrap().into_parts();
+            let state = state.clone();
+            async move { Pagination::from_request_parts(&mut parts, &state).await }
+        };
+
+        let page = extract("/api/list?limit=100&offset=5&other=x")
+            .await
+            .unwrap();
+        assert_eq!((page.limit, page.offset), (10, 5));
+
+        let ApiError { status, .. } = extract("/api/list?limit=many").await.unwrap_err();
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn test_paginate() {
+        let items: Vec<i32> = (0..10).collect();
+
+        assert_eq!(paginate(items.clone(), 0, 3), vec![0, 1, 2]);
+        assert_eq!(paginate(items.clone(), 8, 3), vec![8, 9]);
+        assert!(paginate(items, 20, 3).is_empty());
+    }
+}
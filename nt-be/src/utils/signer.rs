@@ -0,0 +1,23 @@
+//! Signer Key Pool
+//!
+//! Transactions signed with one access key must use strictly increasing nonces, so
+//! concurrent submissions from a single key contend for it. near-api's `Signer` holds
+//! a pool of keys and rotates to the next one on every transaction, so each key added
+//! here (all access keys of `SIGNER_ID`) takes a share of the submissions.
+
+use near_api::errors::PublicKeyError;
+use near_api::{SecretKey, Signer};
+use std::sync::Arc;
+
+/// Build a signer rotating round-robin across `keys`
+pub async fn build_signer(keys: &[SecretKey]) -> Result<Arc<Signer>, PublicKeyError> {
+    let (first, rest) = keys
+        .split_first()
+        .ok_or(PublicKeyError::PublicKeyIsNotAvailable)?;
+
+    let signer = Signer::from_secret_key(first.clone())?;
+    for key in rest {
+        signer.add_secret_key_to_pool(key.clone()).await?;
+    }
+    Ok(signer)
+}
@@ -5,12 +5,6 @@
 #[cfg(test)]
 use crate::AppState;
 
-#[cfg(test)]
-use moka::future::Cache;
-
-#[cfg(test)]
-use near_api::{NetworkConfig, RPCEndpoint, Signer};
-
 #[cfg(test)]
 use std::time::Duration;
 
@@ -36,10 +30,7 @@ pub async fn init_test_state() -> AppState {
 
     let env_vars = crate::utils::env::EnvVars::default();
 
-    let cache = Cache::builder()
-        .max_capacity(10_000)
-        .time_to_live(Duration::from_secs(600))
-        .build();
+    let cache = crate::utils::cache::AppCache::memory();
 
     // Create a dummy pool that won't be used in unit tests
     // Tests that need DB should use sqlx::test macro instead
@@ -52,28 +43,77 @@ pub async fn init_test_state() -> AppState {
     AppState {
         http_client: reqwest::Client::new(),
         cache,
-        signer: Signer::from_secret_key(env_vars.signer_key.clone())
+        signer: crate::utils::signer::build_signer(&env_vars.signer_keys)
+            .await
             .expect("Failed to create signer."),
         signer_id: env_vars.signer_id.clone(),
-        network: NetworkConfig {
-            rpc_endpoints: vec![
-                RPCEndpoint::new("https://rpc.mainnet.fastnear.com/".parse().unwrap())
-                    .with_api_key(env_vars.fastnear_api_key.clone()),
-            ],
-            ..NetworkConfig::mainnet()
-        },
-        archival_network: NetworkConfig {
-            rpc_endpoints: vec![
-                RPCEndpoint::new(
-                    "https://archival-rpc.mainnet.fastnear.com/"
-                        .parse()
-                        .unwrap(),
-                )
-                .with_api_key(env_vars.fastnear_api_key.clone()),
-            ],
-            ..NetworkConfig::mainnet()
-        },
+        network: crate::utils::network::build_network(
+            crate::utils::network::NetworkKind::Regular,
+            &env_vars.fastnear_api_keys,
+        ),
+        archival_network: crate::utils::network::build_network(
+            crate::utils::network::NetworkKind::Archival,
+            &env_vars.fastnear_api_keys,
+        ),
+        fastnear_keys: crate::utils::api_keys::ApiKeyRotation::new(
+            env_vars.fastnear_api_keys.clone(),
+        ),
         env_vars,
         db_pool,
+        ref_whitelist_refreshed_at: tokio::sync::RwLock::new(None),
+        monitor_progress: crate::handlers::balance_changes::monitor_progress::progress_channel(),
+        monitor_liveness: crate::handlers::balance_changes::monitor_liveness::MonitorLiveness::new(
+        ),
+        blocks_per_hour: crate::handlers::block::rate::BlocksPerHour::new(),
+        monitor_ceiling: crate::handlers::balance_changes::monitor_ceiling::MonitorCeiling::default(
+        ),
+        fill_cancellations: std::sync::Arc::new(
+            crate::handlers::balance_changes::fill_cancellation::FillCancellations::new(),
+        ),
+        rpc_rate_limit: None,
+    }
+}
+
+/// Serve `app` on a random local port
+///
+/// # Returns
+/// The server's base URL, e.g. `http://127.0.0.1:12345/`
+#[cfg(test)]
+pub async fn spawn_mock_server(app: axum::Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
+
+/// Spawn a mock JSON-RPC node answering every request with `handler`
+///
+/// # Returns
+/// A mainnet network configuration whose only endpoint is the mock node
+#[cfg(test)]
+pub async fn spawn_mock_rpc<H, T>(handler: H) -> near_api::NetworkConfig
+where
+    H: axum::handler::Handler<T, ()>,
+    T: 'static,
+{
+    spawn_mock_rpc_with_state(handler, ()).await
+}
+
+/// Like `spawn_mock_rpc`, for handlers that extract `State` (e.g. request counters)
+#[cfg(test)]
+pub async fn spawn_mock_rpc_with_state<H, T, S>(handler: H, state: S) -> near_api::NetworkConfig
+where
+    H: axum::handler::Handler<T, S>,
+    T: 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    let app = axum::Router::new()
+        .route("/", axum::routing::post(handler))
+        .with_state(state);
+    let url = spawn_mock_server(app).await;
+
+    near_api::NetworkConfig {
+        rpc_endpoints: vec![near_api::RPCEndpoint::new(url.parse().unwrap())],
+        ..near_api::NetworkConfig::mainnet()
     }
 }
@@ -0,0 +1,35 @@
+//! External Call Timeouts
+//!
+//! FastNear, RPC nodes and other upstreams can stall without ever answering. Handlers
+//! wrap their external calls in `with_timeout` (with `AppState::external_timeout`), so
+//! a stalled upstream answers the client with a 504 instead of tying up the task.
+
+use std::future::Future;
+use std::time::Duration;
+
+use super::api_error::ApiError;
+
+/// Default for `EXTERNAL_TIMEOUT_SECONDS`
+pub const DEFAULT_EXTERNAL_TIMEOUT_SECONDS: u64 = 15;
+
+/// Await an external call for at most `timeout`
+///
+/// # Arguments
+/// * `upstream` - What is being called, for the error message (e.g. "FastNear")
+///
+/// # Returns
+/// The call's output, or a 504 `ApiError` if it didn't complete in time
+pub async fn with_timeout<F: Future>(
+    timeout: Duration,
+    upstream: &str,
+    future: F,
+) -> Result<F::Output, ApiError> {
+    tokio::time::timeout(timeout, future).await.map_err(|_| {
+        eprintln!("{} did not respond within {:?}", upstream, timeout);
+        ApiError::gateway_timeout(format!(
+            "{} did not respond within {} seconds",
+            upstream,
+            timeout.as_secs_f64()
+        ))
+    })
+}
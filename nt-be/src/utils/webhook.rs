@@ -0,0 +1,285 @@
+//! Webhook Signatures
+//!
+//! Webhook requests carry an `X-Signature: t=<unix seconds>,v1=<hex>` header, where
+//! `v1` is the HMAC-SHA256 of `<t>.<body>` keyed with the webhook's secret. Signing
+//! the timestamp with the body lets consumers reject replays of old deliveries.
+//!
+//! Consumers verify a request by recomputing the HMAC over the raw body as received
+//! (before any JSON parsing) and checking `t` is recent; `verify` does both.
+//!
+//! Webhook URLs must point at the public internet: `resolve_public_url` rejects
+//! hosts resolving to private, loopback, link-local (including the cloud metadata
+//! address 169.254.169.254) or otherwise reserved addresses, both when a webhook is
+//! registered and before each delivery. `WEBHOOK_ALLOW_PRIVATE_URLS` lifts this for
+//! local development.
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::net::{IpAddr, SocketAddr};
+
+/// Header carrying the payload signature
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// Maximum age (and clock skew) of a signature accepted by consumers
+pub const DEFAULT_TOLERANCE_SECS: i64 = 300;
+
+/// Reasons a signature is rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    /// The header has no `t` or no `v1`
+    Malformed,
+    /// `t` is further than the tolerance from now
+    Expired,
+    /// No `v1` matches the body
+    Mismatch,
+}
+
+impl std::fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignatureError::Malformed => write!(f, "Malformed signature header"),
+            SignatureError::Expired => write!(f, "Signature timestamp is outside the tolerance"),
+            SignatureError::Mismatch => write!(f, "Signature does not match the body"),
+        }
+    }
+}
+
+impl std::error::Error for SignatureError {}
+
+fn mac(secret: &str, timestamp: i64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// `X-Signature` header value for a body sent at `timestamp` (unix seconds)
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let signature = hex::encode(mac(secret, timestamp, body).finalize().into_bytes());
+    format!("t={},v1={}", timestamp, signature)
+}
+
+/// Check an `X-Signature` header against the raw body
+///
+/// `now` is the current unix time in seconds; signatures more than `tolerance_secs`
+/// away from it are rejected. Any of several `v1` entries may match, so secrets can
+/// be rotated.
+pub fn verify(
+    secret: &str,
+    header: &str,
+    body: &[u8],
+    now: i64,
+    tolerance_secs: i64,
+) -> Result<(), SignatureError> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.push(value),
+            _ => {}
+        }
+    }
+
+    let timestamp = timestamp.ok_or(SignatureError::Malformed)?;
+    if signatures.is_empty() {
+        return Err(SignatureError::Malformed);
+    }
+    if (now - timestamp).abs() > tolerance_secs {
+        return Err(SignatureError::Expired);
+    }
+
+    let matches = signatures.iter().any(|signature| {
+        hex::decode(signature)
+            .is_ok_and(|bytes| mac(secret, timestamp, body).verify_slice(&bytes).is_ok())
+    });
+    if matches {
+        Ok(())
+    } else {
+        Err(SignatureError::Mismatch)
+    }
+}
+
+/// Whether an address is reachable on the public internet
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local, fc00::/7
+                || (first & 0xfe00) == 0xfc00
+                // Link-local, fe80::/10
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+/// Resolve a webhook URL, refusing targets that aren't on the public internet
+///
+/// `allow_private` (`WEBHOOK_ALLOW_PRIVATE_URLS`) skips the check.
+///
+/// # Returns
+/// The URL's host and the addresses to connect to (empty when private URLs are
+/// allowed, leaving resolution to the HTTP client), or why the URL is refused
+pub async fn resolve_public_url(
+    url: &str,
+    allow_private: bool,
+) -> Result<(String, Vec<SocketAddr>), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| "url must be an http(s) URL")?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("url must be an http(s) URL".to_string());
+    }
+    let host = parsed.host_str().ok_or("url has no host")?.to_string();
+    if allow_private {
+        return Ok((host, Vec::new()));
+    }
+
+    let port = parsed.port_or_known_default().unwrap_or(443);
+    // IPv6 literals are bracketed in URLs
+    let bare_host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((bare_host, port))
+        .await
+        .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+        .collect();
+
+    if addrs.is_empty() {
+        return Err(format!("{} does not resolve to any address", host));
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+        return Err(format!(
+            "{} resolves to non-public address {}",
+            host,
+            addr.ip()
+        ));
+    }
+    Ok((host, addrs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_non_public_addresses_are_rejected() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{} is not public", ip);
+        }
+        for ip in ["93.184.215.14", "2606:2800:21f:cb07:6820:80da:af6b:8b2c"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{} is public", ip);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_private_url_literals_are_refused() {
+        for url in [
+            "http://127.0.0.1:8080/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/hook",
+            "ftp://example.com/hook",
+        ] {
+            assert!(
+                resolve_public_url(url, false).await.is_err(),
+                "{} is refused",
+                url
+            );
+        }
+
+        let (host, addrs) = resolve_public_url("http://127.0.0.1:8080/hook", true)
+            .await
+            .unwrap();
+        assert_eq!(host, "127.0.0.1");
+        assert!(addrs.is_empty(), "Resolution is left to the client");
+    }
+
+    #[test]
+    fn test_sign_verify_round_trip() {
+        let body = br#"{"event":"balance_change","balance_after":"2.5"}"#;
+        let header = sign("s3cret", 1_700_000_000, body);
+        assert!(header.starts_with("t=1700000000,v1="));
+
+        assert_eq!(
+            verify(
+                "s3cret",
+                &header,
+                body,
+                1_700_000_060,
+                DEFAULT_TOLERANCE_SECS
+            ),
+            Ok(())
+        );
+
+        let tampered = br#"{"event":"balance_change","balance_after":"9.5"}"#;
+        assert_eq!(
+            verify(
+                "s3cret",
+                &header,
+                tampered,
+                1_700_000_060,
+                DEFAULT_TOLERANCE_SECS
+            ),
+            Err(SignatureError::Mismatch)
+        );
+        assert_eq!(
+            verify(
+                "other",
+                &header,
+                body,
+                1_700_000_060,
+                DEFAULT_TOLERANCE_SECS
+            ),
+            Err(SignatureError::Mismatch)
+        );
+        assert_eq!(
+            verify(
+                "s3cret",
+                &header,
+                body,
+                1_700_001_000,
+                DEFAULT_TOLERANCE_SECS
+            ),
+            Err(SignatureError::Expired)
+        );
+        assert_eq!(
+            verify(
+                "s3cret",
+                "v1=abc",
+                body,
+                1_700_000_000,
+                DEFAULT_TOLERANCE_SECS
+            ),
+            Err(SignatureError::Malformed)
+        );
+    }
+}
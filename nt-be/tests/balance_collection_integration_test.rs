@@ -1,9 +1,11 @@
 #![allow(clippy::collapsible_if)]
 #![allow(clippy::io_other_error)]
 
-use near_api::{NetworkConfig, RPCEndpoint};
+use near_api::NetworkConfig;
 use nt_be::handlers::balance_changes::gap_detector::find_gaps;
 use nt_be::handlers::balance_changes::gap_filler::fill_gaps;
+use nt_be::utils::fill_config::FillConfig;
+use nt_be::utils::network::{NetworkKind, build_network};
 use sqlx::{PgPool, types::BigDecimal};
 use std::str::FromStr;
 
@@ -18,18 +20,7 @@ fn create_archival_network() -> NetworkConfig {
     let fastnear_api_key =
         std::env::var("FASTNEAR_API_KEY").expect("FASTNEAR_API_KEY must be set in .env");
 
-    // Use fastnear archival RPC which supports historical queries
-    NetworkConfig {
-        rpc_endpoints: vec![
-            RPCEndpoint::new(
-                "https://archival-rpc.mainnet.fastnear.com/"
-                    .parse()
-                    .unwrap(),
-            )
-            .with_api_key(fastnear_api_key),
-        ],
-        ..NetworkConfig::mainnet()
-    }
+    build_network(NetworkKind::Archival, &[fastnear_api_key])
 }
 
 /// Test that gap filler can find and fill a gap with live RPC data
@@ -43,9 +34,16 @@ async fn test_fill_gap_end_to_end(pool: PgPool) -> sqlx::Result<()> {
     // Use block range from real data - we know there are multiple changes between 178142668 and 178148638
     // Start from a later block and let the system fill gaps backward
     let start_block: i64 = 178_149_000;
-    let filled = fill_gaps(&pool, &network, account_id, token_id, start_block)
-        .await
-        .expect("fill_gaps should not error");
+    let filled = fill_gaps(
+        &pool,
+        &network,
+        &FillConfig::default(),
+        account_id,
+        token_id,
+        start_block,
+    )
+    .await
+    .expect("fill_gaps should not error");
 
     assert!(!filled.is_empty(), "Should have found and filled gaps");
     println!("Filled {} initial records", filled.len());
@@ -98,9 +96,16 @@ async fn test_fill_gap_end_to_end(pool: PgPool) -> sqlx::Result<()> {
     println!("Detected {} gap(s)", gaps_before.len());
 
     // Fill the gap
-    let refilled = fill_gaps(&pool, &network, account_id, token_id, start_block)
-        .await
-        .expect("fill_gaps should not error");
+    let refilled = fill_gaps(
+        &pool,
+        &network,
+        &FillConfig::default(),
+        account_id,
+        token_id,
+        start_block,
+    )
+    .await
+    .expect("fill_gaps should not error");
 
     assert!(!refilled.is_empty(), "Should have refilled the gap");
     println!("Refilled {} record(s)", refilled.len());
@@ -164,6 +169,7 @@ async fn test_seed_initial_balance(pool: PgPool) -> sqlx::Result<()> {
     let result = seed_initial_balance(
         &pool,
         &network,
+        &FillConfig::default(),
         account_id,
         token_id,
         current_block,
@@ -251,9 +257,16 @@ async fn test_fill_gaps_with_bootstrap(pool: PgPool) -> sqlx::Result<()> {
         account_id, token_id, up_to_block
     );
 
-    let filled1 = fill_gaps(&pool, &network, account_id, token_id, up_to_block)
-        .await
-        .expect("fill_gaps should not error");
+    let filled1 = fill_gaps(
+        &pool,
+        &network,
+        &FillConfig::default(),
+        account_id,
+        token_id,
+        up_to_block,
+    )
+    .await
+    .expect("fill_gaps should not error");
 
     println!("First call returned {} records", filled1.len());
     assert_eq!(filled1.len(), 2, "First call should find exactly 2 records");
@@ -331,9 +344,16 @@ async fn test_fill_gaps_with_bootstrap(pool: PgPool) -> sqlx::Result<()> {
     // --- Second call: should find gap to past (if balance_before != 0) ---
     println!("\n=== Second call to fill_gaps ===");
 
-    let filled2 = fill_gaps(&pool, &network, account_id, token_id, up_to_block)
-        .await
-        .expect("fill_gaps should not error on second call");
+    let filled2 = fill_gaps(
+        &pool,
+        &network,
+        &FillConfig::default(),
+        account_id,
+        token_id,
+        up_to_block,
+    )
+    .await
+    .expect("fill_gaps should not error on second call");
 
     println!("Second call returned {} records", filled2.len());
     assert_eq!(filled2.len(), 1, "Second call should find exactly 1 record");
@@ -709,9 +729,16 @@ async fn test_fill_gaps_loop_until_complete(pool: PgPool) -> sqlx::Result<()> {
             break;
         }
 
-        let filled = fill_gaps(&pool, &network, account_id, token_id, up_to_block)
-            .await
-            .expect("fill_gaps should not error");
+        let filled = fill_gaps(
+            &pool,
+            &network,
+            &FillConfig::default(),
+            account_id,
+            token_id,
+            up_to_block,
+        )
+        .await
+        .expect("fill_gaps should not error");
 
         let count: (i64,) = sqlx::query_as(
             "SELECT COUNT(*) FROM balance_changes WHERE account_id = $1 AND token_id = $2",
@@ -933,14 +960,22 @@ async fn test_continuous_monitoring(pool: PgPool) -> sqlx::Result<()> {
     println!("Running monitoring cycle...");
     let network = create_archival_network();
     let up_to_block = 177_000_000i64;
-    run_monitor_cycle(&pool, &network, up_to_block)
-        .await
-        .map_err(|e| {
-            sqlx::Error::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                e.to_string(),
-            ))
-        })?;
+    run_monitor_cycle(
+        &pool,
+        &network,
+        &FillConfig::default(),
+        up_to_block,
+        0,
+        None,
+        None,
+    )
+    .await
+    .map_err(|e| {
+        sqlx::Error::Io(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            e.to_string(),
+        ))
+    })?;
 
     // Verify last_synced_at was updated
     let after_sync = sqlx::query!(
@@ -994,14 +1029,22 @@ async fn test_continuous_monitoring(pool: PgPool) -> sqlx::Result<()> {
     let sync_time = after_sync.last_synced_at;
 
     // Run another cycle
-    run_monitor_cycle(&pool, &network, up_to_block)
-        .await
-        .map_err(|e| {
-            sqlx::Error::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                e.to_string(),
-            ))
-        })?;
+    run_monitor_cycle(
+        &pool,
+        &network,
+        &FillConfig::default(),
+        up_to_block,
+        0,
+        None,
+        None,
+    )
+    .await
+    .map_err(|e| {
+        sqlx::Error::Io(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            e.to_string(),
+        ))
+    })?;
 
     // Verify last_synced_at didn't change (account was disabled)
     let after_disabled = sqlx::query!(
@@ -1033,14 +1076,7 @@ async fn test_fill_gap_with_transaction_hash_block_178148634(pool: PgPool) -> sq
     println!("\n=== Testing Balance Change Record with Transaction Hash (Block 178148634) ===\n");
 
     // Setup network config
-    let network = NetworkConfig {
-        rpc_endpoints: vec![RPCEndpoint::new(
-            "https://archival-rpc.mainnet.fastnear.com/"
-                .parse()
-                .unwrap(),
-        )],
-        ..NetworkConfig::mainnet()
-    };
+    let network = build_network(NetworkKind::Archival, &[]);
 
     let account_id = "petersalomonsen.near";
     let token_id = "near";
@@ -1053,16 +1089,22 @@ async fn test_fill_gap_with_transaction_hash_block_178148634(pool: PgPool) -> sq
 
     // Directly insert the balance change record for block 178148634
     // This will use get_account_changes to capture the transaction hash
-    let filled_gap =
-        insert_balance_change_record(&pool, &network, account_id, token_id, target_block)
-            .await
-            .map_err(|e| {
-                sqlx::Error::Io(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    e.to_string(),
-                ))
-            })?
-            .expect("Should insert record");
+    let filled_gap = insert_balance_change_record(
+        &pool,
+        &network,
+        &FillConfig::default(),
+        account_id,
+        token_id,
+        target_block,
+    )
+    .await
+    .map_err(|e| {
+        sqlx::Error::Io(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            e.to_string(),
+        ))
+    })?
+    .expect("Should insert record");
 
     println!("✓ Record inserted at block {}", filled_gap.block_height);
 
@@ -1278,14 +1320,22 @@ async fn test_ft_contract_as_counterparty(pool: PgPool) -> sqlx::Result<()> {
 
     // Run monitoring cycle to collect NEAR balance changes
     println!("\n=== Running Monitoring Cycle ===");
-    run_monitor_cycle(&pool, &network, up_to_block)
-        .await
-        .map_err(|e| {
-            sqlx::Error::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                e.to_string(),
-            ))
-        })?;
+    run_monitor_cycle(
+        &pool,
+        &network,
+        &FillConfig::default(),
+        up_to_block,
+        0,
+        None,
+        None,
+    )
+    .await
+    .map_err(|e| {
+        sqlx::Error::Io(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            e.to_string(),
+        ))
+    })?;
 
     // Query all counterparties from NEAR balance changes
     let counterparties: Vec<String> = sqlx::query_scalar(
@@ -1401,14 +1451,22 @@ async fn test_ft_token_discovery_through_monitoring(pool: PgPool) -> sqlx::Resul
     println!("\n=== First Monitoring Cycle ===");
     println!("Up to block: {}", up_to_block);
 
-    run_monitor_cycle(&pool, &network, up_to_block)
-        .await
-        .map_err(|e| {
-            sqlx::Error::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                e.to_string(),
-            ))
-        })?;
+    run_monitor_cycle(
+        &pool,
+        &network,
+        &FillConfig::default(),
+        up_to_block,
+        0,
+        None,
+        None,
+    )
+    .await
+    .map_err(|e| {
+        sqlx::Error::Io(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            e.to_string(),
+        ))
+    })?;
 
     // Check how many NEAR records were collected
     let near_count: (i64,) = sqlx::query_as(
@@ -1433,14 +1491,22 @@ async fn test_ft_token_discovery_through_monitoring(pool: PgPool) -> sqlx::Resul
     println!("The second cycle should collect balance changes for discovered tokens");
 
     // Run second monitoring cycle - should pick up discovered FT tokens
-    run_monitor_cycle(&pool, &network, up_to_block)
-        .await
-        .map_err(|e| {
-            sqlx::Error::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                e.to_string(),
-            ))
-        })?;
+    run_monitor_cycle(
+        &pool,
+        &network,
+        &FillConfig::default(),
+        up_to_block,
+        0,
+        None,
+        None,
+    )
+    .await
+    .map_err(|e| {
+        sqlx::Error::Io(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            e.to_string(),
+        ))
+    })?;
 
     println!("\n=== Verifying Automatic FT Token Discovery ===");
 
@@ -1646,14 +1712,21 @@ async fn test_ft_discovery_petersalomonsen_block_178086209(pool: PgPool) -> sqlx
     // Directly fill gaps for NEAR - use target_block + 1 to ensure we search down to include target_block
     // The gap filler will seed from 178086210 and search backwards, which should find 178086209
     println!("\n=== Collecting NEAR Balance Changes ===");
-    let filled = fill_gaps(&pool, &network, account_id, "near", target_block + 1)
-        .await
-        .map_err(|e| {
-            sqlx::Error::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                e.to_string(),
-            ))
-        })?;
+    let filled = fill_gaps(
+        &pool,
+        &network,
+        &FillConfig::default(),
+        account_id,
+        "near",
+        target_block + 1,
+    )
+    .await
+    .map_err(|e| {
+        sqlx::Error::Io(std::io::Error::new(
+            std::io::ErrorKind::Other,
+            e.to_string(),
+        ))
+    })?;
 
     println!("Filled {} NEAR balance change gaps", filled.len());
 
@@ -1855,9 +1928,17 @@ async fn test_discover_intents_tokens_webassemblymusic_treasury(pool: PgPool) ->
     .await?;
 
     // Run monitor cycle - should discover intents tokens and find balance changes
-    run_monitor_cycle(&pool, &network, monitor_block)
-        .await
-        .expect("Monitor cycle should complete");
+    run_monitor_cycle(
+        &pool,
+        &network,
+        &FillConfig::default(),
+        monitor_block,
+        0,
+        None,
+        None,
+    )
+    .await
+    .expect("Monitor cycle should complete");
 
     // Hard assertion: Must discover BTC intents token
     let btc_token = "intents.near:nep141:btc.omft.near";
@@ -1876,9 +1957,17 @@ async fn test_discover_intents_tokens_webassemblymusic_treasury(pool: PgPool) ->
     );
 
     // Run second monitor cycle to fill gaps for discovered intents tokens
-    run_monitor_cycle(&pool, &network, monitor_block)
-        .await
-        .expect("Second monitor cycle should complete");
+    run_monitor_cycle(
+        &pool,
+        &network,
+        &FillConfig::default(),
+        monitor_block,
+        0,
+        None,
+        None,
+    )
+    .await
+    .expect("Second monitor cycle should complete");
 
     // Hard assertion: Must find the BTC balance change at block 165324279
     let btc_change = sqlx::query!(
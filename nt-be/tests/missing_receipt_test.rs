@@ -1,7 +1,9 @@
-use near_api::{NetworkConfig, RPCEndpoint};
+use near_api::NetworkConfig;
 use nt_be::handlers::balance_changes::balance::ft::get_balance_at_block as get_ft_balance;
 use nt_be::handlers::balance_changes::block_info::get_block_timestamp;
 use nt_be::handlers::balance_changes::gap_filler::fill_gaps;
+use nt_be::utils::fill_config::FillConfig;
+use nt_be::utils::network::{NetworkKind, build_network};
 use sqlx::PgPool;
 use sqlx::types::BigDecimal;
 use std::str::FromStr;
@@ -14,17 +16,7 @@ fn create_archival_network() -> NetworkConfig {
     let fastnear_api_key =
         std::env::var("FASTNEAR_API_KEY").expect("FASTNEAR_API_KEY must be set in .env");
 
-    NetworkConfig {
-        rpc_endpoints: vec![
-            RPCEndpoint::new(
-                "https://archival-rpc.mainnet.fastnear.com/"
-                    .parse()
-                    .unwrap(),
-            )
-            .with_api_key(fastnear_api_key),
-        ],
-        ..NetworkConfig::mainnet()
-    }
+    build_network(NetworkKind::Archival, &[fastnear_api_key])
 }
 
 /// Test reproducing the exact "No receipt found" error from production monitoring
@@ -149,6 +141,7 @@ async fn test_fill_gap_to_past_with_insufficient_lookback(pool: PgPool) -> sqlx:
     let result = fill_gaps(
         &pool,
         &archival_network,
+        &FillConfig::default(),
         account_id,
         token_contract,
         snapshot_block,
@@ -1,6 +1,8 @@
-use near_api::{NetworkConfig, RPCEndpoint};
+use near_api::NetworkConfig;
 use nt_be::handlers::balance_changes::gap_detector::find_gaps;
 use nt_be::handlers::balance_changes::gap_filler::fill_gaps;
+use nt_be::utils::fill_config::FillConfig;
+use nt_be::utils::network::{NetworkKind, build_network};
 use sqlx::PgPool;
 use sqlx::types::BigDecimal;
 use sqlx::types::chrono::DateTime;
@@ -21,17 +23,7 @@ fn create_archival_network() -> NetworkConfig {
     let fastnear_api_key =
         std::env::var("FASTNEAR_API_KEY").expect("FASTNEAR_API_KEY must be set in .env");
 
-    NetworkConfig {
-        rpc_endpoints: vec![
-            RPCEndpoint::new(
-                "https://archival-rpc.mainnet.fastnear.com/"
-                    .parse()
-                    .unwrap(),
-            )
-            .with_api_key(fastnear_api_key),
-        ],
-        ..NetworkConfig::mainnet()
-    }
+    build_network(NetworkKind::Archival, &[fastnear_api_key])
 }
 
 /// Test gap detection and filling with a chain of SNAPSHOT records
@@ -216,9 +208,16 @@ async fn test_fill_gap_between_snapshot_chain(pool: PgPool) -> sqlx::Result<()>
 
     // Step 3: Fill gaps
     println!("\n--- Step 3: Fill gaps ---");
-    let filled = fill_gaps(&pool, &archival_network, account_id, token_id, 178685501)
-        .await
-        .expect("Should be able to fill gaps - will insert UNKNOWN counterparty");
+    let filled = fill_gaps(
+        &pool,
+        &archival_network,
+        &FillConfig::default(),
+        account_id,
+        token_id,
+        178685501,
+    )
+    .await
+    .expect("Should be able to fill gaps - will insert UNKNOWN counterparty");
 
     println!("\n✓ Gap filling completed");
     println!("  Filled {} gaps", filled.len());
@@ -1,5 +1,7 @@
-use near_api::{NetworkConfig, RPCEndpoint};
+use near_api::NetworkConfig;
 use nt_be::handlers::balance_changes::gap_filler::fill_gaps;
+use nt_be::utils::fill_config::FillConfig;
+use nt_be::utils::network::{NetworkKind, build_network};
 use sqlx::PgPool;
 use sqlx::types::BigDecimal;
 use sqlx::types::chrono::{DateTime, Utc};
@@ -19,17 +21,7 @@ fn create_archival_network() -> NetworkConfig {
     let fastnear_api_key =
         std::env::var("FASTNEAR_API_KEY").expect("FASTNEAR_API_KEY must be set in .env");
 
-    NetworkConfig {
-        rpc_endpoints: vec![
-            RPCEndpoint::new(
-                "https://archival-rpc.mainnet.fastnear.com/"
-                    .parse()
-                    .unwrap(),
-            )
-            .with_api_key(fastnear_api_key),
-        ],
-        ..NetworkConfig::mainnet()
-    }
+    build_network(NetworkKind::Archival, &[fastnear_api_key])
 }
 
 /// Test gap detection when SNAPSHOT has balance 0 but history shows non-zero balance
@@ -201,6 +193,7 @@ async fn test_fill_gap_before_zero_snapshot(pool: PgPool) -> sqlx::Result<()> {
     let filled_run1 = fill_gaps(
         &pool,
         &archival_network,
+        &FillConfig::default(),
         account_id,
         token_id,
         snapshot_block,
@@ -263,6 +256,7 @@ async fn test_fill_gap_before_zero_snapshot(pool: PgPool) -> sqlx::Result<()> {
     let filled_run2 = fill_gaps(
         &pool,
         &archival_network,
+        &FillConfig::default(),
         account_id,
         token_id,
         lookback_boundary,
@@ -65,10 +65,10 @@ export async function getUserTreasuries(
   try {
     const url = `${BACKEND_API_BASE}/user/treasuries`;
 
-    const response = await axios.get<Treasury[]>(url, {
+    const response = await axios.get<{ treasuries: Treasury[] }>(url, {
       params: { accountId },
     });
-    return response.data;
+    return response.data.treasuries;
   } catch (error) {
     console.error("Error getting user treasuries", error);
     return [];
@@ -97,6 +97,8 @@ export interface TreasuryAsset {
 export interface TreasuryAssets {
   tokens: TreasuryAsset[];
   totalBalanceUSD: Big;
+  /** True when some balances couldn't be fetched and tokens may be missing */
+  partial?: boolean;
 }
 
 interface TreasuryAssetRaw {
@@ -114,6 +116,11 @@ interface TreasuryAssetRaw {
   icon: string;
 }
 
+interface TreasuryAssetsResponseRaw {
+  tokens: TreasuryAssetRaw[];
+  partial: boolean;
+}
+
 /**
  * Get treasury assets (tokens with balances and prices)
  * Fetches from backend which aggregates data from Ref Finance and FastNear
@@ -127,12 +134,12 @@ export async function getTreasuryAssets(
   try {
     const url = `${BACKEND_API_BASE}/user/assets`;
 
-    const response = await axios.get<TreasuryAssetRaw[]>(url, {
+    const response = await axios.get<TreasuryAssetsResponseRaw>(url, {
       params: { accountId: treasuryId },
     });
 
     // Transform raw tokens with USD values
-    const tokensWithUSD = response.data.map((token) => {
+    const tokensWithUSD = response.data.tokens.map((token) => {
       const balance = Big(token.balance).div(Big(10).pow(token.decimals));
       const price = parseFloat(token.price);
       const balanceUSD = balance.mul(price).toNumber();
@@ -172,6 +179,7 @@ export async function getTreasuryAssets(
     return {
       tokens,
       totalBalanceUSD: totalUSD,
+      partial: response.data.partial,
     };
   } catch (error) {
     console.error("Error getting whitelist tokens", error);